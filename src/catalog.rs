@@ -1,2 +1,3 @@
 pub mod table_schema;
-pub mod catalog;
\ No newline at end of file
+pub mod sequence_schema;
+pub mod catalog;
@@ -1,26 +1,229 @@
-use crate::storage::{buffer_pool::{self, BufferPoolManager, PageId}, table_page::{TablePage, TupleId}};
+use crate::{config::config::PAGE_SIZE, parse::scanner::{INTERNAL_ERROR, PROGRAM_LIMIT_EXCEEDED, TError}, storage::{buffer_pool::{self, BufferPoolManager, PageId}, table_page::{RecordId, TablePage, TupleId}}};
 
-use super::table_schema::{self, TableSchema};
+use super::{sequence_schema::SequenceSchema, table_schema::{self, TableSchema}};
 
 
+// the first byte of every catalog entry says what it describes
+const TABLE_ENTRY: u8 = 0;
+const SEQUENCE_ENTRY: u8 = 1;
+const FORMAT_ENTRY: u8 = 2;
 
-pub fn load_catalog(buffer_pool: &mut BufferPoolManager) -> Vec<TableSchema> {
-    let mut tables = Vec::new();
-    let p = buffer_pool.get_catalog_page();
-    match p {
-        Some(p) => {
-            let table = TablePage::new(p);
+/**
+ * The first entry of the catalog is [FORMAT_ENTRY] [CATALOG_MAGIC] [version (2)], written when the
+ * catalog is still empty. Catalogs from before it existed don't start with it, their entries are
+ * laid out differently so they are refused instead of being misread.
+ */
+const CATALOG_MAGIC: &[u8; 8] = b"TOYSQLDB";
+const CATALOG_FORMAT_VERSION: u16 = 1;
 
-            // TODO look through next page
-            for tuple_id in 0..table.get_num_tuples() {
-                let tuple_id = TupleId(tuple_id.into());
-                let table_schema_tuple = table.get_tuple(tuple_id);
-                let table_schema = TableSchema::deserialize(table_schema_tuple);
-                tables.push(table_schema);
+pub fn load_catalog(buffer_pool: &mut BufferPoolManager) -> Result<Vec<TableSchema>, TError> {
+    load_entries(buffer_pool, TABLE_ENTRY)?.into_iter().map(TableSchema::deserialize).collect()
+}
+
+pub fn insert_table(buffer_pool: &mut BufferPoolManager, table_schema: &TableSchema) -> Result<(), TError> {
+    add_entry(buffer_pool, TABLE_ENTRY, table_schema.serialize()?)?;
+    Ok(())
+}
+
+// swaps the entry of the table called name for the schema, which may have another name
+pub fn replace_table(buffer_pool: &mut BufferPoolManager, name: &str, table_schema: &TableSchema) -> Result<(), TError> {
+    let matches = |entry: &[u8]| Ok(TableSchema::deserialize(entry.to_vec())?.name == name);
+    match replace_entry(buffer_pool, TABLE_ENTRY, matches, table_schema.serialize()?)? {
+        true => Ok(()),
+        false => Err(TError::ExecutionError(INTERNAL_ERROR, format!("table {:?} not found in catalog", name))),
+    }
+}
+
+// marks the catalog entry of the table as deleted, its space is reused once its catalog page is full
+// and its pages are not freed here
+pub fn delete_table(buffer_pool: &mut BufferPoolManager, name: &str) -> Result<(), TError> {
+    match find_entry(buffer_pool, TABLE_ENTRY, |entry| Ok(TableSchema::deserialize(entry.to_vec())?.name == name), None)? {
+        Some(record_id) => catalog_page(buffer_pool, record_id.page_id)?.delete_tuple(&record_id.tuple_id),
+        None => Err(TError::ExecutionError(INTERNAL_ERROR, format!("table {:?} not found in catalog", name))),
+    }
+}
+
+pub fn insert_sequence(buffer_pool: &mut BufferPoolManager, sequence: &SequenceSchema) -> Result<(), TError> {
+    add_entry(buffer_pool, SEQUENCE_ENTRY, sequence.serialize()?)?;
+    Ok(())
+}
+
+pub fn load_sequences(buffer_pool: &mut BufferPoolManager) -> Result<Vec<SequenceSchema>, TError> {
+    load_entries(buffer_pool, SEQUENCE_ENTRY)?.iter().map(|entry| SequenceSchema::deserialize(entry)).collect()
+}
+
+pub fn find_sequence(buffer_pool: &mut BufferPoolManager, name: &str) -> Result<Option<SequenceSchema>, TError> {
+    Ok(load_sequences(buffer_pool)?.into_iter().find(|s| s.name == name))
+}
+
+// writes the state of the sequence back after a value was taken
+pub fn update_sequence(buffer_pool: &mut BufferPoolManager, sequence: &SequenceSchema) -> Result<(), TError> {
+    match find_entry(buffer_pool, SEQUENCE_ENTRY, |entry| Ok(SequenceSchema::deserialize(entry)?.name == sequence.name), None)? {
+        Some(record_id) => {
+            let mut entry = vec![SEQUENCE_ENTRY];
+            entry.extend(sequence.serialize()?);
+            catalog_page(buffer_pool, record_id.page_id)?.update_tuple(&record_id.tuple_id, &entry)
+        },
+        None => Err(TError::ExecutionError(INTERNAL_ERROR, format!("sequence {:?} not found in catalog", sequence.name))),
+    }
+}
+
+pub fn replace_sequence(buffer_pool: &mut BufferPoolManager, name: &str, sequence: &SequenceSchema) -> Result<(), TError> {
+    let matches = |entry: &[u8]| Ok(SequenceSchema::deserialize(entry)?.name == name);
+    match replace_entry(buffer_pool, SEQUENCE_ENTRY, matches, sequence.serialize()?)? {
+        true => Ok(()),
+        false => Err(TError::ExecutionError(INTERNAL_ERROR, format!("sequence {:?} not found in catalog", name))),
+    }
+}
+
+pub fn delete_sequence(buffer_pool: &mut BufferPoolManager, name: &str) -> Result<(), TError> {
+    match find_entry(buffer_pool, SEQUENCE_ENTRY, |entry| Ok(SequenceSchema::deserialize(entry)?.name == name), None)? {
+        Some(record_id) => catalog_page(buffer_pool, record_id.page_id)?.delete_tuple(&record_id.tuple_id),
+        None => Err(TError::ExecutionError(INTERNAL_ERROR, format!("sequence {:?} not found in catalog", name))),
+    }
+}
+
+/**
+ * The catalog starts at page 0 and goes on in the pages linked from it through their next page id,
+ * like the pages of a table. Only the first page has the format entry.
+ */
+fn catalog_page<'a>(buffer_pool: &mut BufferPoolManager<'a>, page_id: u32) -> Result<TablePage<'a>, TError> {
+    if page_id != 0 {
+        return Ok(TablePage::new(buffer_pool.fetch_page(PageId(page_id.try_into().unwrap()))?));
+    }
+    let mut table = TablePage::new(buffer_pool.get_catalog_page()?);
+    check_format(&mut table)?;
+    Ok(table)
+}
+
+fn format_entry(version: u16) -> Vec<u8> {
+    let mut entry = vec![FORMAT_ENTRY];
+    entry.extend(CATALOG_MAGIC);
+    entry.extend(version.to_le_bytes());
+    entry
+}
+
+// an empty catalog is given the current format, any other one has to have it already
+fn check_format(table: &mut TablePage) -> Result<(), TError> {
+    if table.get_num_tuples() == 0 {
+        return match table.insert_tuple(format_entry(CATALOG_FORMAT_VERSION)) {
+            Some(_) => Ok(()),
+            None => Err(TError::StorageError(String::from("catalog page has no room for its format entry"))),
+        };
+    }
+    let entry = table.get_tuple(TupleId(0))?;
+    match entry.strip_prefix(&[FORMAT_ENTRY]).and_then(|rest| rest.strip_prefix(CATALOG_MAGIC)) {
+        Some(version) if version == CATALOG_FORMAT_VERSION.to_le_bytes() => Ok(()),
+        Some(version) => Err(TError::StorageError(format!(
+            "catalog has format version {}, this version of the database reads version {}",
+            version.try_into().map_or(String::from("?"), |v| u16::from_le_bytes(v).to_string()), CATALOG_FORMAT_VERSION
+        ))),
+        None => Err(TError::StorageError(String::from(
+            "catalog was written by an older version of the database without a format version and can't be read, move the data file away to start over"
+        ))),
+    }
+}
+
+// the live entries of the given kind on all catalog pages, without their kind
+fn load_entries(buffer_pool: &mut BufferPoolManager, kind: u8) -> Result<Vec<Vec<u8>>, TError> {
+    let mut entries = Vec::new();
+    let mut next_page_id = Some(0);
+    while let Some(page_id) = next_page_id {
+        let table = catalog_page(buffer_pool, page_id)?;
+        for tuple_id in (0..usize::from(table.get_num_tuples())).map(TupleId) {
+            if table.is_deleted(&tuple_id) {
+                continue;
+            }
+            let entry = table.get_tuple(tuple_id)?;
+            if entry.first() == Some(&kind) {
+                entries.push(entry[1..].to_vec());
             }
-            
+        }
+        next_page_id = table.get_next_page_id();
+    }
+    Ok(entries)
+}
+
+/**
+ * The new entry is written before the old one is deleted, so when the catalog has no room for it
+ * the old entry is still there. Returns false if there is no entry that matches.
+ */
+fn replace_entry(buffer_pool: &mut BufferPoolManager, kind: u8, matches: impl Fn(&[u8]) -> Result<bool, TError>, data: Vec<u8>) -> Result<bool, TError> {
+    if find_entry(buffer_pool, kind, &matches, None)?.is_none() {
+        return Ok(false);
+    }
+    // the new entry can land on a page before the old one, so the old one is the match that isn't it
+    let added = add_entry(buffer_pool, kind, data)?;
+    match find_entry(buffer_pool, kind, &matches, Some(&added))? {
+        Some(record_id) => catalog_page(buffer_pool, record_id.page_id)?.delete_tuple(&record_id.tuple_id)?,
+        None => return Err(TError::StorageError(String::from("catalog entry disappeared while it was replaced"))),
+    }
+    Ok(true)
+}
+
+// the entry goes on the first catalog page with room for it, when none has a new page is linked after the last one
+fn add_entry(buffer_pool: &mut BufferPoolManager, kind: u8, data: Vec<u8>) -> Result<RecordId, TError> {
+    let mut entry = vec![kind];
+    entry.extend(data);
+    let mut page_id = 0;
+    loop {
+        let mut table = catalog_page(buffer_pool, page_id)?;
+        if let Some(tuple_id) = table.insert_tuple(entry.clone()) {
+            return Ok(RecordId { page_id, tuple_id });
+        }
+        // deleted entries keep their space until the page runs out of it, compacting renumbers the
+        // entries of the page, which is fine as long as it is done before anything is found on it
+        table.compact()?;
+        if let Some(tuple_id) = table.insert_tuple(entry.clone()) {
+            return Ok(RecordId { page_id, tuple_id });
+        }
+        match table.get_next_page_id() {
+            Some(next_page_id) => page_id = next_page_id,
+            None => return append_catalog_page(buffer_pool, table, entry),
+        }
+    }
+}
+
+// the page is only linked once the entry is on it, an entry too big for any page leaves the catalog as it was
+fn append_catalog_page(buffer_pool: &mut BufferPoolManager, mut last: TablePage, entry: Vec<u8>) -> Result<RecordId, TError> {
+    let new_page = buffer_pool.new_page()?;
+    let page_id = new_page.borrow().get_page_id().unwrap();
+    let mut table = TablePage::new(new_page);
+    match table.insert_tuple(entry) {
+        Some(tuple_id) => {
+            last.set_next_page_id(page_id.0.try_into().unwrap());
+            Ok(RecordId { page_id: page_id.0.try_into().unwrap(), tuple_id })
         },
-        None => {}
+        None => {
+            // unpin the page so it can be deleted
+            std::mem::drop(table);
+            buffer_pool.delete_page(&page_id);
+            Err(catalog_full())
+        },
+    }
+}
+
+// a limit of the catalog, nothing in it is damaged
+fn catalog_full() -> TError {
+    TError::ExecutionError(PROGRAM_LIMIT_EXCEEDED, format!("catalog entry is too big, a schema or sequence has to fit in a catalog page of {} bytes", PAGE_SIZE))
+}
+
+// the live entry of the given kind that matches on any catalog page, other than the one to skip,
+// matches is passed the entry without its kind
+fn find_entry(buffer_pool: &mut BufferPoolManager, kind: u8, matches: impl Fn(&[u8]) -> Result<bool, TError>, skip: Option<&RecordId>) -> Result<Option<RecordId>, TError> {
+    let mut next_page_id = Some(0);
+    while let Some(page_id) = next_page_id {
+        let table = catalog_page(buffer_pool, page_id)?;
+        for tuple_id in (0..usize::from(table.get_num_tuples())).map(TupleId) {
+            if table.is_deleted(&tuple_id) || skip.is_some_and(|skip| skip.page_id == page_id && skip.tuple_id == tuple_id) {
+                continue;
+            }
+            let entry = table.get_tuple(tuple_id.clone())?;
+            if entry.first() == Some(&kind) && matches(&entry[1..])? {
+                return Ok(Some(RecordId { page_id, tuple_id }));
+            }
+        }
+        next_page_id = table.get_next_page_id();
     }
-    tables
-}
\ No newline at end of file
+    Ok(None)
+}
@@ -0,0 +1,102 @@
+use crate::parse::scanner::{OBJECT_NOT_IN_PREREQUISITE_STATE, SEQUENCE_GENERATOR_LIMIT_EXCEEDED, TError};
+
+use super::table_schema::{read_bytes, read_name, read_u8, write_name};
+
+
+/**
+ * A sequence hands out increasing numbers with nextval. Its state is part of its
+ * catalog entry, which is rewritten in place every time a value is taken.
+ */
+#[derive(Clone, Debug)]
+pub struct SequenceSchema {
+    pub name: String,
+    pub start: i64,
+    pub increment: i64,
+    // the last value handed out, only meaningful once called is set
+    pub last_value: i64,
+    pub called: bool,
+    // the table of the identity column the sequence was made for, it is dropped along with it
+    pub owner: Option<String>,
+}
+
+impl SequenceSchema {
+    pub fn new(name: String, start: Option<i64>, increment: Option<i64>, owner: Option<String>) -> SequenceSchema {
+        let start = start.unwrap_or(1);
+        SequenceSchema { name, start, increment: increment.unwrap_or(1), last_value: start, called: false, owner }
+    }
+
+    pub fn next_value(&mut self) -> Result<i64, TError> {
+        let value = if self.called {
+            match self.last_value.checked_add(self.increment) {
+                Some(value) => value,
+                None => return Err(TError::ExecutionError(SEQUENCE_GENERATOR_LIMIT_EXCEEDED, format!("nextval: reached maximum value of sequence {:?}", self.name))),
+            }
+        } else {
+            self.start
+        };
+        self.last_value = value;
+        self.called = true;
+        Ok(value)
+    }
+
+    // since there is one session, the current value is the last one nextval handed out
+    pub fn current_value(&self) -> Result<i64, TError> {
+        if !self.called {
+            return Err(TError::ExecutionError(OBJECT_NOT_IN_PREREQUISITE_STATE, format!("currval of sequence {:?} is not yet defined", self.name)));
+        }
+        Ok(self.last_value)
+    }
+
+    pub fn serialize(&self) -> Result<Vec<u8>, TError> {
+        // [name length] [name] [start (8)] [increment (8)] [last value (8)] [called]
+        // [owner length] [owner]
+        // where an owner of length 0 means there is none
+        // taking a value doesn't change the size, so the entry can be overwritten in place
+        let mut res = Vec::new();
+        write_name("sequence name", &self.name, &mut res)?;
+        res.extend(self.start.to_le_bytes());
+        res.extend(self.increment.to_le_bytes());
+        res.extend(self.last_value.to_le_bytes());
+        res.push(u8::from(self.called));
+        write_name("table name", self.owner.as_deref().unwrap_or(""), &mut res)?;
+        Ok(res)
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<SequenceSchema, TError> {
+        let mut bytes_read = 0;
+        let name = read_name(data, &mut bytes_read)?;
+        let start = read_i64(data, &mut bytes_read)?;
+        let increment = read_i64(data, &mut bytes_read)?;
+        let last_value = read_i64(data, &mut bytes_read)?;
+        let called = read_u8(data, &mut bytes_read)? != 0;
+        let owner = Some(read_name(data, &mut bytes_read)?).filter(|o| !o.is_empty());
+        Ok(SequenceSchema { name, start, increment, last_value, called, owner })
+    }
+}
+
+fn read_i64(data: &[u8], bytes_read: &mut usize) -> Result<i64, TError> {
+    Ok(i64::from_le_bytes(read_bytes(data, bytes_read, 8)?.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SequenceSchema;
+
+    #[test]
+    fn next_value_and_catalog_round_trip() {
+        let mut s = SequenceSchema::new("s".to_owned(), Some(10), Some(5), Some("t".to_owned()));
+        assert!(s.current_value().is_err());
+        let size = s.serialize().unwrap().len();
+        assert_eq!(s.next_value().unwrap(), 10);
+        assert_eq!(s.next_value().unwrap(), 15);
+        assert_eq!(s.serialize().unwrap().len(), size);
+
+        let mut s = SequenceSchema::deserialize(&s.serialize().unwrap()).unwrap();
+        assert_eq!(s.owner.as_deref(), Some("t"));
+        assert_eq!(s.current_value().unwrap(), 15);
+        assert_eq!(s.next_value().unwrap(), 20);
+
+        s.last_value = i64::MAX;
+        assert!(s.next_value().is_err());
+    }
+}
@@ -1,135 +1,786 @@
-use std::str;
+use std::{borrow::Cow, fmt, str};
 
-#[derive(Clone, Debug)]
+use crate::{config::config::PAGE_SIZE, parse::{scanner::{TError, NAME_TOO_LONG, NUMERIC_VALUE_OUT_OF_RANGE, PROGRAM_LIMIT_EXCEEDED}, token::LiteralValue}, types::{array::{decode_array, encode_array}, datetime::Interval, decimal::Decimal, json::Json, numeric::normalize_float}};
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum ColumnType {
+    SmallInt,
     Int,
-    Bool
+    BigInt,
+    Real,
+    Double,
+    // precision and scale, without them any decimal fits
+    Decimal(Option<(u8, u8)>),
+    Date,
+    Time,
+    Timestamp,
+    Interval,
+    Bool,
+    Text,
+    Bytea,
+    Json,
+    Uuid,
+    Array(Box<ColumnType>),
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnType::SmallInt => write!(f, "smallint"),
+            ColumnType::Int => write!(f, "integer"),
+            ColumnType::BigInt => write!(f, "bigint"),
+            ColumnType::Real => write!(f, "real"),
+            ColumnType::Double => write!(f, "double precision"),
+            ColumnType::Decimal(Some((precision, scale))) => write!(f, "decimal({},{})", precision, scale),
+            ColumnType::Decimal(None) => write!(f, "decimal"),
+            ColumnType::Date => write!(f, "date"),
+            ColumnType::Time => write!(f, "time"),
+            ColumnType::Timestamp => write!(f, "timestamp"),
+            ColumnType::Interval => write!(f, "interval"),
+            ColumnType::Bool => write!(f, "bool"),
+            ColumnType::Text => write!(f, "text"),
+            ColumnType::Bytea => write!(f, "bytea"),
+            ColumnType::Json => write!(f, "json"),
+            ColumnType::Uuid => write!(f, "uuid"),
+            ColumnType::Array(element_type) => write!(f, "{}[]", element_type),
+        }
+    }
 }
 
-pub fn encode_column_type(t: &ColumnType) -> u8 {
+// [type] and for decimals [precision][scale], where a precision of 0 means there is none,
+// and for arrays the type of their elements
+// bigint is 0 because ints used to be 8 bytes
+pub fn encode_column_type(t: &ColumnType, res: &mut Vec<u8>) {
     match t {
-        ColumnType::Int => 0,
-        ColumnType::Bool => 1,
+        ColumnType::BigInt => res.push(0),
+        ColumnType::Bool => res.push(1),
+        ColumnType::Text => res.push(2),
+        ColumnType::SmallInt => res.push(3),
+        ColumnType::Int => res.push(4),
+        ColumnType::Real => res.push(5),
+        ColumnType::Double => res.push(6),
+        ColumnType::Decimal(params) => {
+            let (precision, scale) = params.unwrap_or((0, 0));
+            res.extend([7, precision, scale]);
+        },
+        ColumnType::Date => res.push(8),
+        ColumnType::Time => res.push(9),
+        ColumnType::Timestamp => res.push(10),
+        ColumnType::Interval => res.push(11),
+        ColumnType::Bytea => res.push(12),
+        ColumnType::Json => res.push(13),
+        ColumnType::Array(element_type) => {
+            res.push(14);
+            encode_column_type(element_type, res);
+        },
+        ColumnType::Uuid => res.push(15),
     }
 }
 
-pub fn decode_column_type(u: u8) -> ColumnType {
-    match u {
-        0 => ColumnType::Int,
+pub fn decode_column_type(data: &[u8], bytes_read: &mut usize) -> Result<ColumnType, TError> {
+    let t = read_u8(data, bytes_read)?;
+    let column_type = match t {
+        0 => ColumnType::BigInt,
         1 => ColumnType::Bool,
-        _ => panic!("unexpected column type")
-    }
+        2 => ColumnType::Text,
+        3 => ColumnType::SmallInt,
+        4 => ColumnType::Int,
+        5 => ColumnType::Real,
+        6 => ColumnType::Double,
+        7 => {
+            let (precision, scale) = (read_u8(data, bytes_read)?, read_u8(data, bytes_read)?);
+            ColumnType::Decimal(Some((precision, scale)).filter(|(p, _)| *p != 0))
+        },
+        8 => ColumnType::Date,
+        9 => ColumnType::Time,
+        10 => ColumnType::Timestamp,
+        11 => ColumnType::Interval,
+        12 => ColumnType::Bytea,
+        13 => ColumnType::Json,
+        14 => ColumnType::Array(Box::new(decode_column_type(data, bytes_read)?)),
+        15 => ColumnType::Uuid,
+        _ => return Err(TError::StorageError(format!("unknown column type {} in the catalog", t))),
+    };
+    Ok(column_type)
 }
 
 #[derive(Clone)]
 pub struct Column {
     pub name: String,
-    pub column_type: ColumnType
+    pub column_type: ColumnType,
+    // position in the tuple layout, never reused after the column is dropped
+    pub id: u16,
+    // schema versions the column was added and dropped in
+    pub added_in: u16,
+    pub dropped_in: Option<u16>,
+    // value for tuples written before the column was added
+    pub default: Option<LiteralValue>,
+    // source of the DEFAULT expression, evaluated for inserts that leave the column out
+    pub default_expr: Option<String>,
+    // identity column whose values only come from its sequence
+    pub generated_always: bool,
 }
 
 impl Column {
     pub fn new(name: String, column_type: ColumnType) -> Column {
-        Column {name, column_type}
+        Column {name, column_type, id: 0, added_in: 0, dropped_in: None, default: None, default_expr: None, generated_always: false}
+    }
+}
+
+// rows written to the table have to make expr true or null
+#[derive(Clone, Debug)]
+pub struct CheckSchema {
+    pub name: String,
+    // source of the expression, bound again whenever rows are written
+    pub expr: String,
+}
+
+// a b+ tree over some columns of the table, or over expressions of them, see storage::b_plus_tree
+#[derive(Clone, Debug)]
+pub struct IndexSchema {
+    pub name: String,
+    // ids of the indexed columns, in key order
+    pub columns: Vec<u16>,
+    // sources of the indexed expressions, bound again whenever rows are written,
+    // an index is either over columns or over expressions
+    pub exprs: Vec<String>,
+    pub unique: bool,
+    pub primary: bool,
+    pub root_page_id: u32,
+}
+
+impl IndexSchema {
+    /**
+     * The key a row is stored under in an index over columns, None when one of them is null. Nulls never
+     * conflict with each other so such rows are left out of the index.
+     *
+     * Keys compare bytewise in the same order as the values: ints are big endian with the
+     * sign bit flipped, text has its zero bytes escaped as 0 255 and ends with 0 1.
+     * Json keys are its binary encoding escaped like text, they are only good for equality.
+     * Arrays have 1 and the key of each element, or 2 for a null one, and end with 0.
+     */
+    pub fn key(&self, schema: &TableSchema, row: &[LiteralValue]) -> Option<Vec<u8>> {
+        let values: Vec<&LiteralValue> = self.columns.iter().map(|id| &row[schema.column_position(*id)]).collect();
+        encode_key(&values)
     }
 }
 
+// see IndexSchema::key
+pub fn encode_key(values: &[&LiteralValue]) -> Option<Vec<u8>> {
+    let mut res = Vec::new();
+    for value in values {
+        match value {
+            LiteralValue::IntValue(i) => res.extend(((*i as u64) ^ (1 << 63)).to_be_bytes()),
+            // flipping the sign bit puts positive floats after negative ones,
+            // flipping all the bits of negative ones puts larger ones first
+            LiteralValue::FloatValue(f) => {
+                let bits = normalize_float(*f).to_bits();
+                let bits = if bits >> 63 == 1 { !bits } else { bits ^ (1 << 63) };
+                res.extend(bits.to_be_bytes());
+            },
+            LiteralValue::DecimalValue(d) => d.encode_key(&mut res),
+            LiteralValue::DateValue(d) => res.extend(((*d as u32) ^ (1 << 31)).to_be_bytes()),
+            LiteralValue::TimeValue(t) | LiteralValue::TimestampValue(t) => res.extend(((*t as u64) ^ (1 << 63)).to_be_bytes()),
+            LiteralValue::IntervalValue(i) => res.extend(((i.total_micros() as u128) ^ (1 << 127)).to_be_bytes()),
+            LiteralValue::BooleanValue(b) => res.push(u8::from(*b)),
+            LiteralValue::UuidValue(u) => res.extend(u),
+            LiteralValue::StringValue(_) | LiteralValue::BytesValue(_) | LiteralValue::JsonValue(_) => {
+                for byte in varlena_bytes(value).unwrap().iter() {
+                    res.push(*byte);
+                    if *byte == 0 {
+                        res.push(255);
+                    }
+                }
+                res.extend([0, 1]);
+            },
+            LiteralValue::ArrayValue(elements) => {
+                for element in elements {
+                    match encode_key(&[element]) {
+                        Some(key) => {
+                            res.push(1);
+                            res.extend(key);
+                        },
+                        None => res.push(2),
+                    }
+                }
+                res.push(0);
+            },
+            LiteralValue::NullValue => return None,
+        }
+    }
+    Some(res)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReferentialAction {
+    Restrict,
+    Cascade,
+    SetNull,
+}
+
+fn encode_referential_action(action: ReferentialAction) -> u8 {
+    match action {
+        ReferentialAction::Restrict => 0,
+        ReferentialAction::Cascade => 1,
+        ReferentialAction::SetNull => 2,
+    }
+}
+
+fn decode_referential_action(u: u8) -> Result<ReferentialAction, TError> {
+    match u {
+        0 => Ok(ReferentialAction::Restrict),
+        1 => Ok(ReferentialAction::Cascade),
+        2 => Ok(ReferentialAction::SetNull),
+        _ => Err(TError::StorageError(format!("unknown referential action {} in the catalog", u))),
+    }
+}
+
+/**
+ * Rows of this table whose columns aren't null have to match a row of the referenced table.
+ * The referenced columns always have a unique index, columns are kept in the order of that
+ * index so a row's values can be looked up in it directly.
+ */
+#[derive(Clone, Debug)]
+pub struct ForeignKeySchema {
+    pub name: String,
+    pub columns: Vec<u16>,
+    pub table: String,
+    pub referenced_columns: Vec<u16>,
+    pub on_delete: ReferentialAction,
+    pub on_update: ReferentialAction,
+}
+
 #[derive(Clone)]
 pub struct TableSchema {
     pub name: String,
     pub first_page_id: u32,
-    pub columns: Vec<Column>
+    pub columns: Vec<Column>,
+    // bumped by every change to the tuple layout
+    pub version: u16,
+    // kept so tuples written before they were dropped can still be read
+    pub dropped_columns: Vec<Column>,
+    pub indexes: Vec<IndexSchema>,
+    pub foreign_keys: Vec<ForeignKeySchema>,
+    pub checks: Vec<CheckSchema>,
 }
 
 impl TableSchema {
-    pub fn new(name: String, columns: Vec<Column>, first_page_id: u32) -> TableSchema {
-        TableSchema {name, first_page_id, columns}
+    pub fn new(name: String, mut columns: Vec<Column>, first_page_id: u32) -> TableSchema {
+        for (id, column) in columns.iter_mut().enumerate() {
+            column.id = u16::try_from(id).unwrap();
+        }
+        TableSchema {name, first_page_id, columns, version: 0, dropped_columns: Vec::new(), indexes: Vec::new(), foreign_keys: Vec::new(), checks: Vec::new()}
     }
-    pub fn serialize(&self) -> Vec<u8> {
-        // layout in bytes
-        // [length of name] [name] [page id of first page] [number of columns] [column entries]+
-        // where each column entry is [column type][column name length][column name]
+
+    pub fn add_column(&mut self, name: String, column_type: ColumnType, default: Option<LiteralValue>, default_expr: Option<String>) {
+        self.version += 1;
+        let id = self.columns.iter().chain(&self.dropped_columns).map(|c| c.id + 1).max().unwrap_or(0);
+        self.columns.push(Column {name, column_type, id, added_in: self.version, dropped_in: None, default, default_expr, generated_always: false});
+    }
+
+    pub fn drop_column(&mut self, name: &str) {
+        self.version += 1;
+        let index = self.columns.iter().position(|c| c.name == name).unwrap();
+        let mut column = self.columns.remove(index);
+        column.dropped_in = Some(self.version);
+        self.dropped_columns.push(column);
+    }
+
+    pub fn column_id(&self, name: &str) -> Option<u16> {
+        self.columns.iter().find(|c| c.name == name).map(|c| c.id)
+    }
+
+    pub fn column_name(&self, id: u16) -> &str {
+        &self.columns.iter().find(|c| c.id == id).unwrap().name
+    }
+
+    // where the column is in decoded rows
+    pub fn column_position(&self, id: u16) -> usize {
+        self.columns.iter().position(|c| c.id == id).unwrap()
+    }
+
+    // the columns stored in a tuple written under the given version, in order
+    fn columns_at_version(&self, version: u16) -> Vec<&Column> {
+        let mut columns: Vec<&Column> = self.columns.iter().chain(&self.dropped_columns)
+            .filter(|c| c.added_in <= version && c.dropped_in.is_none_or(|dropped_in| version < dropped_in))
+            .collect();
+        columns.sort_by_key(|c| c.id);
+        columns
+    }
+
+    /**
+     * Tuple layout:
+     *  | schema version (2) | null bitmap | values of the non null columns |
+     *
+     * A tuple holds the columns of the schema version it was written under and the null bitmap
+     * has one bit per column. Columns added after that read as their default, columns dropped
+     * since are skipped, so altering a table doesn't rewrite its tuples.
+     *
+     * While the tuple is bigger than TOAST_TUPLE_THRESHOLD its largest varlena values are
+     * given to store, which writes them to overflow pages, and only their length, with the
+     * TOASTED bit set, and the id of the first page are kept.
+     */
+    pub fn encode_tuple(&self, values: &[LiteralValue], store: &mut dyn FnMut(&[u8]) -> Result<u32, TError>) -> Result<Vec<u8>, TError> {
         let mut res = Vec::new();
+        res.extend(self.version.to_le_bytes());
+        let mut null_bitmap = vec![0u8; values.len().div_ceil(8)];
+        for (index, value) in values.iter().enumerate() {
+            if let LiteralValue::NullValue = value {
+                null_bitmap[index / 8] |= 1 << (index % 8);
+            }
+        }
+        res.extend(null_bitmap);
 
-        let name_length = self.name.len();
-        let name_length = u8::try_from(name_length).unwrap(); // name length has to fit in u8
+        let mut encoded: Vec<Vec<u8>> = values.iter().zip(&self.columns).map(|(value, column)| {
+            let mut res = Vec::new();
+            if !matches!(value, LiteralValue::NullValue) {
+                encode_value(value, &column.column_type, &mut res)?;
+            }
+            Ok(res)
+        }).collect::<Result<_, TError>>()?;
+        let mut size = res.len() + encoded.iter().map(|e| e.len()).sum::<usize>();
+        let mut candidates = Vec::new();
+        for (index, (value, column)) in values.iter().zip(&self.columns).enumerate() {
+            if let Some(bytes) = stored_bytes(value, &column.column_type)?.filter(|b| b.len() > 4) {
+                candidates.push((index, bytes));
+            }
+        }
+        candidates.sort_by_key(|(index, _)| encoded[*index].len());
+        while size > TOAST_TUPLE_THRESHOLD {
+            let (index, bytes) = match candidates.pop() {
+                Some(candidate) => candidate,
+                None => break,
+            };
+            let mut pointer = (u32::try_from(bytes.len()).unwrap() | TOASTED).to_le_bytes().to_vec();
+            pointer.extend(store(&bytes)?.to_le_bytes());
+            size = size - encoded[index].len() + pointer.len();
+            encoded[index] = pointer;
+        }
+        res.extend(encoded.concat());
+        Ok(res)
+    }
 
-        res.push(name_length);
-        res.extend(self.name.as_bytes());
+    // load reads back the values encode_tuple moved to overflow pages, given the first page and their length
+    pub fn decode_tuple(&self, tuple: &[u8], load: &mut dyn FnMut(u32, usize) -> Result<Vec<u8>, TError>) -> Result<Vec<LiteralValue>, TError> {
+        // see encode_tuple
+        let truncated = || TError::StorageError(String::from("tuple header is truncated"));
+        let version = u16::from_le_bytes(tuple.get(0..2).ok_or_else(truncated)?.try_into().unwrap());
+        let stored_columns = self.columns_at_version(version);
+        let null_bitmap = tuple.get(2..2 + stored_columns.len().div_ceil(8)).ok_or_else(truncated)?;
+        let mut bytes_read = 2 + null_bitmap.len();
+
+        let mut stored_values = Vec::new();
+        for (index, column) in stored_columns.iter().enumerate() {
+            let value = if null_bitmap[index / 8] & (1 << (index % 8)) != 0 {
+                LiteralValue::NullValue
+            } else if let Some((page_id, length)) = toast_pointer(&column.column_type, tuple, &mut bytes_read) {
+                let bytes = load(page_id, length)?;
+                match &column.column_type {
+                    ColumnType::Bytea => LiteralValue::BytesValue(bytes),
+                    ColumnType::Json => LiteralValue::JsonValue(Json::decode(&bytes, &mut 0)?),
+                    ColumnType::Array(element_type) => LiteralValue::ArrayValue(decode_array(&bytes, element_type)?),
+                    _ => LiteralValue::StringValue(String::from_utf8(bytes).map_err(|_| not_utf8())?),
+                }
+            } else {
+                decode_value(&column.column_type, tuple, &mut bytes_read)?
+            };
+            stored_values.push((column.id, value));
+        }
+
+        Ok(self.columns.iter().map(|column| {
+            match stored_values.iter().find(|(id, _)| *id == column.id) {
+                Some((_, value)) => value.clone(),
+                None => column.default.clone().unwrap_or(LiteralValue::NullValue),
+            }
+        }).collect())
+    }
+
+    // the first overflow pages of the values of a tuple that were moved out of it, including dropped columns
+    pub fn overflow_pages(&self, tuple: &[u8]) -> Result<Vec<u32>, TError> {
+        let mut pages = Vec::new();
+        // only the pointers are needed, so the values aren't read
+        self.decode_tuple(tuple, &mut |page_id, _| {
+            pages.push(page_id);
+            Ok(Vec::new())
+        })?;
+        Ok(pages)
+    }
+
+    pub fn serialize(&self) -> Result<Vec<u8>, TError> {
+        // layout in bytes
+        // [length of name] [name] [page id of first page] [schema version (2)]
+        // [number of columns] [column entries]+ [number of dropped columns] [column entries]+
+        // [number of indexes] [index entries]+ [number of foreign keys] [foreign key entries]+
+        // [number of checks] [check entries]+
+        // where each column entry is
+        // [column type][column name length][column name][id (2)][added in (2)][dropped in (2)][has default][default]
+        // [default expression length (2)][default expression][generated always]
+        // dropped in is 0 for columns that weren't dropped, a drop always makes a new version
+        // and each index entry is
+        // [index name length][index name][flags][root page id (4)][number of columns][column ids (2)]+
+        // [number of expressions][expression length (2)][expression]+
+        // with flags 1 for unique and 2 for primary key
+        // and each foreign key entry is
+        // [name length][name][referenced table name length][referenced table name]
+        // [number of columns][column id (2)][referenced column id (2)]+ [on delete][on update]
+        // and each check entry is
+        // [name length][name][expression length (2)][expression]
+        // where an expression of length 0 means there is none
+        let mut res = Vec::new();
+
+        write_name("table name", &self.name, &mut res)?;
 
         res.extend(self.first_page_id.to_le_bytes());
+        res.extend(self.version.to_le_bytes());
 
-        let num_columns = u8::try_from(self.columns.len()).unwrap();
-        res.push(num_columns);
+        for columns in [&self.columns, &self.dropped_columns] {
+            write_count("columns", columns.len(), &mut res)?;
 
-        for column in &self.columns {
-            res.push(encode_column_type(&column.column_type));
-            let col_len = u8::try_from(column.name.len()).unwrap();
-            res.push(col_len);
-            res.extend(column.name.as_bytes());
+            for column in columns {
+                encode_column_type(&column.column_type, &mut res);
+                write_name("column name", &column.name, &mut res)?;
+                res.extend(column.id.to_le_bytes());
+                res.extend(column.added_in.to_le_bytes());
+                res.extend(column.dropped_in.unwrap_or(0).to_le_bytes());
+                match &column.default {
+                    Some(LiteralValue::NullValue) | None => res.push(0),
+                    Some(default) => {
+                        res.push(1);
+                        encode_value(default, &column.column_type, &mut res)?;
+                    },
+                }
+                write_source(column.default_expr.as_deref().unwrap_or(""), &mut res)?;
+                res.push(u8::from(column.generated_always));
+            }
+        }
+
+        write_count("indexes", self.indexes.len(), &mut res)?;
+        for index in &self.indexes {
+            write_name("index name", &index.name, &mut res)?;
+            res.push(u8::from(index.unique) | (u8::from(index.primary) << 1));
+            res.extend(index.root_page_id.to_le_bytes());
+            write_count("index columns", index.columns.len(), &mut res)?;
+            for id in &index.columns {
+                res.extend(id.to_le_bytes());
+            }
+            write_count("index expressions", index.exprs.len(), &mut res)?;
+            for expr in &index.exprs {
+                write_source(expr, &mut res)?;
+            }
         }
 
-        res
+        write_count("foreign keys", self.foreign_keys.len(), &mut res)?;
+        for foreign_key in &self.foreign_keys {
+            write_name("constraint name", &foreign_key.name, &mut res)?;
+            write_name("table name", &foreign_key.table, &mut res)?;
+            write_count("foreign key columns", foreign_key.columns.len(), &mut res)?;
+            for (id, referenced_id) in foreign_key.columns.iter().zip(&foreign_key.referenced_columns) {
+                res.extend(id.to_le_bytes());
+                res.extend(referenced_id.to_le_bytes());
+            }
+            res.push(encode_referential_action(foreign_key.on_delete));
+            res.push(encode_referential_action(foreign_key.on_update));
+        }
+
+        write_count("check constraints", self.checks.len(), &mut res)?;
+        for check in &self.checks {
+            write_name("constraint name", &check.name, &mut res)?;
+            write_source(&check.expr, &mut res)?;
+        }
+
+        Ok(res)
     }
 
-    pub fn deserialize(data: Vec<u8>) -> TableSchema {
+    pub fn deserialize(data: Vec<u8>) -> Result<TableSchema, TError> {
         // see serialize
         let mut bytes_read = 0;
         
-        let name_len = usize::from(data[0]);
-        bytes_read += 1;
+        let name = read_name(&data, &mut bytes_read)?;
+
+        let first_page_id = read_u32(&data, &mut bytes_read)?;
+
+        let version = read_u16(&data, &mut bytes_read)?;
 
-        let name = &data[bytes_read..bytes_read+name_len];
-        let name: String = str::from_utf8(name).unwrap().to_string();
-        bytes_read += name_len;
+        let mut column_lists = Vec::new();
+        for _ in 0..2 {
+            let num_col = usize::from(read_u8(&data, &mut bytes_read)?);
 
-        let first_page_id = &data[bytes_read..bytes_read+4];
-        let first_page_id = u32::from_le_bytes(first_page_id.try_into().unwrap());
-        bytes_read += 4;
+            let mut columns = Vec::new();
 
-        let num_col = usize::from(data[bytes_read]);
-        bytes_read += 1;
+            loop {
+                if columns.len() == num_col {
+                    break;
+                }
+                let col_type = decode_column_type(&data, &mut bytes_read)?;
+                let col_name = read_name(&data, &mut bytes_read)?;
 
-        let mut columns = Vec::new();
+                let id = read_u16(&data, &mut bytes_read)?;
+                let added_in = read_u16(&data, &mut bytes_read)?;
+                let dropped_in = match read_u16(&data, &mut bytes_read)? {
+                    0 => None,
+                    dropped_in => Some(dropped_in),
+                };
+                let has_default = read_u8(&data, &mut bytes_read)? != 0;
+                let default = if has_default { Some(decode_value(&col_type, &data, &mut bytes_read)?) } else { None };
+                let default_expr = Some(read_source(&data, &mut bytes_read)?).filter(|s| !s.is_empty());
+                let generated_always = read_u8(&data, &mut bytes_read)? != 0;
 
-        loop {
-            if columns.len() == num_col {
-                break;
+                columns.push(Column {name: col_name, column_type: col_type, id, added_in, dropped_in, default, default_expr, generated_always});
             }
-            let col_type = decode_column_type(data[bytes_read]);
-            bytes_read += 1;
-            let col_name_len = usize::from(data[bytes_read]);
-            bytes_read += 1;
-            let col_name = str::from_utf8(&data[bytes_read..bytes_read+col_name_len]).unwrap().to_string();
-            bytes_read += col_name_len;
+            column_lists.push(columns);
+        }
+        let dropped_columns = column_lists.pop().unwrap_or_default();
+        let columns = column_lists.pop().unwrap_or_default();
+
+        let num_indexes = read_u8(&data, &mut bytes_read)?;
+        let mut indexes = Vec::new();
+        for _ in 0..num_indexes {
+            let name = read_name(&data, &mut bytes_read)?;
+            let flags = read_u8(&data, &mut bytes_read)?;
+            let root_page_id = read_u32(&data, &mut bytes_read)?;
+            let num_columns = read_u8(&data, &mut bytes_read)?;
+            let columns = (0..num_columns).map(|_| read_u16(&data, &mut bytes_read)).collect::<Result<_, _>>()?;
+            let num_exprs = read_u8(&data, &mut bytes_read)?;
+            let exprs = (0..num_exprs).map(|_| read_source(&data, &mut bytes_read)).collect::<Result<_, _>>()?;
+            indexes.push(IndexSchema {name, columns, exprs, unique: flags & 1 != 0, primary: flags & 2 != 0, root_page_id});
+        }
 
-            columns.push(Column::new(col_name, col_type));
+        let num_foreign_keys = read_u8(&data, &mut bytes_read)?;
+        let mut foreign_keys = Vec::new();
+        for _ in 0..num_foreign_keys {
+            let name = read_name(&data, &mut bytes_read)?;
+            let table = read_name(&data, &mut bytes_read)?;
+            let num_columns = read_u8(&data, &mut bytes_read)?;
+            let mut columns = Vec::new();
+            let mut referenced_columns = Vec::new();
+            for _ in 0..num_columns {
+                columns.push(read_u16(&data, &mut bytes_read)?);
+                referenced_columns.push(read_u16(&data, &mut bytes_read)?);
+            }
+            let on_delete = decode_referential_action(read_u8(&data, &mut bytes_read)?)?;
+            let on_update = decode_referential_action(read_u8(&data, &mut bytes_read)?)?;
+            foreign_keys.push(ForeignKeySchema {name, columns, table, referenced_columns, on_delete, on_update});
         }
 
+        let num_checks = read_u8(&data, &mut bytes_read)?;
+        let mut checks = Vec::new();
+        for _ in 0..num_checks {
+            let name = read_name(&data, &mut bytes_read)?;
+            let expr = read_source(&data, &mut bytes_read)?;
+            checks.push(CheckSchema {name, expr});
+        }
 
-        TableSchema::new(name, columns, first_page_id)
+        Ok(TableSchema {name, first_page_id, columns, version, dropped_columns, indexes, foreign_keys, checks})
     }
 }
 
+// [length] [utf-8 bytes], names have to fit in 255 bytes
+pub fn write_name(what: &str, name: &str, res: &mut Vec<u8>) -> Result<(), TError> {
+    let len = u8::try_from(name.len())
+        .map_err(|_| TError::ExecutionError(NAME_TOO_LONG, format!("{} {:?}... is longer than 255 bytes", what, name.chars().take(16).collect::<String>())))?;
+    res.push(len);
+    res.extend(name.as_bytes());
+    Ok(())
+}
+
+// the number of entries that follow, at most 255
+fn write_count(what: &str, count: usize, res: &mut Vec<u8>) -> Result<(), TError> {
+    let count = u8::try_from(count).map_err(|_| TError::ExecutionError(PROGRAM_LIMIT_EXCEEDED, format!("tables can have at most 255 {}", what)))?;
+    res.push(count);
+    Ok(())
+}
+
+// [length (2)] [utf-8 bytes]
+fn write_source(source: &str, res: &mut Vec<u8>) -> Result<(), TError> {
+    let len = u16::try_from(source.len()).map_err(|_| TError::ExecutionError(PROGRAM_LIMIT_EXCEEDED, String::from("expression is longer than 65535 bytes")))?;
+    res.extend(len.to_le_bytes());
+    res.extend(source.as_bytes());
+    Ok(())
+}
+
+fn read_source(data: &[u8], bytes_read: &mut usize) -> Result<String, TError> {
+    let len = usize::from(read_u16(data, bytes_read)?);
+    read_string(data, bytes_read, len)
+}
+
+// see write_name
+pub fn read_name(data: &[u8], bytes_read: &mut usize) -> Result<String, TError> {
+    let len = usize::from(read_u8(data, bytes_read)?);
+    read_string(data, bytes_read, len)
+}
+
+// len bytes of utf-8, which have to be in the data
+pub fn read_string(data: &[u8], bytes_read: &mut usize, len: usize) -> Result<String, TError> {
+    let bytes = read_bytes(data, bytes_read, len)?;
+    let string = str::from_utf8(bytes).map_err(|_| TError::StorageError(String::from("catalog entry has a name that isn't utf-8")))?;
+    Ok(string.to_owned())
+}
+
+// the next len bytes of a catalog entry, an entry that ends before them is a storage error
+pub fn read_bytes<'d>(data: &'d [u8], bytes_read: &mut usize, len: usize) -> Result<&'d [u8], TError> {
+    let bytes = data.get(*bytes_read..bytes_read.saturating_add(len)).ok_or_else(|| TError::StorageError(String::from("catalog entry is truncated")))?;
+    *bytes_read += len;
+    Ok(bytes)
+}
+
+pub fn read_u8(data: &[u8], bytes_read: &mut usize) -> Result<u8, TError> {
+    Ok(read_bytes(data, bytes_read, 1)?[0])
+}
+
+fn read_u16(data: &[u8], bytes_read: &mut usize) -> Result<u16, TError> {
+    Ok(u16::from_le_bytes(read_bytes(data, bytes_read, 2)?.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], bytes_read: &mut usize) -> Result<u32, TError> {
+    Ok(u32::from_le_bytes(read_bytes(data, bytes_read, 4)?.try_into().unwrap()))
+}
+
+// tuples bigger than this have values moved to overflow pages, so a page holds a few of them at least
+const TOAST_TUPLE_THRESHOLD: usize = PAGE_SIZE / 4;
+
+// set in the length of a varlena value in a tuple when its bytes are in overflow pages
+const TOASTED: u32 = 1 << 31;
+
+// the bytes of the values that can be moved to overflow pages, json is stored in its binary encoding
+fn varlena_bytes(value: &LiteralValue) -> Option<Cow<'_, [u8]>> {
+    match value {
+        LiteralValue::StringValue(s) => Some(Cow::Borrowed(s.as_bytes())),
+        LiteralValue::BytesValue(b) => Some(Cow::Borrowed(b)),
+        LiteralValue::JsonValue(j) => Some(Cow::Owned(j.to_bytes())),
+        _ => None,
+    }
+}
+
+// like varlena_bytes, including arrays, which need the type of their elements to be encoded
+fn stored_bytes<'v>(value: &'v LiteralValue, column_type: &ColumnType) -> Result<Option<Cow<'v, [u8]>>, TError> {
+    match (value, column_type) {
+        (LiteralValue::ArrayValue(elements), ColumnType::Array(element_type)) => {
+            let mut res = Vec::new();
+            encode_array(elements, element_type, &mut res)?;
+            Ok(Some(Cow::Owned(res)))
+        },
+        _ => Ok(varlena_bytes(value)),
+    }
+}
+
+// the first overflow page and the length of a value moved out of its tuple, None if it's stored in it
+fn toast_pointer(column_type: &ColumnType, tuple: &[u8], bytes_read: &mut usize) -> Option<(u32, usize)> {
+    if !matches!(column_type, ColumnType::Text | ColumnType::Bytea | ColumnType::Json | ColumnType::Array(_)) {
+        return None;
+    }
+    // a truncated pointer is left to decode_value to report
+    let header = u32::from_le_bytes(tuple.get(*bytes_read..*bytes_read+4)?.try_into().unwrap());
+    if header & TOASTED == 0 {
+        return None;
+    }
+    let page_id = u32::from_le_bytes(tuple.get(*bytes_read+4..*bytes_read+8)?.try_into().unwrap());
+    *bytes_read += 8;
+    Some((page_id, usize::try_from(header & !TOASTED).unwrap()))
+}
+
+// ints are 2, 4 or 8 bytes little endian, reals and doubles 4 and 8, decimals see Decimal::encode,
+// dates 4, times and timestamps 8, intervals see Interval::encode,
+// bools 1 byte and text, bytea and json are [length (4)] [bytes], json in its binary encoding
+// values are stored in the width of their column, the planner casts them to its type before
+pub fn encode_value(value: &LiteralValue, column_type: &ColumnType, res: &mut Vec<u8>) -> Result<(), TError> {
+    let out_of_range = |name: &str| TError::ExecutionError(NUMERIC_VALUE_OUT_OF_RANGE, format!("{} out of range", name));
+    match (value, column_type) {
+        (LiteralValue::IntValue(i), ColumnType::SmallInt) => res.extend(i16::try_from(*i).map_err(|_| out_of_range("smallint"))?.to_le_bytes()),
+        (LiteralValue::IntValue(i), ColumnType::Int) => res.extend(i32::try_from(*i).map_err(|_| out_of_range("integer"))?.to_le_bytes()),
+        (LiteralValue::IntValue(i), _) => res.extend(i.to_le_bytes()),
+        (LiteralValue::FloatValue(f), ColumnType::Real) => res.extend((*f as f32).to_le_bytes()),
+        (LiteralValue::FloatValue(f), _) => res.extend(f.to_le_bytes()),
+        (LiteralValue::DecimalValue(d), _) => d.encode(res),
+        (LiteralValue::DateValue(d), _) => res.extend(d.to_le_bytes()),
+        (LiteralValue::TimeValue(t) | LiteralValue::TimestampValue(t), _) => res.extend(t.to_le_bytes()),
+        (LiteralValue::IntervalValue(i), _) => i.encode(res),
+        (LiteralValue::BooleanValue(b), _) => res.push(u8::from(*b)),
+        (LiteralValue::UuidValue(u), _) => res.extend(u),
+        (LiteralValue::StringValue(_) | LiteralValue::BytesValue(_) | LiteralValue::JsonValue(_) | LiteralValue::ArrayValue(_), _) => {
+            let Some(bytes) = stored_bytes(value, column_type)? else {
+                return Err(TError::StorageError(String::from("an array can only be stored in an array column")));
+            };
+            res.extend(u32::try_from(bytes.len()).unwrap().to_le_bytes());
+            res.extend(bytes.iter());
+        },
+        (LiteralValue::NullValue, _) => return Err(TError::StorageError(String::from("nulls are not stored as values"))),
+    }
+    Ok(())
+}
+
+// values that run past the end of the data or don't hold what their column does are a storage error
+pub fn decode_value(column_type: &ColumnType, data: &[u8], bytes_read: &mut usize) -> Result<LiteralValue, TError> {
+    let mut read = |n: usize| {
+        let bytes = data.get(*bytes_read..bytes_read.saturating_add(n)).ok_or_else(|| TError::StorageError(String::from("stored value is truncated")))?;
+        *bytes_read += n;
+        Ok::<_, TError>(bytes)
+    };
+    Ok(match column_type {
+        ColumnType::SmallInt => LiteralValue::IntValue(i64::from(i16::from_le_bytes(read(2)?.try_into().unwrap()))),
+        ColumnType::Int => LiteralValue::IntValue(i64::from(i32::from_le_bytes(read(4)?.try_into().unwrap()))),
+        ColumnType::BigInt => LiteralValue::IntValue(i64::from_le_bytes(read(8)?.try_into().unwrap())),
+        ColumnType::Real => LiteralValue::FloatValue(real_to_f64(f32::from_le_bytes(read(4)?.try_into().unwrap()))),
+        ColumnType::Double => LiteralValue::FloatValue(f64::from_le_bytes(read(8)?.try_into().unwrap())),
+        ColumnType::Decimal(_) => LiteralValue::DecimalValue(Decimal::decode(read(17)?, &mut 0)),
+        ColumnType::Date => LiteralValue::DateValue(i32::from_le_bytes(read(4)?.try_into().unwrap())),
+        ColumnType::Time => LiteralValue::TimeValue(i64::from_le_bytes(read(8)?.try_into().unwrap())),
+        ColumnType::Timestamp => LiteralValue::TimestampValue(i64::from_le_bytes(read(8)?.try_into().unwrap())),
+        ColumnType::Interval => LiteralValue::IntervalValue(Interval::decode(read(16)?, &mut 0)),
+        ColumnType::Bool => LiteralValue::BooleanValue(read(1)?[0] != 0),
+        ColumnType::Uuid => LiteralValue::UuidValue(read(16)?.try_into().unwrap()),
+        ColumnType::Text | ColumnType::Bytea | ColumnType::Json | ColumnType::Array(_) => {
+            let len = usize::try_from(u32::from_le_bytes(read(4)?.try_into().unwrap())).unwrap();
+            let bytes = read(len)?;
+            match column_type {
+                ColumnType::Bytea => LiteralValue::BytesValue(bytes.to_vec()),
+                ColumnType::Json => LiteralValue::JsonValue(Json::decode(bytes, &mut 0)?),
+                ColumnType::Array(element_type) => LiteralValue::ArrayValue(decode_array(bytes, element_type)?),
+                _ => LiteralValue::StringValue(str::from_utf8(bytes).map_err(|_| not_utf8())?.to_owned()),
+            }
+        },
+    })
+}
+
+fn not_utf8() -> TError {
+    TError::StorageError(String::from("stored text isn't utf-8"))
+}
+
+// the double with the shortest text that reads back as the same real, so 0.1 stays 0.1
+pub fn real_to_f64(f: f32) -> f64 {
+    f.to_string().parse().unwrap()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::test::TestSetup;
+    use crate::parse::{scanner::TError, token::LiteralValue};
 
-    use super::{Column, ColumnType, TableSchema};
+    use super::{CheckSchema, Column, ColumnType, ForeignKeySchema, IndexSchema, ReferentialAction, TableSchema};
 
     
     #[test]
     fn simple_serialize() {
         let c = Column::new("1".to_owned(), ColumnType::Bool);
         let t = TableSchema::new("0".to_owned(), vec![c], 1);
-        let s = t.serialize();
+        let s = t.serialize().unwrap();
         let expected: Vec<u8> = vec![
             1, //len of table name 
             48, //name
             1,0,0,0, // page id 1 (u32) in LE
+            0,0, // schema version
             1, //number of col
             1, //col type
             1, // len of col name
             49, // col name
+            0,0, // col id
+            0,0, // added in
+            0,0, // dropped in
+            0, // no default
+            0,0, // no default expression
+            0, // not generated always
+            0, // number of dropped col
+            0, // number of indexes
+            0, // number of foreign keys
+            0, // number of checks
             ];
         assert_eq!(s, expected)
     }
@@ -140,12 +791,23 @@ mod tests {
             1, //len of table name 
             48, //name
             1,0,0,0, // page id 1 (u32) in LE
+            0,0, // schema version
             1, //number of col
             1, //col type
             1, // len of col name
             49, // col name
+            0,0, // col id
+            0,0, // added in
+            0,0, // dropped in
+            0, // no default
+            0,0, // no default expression
+            0, // not generated always
+            0, // number of dropped col
+            0, // number of indexes
+            0, // number of foreign keys
+            0, // number of checks
             ];
-        let t = TableSchema::deserialize(data);
+        let t = TableSchema::deserialize(data).unwrap();
 
         assert_eq!(t.name, String::from("0"));
         assert_eq!(t.columns.len(), 1);
@@ -153,4 +815,120 @@ mod tests {
         assert!(matches!(t.columns[0].column_type, ColumnType::Bool));
     }
 
+    #[test]
+    fn corrupt_catalog_entries() {
+        let t = TableSchema::new("t".to_owned(), vec![Column::new("a".to_owned(), ColumnType::Int)], 1);
+        let data = t.serialize().unwrap();
+
+        // the column type comes right after the name, page id, version and number of columns
+        let mut unknown_type = data.clone();
+        unknown_type[9] = 200;
+        assert!(matches!(TableSchema::deserialize(unknown_type), Err(TError::StorageError(m)) if m == "unknown column type 200 in the catalog"));
+
+        let mut bad_name = data.clone();
+        bad_name[1] = 0xff;
+        assert!(matches!(TableSchema::deserialize(bad_name), Err(TError::StorageError(_))));
+
+        // every way of cutting the entry short
+        let mut t = t;
+        t.columns[0].column_type = ColumnType::Decimal(Some((10, 2)));
+        t.indexes.push(IndexSchema { name: "t_pkey".to_owned(), columns: vec![0], exprs: Vec::new(), unique: true, primary: true, root_page_id: 2 });
+        t.checks.push(CheckSchema { name: "t_a_check".to_owned(), expr: "a > 0".to_owned() });
+        let data = t.serialize().unwrap();
+        for len in 0..data.len() {
+            assert!(matches!(TableSchema::deserialize(data[..len].to_vec()), Err(TError::StorageError(_))), "{}", len);
+        }
+        assert_eq!(TableSchema::deserialize(data).unwrap().checks.len(), 1);
+    }
+
+    #[test]
+    fn corrupt_tuples() {
+        let columns = vec![Column::new("a".to_owned(), ColumnType::Int), Column::new("b".to_owned(), ColumnType::Text), Column::new("c".to_owned(), ColumnType::Json)];
+        let t = TableSchema::new("t".to_owned(), columns, 1);
+        let json = LiteralValue::JsonValue(crate::types::json::Json::parse("[1, \"x\"]").unwrap());
+        let tuple = t.encode_tuple(&[LiteralValue::IntValue(1), LiteralValue::StringValue("é".to_owned()), json], &mut |_| unreachable!()).unwrap();
+
+        // a value cut short, text that isn't utf-8 and json with an unknown tag
+        let mut bad_text = tuple.clone();
+        bad_text[11] = 0xff;
+        let mut bad_json = tuple.clone();
+        bad_json[17] = 9;
+        for corrupt in [&tuple[..tuple.len() - 1], &tuple[..5], &tuple[..2], &tuple[..1], &bad_text, &bad_json] {
+            assert!(matches!(t.decode_tuple(corrupt, &mut |_, _| unreachable!()), Err(TError::StorageError(_))), "{:?}", corrupt);
+        }
+        assert!(matches!(t.overflow_pages(&tuple[..1]), Err(TError::StorageError(_))));
+        assert!(matches!(t.encode_tuple(&[LiteralValue::IntValue(1 << 40), LiteralValue::NullValue, LiteralValue::NullValue], &mut |_| unreachable!()), Err(TError::ExecutionError(..))));
+    }
+
+    #[test]
+    fn tuples_of_older_versions() {
+        let mut t = TableSchema::new("t".to_owned(), vec![Column::new("a".to_owned(), ColumnType::Int), Column::new("b".to_owned(), ColumnType::Text)], 1);
+        let v0 = t.encode_tuple(&[LiteralValue::IntValue(1), LiteralValue::NullValue], &mut |_| unreachable!()).unwrap();
+
+        t.add_column("c".to_owned(), ColumnType::Bool, Some(LiteralValue::BooleanValue(true)), Some("true".to_owned()));
+        t.drop_column("a");
+        let v2 = t.encode_tuple(&[LiteralValue::StringValue("x".to_owned()), LiteralValue::BooleanValue(false)], &mut |_| unreachable!()).unwrap();
+
+        // the schema has to survive the catalog too
+        let t = TableSchema::deserialize(t.serialize().unwrap()).unwrap();
+        assert_eq!(t.version, 2);
+        assert_eq!(t.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["b", "c"]);
+        assert_eq!(t.columns[1].default_expr.as_deref(), Some("true"));
+        assert_eq!(t.decode_tuple(&v0, &mut |_, _| unreachable!()).unwrap(), vec![LiteralValue::NullValue, LiteralValue::BooleanValue(true)]);
+        assert_eq!(t.decode_tuple(&v2, &mut |_, _| unreachable!()).unwrap(), vec![LiteralValue::StringValue("x".to_owned()), LiteralValue::BooleanValue(false)]);
+    }
+
+    #[test]
+    fn large_values_move_to_overflow_pages() {
+        let columns = vec![Column::new("a".to_owned(), ColumnType::Text), Column::new("b".to_owned(), ColumnType::Bytea), Column::new("c".to_owned(), ColumnType::Text)];
+        let t = TableSchema::new("t".to_owned(), columns, 1);
+        let row = vec![LiteralValue::StringValue("a".repeat(600)), LiteralValue::BytesValue(vec![7; 5000]), LiteralValue::StringValue("small".to_owned())];
+
+        // each stored value stands for a list of overflow pages
+        let mut stored: Vec<Vec<u8>> = Vec::new();
+        let tuple = t.encode_tuple(&row, &mut |value| {
+            stored.push(value.to_vec());
+            Ok(u32::try_from(stored.len()).unwrap())
+        }).unwrap();
+        // the largest value alone is enough to bring the tuple under the threshold
+        assert_eq!(stored.len(), 1);
+        assert!(tuple.len() < 700);
+        assert_eq!(t.overflow_pages(&tuple).unwrap(), vec![1]);
+        assert_eq!(t.decode_tuple(&tuple, &mut |page_id, _| Ok(stored[page_id as usize - 1].clone())).unwrap(), row);
+    }
+
+    #[test]
+    fn index_keys_sort_like_values() {
+        let mut t = TableSchema::new("t".to_owned(), vec![Column::new("a".to_owned(), ColumnType::Int), Column::new("b".to_owned(), ColumnType::Text)], 1);
+        t.indexes.push(IndexSchema {name: "t_a_b_key".to_owned(), columns: vec![0, 1], exprs: Vec::new(), unique: true, primary: false, root_page_id: 2});
+        t.indexes.push(IndexSchema {name: "t_expr_idx".to_owned(), columns: Vec::new(), exprs: vec!["a + 1".to_owned(), "b".to_owned()], unique: false, primary: false, root_page_id: 3});
+        let t = TableSchema::deserialize(t.serialize().unwrap()).unwrap();
+        let index = &t.indexes[0];
+        assert_eq!(index.columns, vec![0, 1]);
+        assert!(index.unique && !index.primary);
+        assert_eq!((t.indexes[1].exprs.as_slice(), t.indexes[1].root_page_id), (["a + 1".to_owned(), "b".to_owned()].as_slice(), 3));
+
+        let key = |a: i64, b: &str| index.key(&t, &[LiteralValue::IntValue(a), LiteralValue::StringValue(b.to_owned())]).unwrap();
+        let mut keys = vec![key(1, "b"), key(-5, "z"), key(1, "a\0"), key(1, "a"), key(300, ""), key(-300, "a")];
+        keys.sort();
+        assert_eq!(keys, vec![key(-300, "a"), key(-5, "z"), key(1, "a"), key(1, "a\0"), key(1, "b"), key(300, "")]);
+        assert_eq!(index.key(&t, &[LiteralValue::IntValue(1), LiteralValue::NullValue]), None);
+    }
+
+    #[test]
+    fn foreign_keys_survive_the_catalog() {
+        let mut t = TableSchema::new("t".to_owned(), vec![Column::new("a".to_owned(), ColumnType::Int), Column::new("b".to_owned(), ColumnType::Int)], 1);
+        t.foreign_keys.push(ForeignKeySchema {
+            name: "t_b_a_fkey".to_owned(), columns: vec![1, 0], table: "p".to_owned(), referenced_columns: vec![3, 2],
+            on_delete: ReferentialAction::Cascade, on_update: ReferentialAction::SetNull,
+        });
+        t.checks.push(CheckSchema { name: "t_check".to_owned(), expr: "a < b".to_owned() });
+        let t = TableSchema::deserialize(t.serialize().unwrap()).unwrap();
+        assert_eq!((t.checks[0].name.as_str(), t.checks[0].expr.as_str()), ("t_check", "a < b"));
+        let foreign_key = &t.foreign_keys[0];
+        assert_eq!(foreign_key.name, "t_b_a_fkey");
+        assert_eq!(foreign_key.table, "p");
+        assert_eq!((&foreign_key.columns, &foreign_key.referenced_columns), (&vec![1, 0], &vec![3, 2]));
+        assert_eq!((foreign_key.on_delete, foreign_key.on_update), (ReferentialAction::Cascade, ReferentialAction::SetNull));
+    }
 }
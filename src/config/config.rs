@@ -3,3 +3,5 @@ pub const PAGE_SIZE: usize = 4096; // 4 KB
 pub const DATA_DIR: &str = "data";
 
 pub const DATA_FILE: &str = "data.db";
+
+pub const HISTORY_FILE: &str = "history";
@@ -1,2 +1,4 @@
 pub mod execution;
-pub mod operators;
\ No newline at end of file
+pub mod operators;
+pub mod expression;
+pub mod constraints;
\ No newline at end of file
@@ -0,0 +1,382 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{catalog::table_schema::{encode_key, ForeignKeySchema, IndexSchema, ReferentialAction, TableSchema}, parse::{ast::Expr, parser::Parser, scanner::{CHECK_VIOLATION, FOREIGN_KEY_VIOLATION, NOT_NULL_VIOLATION, Scanner, TError, UNIQUE_VIOLATION}, token::LiteralValue}, planner::planner::bind_stored_expr, storage::{b_plus_tree::BPlusTree, buffer_pool::BufferPoolManager, table_page::RecordId}};
+
+use super::operators::{ExecutionContext, SeqScan};
+
+
+// a row written by a statement, they are all collected before anything is written
+pub enum RowChange {
+    Insert(Vec<LiteralValue>),
+    // the row before and after
+    Update(RecordId, Vec<LiteralValue>, Vec<LiteralValue>),
+    Delete(RecordId, Vec<LiteralValue>),
+}
+
+// changes along with the position of their table in the list of tables
+pub type ChangeSet = Vec<(usize, RowChange)>;
+
+
+/**
+ * Checks that writing rows into the table keeps its primary key and unique constraints,
+ * before anything is written, so a failing statement leaves the table as it was.
+ * The rows are compared against the index and against each other. replaced holds the
+ * rows an update is about to overwrite, their current keys don't count as conflicts.
+ */
+pub fn check_unique(buffer_pool: &mut BufferPoolManager, schema: &TableSchema, rows: &[&[LiteralValue]], replaced: &HashSet<RecordId>) -> Result<(), TError> {
+    for index in &schema.indexes {
+        if index.primary {
+            for row in rows {
+                for id in &index.columns {
+                    if let LiteralValue::NullValue = row[schema.column_position(*id)] {
+                        return Err(TError::ConstraintError(NOT_NULL_VIOLATION, format!(
+                            "null value in column {:?} violates primary key {:?}", schema.column_name(*id), index.name
+                        )));
+                    }
+                }
+            }
+        }
+        if !index.unique {
+            continue;
+        }
+
+        let tree = BPlusTree::new(index.root_page_id);
+        let mut keys = HashSet::new();
+        for row in rows {
+            let values = index_values(buffer_pool, schema, index, row)?;
+            let key = match encode_key(&values.iter().collect::<Vec<_>>()) {
+                Some(key) => key,
+                None => continue,
+            };
+            let taken = !keys.insert(key.clone()) || tree.get(buffer_pool, &key)?.iter().any(|r| !replaced.contains(r));
+            if taken {
+                return Err(duplicate_key(schema, index, &values));
+            }
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Checks that the rows written by the changes satisfy the check constraints of their table.
+ * A check that evaluates to null passes, like in postgres.
+ */
+pub fn check_checks(buffer_pool: &mut BufferPoolManager, tables: &[TableSchema], changes: &ChangeSet) -> Result<(), TError> {
+    for (index, schema) in tables.iter().enumerate() {
+        if schema.checks.is_empty() {
+            continue;
+        }
+        let rows: Vec<&Vec<LiteralValue>> = changes.iter().filter_map(|(table, change)| match change {
+            RowChange::Insert(new) | RowChange::Update(_, _, new) if *table == index => Some(new),
+            _ => None,
+        }).collect();
+        if rows.is_empty() {
+            continue;
+        }
+        let mut ctx = ExecutionContext::new(buffer_pool);
+        for check in &schema.checks {
+            let (mut expr, _) = bind_stored_expr(Some(schema), &check.expr)?;
+            for row in &rows {
+                if let LiteralValue::BooleanValue(false) = expr.evaluate(row, &mut ctx)? {
+                    return Err(TError::ConstraintError(CHECK_VIOLATION, format!(
+                        "new row for table {:?} violates check constraint {:?}", schema.name, check.name
+                    )));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// values are the ones of the key, see index_values
+fn duplicate_key(schema: &TableSchema, index: &IndexSchema, values: &[LiteralValue]) -> TError {
+    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    TError::ConstraintError(UNIQUE_VIOLATION, format!(
+        "duplicate key value violates unique constraint {:?}, key ({})=({}) already exists", index.name, index_key_names(schema, index), values.join(", ")
+    ))
+}
+
+// the columns of an index, or the sources of its expressions
+fn index_key_names(schema: &TableSchema, index: &IndexSchema) -> String {
+    match index.exprs.is_empty() {
+        true => index.columns.iter().map(|id| schema.column_name(*id)).collect::<Vec<_>>().join(", "),
+        false => index.exprs.iter().map(|source| display_index_expr(source)).collect::<Vec<_>>().join(", "),
+    }
+}
+
+// an indexed expression as it is shown in a key, in parentheses unless it is a column
+fn display_index_expr(source: &str) -> String {
+    let mut scanner = Scanner::new();
+    let expr = scanner.scan(&source.to_owned()).ok().and_then(|()| Parser::new().parse_expr(&scanner.tokens).ok());
+    match expr {
+        Some(expr @ Expr::ColumnReference(_)) => expr.to_string(),
+        Some(expr) => format!("({})", expr),
+        None => source.to_owned(),
+    }
+}
+
+/**
+ * The values a row is indexed by, its columns or the expressions of the index evaluated
+ * against it. The key is made from them with encode_key.
+ */
+pub fn index_values(buffer_pool: &mut BufferPoolManager, schema: &TableSchema, index: &IndexSchema, row: &[LiteralValue]) -> Result<Vec<LiteralValue>, TError> {
+    if index.exprs.is_empty() {
+        return Ok(index.columns.iter().map(|id| row[schema.column_position(*id)].clone()).collect());
+    }
+    let mut ctx = ExecutionContext::new(buffer_pool);
+    let mut values = Vec::new();
+    for source in &index.exprs {
+        let (mut expr, _) = bind_stored_expr(Some(schema), source)?;
+        values.push(expr.evaluate(row, &mut ctx)?);
+    }
+    Ok(values)
+}
+
+fn index_key(buffer_pool: &mut BufferPoolManager, schema: &TableSchema, index: &IndexSchema, row: &[LiteralValue]) -> Result<Option<Vec<u8>>, TError> {
+    if index.exprs.is_empty() {
+        return Ok(index.key(schema, row));
+    }
+    let values = index_values(buffer_pool, schema, index, row)?;
+    Ok(encode_key(&values.iter().collect::<Vec<_>>()))
+}
+
+/**
+ * Adds the changes asked for by foreign keys referencing the changed rows: referencing rows
+ * are deleted or updated along with them, or their columns are set to null. With restrict
+ * changing a referenced key is an error. Cascaded changes can cascade further, so this goes
+ * on until a round adds no more changes.
+ */
+pub fn cascade(buffer_pool: &mut BufferPoolManager, tables: &[TableSchema], changes: &mut ChangeSet) -> Result<(), TError> {
+    // where the change to a stored row is, a row is only changed once
+    let mut positions: HashMap<(usize, RecordId), usize> = HashMap::new();
+    for (position, (table, change)) in changes.iter().enumerate() {
+        if let RowChange::Update(record_id, _, _) | RowChange::Delete(record_id, _) = change {
+            positions.insert((*table, record_id.clone()), position);
+        }
+    }
+
+    let mut processed = 0;
+    while processed < changes.len() {
+        let round = processed..changes.len();
+        processed = changes.len();
+        for (child_index, child) in tables.iter().enumerate() {
+            for foreign_key in &child.foreign_keys {
+                let parent_index = tables.iter().position(|t| t.name == foreign_key.table).unwrap();
+                let parent = &tables[parent_index];
+
+                // referenced keys going away, with the values they change to or None when deleted
+                let mut removed: HashMap<Vec<LiteralValue>, Option<Vec<LiteralValue>>> = HashMap::new();
+                for (table, change) in &changes[round.clone()] {
+                    if *table != parent_index {
+                        continue;
+                    }
+                    match change {
+                        RowChange::Delete(_, old) => {
+                            if let Some(key) = key_values(parent, &foreign_key.referenced_columns, old) {
+                                removed.insert(key, None);
+                            }
+                        },
+                        RowChange::Update(_, old, new) => {
+                            let new_values: Vec<LiteralValue> = foreign_key.referenced_columns.iter().map(|id| new[parent.column_position(*id)].clone()).collect();
+                            if let Some(key) = key_values(parent, &foreign_key.referenced_columns, old) {
+                                if key != new_values {
+                                    removed.insert(key, Some(new_values));
+                                }
+                            }
+                        },
+                        RowChange::Insert(_) => {},
+                    }
+                }
+                if removed.is_empty() {
+                    continue;
+                }
+
+                let mut referencing = Vec::new();
+                {
+                    let mut ctx = ExecutionContext::new(buffer_pool);
+                    let mut scan = SeqScan::new(child.clone());
+                    while let Some((record_id, row)) = scan.next_with_record_id(&mut ctx)? {
+                        if let Some(new_key) = key_values(child, &foreign_key.columns, &row).and_then(|key| removed.get(&key)) {
+                            referencing.push((record_id, row, new_key.clone()));
+                        }
+                    }
+                }
+
+                for (record_id, row, new_key) in referencing {
+                    let existing = positions.get(&(child_index, record_id.clone())).copied();
+                    if let Some(position) = existing {
+                        if let RowChange::Delete(_, _) = changes[position].1 {
+                            continue;
+                        }
+                    }
+                    let action = if new_key.is_some() { foreign_key.on_update } else { foreign_key.on_delete };
+                    // the new values of the referencing columns, None to delete the row
+                    let new_values = match (action, new_key) {
+                        (ReferentialAction::Restrict, new_key) => return Err(still_referenced(child, foreign_key, &row, new_key.is_none())),
+                        (ReferentialAction::Cascade, new_key) => new_key,
+                        (ReferentialAction::SetNull, _) => Some(vec![LiteralValue::NullValue; foreign_key.columns.len()]),
+                    };
+                    let set_values = |new: &mut Vec<LiteralValue>, values: Vec<LiteralValue>| {
+                        for (id, value) in foreign_key.columns.iter().zip(values) {
+                            new[child.column_position(*id)] = value;
+                        }
+                    };
+                    match (existing, new_values) {
+                        (Some(position), None) => {
+                            if let RowChange::Update(_, old, _) = &changes[position].1 {
+                                changes[position].1 = RowChange::Delete(record_id, old.clone());
+                            }
+                        },
+                        (Some(position), Some(values)) => {
+                            if let RowChange::Update(_, _, new) = &mut changes[position].1 {
+                                set_values(new, values);
+                            }
+                        },
+                        (None, None) => {
+                            positions.insert((child_index, record_id.clone()), changes.len());
+                            changes.push((child_index, RowChange::Delete(record_id, row)));
+                        },
+                        (None, Some(values)) => {
+                            let mut new = row.clone();
+                            set_values(&mut new, values);
+                            positions.insert((child_index, record_id.clone()), changes.len());
+                            changes.push((child_index, RowChange::Update(record_id, row, new)));
+                        },
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn still_referenced(child: &TableSchema, foreign_key: &ForeignKeySchema, row: &[LiteralValue], delete: bool) -> TError {
+    let (columns, values) = describe_key(child, &foreign_key.columns, row);
+    TError::ConstraintError(FOREIGN_KEY_VIOLATION, format!(
+        "{} on table {:?} violates foreign key {:?} of table {:?}, key ({})=({}) is still referenced",
+        if delete { "delete" } else { "update" }, foreign_key.table, foreign_key.name, child.name, columns, values
+    ))
+}
+
+/**
+ * Checks that the rows written by the changes reference rows that are still there after
+ * them, either already stored and not changed, or written by the changes themselves.
+ * References are only checked when none of their columns are null.
+ */
+pub fn check_foreign_keys(buffer_pool: &mut BufferPoolManager, tables: &[TableSchema], changes: &ChangeSet) -> Result<(), TError> {
+    let mut removed: HashSet<(usize, &RecordId)> = HashSet::new();
+    for (table, change) in changes {
+        if let RowChange::Update(record_id, _, _) | RowChange::Delete(record_id, _) = change {
+            removed.insert((*table, record_id));
+        }
+    }
+
+    for (child_index, child) in tables.iter().enumerate() {
+        for foreign_key in &child.foreign_keys {
+            let parent_index = tables.iter().position(|t| t.name == foreign_key.table).unwrap();
+            let parent = &tables[parent_index];
+            let index = parent.indexes.iter().find(|i| i.unique && i.columns == foreign_key.referenced_columns).unwrap();
+            let tree = BPlusTree::new(index.root_page_id);
+
+            let mut written_keys = HashSet::new();
+            for (table, change) in changes {
+                if let (true, RowChange::Insert(new) | RowChange::Update(_, _, new)) = (*table == parent_index, change) {
+                    written_keys.extend(key_values(parent, &foreign_key.referenced_columns, new));
+                }
+            }
+
+            for (table, change) in changes {
+                if *table != child_index {
+                    continue;
+                }
+                let (old, new) = match change {
+                    RowChange::Insert(new) => (None, new),
+                    RowChange::Update(_, old, new) => (Some(old), new),
+                    RowChange::Delete(_, _) => continue,
+                };
+                let key = match key_values(child, &foreign_key.columns, new) {
+                    Some(key) => key,
+                    None => continue,
+                };
+                // rows whose reference didn't change were taken care of by cascade
+                if old.is_some_and(|old| key_values(child, &foreign_key.columns, old).as_ref() == Some(&key)) {
+                    continue;
+                }
+                if written_keys.contains(&key) {
+                    continue;
+                }
+                let encoded = encode_key(&key.iter().collect::<Vec<_>>()).unwrap();
+                if tree.get(buffer_pool, &encoded)?.iter().any(|r| !removed.contains(&(parent_index, r))) {
+                    continue;
+                }
+                let (columns, values) = describe_key(child, &foreign_key.columns, new);
+                return Err(TError::ConstraintError(FOREIGN_KEY_VIOLATION, format!(
+                    "insert or update on table {:?} violates foreign key {:?}, key ({})=({}) is not present in table {:?}",
+                    child.name, foreign_key.name, columns, values, parent.name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+// the values of the columns, None if one of them is null
+fn key_values(schema: &TableSchema, columns: &[u16], row: &[LiteralValue]) -> Option<Vec<LiteralValue>> {
+    let values: Vec<LiteralValue> = columns.iter().map(|id| row[schema.column_position(*id)].clone()).collect();
+    if values.iter().any(|v| matches!(v, LiteralValue::NullValue)) {
+        return None;
+    }
+    Some(values)
+}
+
+// column names and values for error messages
+fn describe_key(schema: &TableSchema, columns: &[u16], row: &[LiteralValue]) -> (String, String) {
+    let names: Vec<&str> = columns.iter().map(|id| schema.column_name(*id)).collect();
+    let values: Vec<String> = columns.iter().map(|id| row[schema.column_position(*id)].to_string()).collect();
+    (names.join(", "), values.join(", "))
+}
+
+// adds the rows already in the table to a new index, a unique one fails if two of them have the same key
+pub fn build_index(buffer_pool: &mut BufferPoolManager, schema: &TableSchema, index: &IndexSchema) -> Result<(), TError> {
+    let mut rows = Vec::new();
+    let mut scan = SeqScan::new(schema.clone());
+    let mut ctx = ExecutionContext::new(buffer_pool);
+    while let Some(row) = scan.next_with_record_id(&mut ctx)? {
+        rows.push(row);
+    }
+    let tree = BPlusTree::new(index.root_page_id);
+    let mut keys = HashSet::new();
+    for (record_id, row) in rows {
+        let values = index_values(buffer_pool, schema, index, &row)?;
+        let key = match encode_key(&values.iter().collect::<Vec<_>>()) {
+            Some(key) => key,
+            None => continue,
+        };
+        if index.unique && !keys.insert(key.clone()) {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            return Err(TError::ConstraintError(UNIQUE_VIOLATION, format!(
+                "could not create unique index {:?}, key ({})=({}) is duplicated", index.name, index_key_names(schema, index), values.join(", ")
+            )));
+        }
+        tree.insert(buffer_pool, &key, record_id)?;
+    }
+    Ok(())
+}
+
+pub fn insert_index_entries(buffer_pool: &mut BufferPoolManager, schema: &TableSchema, row: &[LiteralValue], record_id: &RecordId) -> Result<(), TError> {
+    for index in &schema.indexes {
+        if let Some(key) = index_key(buffer_pool, schema, index, row)? {
+            BPlusTree::new(index.root_page_id).insert(buffer_pool, &key, record_id.clone())?;
+        }
+    }
+    Ok(())
+}
+
+pub fn remove_index_entries(buffer_pool: &mut BufferPoolManager, schema: &TableSchema, row: &[LiteralValue], record_id: &RecordId) -> Result<(), TError> {
+    for index in &schema.indexes {
+        if let Some(key) = index_key(buffer_pool, schema, index, row)? {
+            BPlusTree::new(index.root_page_id).remove(buffer_pool, &key, record_id)?;
+        }
+    }
+    Ok(())
+}
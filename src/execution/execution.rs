@@ -1,159 +1,1601 @@
-use crate::{catalog::table_schema::{Column, TableSchema}, parse::ast::{CreateTableStatement, Expr, Statement}, planner::query_plan::{CreateTablePlan, InsertPlan, QueryPlan, SelectPlan}, storage::{buffer_pool::{BufferPoolManager, PageId}, table_page::TablePage}};
+use std::{collections::HashSet, fmt};
 
+use crate::{catalog::{catalog::{delete_sequence, delete_table, find_sequence, insert_sequence, insert_table, load_sequences, replace_sequence, replace_table}, table_schema::{Column, TableSchema}}, execution::{constraints::{build_index, cascade, check_checks, check_foreign_keys, check_unique, insert_index_entries, remove_index_entries, ChangeSet, RowChange}, expression::Expression, operators::{ExecutionContext, SeqScan}}, parse::{ast::{CreateTableStatement, Statement}, scanner::{DEPENDENT_OBJECTS_STILL_EXIST, DUPLICATE_TABLE, INTERNAL_ERROR, PROGRAM_LIMIT_EXCEEDED, UNDEFINED_TABLE, TError}, token::LiteralValue}, planner::{planner::to_column_type, query_plan::{AlterTablePlan, CreateIndexPlan, CreateTablePlan, DropIndexPlan, DeletePlan, DropSequencePlan, DropTablePlan, InsertPlan, QueryPlan, SelectPlan, TruncatePlan, UpdatePlan}}, storage::{b_plus_tree::BPlusTree, buffer_pool::{BufferPoolManager, PageId}, overflow::{free_overflow, write_overflow}, table_page::{RecordId, TablePage, TupleId}}};
 
-pub fn execute(buffer_pool: &mut BufferPoolManager, tables: &mut Vec<TableSchema>, plan: QueryPlan) {
+
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<LiteralValue>>,
+}
+
+impl fmt::Display for ResultSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // psql style table:
+        //  a | b
+        // ---+---
+        //  1 | 2
+        // (1 row)
+        let rows: Vec<Vec<String>> = self.rows.iter().map(|r| r.iter().map(|v| v.to_string()).collect()).collect();
+        let mut widths: Vec<usize> = self.columns.iter().map(|c| c.len()).collect();
+        for row in &rows {
+            for (i, value) in row.iter().enumerate() {
+                widths[i] = widths[i].max(value.len());
+            }
+        }
+
+        let header: Vec<String> = self.columns.iter().zip(&widths).map(|(c, w)| format!(" {:<w$} ", c, w = w)).collect();
+        writeln!(f, "{}", header.join("|"))?;
+        let separator: Vec<String> = widths.iter().map(|w| "-".repeat(w + 2)).collect();
+        writeln!(f, "{}", separator.join("+"))?;
+        for row in &rows {
+            let line: Vec<String> = row.iter().zip(&widths).map(|(v, w)| format!(" {:<w$} ", v, w = w)).collect();
+            writeln!(f, "{}", line.join("|"))?;
+        }
+        match rows.len() {
+            1 => write!(f, "(1 row)"),
+            n => write!(f, "({} rows)", n),
+        }
+    }
+}
+
+pub fn execute(buffer_pool: &mut BufferPoolManager, tables: &mut Vec<TableSchema>, plan: QueryPlan) -> Result<Option<ResultSet>, TError> {
     match plan {
         QueryPlan::CreateTablePlan(plan) => {
-            execute_create_table(buffer_pool, tables, &plan)
+            execute_create_table(buffer_pool, tables, &plan)?;
+            Ok(None)
+        },
+        QueryPlan::InsertPlan(mut plan) => {
+            execute_insert_values(buffer_pool, tables, &mut plan)?;
+            Ok(None)
+        },
+        QueryPlan::SelectPlan(mut plan) => Ok(Some(execute_select(buffer_pool, &mut plan)?)),
+        QueryPlan::DropTablePlan(plan) => {
+            execute_drop_table(buffer_pool, tables, &plan)?;
+            Ok(None)
+        },
+        QueryPlan::TruncatePlan(plan) => {
+            execute_truncate(buffer_pool, tables, &plan)?;
+            Ok(None)
         },
-        QueryPlan::InsertPlan(plan) => {
-            execute_insert_values(buffer_pool, tables, &plan)
+        QueryPlan::AlterTablePlan(plan) => {
+            execute_alter_table(buffer_pool, tables, plan)?;
+            Ok(None)
+        },
+        QueryPlan::UpdatePlan(mut plan) => {
+            execute_update(buffer_pool, tables, &mut plan)?;
+            Ok(None)
+        },
+        QueryPlan::DeletePlan(mut plan) => {
+            execute_delete(buffer_pool, tables, &mut plan)?;
+            Ok(None)
+        },
+        QueryPlan::CreateSequencePlan(plan) => {
+            if find_sequence(buffer_pool, &plan.sequence.name)?.is_some() {
+                return Err(TError::ExecutionError(DUPLICATE_TABLE, format!("relation {:?} already exists", plan.sequence.name)));
+            }
+            insert_sequence(buffer_pool, &plan.sequence)?;
+            Ok(None)
+        },
+        QueryPlan::DropSequencePlan(plan) => {
+            execute_drop_sequence(buffer_pool, &plan)?;
+            Ok(None)
+        },
+        QueryPlan::CreateIndexPlan(plan) => {
+            execute_create_index(buffer_pool, tables, plan)?;
+            Ok(None)
+        },
+        QueryPlan::DropIndexPlan(plan) => {
+            execute_drop_index(buffer_pool, tables, &plan)?;
+            Ok(None)
         },
-        QueryPlan::SelectPlan(plan) => execute_select(buffer_pool, tables, &plan),
     }
 }
 
-fn execute_create_table(buffer_pool: &mut BufferPoolManager, tables: &mut Vec<TableSchema>, plan: &CreateTablePlan) {
+fn execute_create_table(buffer_pool: &mut BufferPoolManager, tables: &mut Vec<TableSchema>, plan: &CreateTablePlan) -> Result<(), TError> {
     let stmt = &plan.stmt;
+    for sequence in &plan.sequences {
+        if find_sequence(buffer_pool, &sequence.name)?.is_some() {
+            return Err(TError::ExecutionError(DUPLICATE_TABLE, format!("relation {:?} already exists", sequence.name)));
+        }
+    }
     let table_name = stmt.token.lexeme.clone();
-    let columns = stmt.columns.iter().map(|c| {
-        Column {name: c.token.lexeme.clone(), column_type: match c.column_type {
-            crate::parse::ast::ColumnType::Bool => crate::catalog::table_schema::ColumnType::Bool,
-            crate::parse::ast::ColumnType::Int => crate::catalog::table_schema::ColumnType::Int,
-        } }
-    }).collect();
+    let columns = stmt.columns.iter().map(|c| Column::new(c.token.lexeme.clone(), to_column_type(&c.column_type))).collect();
     
 
     
-    let new_page = buffer_pool.new_page().unwrap();
-    let new_page_id = new_page.borrow().get_page_id().unwrap();
+    let new_page_id = buffer_pool.new_page()?.borrow().get_page_id().unwrap();
+
+    let mut new_table = TableSchema::new(table_name, columns, new_page_id.0.try_into().unwrap());
+    new_table.foreign_keys = plan.foreign_keys.clone();
+    new_table.checks = plan.checks.clone();
+    for (column, c) in new_table.columns.iter_mut().zip(&stmt.columns) {
+        column.default_expr = c.default.as_ref().map(|d| d.source.clone());
+        column.generated_always = c.identity.as_ref().is_some_and(|i| i.always);
+    }
+    let mut inserted_sequences = Vec::new();
+    let created = create_table_storage(buffer_pool, &mut new_table, plan, &mut inserted_sequences);
+    if let Err(e) = created {
+        // what was allocated for a table the catalog has no room for is given back
+        for sequence in inserted_sequences {
+            delete_sequence(buffer_pool, sequence)?;
+        }
+        free_table_storage(buffer_pool, &new_table)?;
+        return Err(e);
+    }
 
-    let new_table = TableSchema::new(table_name, columns, new_page_id.0.try_into().unwrap());
+    tables.push(new_table);
+    Ok(())
+}
 
-    let page = buffer_pool.get_catalog_page().unwrap();
-    let mut table = TablePage::new(page);
+// the index trees, sequences and catalog entry of a new table, the sequences that were entered are collected
+fn create_table_storage<'p>(buffer_pool: &mut BufferPoolManager, table: &mut TableSchema, plan: &'p CreateTablePlan, inserted_sequences: &mut Vec<&'p str>) -> Result<(), TError> {
+    for index in &plan.indexes {
+        let tree = BPlusTree::create(buffer_pool)?;
+        table.indexes.push(index.clone());
+        table.indexes.last_mut().unwrap().root_page_id = tree.root_page_id;
+    }
+    for sequence in &plan.sequences {
+        insert_sequence(buffer_pool, sequence)?;
+        inserted_sequences.push(&sequence.name);
+    }
+    insert_table(buffer_pool, table)
+}
+
+// frees the pages of the table's index trees and of its tuples
+fn free_table_storage(buffer_pool: &mut BufferPoolManager, table: &TableSchema) -> Result<(), TError> {
+    for index in &table.indexes {
+        BPlusTree::new(index.root_page_id).free_pages(buffer_pool)?;
+    }
+    free_pages(buffer_pool, table, table.first_page_id)
+}
 
-    table.insert_tuple(new_table.serialize()).unwrap();
+fn execute_drop_table(buffer_pool: &mut BufferPoolManager, tables: &mut Vec<TableSchema>, plan: &DropTablePlan) -> Result<(), TError> {
+    let index = match tables.iter().position(|t| t.name == plan.table) {
+        Some(index) => index,
+        None if plan.if_exists => return Ok(()),
+        None => return Err(TError::ExecutionError(UNDEFINED_TABLE, format!("table {:?} not found", plan.table))),
+    };
+    delete_table(buffer_pool, &plan.table)?;
+    for sequence in load_sequences(buffer_pool)?.iter().filter(|s| s.owner.as_deref() == Some(plan.table.as_str())) {
+        delete_sequence(buffer_pool, &sequence.name)?;
+    }
+    let table = tables.remove(index);
+    free_table_storage(buffer_pool, &table)
+}
 
-    tables.push(new_table);
+// sequences made for identity columns go away with their table
+fn execute_drop_sequence(buffer_pool: &mut BufferPoolManager, plan: &DropSequencePlan) -> Result<(), TError> {
+    let sequence = match find_sequence(buffer_pool, &plan.name)? {
+        Some(sequence) => sequence,
+        None if plan.if_exists => return Ok(()),
+        None => return Err(TError::ExecutionError(UNDEFINED_TABLE, format!("sequence {:?} does not exist", plan.name))),
+    };
+    if let Some(owner) = sequence.owner {
+        return Err(TError::ExecutionError(DEPENDENT_OBJECTS_STILL_EXIST, format!("cannot drop sequence {:?}, an identity column of table {:?} uses it", plan.name, owner)));
+    }
+    delete_sequence(buffer_pool, &plan.name)
 }
 
-fn execute_insert_values(buffer_pool: &mut BufferPoolManager, tables: &mut Vec<TableSchema>, plan: &InsertPlan) {
-    let schema = tables.iter().find(|x| x.name == plan.table).unwrap();
-    let latest_page = find_latest_page(schema);
-    let page = buffer_pool.fetch_page(PageId(latest_page.try_into().unwrap())).unwrap();
-    let mut table = TablePage::new(page);
-    for row in &plan.values {
-        let tuple = values_row_to_tuple(row);
-        match table.insert_tuple(tuple) {
-            Some(_) => {},
-            None => {
-                todo!("allocate new page for table")
+fn execute_create_index(buffer_pool: &mut BufferPoolManager, tables: &mut [TableSchema], plan: CreateIndexPlan) -> Result<(), TError> {
+    let table = match tables.iter_mut().find(|t| t.name == plan.table) {
+        Some(table) => table,
+        None => return Err(TError::ExecutionError(UNDEFINED_TABLE, format!("table {:?} not found", plan.table))),
+    };
+    let mut index = plan.index;
+    index.root_page_id = BPlusTree::create(buffer_pool)?.root_page_id;
+    if let Err(e) = build_index(buffer_pool, table, &index) {
+        BPlusTree::new(index.root_page_id).free_pages(buffer_pool)?;
+        return Err(e);
+    }
+    let mut indexed = table.clone();
+    indexed.indexes.push(index);
+    if let Err(e) = replace_table(buffer_pool, &table.name, &indexed) {
+        BPlusTree::new(indexed.indexes.last().unwrap().root_page_id).free_pages(buffer_pool)?;
+        return Err(e);
+    }
+    *table = indexed;
+    Ok(())
+}
+
+fn execute_drop_index(buffer_pool: &mut BufferPoolManager, tables: &mut [TableSchema], plan: &DropIndexPlan) -> Result<(), TError> {
+    let table = match tables.iter_mut().find(|t| plan.table.as_ref() == Some(&t.name)) {
+        Some(table) => table,
+        None => return Ok(()),
+    };
+    let position = table.indexes.iter().position(|i| i.name == plan.name).unwrap();
+    let mut unindexed = table.clone();
+    let index = unindexed.indexes.remove(position);
+    replace_table(buffer_pool, &table.name, &unindexed)?;
+    *table = unindexed;
+    BPlusTree::new(index.root_page_id).free_pages(buffer_pool)
+}
+
+// the table gets a new empty first page and empty indexes, so truncating is a drop and create under the same name
+fn execute_truncate(buffer_pool: &mut BufferPoolManager, tables: &mut [TableSchema], plan: &TruncatePlan) -> Result<(), TError> {
+    let table = match tables.iter_mut().find(|t| t.name == plan.table) {
+        Some(table) => table,
+        None => return Err(TError::ExecutionError(UNDEFINED_TABLE, format!("table {:?} not found", plan.table))),
+    };
+    let new_page_id = buffer_pool.new_page()?.borrow().get_page_id().unwrap();
+    let mut truncated = table.clone();
+    truncated.first_page_id = new_page_id.0.try_into().unwrap();
+    truncated.indexes.clear();
+    let mut created = Ok(());
+    for index in &table.indexes {
+        match BPlusTree::create(buffer_pool) {
+            Ok(tree) => {
+                truncated.indexes.push(index.clone());
+                truncated.indexes.last_mut().unwrap().root_page_id = tree.root_page_id;
             },
+            Err(e) => {
+                created = Err(e);
+                break;
+            },
+        }
+    }
+    // the table keeps its rows until the catalog has the empty pages
+    if let Err(e) = created.and_then(|()| replace_table(buffer_pool, &table.name, &truncated)) {
+        free_table_storage(buffer_pool, &truncated)?;
+        return Err(e);
+    }
+    let old = std::mem::replace(table, truncated);
+    free_table_storage(buffer_pool, &old)
+}
+
+// tuples are left as they are, they are read according to the schema version they were written under
+fn execute_alter_table(buffer_pool: &mut BufferPoolManager, tables: &mut [TableSchema], plan: AlterTablePlan) -> Result<(), TError> {
+    let table = match tables.iter_mut().find(|t| t.name == plan.table) {
+        Some(table) => table,
+        None => return Err(TError::ExecutionError(UNDEFINED_TABLE, format!("table {:?} not found", plan.table))),
+    };
+    replace_table(buffer_pool, &plan.table, &plan.schema)?;
+    let table_name = plan.schema.name.clone();
+    *table = plan.schema;
+
+    if table_name != plan.table {
+        for mut sequence in load_sequences(buffer_pool)?.into_iter().filter(|s| s.owner.as_deref() == Some(plan.table.as_str())) {
+            sequence.owner = Some(table_name.clone());
+            replace_sequence(buffer_pool, &sequence.name, &sequence)?;
+        }
+    }
+
+    // foreign keys refer to tables by name
+    for other in tables.iter_mut().filter(|t| t.name != table_name) {
+        if other.foreign_keys.iter().all(|f| f.table != plan.table) {
+            continue;
+        }
+        let mut renamed = other.clone();
+        for foreign_key in renamed.foreign_keys.iter_mut().filter(|f| f.table == plan.table) {
+            foreign_key.table = table_name.clone();
+        }
+        replace_table(buffer_pool, &other.name, &renamed)?;
+        *other = renamed;
+    }
+    Ok(())
+}
+
+// delete every page in the linked list starting at first_page_id, and the overflow pages of its tuples
+fn free_pages(buffer_pool: &mut BufferPoolManager, table: &TableSchema, first_page_id: u32) -> Result<(), TError> {
+    let mut next_page_id = Some(first_page_id);
+    while let Some(page_id) = next_page_id {
+        let page_id = PageId(page_id.try_into().unwrap());
+        let mut overflow_pages = Vec::new();
+        let table_page = TablePage::new(buffer_pool.fetch_page(page_id.clone())?);
+        for tuple_id in (0..usize::from(table_page.get_num_tuples())).map(TupleId) {
+            if !table_page.is_deleted(&tuple_id) {
+                overflow_pages.extend(table.overflow_pages(&table_page.get_tuple(tuple_id)?)?);
+            }
         }
+        next_page_id = table_page.get_next_page_id();
+        // unpin the page so it can be deleted
+        std::mem::drop(table_page);
+        for first_page_id in overflow_pages {
+            free_overflow(buffer_pool, first_page_id)?;
+        }
+        if !buffer_pool.delete_page(&page_id) {
+            return Err(TError::ExecutionError(INTERNAL_ERROR, format!("page {} is still in use", page_id.0)));
+        }
+    }
+    Ok(())
+}
+
+fn execute_insert_values(buffer_pool: &mut BufferPoolManager, tables: &mut [TableSchema], plan: &mut InsertPlan) -> Result<(), TError> {
+    let table = tables.iter().position(|x| x.name == plan.table).unwrap();
+    let mut changes = Vec::new();
+    {
+        let mut ctx = ExecutionContext::new(buffer_pool);
+        for values in &mut plan.values {
+            let row = values.iter_mut().map(|value| value.evaluate(&[], &mut ctx)).collect::<Result<Vec<_>, TError>>()?;
+            changes.push((table, RowChange::Insert(row)));
+        }
+    }
+    write_changes(buffer_pool, tables, changes)
+}
+
+// all the matching rows are read before anything is written, so rows moved by the update aren't seen again
+fn execute_update(buffer_pool: &mut BufferPoolManager, tables: &mut [TableSchema], plan: &mut UpdatePlan) -> Result<(), TError> {
+    let table = tables.iter().position(|x| x.name == plan.table).unwrap();
+    let mut changes = Vec::new();
+    {
+        let mut ctx = ExecutionContext::new(buffer_pool);
+        let mut scan = SeqScan::new(tables[table].clone());
+        while let Some((record_id, row)) = scan.next_with_record_id(&mut ctx)? {
+            if !matches_predicate(&mut plan.predicate, &row, &mut ctx)? {
+                continue;
+            }
+            let mut new_row = row.clone();
+            for (index, value) in &mut plan.assignments {
+                new_row[*index] = value.evaluate(&row, &mut ctx)?;
+            }
+            changes.push((table, RowChange::Update(record_id, row, new_row)));
+        }
+    }
+    write_changes(buffer_pool, tables, changes)
+}
+
+fn execute_delete(buffer_pool: &mut BufferPoolManager, tables: &mut [TableSchema], plan: &mut DeletePlan) -> Result<(), TError> {
+    let table = tables.iter().position(|x| x.name == plan.table).unwrap();
+    let mut changes = Vec::new();
+    {
+        let mut ctx = ExecutionContext::new(buffer_pool);
+        let mut scan = SeqScan::new(tables[table].clone());
+        while let Some((record_id, row)) = scan.next_with_record_id(&mut ctx)? {
+            if matches_predicate(&mut plan.predicate, &row, &mut ctx)? {
+                changes.push((table, RowChange::Delete(record_id, row)));
+            }
+        }
+    }
+    write_changes(buffer_pool, tables, changes)
+}
+
+fn matches_predicate(predicate: &mut Option<Expression>, row: &[LiteralValue], ctx: &mut ExecutionContext) -> Result<bool, TError> {
+    match predicate {
+        Some(predicate) => Ok(matches!(predicate.evaluate(row, ctx)?, LiteralValue::BooleanValue(true))),
+        None => Ok(true),
     }
 }
 
-fn values_row_to_tuple(values: &Vec<Expr>) -> Vec<u8> {
-    let mut res = Vec::new();
-    for value in values {
-        match value {
-            Expr::ColumnReference(_) => todo!(),
-            Expr::Literal(l) => match l.value {
-                crate::parse::token::LiteralValue::IntValue(i) => {
-                    res.extend(i.to_le_bytes());
+/**
+ * Writes the rows changed by a statement. Foreign key actions are added to the changes
+ * and every constraint is checked before anything is written, so a failing statement
+ * leaves the tables untouched. Updated rows are written as new tuples and the old ones
+ * are marked as deleted.
+ */
+fn write_changes(buffer_pool: &mut BufferPoolManager, tables: &[TableSchema], mut changes: ChangeSet) -> Result<(), TError> {
+    cascade(buffer_pool, tables, &mut changes)?;
+    check_checks(buffer_pool, tables, &changes)?;
+
+    for (index, schema) in tables.iter().enumerate() {
+        let mut rows: Vec<&[LiteralValue]> = Vec::new();
+        let mut replaced = HashSet::new();
+        for (table, change) in &changes {
+            if *table != index {
+                continue;
+            }
+            match change {
+                RowChange::Insert(new) => rows.push(new),
+                RowChange::Update(record_id, _, new) => {
+                    rows.push(new);
+                    replaced.insert(record_id.clone());
                 },
-                crate::parse::token::LiteralValue::StringValue(_) => todo!(),
-                crate::parse::token::LiteralValue::BooleanValue(b) => {
-                    res.push(match b {
-                        true => 1,
-                        false => 0,
-                    })
+                RowChange::Delete(record_id, _) => {
+                    replaced.insert(record_id.clone());
                 },
-                crate::parse::token::LiteralValue::NullValue => todo!(),
             }
         }
+        if !rows.is_empty() {
+            check_unique(buffer_pool, schema, &rows, &replaced)?;
+        }
+    }
+    check_foreign_keys(buffer_pool, tables, &changes)?;
+
+    // old rows go first, so their keys are free for the new ones
+    for (table, change) in &changes {
+        if let RowChange::Update(record_id, old, _) | RowChange::Delete(record_id, old) = change {
+            remove_index_entries(buffer_pool, &tables[*table], old, record_id)?;
+            delete_tuple(buffer_pool, &tables[*table], record_id)?;
+        }
+    }
+    for (table, change) in &changes {
+        if let RowChange::Insert(new) | RowChange::Update(_, _, new) = change {
+            let schema = &tables[*table];
+            let tuple = schema.encode_tuple(new, &mut |value| write_overflow(buffer_pool, value))?;
+            let record_id = append_tuple(buffer_pool, schema, tuple)?;
+            insert_index_entries(buffer_pool, schema, new, &record_id)?;
+        }
     }
-    res
+    Ok(())
 }
 
-fn find_latest_page(table: &TableSchema) -> u32 {
-    // this is incredibly dumb
-    // TODO follow the linked list of pages
-    table.first_page_id // for now we assume tables are only ever 1 page big
+// the overflow pages of the tuple's values are freed with it
+fn delete_tuple(buffer_pool: &mut BufferPoolManager, table: &TableSchema, record_id: &RecordId) -> Result<(), TError> {
+    let mut table_page = TablePage::new(buffer_pool.fetch_page(PageId(record_id.page_id.try_into().unwrap()))?);
+    let overflow_pages = table.overflow_pages(&table_page.get_tuple(record_id.tuple_id.clone())?)?;
+    table_page.delete_tuple(&record_id.tuple_id)?;
+    for first_page_id in overflow_pages {
+        free_overflow(buffer_pool, first_page_id)?;
+    }
+    Ok(())
 }
 
-fn execute_select(buffer_pool: &mut BufferPoolManager, tables: &mut Vec<TableSchema>, plan: &SelectPlan) {
-    todo!()
+// add the tuple to the last page of the table, linking in a new page when that one is full
+fn append_tuple(buffer_pool: &mut BufferPoolManager, table: &TableSchema, tuple: Vec<u8>) -> Result<RecordId, TError> {
+    let page_id = find_latest_page(buffer_pool, table)?;
+    let page = buffer_pool.fetch_page(PageId(page_id.try_into().unwrap()))?;
+    let mut table_page = TablePage::new(page);
+    if let Some(tuple_id) = table_page.insert_tuple(tuple.clone()) {
+        return Ok(RecordId { page_id, tuple_id });
+    }
+
+    let new_page = buffer_pool.new_page()?;
+    let new_page_id: u32 = new_page.borrow().get_page_id().unwrap().0.try_into().unwrap();
+    table_page.set_next_page_id(new_page_id);
+    match TablePage::new(new_page).insert_tuple(tuple) {
+        Some(tuple_id) => Ok(RecordId { page_id: new_page_id, tuple_id }),
+        None => Err(TError::ExecutionError(PROGRAM_LIMIT_EXCEEDED, format!("tuple is too big to fit in a page of table {:?}", table.name))),
+    }
+}
+
+fn find_latest_page(buffer_pool: &mut BufferPoolManager, table: &TableSchema) -> Result<u32, TError> {
+    let mut page_id = table.first_page_id;
+    loop {
+        let page = buffer_pool.fetch_page(PageId(page_id.try_into().unwrap()))?;
+        match TablePage::new(page).get_next_page_id() {
+            Some(next_page_id) => page_id = next_page_id,
+            None => return Ok(page_id),
+        }
+    }
+}
+
+fn execute_select(buffer_pool: &mut BufferPoolManager, plan: &mut SelectPlan) -> Result<ResultSet, TError> {
+    let mut ctx = ExecutionContext::new(buffer_pool);
+    plan.root.init(&mut ctx)?;
+    let mut rows = Vec::new();
+    while let Some(row) = plan.root.next(&mut ctx)? {
+        rows.push(row);
+    }
+    Ok(ResultSet { columns: plan.columns.clone(), rows })
 }
 
 
 #[cfg(test)]
 mod tests {
-    use std::{cell::RefCell, rc::Rc};
+    use crate::{catalog::{catalog::load_catalog, table_schema::{Column, TableSchema}}, config::config::PAGE_SIZE, execution::expression::Expression, types::decimal::Decimal, parse::{ast::CreateTableStatement, parser::Parser, scanner::{Scanner, TError}, token::{LiteralValue, Span, Token, TokenType}}, planner::{planner::plan, query_plan::{CreateTablePlan, InsertPlan}}, storage::{buffer_pool::{BufferPoolManager, PageId}, table_page::TablePage}, test::TestSetup};
 
-    use crate::{catalog::table_schema::{Column, TableSchema}, config::config::PAGE_SIZE, parse::{ast::{CreateTableStatement, Expr, Literal}, token::{Token, TokenType}}, planner::query_plan::{CreateTablePlan, InsertPlan}, storage::{buffer_pool::{self, BufferPoolManager, FrameId, PageId, PageTableEntry}, table_page::{TablePage, TupleId}}, test::TestSetup};
+    use super::{execute, execute_create_table, load_sequences, execute_insert_values, find_latest_page, ResultSet};
 
-    use super::{execute_create_table, execute_insert_values, find_latest_page};
+    // scan, parse, plan and execute every statement in sql, returning the result sets
+    fn run(buffer_pool: &mut BufferPoolManager, tables: &mut Vec<TableSchema>, sql: &str) -> Vec<ResultSet> {
+        let mut scanner = Scanner::new();
+        scanner.scan(&sql.to_owned()).unwrap();
+        let statements = Parser::new().parse(&scanner.tokens).unwrap();
+        let mut results = Vec::new();
+        for stmt in statements {
+            let plan = plan(tables, stmt).unwrap();
+            if let Some(result) = execute(buffer_pool, tables, plan).unwrap() {
+                results.push(result);
+            }
+        }
+        results
+    }
+
+    // like run, for a statement that is expected to fail when planned or executed
+    fn run_error(buffer_pool: &mut BufferPoolManager, tables: &mut Vec<TableSchema>, sql: &str) -> TError {
+        let mut scanner = Scanner::new();
+        scanner.scan(&sql.to_owned()).unwrap();
+        let stmt = Parser::new().parse(&scanner.tokens).unwrap().pop().unwrap();
+        match plan(tables, stmt) {
+            Ok(plan) => execute(buffer_pool, tables, plan).err().expect("statement should fail"),
+            Err(e) => e.unspanned(),
+        }
+    }
 
     #[test]
     fn test_create_table() { 
         let _setup = TestSetup;
 
         let plan = CreateTablePlan {stmt: CreateTableStatement {
-            token: Token { token_type: TokenType::Identifier, lexeme: String::from("0"), literal: None, line: 0, id: 0 },
+            token: Token { token_type: TokenType::Identifier, lexeme: String::from("0"), literal: None, line: 0, column: 0, span: Span::default(), id: 0 },
             columns: vec![
                 crate::parse::ast::Column {
-                    token: Token { token_type: TokenType::Identifier, lexeme: String::from("0"), literal: None, line: 0, id: 0 },
-                    column_type: crate::parse::ast::ColumnType::Int},
+                    token: Token { token_type: TokenType::Identifier, lexeme: String::from("0"), literal: None, line: 0, column: 0, span: Span::default(), id: 0 },
+                    column_type: crate::parse::ast::ColumnType::Int, default: None, identity: None, span: Span::default()},
                 crate::parse::ast::Column {
-                    token: Token { token_type: TokenType::Identifier, lexeme: String::from("1"), literal: None, line: 0, id: 0 },
-                    column_type: crate::parse::ast::ColumnType::Int}
-            ]
-        }};
+                    token: Token { token_type: TokenType::Identifier, lexeme: String::from("1"), literal: None, line: 0, column: 0, span: Span::default(), id: 0 },
+                    column_type: crate::parse::ast::ColumnType::Int, default: None, identity: None, span: Span::default()}
+            ],
+            constraints: vec![],
+            span: Span::default(),
+        }, indexes: vec![], foreign_keys: vec![], checks: vec![], sequences: vec![]};
 
         let mut tables = Vec::new();
 
         let pool_size= 4;
         let mut memory = vec![0u8; pool_size * PAGE_SIZE];
-        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2);
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
 
-        execute_create_table(&mut buffer_pool, &mut tables, &plan);
+        execute_create_table(&mut buffer_pool, &mut tables, &plan).unwrap();
 
         assert_eq!(tables.len(), 1);
     }
 
     #[test]
     fn test_insert_values() {
-        let plan = InsertPlan {table: String::from("1"), values: vec![vec![
-            Expr::Literal(Literal {
-            token: Token {token_type: crate::parse::token::TokenType::Int, lexeme: String::from("0"), literal: None, line: 0, id: 0}, value: crate::parse::token::LiteralValue::IntValue(0)
-            }),
-            Expr::Literal(Literal {
-                token: Token {token_type: crate::parse::token::TokenType::Int, lexeme: String::from("1"), literal: None, line: 0, id: 0}, value: crate::parse::token::LiteralValue::IntValue(1)
-                },
-        )]]};
+        let _setup = TestSetup;
+        let mut plan = InsertPlan {table: String::from("1"), values: vec![vec![
+            Expression::Constant(LiteralValue::IntValue(0)),
+            Expression::Constant(LiteralValue::IntValue(1)),
+        ]]};
 
-        let mut tables = vec![TableSchema {
-            name: String::from("1"),
-            first_page_id: 1,
-            columns: vec![
-                Column {name: String::from("0"), column_type: crate::catalog::table_schema::ColumnType::Int},
-                Column {name: String::from("1"), column_type: crate::catalog::table_schema::ColumnType::Int}
-            ]
-        }];
+        let mut tables = vec![TableSchema::new(
+            String::from("1"),
+            vec![
+                Column::new(String::from("0"), crate::catalog::table_schema::ColumnType::Int),
+                Column::new(String::from("1"), crate::catalog::table_schema::ColumnType::Int)
+            ],
+            1,
+        )];
 
         let pool_size= 4;
         let mut memory = vec![0u8; pool_size * PAGE_SIZE];
-        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2);
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
 
-        execute_insert_values(&mut buffer_pool, &mut tables, &plan);
+        execute_insert_values(&mut buffer_pool, &mut tables, &mut plan).unwrap();
 
-        let latest_page = find_latest_page(&tables[0]);
+        let latest_page = find_latest_page(&mut buffer_pool, &tables[0]).unwrap();
         let page = buffer_pool.fetch_page(PageId(latest_page.try_into().unwrap())).unwrap();
         let table = TablePage::new(page);
         assert_eq!(table.get_num_tuples(), 1);
 
     }
 
-}
\ No newline at end of file
+
+    #[test]
+    fn test_select_star_and_aliases() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table t (a int, b bool); insert into t (b, a) values (true, 1), (false, 2);");
+
+        let results = run(&mut buffer_pool, &mut tables, "select * from t;");
+        assert_eq!(results[0].columns, vec!["a", "b"]);
+        assert_eq!(results[0].rows.len(), 2);
+        assert!(matches!(results[0].rows[1][..], [LiteralValue::IntValue(2), LiteralValue::BooleanValue(false)]));
+
+        let results = run(&mut buffer_pool, &mut tables, "select u.b as flag, u.*, a from t u;");
+        assert_eq!(results[0].columns, vec!["flag", "a", "b", "a"]);
+        assert!(matches!(results[0].rows[0][..], [LiteralValue::BooleanValue(true), LiteralValue::IntValue(1), LiteralValue::BooleanValue(true), LiteralValue::IntValue(1)]));
+    }
+
+    #[test]
+    fn test_subqueries() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table t (a int, b int); insert into t (a, b) values (1, 10), (2, 20), (3, 30);");
+        run(&mut buffer_pool, &mut tables, "create table s (x int, y bool); insert into s (x, y) values (1, true), (3, false);");
+
+        let ints = |result: &ResultSet| result.rows.iter().map(|r| match r[0] { LiteralValue::IntValue(i) => i, _ => panic!("expected int") }).collect::<Vec<i64>>();
+
+        let results = run(&mut buffer_pool, &mut tables, "select a from t where a in (select x from s);");
+        assert_eq!(ints(&results[0]), vec![1, 3]);
+
+        let results = run(&mut buffer_pool, &mut tables, "select a from t where a not in (select x from s where y);");
+        assert_eq!(ints(&results[0]), vec![2, 3]);
+
+        // correlated
+        let results = run(&mut buffer_pool, &mut tables, "select a from t where exists (select * from s where s.x = t.a and not y);");
+        assert_eq!(ints(&results[0]), vec![3]);
+        let results = run(&mut buffer_pool, &mut tables, "select a from t where not exists (select * from s where x = a);");
+        assert_eq!(ints(&results[0]), vec![2]);
+        let results = run(&mut buffer_pool, &mut tables, "select a from t where exists (select * from s where s.x > t.a) or a = 3;");
+        assert_eq!(ints(&results[0]), vec![1, 2, 3]);
+
+        // scalar and derived tables
+        let results = run(&mut buffer_pool, &mut tables, "select (select y from s where x = a) as y, d.b from (select a, b from t where b > 10) as d;");
+        assert_eq!(results[0].columns, vec!["y", "b"]);
+        assert!(matches!(results[0].rows[..], [
+            ref r1, ref r2
+        ] if r1[..] == [LiteralValue::NullValue, LiteralValue::IntValue(20)] && r2[..] == [LiteralValue::BooleanValue(false), LiteralValue::IntValue(30)]));
+    }
+
+    #[test]
+    fn test_scalar_subquery_too_many_rows() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table t (a int); insert into t (a) values (1), (2);");
+
+        let mut scanner = Scanner::new();
+        scanner.scan(&"select (select a from t) from t;".to_owned()).unwrap();
+        let stmt = Parser::new().parse(&scanner.tokens).unwrap().pop().unwrap();
+        let plan = plan(&mut tables, stmt).unwrap();
+        assert!(matches!(execute(&mut buffer_pool, &mut tables, plan), Err(TError::ExecutionError(..))));
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table t (a int); insert into t (a) values (1), (1), (1), (2), (3);");
+        run(&mut buffer_pool, &mut tables, "create table s (x int); insert into s (x) values (1), (3), (3), (4);");
+
+        let ints = |sql: &str, tables: &mut Vec<TableSchema>, buffer_pool: &mut BufferPoolManager| {
+            let results = run(buffer_pool, tables, sql);
+            results[0].rows.iter().map(|r| match r[0] { LiteralValue::IntValue(i) => i, _ => panic!("expected int") }).collect::<Vec<i64>>()
+        };
+
+        assert_eq!(ints("select a from t union select x from s;", &mut tables, &mut buffer_pool), vec![1, 2, 3, 4]);
+        assert_eq!(ints("select a from t union all select x from s;", &mut tables, &mut buffer_pool), vec![1, 1, 1, 2, 3, 1, 3, 3, 4]);
+        assert_eq!(ints("select a from t intersect select x from s;", &mut tables, &mut buffer_pool), vec![1, 3]);
+        assert_eq!(ints("select a from t intersect all select x from s union all select x from s;", &mut tables, &mut buffer_pool), vec![1, 3, 1, 3, 3, 4]);
+        assert_eq!(ints("select a from t except select x from s;", &mut tables, &mut buffer_pool), vec![2]);
+        assert_eq!(ints("select a from t except all select x from s;", &mut tables, &mut buffer_pool), vec![1, 1, 2]);
+        // intersect binds tighter than union
+        assert_eq!(ints("select x from s except select a from t intersect select x from s;", &mut tables, &mut buffer_pool), vec![4]);
+        assert_eq!(ints("select a from t where a in (select x from s except (select a from t where a = 1));", &mut tables, &mut buffer_pool), vec![3]);
+
+        // columns get the type both sides convert to, the rows of each side are converted to it
+        let strings = |sql: &str, tables: &mut Vec<TableSchema>, buffer_pool: &mut BufferPoolManager| {
+            let results = run(buffer_pool, tables, sql);
+            results[0].rows.iter().map(|r| r[0].to_string()).collect::<Vec<String>>()
+        };
+        assert_eq!(strings("select x + 1 from (select 1 as x union select 1.5) as d;", &mut tables, &mut buffer_pool), ["2", "2.5"]);
+        assert_eq!(strings("select x * 3 from (select 1.5 as x union all select 3) as d;", &mut tables, &mut buffer_pool), ["4.5", "9"]);
+        assert_eq!(strings("select x + 0.5 from (select a as x from t intersect select 3.0) as d;", &mut tables, &mut buffer_pool), ["3.5"]);
+        assert_eq!(strings("select x from (select 1::smallint as x except select 2::bigint) as d;", &mut tables, &mut buffer_pool), ["1"]);
+        assert_eq!(strings("select x from (select date '2024-01-31' as x union select timestamp '2024-01-31 10:00') as d;", &mut tables, &mut buffer_pool),
+            ["2024-01-31 00:00:00", "2024-01-31 10:00:00"]);
+        // the recursive rows of a cte take the type of its base, which can't be widened for them
+        let sql = "with recursive r (n) as (select 1::decimal union all select (n + 1)::int from r where n < 3) select n + 0.5 from r;";
+        assert_eq!(strings(sql, &mut tables, &mut buffer_pool), ["1.5", "2.5", "3.5"]);
+        let error = run_error(&mut buffer_pool, &mut tables, "with recursive r (n) as (select 1 union all select n + 0.5 from r where n < 2) select n from r;");
+        assert_eq!(error.message(), "recursive query \"r\" column has type integer in non-recursive term but decimal overall");
+
+        // types are named in errors as they are written in sql
+        let error = run_error(&mut buffer_pool, &mut tables, "select a from t union select true;");
+        assert_eq!(error.message(), "union types integer and bool cannot be matched in column 1");
+        let error = run_error(&mut buffer_pool, &mut tables, "select case when true then a else 'x'::text end from t;");
+        assert_eq!(error.message(), "case types integer and text cannot be matched");
+    }
+
+    #[test]
+    fn test_common_table_expressions() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        // org chart, 1 manages 2 and 3, 3 manages 4, 5 manages 6
+        run(&mut buffer_pool, &mut tables, "create table emp (id int, manager int);");
+        run(&mut buffer_pool, &mut tables, "insert into emp (id, manager) values (1, 0), (2, 1), (3, 1), (4, 3), (5, 0), (6, 5);");
+
+        let ints = |sql: &str, tables: &mut Vec<TableSchema>, buffer_pool: &mut BufferPoolManager| {
+            let results = run(buffer_pool, tables, sql);
+            results[0].rows.iter().map(|r| match r[0] { LiteralValue::IntValue(i) => i, _ => panic!("expected int") }).collect::<Vec<i64>>()
+        };
+
+        // inlined
+        assert_eq!(ints("with top (boss) as (select id from emp where manager = 0) select boss from top;", &mut tables, &mut buffer_pool), vec![1, 5]);
+        // materialized, referenced twice
+        assert_eq!(ints("with top as (select id from emp where manager = 0) select id from top where id in (select id from top where id = 5);", &mut tables, &mut buffer_pool), vec![5]);
+        // later ctes can use earlier ones
+        assert_eq!(ints("with a as (select id from emp where manager = 0), b as (select id from emp where manager in (select id from a)) select id from b;", &mut tables, &mut buffer_pool), vec![2, 3, 6]);
+
+        // everyone under 1
+        let reports = "with recursive reports (id) as (select id from emp where id = 1 union all select id from emp where manager in (select id from reports)) select * from reports;";
+        assert_eq!(ints(reports, &mut tables, &mut buffer_pool), vec![1, 2, 3, 4]);
+
+        // union without all stops on a cycle
+        run(&mut buffer_pool, &mut tables, "insert into emp (id, manager) values (1, 4);");
+        let reports = "with recursive reports (id) as (select id from emp where id = 3 union select id from emp where manager in (select id from reports)) select * from reports;";
+        assert_eq!(ints(reports, &mut tables, &mut buffer_pool), vec![3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn test_expressions() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table pet (id int, name text, kind text);");
+        run(&mut buffer_pool, &mut tables, "insert into pet (id, name, kind) values (1, 'Rex', 'dog'), (2, 'Tom', 'cat'), (3, 'rover', 'dog'), (4, 'Polly', 'bird');");
+
+        let strings = |sql: &str, tables: &mut Vec<TableSchema>, buffer_pool: &mut BufferPoolManager| {
+            let results = run(buffer_pool, tables, sql);
+            results[0].rows.iter().map(|r| r[0].to_string()).collect::<Vec<String>>()
+        };
+
+        assert_eq!(strings("select distinct kind from pet;", &mut tables, &mut buffer_pool), vec!["dog", "cat", "bird"]);
+        assert_eq!(strings("select name from pet where id in (1, 4);", &mut tables, &mut buffer_pool), vec!["Rex", "Polly"]);
+        assert_eq!(strings("select name from pet where id not between 2 and 3;", &mut tables, &mut buffer_pool), vec!["Rex", "Polly"]);
+        assert_eq!(strings("select name from pet where name like 'r%';", &mut tables, &mut buffer_pool), vec!["rover"]);
+        assert_eq!(strings("select name from pet where name ilike 'r%';", &mut tables, &mut buffer_pool), vec!["Rex", "rover"]);
+        assert_eq!(strings("select name from pet where name not like '_o%';", &mut tables, &mut buffer_pool), vec!["Rex"]);
+        assert_eq!(
+            strings("select case kind when 'dog' then 'woof' when 'cat' then 'meow' end from pet;", &mut tables, &mut buffer_pool),
+            vec!["woof", "meow", "woof", "NULL"]
+        );
+        assert_eq!(
+            strings("select case when id < 2 then 'first' when id in (2, 3) then 'middle' else 'last' end from pet;", &mut tables, &mut buffer_pool),
+            vec!["first", "middle", "middle", "last"]
+        );
+        // null in an in list makes a miss unknown
+        assert_eq!(strings("select name from pet where id not in (1, null);", &mut tables, &mut buffer_pool), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_drop_and_truncate() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table a (x int); create table b (y int);");
+        run(&mut buffer_pool, &mut tables, "insert into a (x) values (1), (2); insert into b (y) values (3);");
+        let dropped_page_id = tables[0].first_page_id;
+
+        run(&mut buffer_pool, &mut tables, "drop table a; drop table if exists a;");
+        assert_eq!(tables.len(), 1);
+        assert!(load_catalog(&mut buffer_pool).unwrap().iter().all(|t| t.name != "a"));
+        let mut scanner = Scanner::new();
+        scanner.scan(&String::from("select x from a;")).unwrap();
+        let stmt = Parser::new().parse(&scanner.tokens).unwrap().pop().unwrap();
+        assert!(matches!(plan(&mut tables, stmt).map_err(TError::unspanned), Err(TError::PlanError(..))));
+
+        // the dropped table's page is handed out again
+        run(&mut buffer_pool, &mut tables, "create table c (z int);");
+        assert_eq!(tables[1].first_page_id, dropped_page_id);
+        let results = run(&mut buffer_pool, &mut tables, "select z from c;");
+        assert!(results[0].rows.is_empty());
+
+        run(&mut buffer_pool, &mut tables, "truncate b;");
+        let results = run(&mut buffer_pool, &mut tables, "select y from b;");
+        assert!(results[0].rows.is_empty());
+        run(&mut buffer_pool, &mut tables, "insert into b (y) values (4);");
+        let results = run(&mut buffer_pool, &mut tables, "select y from b;");
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(4)]]);
+
+        // the catalog has the same tables, truncated ones are entered again at the end
+        let entries = |tables: &[TableSchema]| {
+            let mut entries: Vec<(String, u32)> = tables.iter().map(|t| (t.name.clone(), t.first_page_id)).collect();
+            entries.sort();
+            entries
+        };
+        assert_eq!(entries(&load_catalog(&mut buffer_pool).unwrap()), entries(&tables));
+    }
+
+    #[test]
+    fn test_alter_table() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table t (a int, b text);");
+        run(&mut buffer_pool, &mut tables, "insert into t (a, b) values (1, 'one'), (2, null);");
+        run(&mut buffer_pool, &mut tables, "alter table t add column c bool default true; alter table t add d int;");
+        run(&mut buffer_pool, &mut tables, "insert into t (a, b, c, d) values (3, 'three', false, 30);");
+        run(&mut buffer_pool, &mut tables, "alter table t drop column a; alter table t rename column b to name; alter table t rename to u;");
+
+        let results = run(&mut buffer_pool, &mut tables, "select * from u;");
+        assert_eq!(results[0].columns, vec!["name", "c", "d"]);
+        assert_eq!(results[0].rows, vec![
+            vec![LiteralValue::StringValue(String::from("one")), LiteralValue::BooleanValue(true), LiteralValue::NullValue],
+            vec![LiteralValue::NullValue, LiteralValue::BooleanValue(true), LiteralValue::NullValue],
+            vec![LiteralValue::StringValue(String::from("three")), LiteralValue::BooleanValue(false), LiteralValue::IntValue(30)],
+        ]);
+
+        // the altered schema is what the catalog loads
+        let catalog = load_catalog(&mut buffer_pool).unwrap();
+        assert_eq!(catalog.len(), 1);
+        assert_eq!(catalog[0].name, "u");
+        assert_eq!(catalog[0].columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>(), vec!["name", "c", "d"]);
+
+        for sql in ["alter table u add name int;", "alter table u drop column a;", "alter table u rename x to y;", "alter table u add e int default true;"] {
+            let mut scanner = Scanner::new();
+            scanner.scan(&sql.to_owned()).unwrap();
+            let stmt = Parser::new().parse(&scanner.tokens).unwrap().pop().unwrap();
+            assert!(matches!(plan(&mut tables, stmt).map_err(TError::unspanned), Err(TError::PlanError(..))), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_alter_table_with_a_full_catalog() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table t (a int, p int); insert into t (a) values (1);");
+        // every dropped column stays in the schema, so it grows until it doesn't fit in a catalog page by itself
+        let mut error = None;
+        for i in 0..2000 {
+            let sql = match i % 2 {
+                0 => format!("alter table t add column c{} int;", i),
+                _ => format!("alter table t drop column c{};", i - 1),
+            };
+            let mut scanner = Scanner::new();
+            scanner.scan(&sql).unwrap();
+            let stmt = Parser::new().parse(&scanner.tokens).unwrap().pop().unwrap();
+            let plan = plan(&mut tables, stmt).unwrap();
+            if let Err(e) = execute(&mut buffer_pool, &mut tables, plan) {
+                error = Some((sql, e));
+                break;
+            }
+        }
+        let (sql, error) = error.unwrap();
+        assert_eq!(error.sqlstate(), "54000");
+        assert!(error.message().starts_with("catalog entry is too big"));
+
+        // the table is still in the catalog as it was before the failed statements
+        for sql in [sql.as_str(), "create index index_with_a_name_longer_than_any_column_the_table_has on t (a);"] {
+            assert!(run_error(&mut buffer_pool, &mut tables, sql).message().starts_with("catalog entry is too big"), "{}", sql);
+        }
+        let catalog = load_catalog(&mut buffer_pool).unwrap();
+        assert_eq!(catalog.len(), 1);
+        assert_eq!((catalog[0].version, catalog[0].columns.len(), catalog[0].indexes.len()), (tables[0].version, tables[0].columns.len(), 0));
+        assert_eq!(catalog[0].first_page_id, tables[0].first_page_id);
+        let results = run(&mut buffer_pool, &mut tables, "select a from t;");
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(1)]]);
+
+        // an entry as big as the one it replaces still fits
+        run(&mut buffer_pool, &mut tables, "truncate t;");
+        let catalog = load_catalog(&mut buffer_pool).unwrap();
+        assert_eq!((catalog.len(), catalog[0].first_page_id), (1, tables[0].first_page_id));
+    }
+
+    #[test]
+    fn test_primary_key_and_unique() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table t (id int primary key, name text unique, a int, b int, unique (a, b));");
+        let indexes: Vec<&str> = tables[0].indexes.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(indexes, vec!["t_pkey", "t_name_key", "t_a_b_key"]);
+        run(&mut buffer_pool, &mut tables, "insert into t (id, name, a, b) values (1, 'x', 1, 1), (2, null, 1, 2), (3, null, 2, 1);");
+
+        for sql in [
+            "insert into t (id, name, a, b) values (1, 'y', 5, 5);",
+            "insert into t (id, name, a, b) values (4, 'x', 5, 5);",
+            "insert into t (id, name, a, b) values (4, 'y', 1, 2);",
+            "insert into t (id, name, a, b) values (4, 'y', 5, 5), (4, 'z', 6, 6);",
+            "insert into t (id, name, a, b) values (null, 'y', 5, 5);",
+            "update t set id = 1 where id = 2;",
+            "update t set a = 1, b = 1;",
+        ] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::ConstraintError(..)), "{}", sql);
+        }
+        // failed statements don't write anything
+        let results = run(&mut buffer_pool, &mut tables, "select id, a from t;");
+        assert_eq!(results[0].rows.len(), 3);
+        assert!(matches!(results[0].rows[1][..], [LiteralValue::IntValue(2), LiteralValue::IntValue(1)]));
+
+        // nulls never conflict, and composite keys only conflict on all of their columns
+        run(&mut buffer_pool, &mut tables, "insert into t (id, name, a, b) values (4, null, 1, null), (5, null, 1, null), (6, 'y', 3, 1);");
+
+        // every row is read before any is written, so keys can be swapped
+        run(&mut buffer_pool, &mut tables, "update t set id = case when id = 1 then 2 when id = 2 then 1 else id end where id in (1, 2);");
+        let results = run(&mut buffer_pool, &mut tables, "select name from t where id = 2;");
+        assert!(matches!(&results[0].rows[..], [row] if row[0] == LiteralValue::StringValue(String::from("x"))));
+        run(&mut buffer_pool, &mut tables, "update t set name = 'z' where id = 3;");
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "update t set name = 'z' where id = 6;"), TError::ConstraintError(..)));
+
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "create table s (a int primary key, b int primary key);"), TError::PlanError(..)));
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "create table s (a int, unique (c));"), TError::PlanError(..)));
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "alter table t drop column b;"), TError::PlanError(..)));
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "update t set c = 1;"), TError::PlanError(..)));
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "update t set id = 'x';"), TError::ExecutionError(..)));
+    }
+
+    #[test]
+    fn test_tables_and_indexes_spanning_pages() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table t (id int primary key, name text);");
+        let rows: Vec<String> = (0..600).map(|i| format!("({}, 'name number {:0>40}')", i, i)).collect();
+        run(&mut buffer_pool, &mut tables, &format!("insert into t (id, name) values {};", rows.join(", ")));
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "insert into t (id, name) values (7, 'again');"), TError::ConstraintError(..)));
+        run(&mut buffer_pool, &mut tables, "update t set name = 'short' where id in (1, 2, 3);");
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "insert into t (id, name) values (2, 'again');"), TError::ConstraintError(..)));
+
+        let results = run(&mut buffer_pool, &mut tables, "select id from t;");
+        assert_eq!(results[0].rows.len(), 600);
+        let results = run(&mut buffer_pool, &mut tables, "select name from t where id = 599;");
+        assert!(matches!(&results[0].rows[..], [row] if row[0] == LiteralValue::StringValue(format!("name number {:0>40}", 599))));
+
+        // truncate starts over with empty indexes
+        run(&mut buffer_pool, &mut tables, "truncate t; insert into t (id, name) values (7, 'again');");
+    }
+
+    #[test]
+    fn test_foreign_keys() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table p (id int primary key, code text unique);");
+        run(&mut buffer_pool, &mut tables, "create table c (id int primary key, pid int references p on delete cascade on update cascade, code text references p (code) on delete set null on update set null);");
+        run(&mut buffer_pool, &mut tables, "create table r (pid int, foreign key (pid) references p (id));");
+        run(&mut buffer_pool, &mut tables, "insert into p (id, code) values (1, 'a'), (2, 'b'), (3, 'c');");
+        run(&mut buffer_pool, &mut tables, "insert into c (id, pid, code) values (10, 1, 'a'), (11, 2, 'b'), (12, null, 'c'), (13, 3, null), (14, null, null);");
+        run(&mut buffer_pool, &mut tables, "insert into r (pid) values (3);");
+
+        for sql in [
+            "insert into c (id, pid, code) values (15, 9, null);",
+            "insert into c (id, pid, code) values (15, null, 'z');",
+            "update c set pid = 9 where id = 10;",
+            "delete from p where id = 3;",
+            "update p set id = 30 where id = 3;",
+        ] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::ConstraintError(..)), "{}", sql);
+        }
+        for sql in ["drop table p;", "truncate p;", "alter table c drop column pid;", "create table s (a int references c (pid));", "create table s (a text references p);"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::PlanError(..)), "{}", sql);
+        }
+
+        run(&mut buffer_pool, &mut tables, "update p set id = 20 where id = 2; update p set code = 'x' where id = 1; delete from p where id = 1;");
+        let results = run(&mut buffer_pool, &mut tables, "select id, pid, code from c;");
+        let mut rows: Vec<String> = results[0].rows.iter().map(|r| format!("{} {} {}", r[0], r[1], r[2])).collect();
+        rows.sort();
+        let null = LiteralValue::NullValue.to_string();
+        assert_eq!(rows, vec![format!("11 20 b"), format!("12 {} c", null), format!("13 3 {}", null), format!("14 {} {}", null, null)]);
+
+        // rows can reference rows of their own table, even ones inserted by the same statement
+        run(&mut buffer_pool, &mut tables, "create table e (id int primary key, boss int references e on delete cascade);");
+        run(&mut buffer_pool, &mut tables, "insert into e (id, boss) values (1, null), (2, 1), (3, 2), (4, 4);");
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "insert into e (id, boss) values (5, 9);"), TError::ConstraintError(..)));
+        run(&mut buffer_pool, &mut tables, "delete from e where id = 1;");
+        let results = run(&mut buffer_pool, &mut tables, "select id from e;");
+        assert!(matches!(&results[0].rows[..], [row] if row[0] == LiteralValue::IntValue(4)));
+
+        // renaming the referenced table keeps the foreign keys pointing at it, also in the catalog
+        run(&mut buffer_pool, &mut tables, "alter table p rename to q; insert into c (id, pid, code) values (15, 20, 'c');");
+        let catalog = load_catalog(&mut buffer_pool).unwrap();
+        let c = catalog.iter().find(|t| t.name == "c").unwrap();
+        assert!(c.foreign_keys.iter().all(|f| f.table == "q"));
+    }
+
+    #[test]
+    fn test_check_and_default() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table t (a int check (a > 0), b int default case when true then 2 end, c text default 'x', check (b < 10 or c = 'y'));");
+        run(&mut buffer_pool, &mut tables, "insert into t (a) values (1); insert into t (a, b, c) values (2, null, null), (3, 20, 'y');");
+        let results = run(&mut buffer_pool, &mut tables, "select a, b, c from t where a = 1;");
+        assert_eq!(results[0].rows[0], vec![LiteralValue::IntValue(1), LiteralValue::IntValue(2), LiteralValue::StringValue("x".to_owned())]);
+        let results = run(&mut buffer_pool, &mut tables, "select b, c from t where a = 2;");
+        assert_eq!(results[0].rows[0], vec![LiteralValue::NullValue, LiteralValue::NullValue]);
+
+        for sql in ["insert into t (a) values (0);", "insert into t (a, b) values (4, 10);", "update t set a = 0 where a = 2;", "update t set c = 'z' where a = 3;"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::ConstraintError(..)), "{}", sql);
+        }
+        for sql in ["create table s (a int check (a));", "create table s (a int check (b > 0));", "insert into t (a, a) values (1, 2);"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::PlanError(..)), "{}", sql);
+        }
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "create table s (a int default 'x');"), TError::ExecutionError(..)));
+
+        // checks follow renamed columns, and keep the columns they use from being dropped
+        run(&mut buffer_pool, &mut tables, "alter table t rename column a to d;");
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "insert into t (d) values (0);"), TError::ConstraintError(..)));
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "alter table t drop column d;"), TError::PlanError(..)));
+        let catalog = load_catalog(&mut buffer_pool).unwrap();
+        let t = catalog.iter().find(|t| t.name == "t").unwrap();
+        assert_eq!(t.checks.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["t_a_check", "t_check"]);
+        assert_eq!(t.columns[1].default_expr.as_deref(), Some("case when true then 2 end"));
+    }
+
+    #[test]
+    fn test_sequences_and_identity() {
+        let _setup = TestSetup;
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        {
+            let mut tables = Vec::new();
+            let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+            run(&mut buffer_pool, &mut tables, "create sequence s start with 10 increment by 5;");
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, "select currval('s');"), TError::ExecutionError(..)));
+            let results = run(&mut buffer_pool, &mut tables, "select nextval('s'), currval('s');");
+            assert_eq!(results[0].rows[0], vec![LiteralValue::IntValue(10), LiteralValue::IntValue(10)]);
+
+            run(&mut buffer_pool, &mut tables, "create table t (id serial primary key, n int generated always as identity (start 100), name text);");
+            run(&mut buffer_pool, &mut tables, "insert into t (name) values ('a'), ('b'); insert into t (id, name) values (7, 'c');");
+            let results = run(&mut buffer_pool, &mut tables, "select id, n from t;");
+            let rows: Vec<_> = results[0].rows.iter().map(|r| (r[0].clone(), r[1].clone())).collect();
+            assert_eq!(rows, vec![
+                (LiteralValue::IntValue(1), LiteralValue::IntValue(100)),
+                (LiteralValue::IntValue(2), LiteralValue::IntValue(101)),
+                (LiteralValue::IntValue(7), LiteralValue::IntValue(102)),
+            ]);
+
+            for sql in ["insert into t (n) values (1);", "update t set n = 1;", "create table u (a text generated always as identity);", "create sequence t;", "select nextval(1);"] {
+                assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::PlanError(..)), "{}", sql);
+            }
+            for sql in ["create sequence s;", "drop sequence nope;", "select nextval('nope');"] {
+                assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::ExecutionError(..)), "{}", sql);
+            }
+            buffer_pool.flush_all_pages().unwrap();
+        }
+
+        // sequences carry on where they were after a restart
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+        let mut tables = load_catalog(&mut buffer_pool).unwrap();
+        run(&mut buffer_pool, &mut tables, "insert into t (name) values ('d');");
+        let results = run(&mut buffer_pool, &mut tables, "select id, n from t where name = 'd';");
+        assert_eq!(results[0].rows[0], vec![LiteralValue::IntValue(3), LiteralValue::IntValue(103)]);
+        let results = run(&mut buffer_pool, &mut tables, "select nextval('s');");
+        assert_eq!(results[0].rows[0], vec![LiteralValue::IntValue(15)]);
+
+        // owned sequences follow their table
+        run(&mut buffer_pool, &mut tables, "alter table t rename to v;");
+        let results = run(&mut buffer_pool, &mut tables, "select currval('t_id_seq');");
+        assert_eq!(results[0].rows[0], vec![LiteralValue::IntValue(3)]);
+        run(&mut buffer_pool, &mut tables, "drop table v; drop sequence s;");
+        assert!(load_sequences(&mut buffer_pool).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_numeric_types() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table n (s smallint, i integer, b bigint, r real, d double precision, m decimal(5,2), f float, x numeric);");
+        run(&mut buffer_pool, &mut tables, "insert into n (s, i, b, r, d, m, f, x) values (1, 2, 3, 0.1, 0.1, 1.005, 2.5e-1, 123456789012345678901234567890.5);");
+        let results = run(&mut buffer_pool, &mut tables, "select s + i, i * b, b / 2, -s, r, d, m, m + 1, f * 2, x from n where m > 1 and r < 1;");
+        let row: Vec<String> = results[0].rows[0].iter().map(|v| v.to_string()).collect();
+        assert_eq!(row, vec!["3", "6", "1", "-1", "0.1", "0.1", "1.01", "2.01", "0.5", "123456789012345678901234567890.5"]);
+
+        let results = run(&mut buffer_pool, &mut tables, "select 1 / 4, 1.0 / 4, 7 % -3, 2 * 0.5 = 1, 1 + 2 * 3 - -1, -9223372036854775808, 1e3;");
+        let row: Vec<String> = results[0].rows[0].iter().map(|v| v.to_string()).collect();
+        assert_eq!(row, vec!["0", "0.2500000000000000", "1", "true", "8", "-9223372036854775808", "1000"]);
+        // numbers with an exponent that don't fit in a decimal are doubles, other ones are an error
+        let results = run(&mut buffer_pool, &mut tables, "select 1.5e-40, -1e308, 1e39 * 2;");
+        assert_eq!(results[0].rows[0], vec![LiteralValue::FloatValue(1.5e-40), LiteralValue::FloatValue(-1e308), LiteralValue::FloatValue(2e39)]);
+        for sql in ["select 1e400;", "select 1e-400;", "select 0.0000000000000000000000000000000000000001;"] {
+            assert!(matches!(Scanner::new().scan(&sql.to_owned()), Err(TError::Spanned(e, _)) if matches!(*e, TError::ScanError(_))), "{}", sql);
+        }
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "select cast(1.5e-40 as decimal);"), TError::ExecutionError(..)));
+
+        for sql in [
+            "insert into n (s) values (32768);", "insert into n (m) values (1000);", "update n set i = i * 2000000000;",
+            "select 9223372036854775807 + 1;", "select - -9223372036854775808;", "select 1 / 0;", "select 1.5 % 0;", "select 1e37 * 1e37;",
+            "select d * 1e37 * 1e37 * 1e37 * 1e37 * 1e37 * 1e37 * 1e37 * 1e37 * 1e37 from n;",
+        ] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::ExecutionError(..)), "{}", sql);
+        }
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "select s from n where s = 'x';"), TError::ExecutionError(..)));
+        for sql in ["select 1 + 'a';", "select -true;"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::PlanError(..)), "{}", sql);
+        }
+        let results = run(&mut buffer_pool, &mut tables, "select s, m from n;");
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(1), LiteralValue::DecimalValue(Decimal::parse("1.01").unwrap())]]);
+    }
+
+    #[test]
+    fn test_dates_and_times() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table e (id int, d date, t time, ts timestamp primary key, i interval);");
+        run(&mut buffer_pool, &mut tables, "insert into e (id, d, t, ts, i) values
+            (1, date '2026-01-31', time '08:30', timestamp '2026-01-31 23:59:59.5', interval '1 month 2 hours'),
+            (2, date '2024-02-29', null, date '2024-03-01', interval '-1 day');");
+        let results = run(&mut buffer_pool, &mut tables, "select d + 1, d - date '2026-01-01', ts + i, d + interval '1 month', t + interval '16 hours',
+            extract(year from d), extract(second from ts), date_trunc('month', ts), i * 2, -i from e where id = 1;");
+        let row: Vec<String> = results[0].rows[0].iter().map(|v| v.to_string()).collect();
+        assert_eq!(row, vec![
+            "2026-02-01", "30", "2026-03-01 01:59:59.5", "2026-02-28 00:00:00", "00:30:00",
+            "2026", "59.5", "2026-01-01 00:00:00", "2 mons 04:00:00", "-1 mons -02:00:00",
+        ]);
+
+        // dates compare with timestamps at their midnight
+        let results = run(&mut buffer_pool, &mut tables, "select id, ts - d from e where ts = date '2024-03-01' and i < interval '0 days' and now() > ts;");
+        assert_eq!(results[0].rows.len(), 1);
+        assert_eq!(results[0].rows[0][1].to_string(), "1 day");
+
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "insert into e (ts) values (timestamp '2024-03-01 00:00');"), TError::ConstraintError(..)));
+        for sql in ["select date '9999-12-31' + 1;", "select interval '1 day' / 0;"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::ExecutionError(..)), "{}", sql);
+        }
+        for sql in ["select d + d from e;", "select extract(dow from t) from e;", "select date_trunc('fortnight', ts) from e;", "insert into e (d) values (1);"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::PlanError(..)), "{}", sql);
+        }
+        for sql in ["select date '2026-02-30';", "select interval '3 fortnights';", "select timestamp 'yesterday';"] {
+            let mut scanner = Scanner::new();
+            scanner.scan(&sql.to_owned()).unwrap();
+            assert!(Parser::new().parse(&scanner.tokens).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_casts_and_coercion() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        // string literals are read as values of the column, numbers and bools are written to text
+        run(&mut buffer_pool, &mut tables, "create table c (id smallint, price decimal(6, 2) default '9.99', d date, note text);");
+        run(&mut buffer_pool, &mut tables, "insert into c (id, d, note) values ('1', '2026-10-19', 1.5), (2, '2026-10-20', true);");
+        run(&mut buffer_pool, &mut tables, "update c set price = 3, note = d where id = 2;");
+        let results = run(&mut buffer_pool, &mut tables, "select id, price, d, note from c where d >= '2026-10-19' and d in ('2026-10-20', '2027-01-01');");
+        let row: Vec<String> = results[0].rows[0].iter().map(|v| v.to_string()).collect();
+        assert_eq!(row, vec!["2", "3.00", "2026-10-20", "2026-10-20"]);
+
+        let results = run(&mut buffer_pool, &mut tables, "select cast('42' as int) + 1, '3.14159'::decimal(4, 2), cast(price as int), id::text like '1', d::timestamp, cast(1 as bool), '1 day'::interval * 2 from c where id = 1;");
+        let row: Vec<String> = results[0].rows[0].iter().map(|v| v.to_string()).collect();
+        assert_eq!(row, vec!["43", "3.14", "10", "true", "2026-10-19 00:00:00", "true", "2 days"]);
+
+        for sql in ["select cast(note as int) from c;", "select '70000'::smallint;", "select cast(price as decimal(2, 1)) from c;", "insert into c (d) values ('tomorrow-ish');"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::ExecutionError(..)), "{}", sql);
+        }
+        for sql in ["select cast(d as int) from c;", "insert into c (id) values (note);", "select id from c where d = 1;"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::PlanError(..)), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_large_values_and_bytea() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table b (id int primary key, body text, data bytea);");
+        // values of several pages, more than the buffer pool holds
+        let body = "lorem ipsum ".repeat(2000);
+        let data: String = (0..10000).map(|i| format!("{:02x}", i % 256)).collect();
+        run(&mut buffer_pool, &mut tables, &format!("insert into b (id, body, data) values (1, '{}', '\\x{}'), (2, 'short', '\\x0aff');", body, data));
+        let results = run(&mut buffer_pool, &mut tables, "select body, data from b where id = 1; select data, data::text from b where id = 2;");
+        assert_eq!(results[0].rows[0][0], LiteralValue::StringValue(body.clone()));
+        assert_eq!(results[0].rows[0][1], LiteralValue::BytesValue((0..10000).map(|i| (i % 256) as u8).collect()));
+        assert_eq!(results[1].rows[0][0].to_string(), "\\x0aff");
+        assert_eq!(results[1].rows[0][1], LiteralValue::StringValue("\\x0aff".to_owned()));
+
+        // updates write the value again and free the old pages, so do deletes, drops and truncates
+        run(&mut buffer_pool, &mut tables, "update b set body = 'x' where id = 1;");
+        let results = run(&mut buffer_pool, &mut tables, "select id from b where body = 'x' and data < '\\x0a';");
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(1)]]);
+        run(&mut buffer_pool, &mut tables, &format!("insert into b (id, body) values (3, '{}');", body));
+        run(&mut buffer_pool, &mut tables, "delete from b where id = 3;");
+        run(&mut buffer_pool, &mut tables, "truncate table b;");
+        run(&mut buffer_pool, &mut tables, &format!("insert into b (id, body) values (4, '{}');", body));
+        run(&mut buffer_pool, &mut tables, "drop table b;");
+
+        for sql in ["select '\\xabc'::bytea;", "select 'é\\q'::bytea;"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::ExecutionError(..)), "{}", sql);
+        }
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "select cast(1 as bytea);"), TError::PlanError(..)));
+    }
+
+    #[test]
+    fn test_json() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table docs (id int primary key, doc jsonb);");
+        run(&mut buffer_pool, &mut tables, r#"insert into docs (id, doc) values
+            (1, '{"name": "ann", "tags": ["a", "b"], "address": {"city": "oslo"}, "age": 31}'),
+            (2, '{"name": "bob", "tags": [], "age": null}'),
+            (3, '[1, 2, 3]'),
+            (4, null);"#);
+        let results = run(&mut buffer_pool, &mut tables, "select doc -> 'name', doc ->> 'name', doc -> 'tags' -> 0, doc #> '{address,city}', doc #>> '{tags,-1}' from docs where id = 1;");
+        assert_eq!(results[0].rows[0].iter().map(|v| v.to_string()).collect::<Vec<_>>(), vec!["\"ann\"", "ann", "\"a\"", "\"oslo\"", "b"]);
+        let results = run(&mut buffer_pool, &mut tables, "select doc, doc -> 'age', doc ->> 'age', doc -> 'missing' from docs where id = 2;");
+        assert_eq!(results[0].rows[0][0].to_string(), r#"{"age": null, "name": "bob", "tags": []}"#);
+        assert_eq!(results[0].rows[0][1].to_string(), "null");
+        assert_eq!(results[0].rows[0][2..], [LiteralValue::NullValue, LiteralValue::NullValue]);
+
+        let results = run(&mut buffer_pool, &mut tables, r#"select id from docs where doc -> 'address' = '{"city":"oslo"}';
+            select id from docs where (doc ->> 'age')::int > 30;
+            select id from docs where json_extract(doc, '$.tags[1]') = 'b';
+            select json_array_length(doc), json_array_length(doc -> 'tags') from docs where id = 3;
+            select json_extract('{"a": [{"b": 2.5}]}', '$.a[0].b'), '[1, [2]]' -> 1, cast('{"x": 7}'::json -> 'x' as int);"#);
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(1)]]);
+        assert_eq!(results[1].rows, vec![vec![LiteralValue::IntValue(1)]]);
+        assert_eq!(results[2].rows, vec![vec![LiteralValue::IntValue(1)]]);
+        assert_eq!(results[3].rows, vec![vec![LiteralValue::IntValue(3), LiteralValue::NullValue]]);
+        assert_eq!(results[4].rows[0].iter().map(|v| v.to_string()).collect::<Vec<_>>(), vec!["2.5", "[2]", "7"]);
+
+        // documents too big for a tuple go to overflow pages like text
+        let big = format!("[{}]", vec!["\"lorem ipsum\""; 3000].join(", "));
+        run(&mut buffer_pool, &mut tables, &format!("insert into docs (id, doc) values (5, '{}');", big));
+        let results = run(&mut buffer_pool, &mut tables, "select json_array_length(doc), doc ->> 2999 from docs where id = 5;");
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(3000), LiteralValue::StringValue("lorem ipsum".to_owned())]]);
+
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "insert into docs (id, doc) values (6, '{\"a\": }');"), TError::ExecutionError(..)));
+        for sql in ["select doc -> 1 from docs where id = 1 and 1 -> 'a' = 1;", "select 'a'::text -> 'b';", "select doc #> 1 from docs;", "select json_array_length(1);"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::PlanError(..)), "{}", sql);
+        }
+        for sql in ["select json_array_length(doc) from docs where id = 1;", "select doc #> 'a' from docs;", "select json_extract(doc, 'a') from docs;", "select (doc -> 'name')::int from docs;"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::ExecutionError(..)), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_indexes() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table docs (id int primary key, n int, doc json);");
+        run(&mut buffer_pool, &mut tables, r#"insert into docs (id, n, doc) values
+            (1, 10, '{"name": "ann", "age": 31}'),
+            (2, 20, '{"name": "bob"}'),
+            (3, 10, '{"name": "cat"}'),
+            (4, null, null);"#);
+        run(&mut buffer_pool, &mut tables, "create unique index docs_name_idx on docs ((doc ->> 'name')); create index docs_n_idx on docs (n);");
+        let results = run(&mut buffer_pool, &mut tables, "select id from docs where doc ->> 'name' = 'bob'; select id from docs where n = 10 and id > 1; select id from docs where 10 = n;");
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(2)]]);
+        assert_eq!(results[1].rows, vec![vec![LiteralValue::IntValue(3)]]);
+        assert_eq!(results[2].rows, vec![vec![LiteralValue::IntValue(1)], vec![LiteralValue::IntValue(3)]]);
+
+        // the indexes follow inserts, updates and deletes
+        let error = run_error(&mut buffer_pool, &mut tables, r#"insert into docs (id, n, doc) values (5, 1, '{"name": "ann"}');"#);
+        assert!(matches!(&error, TError::ConstraintError(..)));
+        assert_eq!(error.message(), r#"duplicate key value violates unique constraint "docs_name_idx", key ((doc ->> 'name'))=(ann) already exists"#);
+        run(&mut buffer_pool, &mut tables, r#"update docs set doc = '{"name": "dan"}', n = 30 where id = 2; delete from docs where id = 1;"#);
+        run(&mut buffer_pool, &mut tables, r#"insert into docs (id, n, doc) values (5, 10, '{"name": "ann"}');"#);
+        let results = run(&mut buffer_pool, &mut tables, "select id from docs where doc ->> 'name' = 'bob'; select id from docs where doc ->> 'name' = 'dan'; select id from docs where n = 10;");
+        assert!(results[0].rows.is_empty());
+        assert_eq!(results[1].rows, vec![vec![LiteralValue::IntValue(2)]]);
+        assert_eq!(results[2].rows, vec![vec![LiteralValue::IntValue(3)], vec![LiteralValue::IntValue(5)]]);
+
+        // renames carry over to the index expressions
+        run(&mut buffer_pool, &mut tables, "alter table docs rename column doc to body; alter table docs rename to notes;");
+        let error = run_error(&mut buffer_pool, &mut tables, r#"insert into notes (id, n, body) values (6, 1, '{"name": "cat"}');"#);
+        assert!(error.message().contains("key ((body ->> 'name'))=(cat)"), "{}", error.message());
+        let results = run(&mut buffer_pool, &mut tables, "select id from notes where body ->> 'name' = 'cat';");
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(3)]]);
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "alter table notes drop column body;"), TError::PlanError(..)));
+
+        for sql in ["create unique index notes_n_idx on notes (n);", "create index docs_pkey on notes (n);", "create index on_missing on missing (n);", "create index notes_now_idx on notes ((now()));", "drop index docs_pkey;", "drop index missing_idx;"] {
+            run_error(&mut buffer_pool, &mut tables, sql);
+        }
+        run(&mut buffer_pool, &mut tables, "drop index docs_name_idx; drop index if exists docs_name_idx;");
+        run(&mut buffer_pool, &mut tables, r#"insert into notes (id, n, body) values (6, 1, '{"name": "cat"}');"#);
+        let results = run(&mut buffer_pool, &mut tables, "select id from notes where body ->> 'name' = 'cat';");
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(3)], vec![LiteralValue::IntValue(6)]]);
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, r#"CREATE TABLE "My Table" ("Id" INT GENERATED ALWAYS AS IDENTITY PRIMARY KEY, "select" int CHECK ("select" > 0), Foo_$ Int);
+            Insert Into "My Table" ("select", FOO_$) Values (1, 10), (2, 20);"#);
+        let results = run(&mut buffer_pool, &mut tables, r#"SELECT "Id", "select", foo_$ FROM "My Table" WHERE "My Table"."select" = 2;
+            select nextval('"My Table_Id_seq"');"#);
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(2), LiteralValue::IntValue(2), LiteralValue::IntValue(20)]]);
+        assert_eq!(results[1].rows, vec![vec![LiteralValue::IntValue(3)]]);
+
+        // the check keeps its quotes through renames
+        run(&mut buffer_pool, &mut tables, r#"alter table "My Table" rename column foo_$ to "Where"; alter table "My Table" rename column "select" to "From";"#);
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, r#"insert into "My Table" ("From", "Where") values (0, 1);"#), TError::ConstraintError(..)));
+        run(&mut buffer_pool, &mut tables, r#"insert into "My Table" ("From", "Where") values (4, 40);"#);
+        let results = run(&mut buffer_pool, &mut tables, r#"select "Id", "Where" from "My Table" where "From" = 4;"#);
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(5), LiteralValue::IntValue(40)]]);
+
+        for sql in [r#"select "id" from "My Table";"#, "select * from my_table;", r#"select "Where" from "my table";"#] {
+            run_error(&mut buffer_pool, &mut tables, sql);
+        }
+
+        // non-reserved keywords are names anywhere the grammar doesn't expect the keyword
+        run(&mut buffer_pool, &mut tables, "create table index (key int primary key, start int default 1, date date check (date > '2000-01-01'), text text, value int, int int);
+            create index key on index (key); create sequence sequence start 5;
+            alter table index add column time time; alter table index rename column int to serial;
+            insert into index (key, date, text, value, time, serial) values (1, '2024-01-31', 'a', 10, '10:00', nextval('sequence'));");
+        let results = run(&mut buffer_pool, &mut tables, "select key, start, text, value + serial, date = date '2024-01-31', time from index where key = 1 and date < timestamp '2025-01-01';");
+        assert_eq!(results[0].rows[0][..5], [LiteralValue::IntValue(1), LiteralValue::IntValue(1), LiteralValue::StringValue("a".to_owned()), LiteralValue::IntValue(15), LiteralValue::BooleanValue(true)]);
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "insert into index (key, date) values (2, '1999-12-31');"), TError::ConstraintError(..)));
+    }
+
+    #[test]
+    fn test_arrays() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table posts (id int primary key, scores smallint[], tags text[]);");
+        run(&mut buffer_pool, &mut tables, r#"insert into posts (id, scores, tags) values
+            (1, array[3, 1, 2], array['rust', 'sql']),
+            (2, '{5, NULL}', '{"a b", "", NULL}'),
+            (3, array[]::smallint[], null);"#);
+        let results = run(&mut buffer_pool, &mut tables, "select scores, tags, scores[1], tags[2], tags[3] from posts where id = 1;
+            select scores, tags, scores[2], tags[0] from posts where id = 2;
+            select array[1, 2.5], array['a', null], array[date '2026-10-19', timestamp '2026-10-19 12:00'], '{1,2}'::int[] = array[1, 2];");
+        assert_eq!(results[0].rows[0].iter().map(|v| v.to_string()).collect::<Vec<_>>(), vec!["{3,1,2}", "{rust,sql}", "3", "sql", "NULL"]);
+        assert_eq!(results[1].rows[0].iter().map(|v| v.to_string()).collect::<Vec<_>>(), vec!["{5,NULL}", r#"{"a b","",NULL}"#, "NULL", "NULL"]);
+        assert_eq!(results[2].rows[0].iter().map(|v| v.to_string()).collect::<Vec<_>>(), vec!["{1,2.5}", "{a,NULL}", r#"{"2026-10-19 00:00:00","2026-10-19 12:00:00"}"#, "true"]);
+
+        // any and all follow three valued logic like in
+        let results = run(&mut buffer_pool, &mut tables, "select id from posts where 'sql' = any(tags);
+            select id from posts where 2 < all(scores);
+            select id from posts where 1 = any('{1,2}');
+            select 1 = any(array[2, null]), 1 = any(array[1, null]), 1 <> all(array[2, null]), 1 = all(array[]::int[]);");
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(1)]]);
+        assert_eq!(results[1].rows, vec![vec![LiteralValue::IntValue(3)]]);
+        assert_eq!(results[2].rows.len(), 3);
+        assert_eq!(results[3].rows, vec![vec![LiteralValue::NullValue, LiteralValue::BooleanValue(true), LiteralValue::NullValue, LiteralValue::BooleanValue(true)]]);
+
+        // unnest is a row source, which can refer to the enclosing query
+        let results = run(&mut buffer_pool, &mut tables, "select * from unnest(array[1, 2, 3]);
+            select t.x from unnest(array['a', 'b']) as t(x) where t.x <> 'a';
+            select id, (select count from unnest(scores) count where count > 1) from posts where id = 2;
+            select id from posts where exists (select * from unnest(tags) tag where tag = 'rust');");
+        assert_eq!(results[0].columns, vec!["unnest"]);
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(1)], vec![LiteralValue::IntValue(2)], vec![LiteralValue::IntValue(3)]]);
+        assert_eq!(results[1].rows, vec![vec![LiteralValue::StringValue("b".to_owned())]]);
+        assert_eq!(results[2].rows, vec![vec![LiteralValue::IntValue(2), LiteralValue::IntValue(5)]]);
+        assert_eq!(results[3].rows, vec![vec![LiteralValue::IntValue(1)]]);
+
+        // big arrays go to overflow pages and arrays can be indexed
+        run(&mut buffer_pool, &mut tables, "create unique index posts_tags_idx on posts (tags);");
+        let big = format!("{{{}}}", (1..=2000).map(|i| i.to_string()).collect::<Vec<_>>().join(","));
+        run(&mut buffer_pool, &mut tables, &format!("insert into posts (id, scores) values (4, '{}');", big));
+        let results = run(&mut buffer_pool, &mut tables, "select scores[2000] from posts where id = 4; select id from posts where tags = array['rust', 'sql'];");
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(2000)]]);
+        assert_eq!(results[1].rows, vec![vec![LiteralValue::IntValue(1)]]);
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "insert into posts (id, tags) values (5, array['rust', 'sql']);"), TError::ConstraintError(..)));
+
+        for sql in ["select array[1, 'a'::text];", "select array[array[1]];", "select 1[1];", "select array[1]['a'::text];", "select 1 = any(1);", "select 'a'::text = any(array[1]);", "select * from unnest(1);"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::PlanError(..)), "{}", sql);
+        }
+        for sql in ["insert into posts (id, scores) values (5, array[100000]);", "insert into posts (id, scores) values (5, '{1,x}');", "insert into posts (id, scores) values (5, '{{1}}');"] {
+            assert!(matches!(run_error(&mut buffer_pool, &mut tables, sql), TError::ExecutionError(..)), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_uuid() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table users (id uuid primary key default gen_random_uuid(), uuid text);
+            create table orders (id int primary key, user_id uuid references users);");
+        run(&mut buffer_pool, &mut tables, "insert into users (id, uuid) values ('A0EEBC99-9C0B-4EF8-BB6D-6BB9BD380A11', 'a'), ('{00000000-0000-0000-0000-000000000001}', 'b');
+            insert into users (uuid) values ('c'), ('d');
+            insert into orders (id, user_id) values (1, 'a0eebc999c0b4ef8bb6d6bb9bd380a11'), (2, 'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11'), (3, '00000000-0000-0000-0000-000000000001');");
+        let results = run(&mut buffer_pool, &mut tables, "select id, uuid from users where id = 'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11';
+            select uuid from users where id < 'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11'::uuid and uuid < 'c';
+            select distinct user_id from orders;
+            select uuid from users where id in (select user_id from orders where id = 3);
+            select id::text from users where uuid = 'c';
+            select gen_random_uuid() = gen_random_uuid();");
+        assert_eq!(results[0].rows[0].iter().map(|v| v.to_string()).collect::<Vec<_>>(), vec!["a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11", "a"]);
+        assert_eq!(results[1].rows, vec![vec![LiteralValue::StringValue("b".to_owned())]]);
+        assert_eq!(results[2].rows.len(), 2);
+        assert_eq!(results[3].rows, vec![vec![LiteralValue::StringValue("b".to_owned())]]);
+        let generated = results[4].rows[0][0].to_string();
+        assert_eq!((generated.len(), &generated[14..15]), (36, "4"));
+        assert_eq!(results[5].rows, vec![vec![LiteralValue::BooleanValue(false)]]);
+
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "insert into users (id) values ('00000000-0000-0000-0000-000000000001');"), TError::ConstraintError(..)));
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "insert into orders (id, user_id) values (4, gen_random_uuid());"), TError::ConstraintError(..)));
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "select id from users where id = 'not a uuid';"), TError::ExecutionError(..)));
+        assert!(matches!(run_error(&mut buffer_pool, &mut tables, "select id from users where id = 1;"), TError::PlanError(..)));
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table t (a int primary key, b int check (b > 0));");
+        run(&mut buffer_pool, &mut tables, "create table u (a int references t); insert into t (a, b) values (2, 2);");
+        let codes: Vec<_> = [
+            "select * from missing;",
+            "insert into t (a, b) values (1, 1 / 0);",
+            "select 9223372036854775807 + 1;",
+            "select (select a from t union all select a from t);",
+            "select 'nope'::uuid;",
+            "select 'yesterday'::date;",
+            "insert into t (a, b) values (2, 1);",
+            "insert into u (a) values (3);",
+            "insert into t (a, b) values (1, 0);",
+            "insert into t (a, b) values (null, 1);",
+        ].iter().map(|sql| run_error(&mut buffer_pool, &mut tables, sql).sqlstate()).collect();
+        assert_eq!(codes, vec!["42P01", "22012", "22003", "21000", "22P02", "22007", "23505", "23503", "23514", "23502"]);
+        // errors found by the planner
+        let codes: Vec<_> = [
+            "select c from t;",
+            "select a from t where b;",
+            "select x.a from (select 1 as a, 2 as a) x;",
+            "select nope(a) from t;",
+            "select cast(true as date);",
+            "alter table t add column b int;",
+            "alter table u rename to t;",
+            "drop table t;",
+        ].iter().map(|sql| run_error(&mut buffer_pool, &mut tables, sql).sqlstate()).collect();
+        assert_eq!(codes, vec!["42703", "42804", "42702", "42883", "42846", "42701", "42P07", "2BP01"]);
+        // errors found by the parser, where a bad typed literal is still about its value
+        let codes: Vec<_> = ["select a from;", "select date '2026-02-30';"].iter().map(|sql| {
+            let mut scanner = Scanner::new();
+            scanner.scan(&sql.to_string()).unwrap();
+            let mut parser = Parser::new();
+            assert!(parser.parse(&scanner.tokens).is_err());
+            parser.errors[0].sqlstate()
+        }).collect();
+        assert_eq!(codes, vec!["42601", "22007"]);
+
+        // a failed statement leaves the database usable
+        let results = run(&mut buffer_pool, &mut tables, "insert into t (a, b) values (1, 1); select a, b from t where a = 1;");
+        assert_eq!(results[0].rows, vec![vec![LiteralValue::IntValue(1), LiteralValue::IntValue(1)]]);
+
+        // names that don't fit in the catalog are an error, and nothing of the table is left behind
+        let long_name = "x".repeat(300);
+        let codes: Vec<_> = [
+            format!("create table {} (a int);", long_name),
+            format!("create table v ({} int);", long_name),
+            format!("create table {} (a int check (a > 0));", "x".repeat(250)),
+            format!("create table {} (a int generated always as identity);", "x".repeat(250)),
+            format!("alter table t rename column b to {};", long_name),
+        ].iter().map(|sql| run_error(&mut buffer_pool, &mut tables, sql).sqlstate()).collect();
+        assert_eq!(codes, vec!["42622"; 5]);
+        assert_eq!(tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), vec!["t", "u"]);
+        assert_eq!(load_catalog(&mut buffer_pool).unwrap().len(), 2);
+        assert!(load_sequences(&mut buffer_pool).unwrap().is_empty());
+
+        assert_eq!(TError::from(std::io::Error::other("disk on fire")).render(""), "error[58030]: disk on fire");
+    }
+
+    #[test]
+    fn test_catalog_space_is_reused() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        for i in 0..200 {
+            run(&mut buffer_pool, &mut tables, &format!("create table x{i} (id serial primary key, a int); drop table x{i};"));
+        }
+        assert!(load_catalog(&mut buffer_pool).unwrap().is_empty());
+        assert!(load_sequences(&mut buffer_pool).unwrap().is_empty());
+        // the dropped entries made room on the first catalog page, so it was never followed by another
+        assert_eq!(TablePage::new(buffer_pool.get_catalog_page().unwrap()).get_next_page_id(), None);
+
+        // dropping one makes room for another
+        run(&mut buffer_pool, &mut tables, "create table a (x int); create table b (x int); drop table a; create table c (x int);");
+        let names: Vec<_> = load_catalog(&mut buffer_pool).unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, ["b", "c"]);
+    }
+
+    #[test]
+    fn test_catalog_spanning_pages() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        // more tables and sequences than fit in one catalog page
+        let name = |i: usize| format!("table_with_a_long_name_{}_{}", "x".repeat(100), i);
+        for i in 0..60 {
+            run(&mut buffer_pool, &mut tables, &format!("create table {} (id serial primary key, a int, b text);", name(i)));
+        }
+        assert!(TablePage::new(buffer_pool.get_catalog_page().unwrap()).get_next_page_id().is_some());
+        assert_eq!((load_catalog(&mut buffer_pool).unwrap().len(), load_sequences(&mut buffer_pool).unwrap().len()), (60, 60));
+
+        // entries on later pages are found to be updated, replaced and deleted
+        run(&mut buffer_pool, &mut tables, &format!("insert into {0} (a) values (1), (2); alter table {0} rename to last; alter table last add column c int;", name(59)));
+        for i in (0..60).step_by(3) {
+            run(&mut buffer_pool, &mut tables, &format!("drop table {};", name(i)));
+        }
+        run(&mut buffer_pool, &mut tables, &format!("create table {} (a int);", name(60)));
+        let catalog = load_catalog(&mut buffer_pool).unwrap();
+        assert_eq!((catalog.len(), load_sequences(&mut buffer_pool).unwrap().len()), (41, 40));
+        assert!(catalog.iter().any(|t| t.name == "last" && t.columns.len() == 4));
+
+        // an entry that is too big for any page leaves no pages or sequences behind
+        let next_page = buffer_pool.new_page().unwrap().borrow().get_page_id().unwrap().0;
+        assert!(buffer_pool.delete_page(&PageId(next_page)));
+        let columns: Vec<_> = (0..200).map(|i| format!("{}_{} int", "c".repeat(20), i)).collect();
+        let error = run_error(&mut buffer_pool, &mut tables, &format!("create table wide (id serial primary key, {});", columns.join(", ")));
+        assert_eq!(error.sqlstate(), "54000");
+        assert!(error.message().starts_with("catalog entry is too big"));
+        assert_eq!(load_sequences(&mut buffer_pool).unwrap().len(), 40);
+        assert_eq!(buffer_pool.new_page().unwrap().borrow().get_page_id().unwrap().0, next_page);
+
+        // the chain of catalog pages is read again when the file is opened again
+        buffer_pool.flush_all_pages().unwrap();
+        std::mem::drop(buffer_pool);
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+        let mut reopened = load_catalog(&mut buffer_pool).unwrap();
+        assert_eq!(reopened.len(), 41);
+        let results = run(&mut buffer_pool, &mut reopened, "insert into last (a) values (3); select id, a from last;");
+        assert_eq!(results[0].rows, vec![
+            vec![LiteralValue::IntValue(1), LiteralValue::IntValue(1)],
+            vec![LiteralValue::IntValue(2), LiteralValue::IntValue(2)],
+            vec![LiteralValue::IntValue(3), LiteralValue::IntValue(3)],
+        ]);
+    }
+
+    #[test]
+    fn test_catalog_format() {
+        let _setup = TestSetup;
+        let mut tables = Vec::new();
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        run(&mut buffer_pool, &mut tables, "create table t (a int);");
+        assert_eq!(load_catalog(&mut buffer_pool).unwrap().len(), 1);
+
+        // a catalog from before the format version, which starts with a table entry without a kind
+        buffer_pool.get_catalog_page().unwrap().borrow_mut().data.fill(0);
+        let mut catalog = TablePage::new(buffer_pool.get_catalog_page().unwrap());
+        catalog.insert_tuple(tables[0].serialize().unwrap()).unwrap();
+        let error = load_catalog(&mut buffer_pool).err().unwrap();
+        assert!(matches!(&error, TError::StorageError(m) if m.contains("without a format version")), "{:?}", error);
+        assert!(run_error(&mut buffer_pool, &mut tables, "create table u (a int);").message().contains("without a format version"));
+
+        // a catalog from a later version
+        buffer_pool.get_catalog_page().unwrap().borrow_mut().data.fill(0);
+        let mut catalog = TablePage::new(buffer_pool.get_catalog_page().unwrap());
+        let mut entry = vec![2];
+        entry.extend(b"TOYSQLDB");
+        entry.extend(7u16.to_le_bytes());
+        catalog.insert_tuple(entry).unwrap();
+        let error = load_catalog(&mut buffer_pool).err().unwrap();
+        assert_eq!(error.message(), "catalog has format version 7, this version of the database reads version 1");
+    }
+}
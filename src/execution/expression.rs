@@ -0,0 +1,515 @@
+use std::cmp::Ordering;
+
+use crate::{catalog::{catalog::{find_sequence, update_sequence}, table_schema::ColumnType}, parse::{scanner::{CARDINALITY_VIOLATION, INVALID_PARAMETER_VALUE, INVALID_TEXT_REPRESENTATION, TError, UNDEFINED_TABLE, normalize_identifier}, token::LiteralValue}, types::{cast::cast, json::{parse_json_path, parse_text_path, Json}, datetime::{compare_temporal, date_trunc, extract, is_temporal_value, now, temporal_arithmetic}, numeric::{arithmetic, compare_numeric, negate, ArithmeticOperator}, uuid::random_uuid}};
+
+use super::operators::{ExecutionContext, Operator};
+
+
+// expressions after the planner has resolved names,
+// column references are replaced by their position in the input row
+pub enum Expression {
+    ColumnValue(usize),
+    // column of a row from an enclosing query, used by correlated subqueries
+    // level 1 is the immediately enclosing query
+    OuterColumnValue { level: usize, index: usize },
+    Constant(LiteralValue),
+    Binary { left: Box<Expression>, operator: BinaryOperator, right: Box<Expression> },
+    Not(Box<Expression>),
+    // arithmetic fails on overflow and division by zero, unlike comparisons it returns a result
+    Arithmetic { left: Box<Expression>, operator: ArithmeticOperator, right: Box<Expression> },
+    Negate(Box<Expression>),
+    // converts a number to another numeric type, checking that it fits, or a date to a timestamp and back
+    Cast { expr: Box<Expression>, to: ColumnType },
+    // subqueries are re-run for every row they are evaluated against
+    Subquery(Box<dyn Operator>),
+    InSubquery { expr: Box<Expression>, subquery: Box<dyn Operator>, negated: bool },
+    Exists(Box<dyn Operator>),
+    // without an operand the conditions are bool, with one they are compared to it
+    Case { operand: Option<Box<Expression>>, when_clauses: Vec<(Expression, Expression)>, else_result: Option<Box<Expression>> },
+    InList { expr: Box<Expression>, list: Vec<Expression>, negated: bool },
+    Between { expr: Box<Expression>, low: Box<Expression>, high: Box<Expression>, negated: bool },
+    Like { expr: Box<Expression>, pattern: Box<Expression>, negated: bool, case_insensitive: bool },
+    Function { function: Function, args: Vec<Expression> },
+    // elements are cast to a single type by the planner
+    Array(Vec<Expression>),
+    // null when the index is outside of the array, which starts at 1
+    Subscript { expr: Box<Expression>, index: Box<Expression> },
+    // left op ANY/ALL (array), like IN with the elements of the array as the list
+    Quantified { left: Box<Expression>, operator: BinaryOperator, all: bool, right: Box<Expression> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+    NextVal,
+    CurrVal,
+    Now,
+    GenRandomUuid,
+    // extract(field from value) and date_trunc(field, value) take the field first
+    Extract,
+    DateTrunc,
+    // -> and ->> take a key or an array index, #> and #>> a path, the text versions give text
+    JsonGet,
+    JsonGetText,
+    JsonPath,
+    JsonPathText,
+    JsonExtract,
+    JsonArrayLength,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+impl Expression {
+    pub fn evaluate(&mut self, row: &[LiteralValue], ctx: &mut ExecutionContext) -> Result<LiteralValue, TError> {
+        match self {
+            Expression::ColumnValue(index) => Ok(row[*index].clone()),
+            Expression::OuterColumnValue { level, index } => {
+                let outer_row = &ctx.outer_rows[ctx.outer_rows.len() - *level];
+                Ok(outer_row[*index].clone())
+            },
+            Expression::Constant(value) => Ok(value.clone()),
+            Expression::Binary { left, operator, right } => {
+                let left = left.evaluate(row, ctx)?;
+                let right = right.evaluate(row, ctx)?;
+                Ok(evaluate_binary(*operator, &left, &right))
+            },
+            Expression::Not(expr) => {
+                match expr.evaluate(row, ctx)? {
+                    LiteralValue::BooleanValue(b) => Ok(LiteralValue::BooleanValue(!b)),
+                    _ => Ok(LiteralValue::NullValue),
+                }
+            },
+            Expression::Arithmetic { left, operator, right } => {
+                let left = left.evaluate(row, ctx)?;
+                let right = right.evaluate(row, ctx)?;
+                if is_temporal_value(&left) || is_temporal_value(&right) {
+                    return temporal_arithmetic(*operator, &left, &right);
+                }
+                arithmetic(*operator, &left, &right)
+            },
+            Expression::Negate(expr) => negate(&expr.evaluate(row, ctx)?),
+            Expression::Cast { expr, to } => cast(&expr.evaluate(row, ctx)?, to),
+            Expression::Subquery(subquery) => {
+                let rows = run_subquery(subquery.as_mut(), row, ctx, 2)?;
+                match rows.len() {
+                    0 => Ok(LiteralValue::NullValue),
+                    1 => Ok(rows[0][0].clone()),
+                    _ => Err(TError::ExecutionError(CARDINALITY_VIOLATION, String::from("more than one row returned by a subquery used as an expression"))),
+                }
+            },
+            Expression::InSubquery { expr, subquery, negated } => {
+                let value = expr.evaluate(row, ctx)?;
+                let rows = run_subquery(subquery.as_mut(), row, ctx, usize::MAX)?;
+                Ok(evaluate_in(&value, rows.iter().map(|r| &r[0]), *negated))
+            },
+            Expression::Exists(subquery) => {
+                let rows = run_subquery(subquery.as_mut(), row, ctx, 1)?;
+                Ok(LiteralValue::BooleanValue(!rows.is_empty()))
+            },
+            Expression::Case { operand, when_clauses, else_result } => {
+                let operand = match operand {
+                    Some(operand) => Some(operand.evaluate(row, ctx)?),
+                    None => None,
+                };
+                for (condition, result) in when_clauses {
+                    let condition = condition.evaluate(row, ctx)?;
+                    let matched = match &operand {
+                        Some(operand) => evaluate_binary(BinaryOperator::Equal, operand, &condition),
+                        None => condition,
+                    };
+                    if matched == LiteralValue::BooleanValue(true) {
+                        return result.evaluate(row, ctx);
+                    }
+                }
+                match else_result {
+                    Some(else_result) => else_result.evaluate(row, ctx),
+                    None => Ok(LiteralValue::NullValue),
+                }
+            },
+            Expression::InList { expr, list, negated } => {
+                let value = expr.evaluate(row, ctx)?;
+                let mut values = Vec::new();
+                for item in list {
+                    values.push(item.evaluate(row, ctx)?);
+                }
+                Ok(evaluate_in(&value, values.iter(), *negated))
+            },
+            Expression::Between { expr, low, high, negated } => {
+                let value = expr.evaluate(row, ctx)?;
+                let low = low.evaluate(row, ctx)?;
+                let high = high.evaluate(row, ctx)?;
+                let above = evaluate_binary(BinaryOperator::GreaterEqual, &value, &low);
+                let below = evaluate_binary(BinaryOperator::LessEqual, &value, &high);
+                Ok(negate_if(evaluate_binary(BinaryOperator::And, &above, &below), *negated))
+            },
+            Expression::Like { expr, pattern, negated, case_insensitive } => {
+                let value = expr.evaluate(row, ctx)?;
+                let pattern = pattern.evaluate(row, ctx)?;
+                let result = match (value, pattern) {
+                    (LiteralValue::StringValue(value), LiteralValue::StringValue(pattern)) => {
+                        let matched = if *case_insensitive {
+                            like_match(&value.to_lowercase(), &pattern.to_lowercase())
+                        } else {
+                            like_match(&value, &pattern)
+                        };
+                        LiteralValue::BooleanValue(matched)
+                    },
+                    _ => LiteralValue::NullValue,
+                };
+                Ok(negate_if(result, *negated))
+            },
+            Expression::Function { function, args } => {
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(arg.evaluate(row, ctx)?);
+                }
+                evaluate_function(*function, &values, ctx)
+            },
+            Expression::Array(elements) => {
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push(element.evaluate(row, ctx)?);
+                }
+                Ok(LiteralValue::ArrayValue(values))
+            },
+            Expression::Subscript { expr, index } => {
+                let value = expr.evaluate(row, ctx)?;
+                let index = index.evaluate(row, ctx)?;
+                let element = match (value, index) {
+                    (LiteralValue::ArrayValue(mut elements), LiteralValue::IntValue(index)) => match usize::try_from(index) {
+                        Ok(index) if (1..=elements.len()).contains(&index) => elements.swap_remove(index - 1),
+                        _ => LiteralValue::NullValue,
+                    },
+                    _ => LiteralValue::NullValue,
+                };
+                Ok(element)
+            },
+            Expression::Quantified { left, operator, all, right } => {
+                let value = left.evaluate(row, ctx)?;
+                let elements = match right.evaluate(row, ctx)? {
+                    LiteralValue::ArrayValue(elements) => elements,
+                    _ => return Ok(LiteralValue::NullValue),
+                };
+                // ANY is true as soon as a comparison is and ALL false, otherwise a null comparison makes the result null
+                let mut result = LiteralValue::BooleanValue(*all);
+                for element in &elements {
+                    match evaluate_binary(*operator, &value, element) {
+                        LiteralValue::BooleanValue(b) if b != *all => return Ok(LiteralValue::BooleanValue(b)),
+                        LiteralValue::NullValue => result = LiteralValue::NullValue,
+                        _ => {},
+                    }
+                }
+                Ok(result)
+            },
+        }
+    }
+}
+
+impl Expression {
+    fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::ColumnValue(_) | Expression::OuterColumnValue { .. } | Expression::Constant(_)
+            | Expression::Subquery(_) | Expression::Exists(_) => Vec::new(),
+            Expression::Binary { left, right, .. } | Expression::Arithmetic { left, right, .. } => vec![left, right],
+            Expression::Not(expr) | Expression::Negate(expr) | Expression::Cast { expr, .. } | Expression::InSubquery { expr, .. } => vec![expr],
+            Expression::Case { operand, when_clauses, else_result } => {
+                let mut res: Vec<&Expression> = operand.iter().map(|e| e.as_ref()).collect();
+                res.extend(when_clauses.iter().flat_map(|(condition, result)| [condition, result]));
+                res.extend(else_result.iter().map(|e| e.as_ref()));
+                res
+            },
+            Expression::InList { expr, list, .. } => std::iter::once(expr.as_ref()).chain(list).collect(),
+            Expression::Between { expr, low, high, .. } => vec![expr, low, high],
+            Expression::Like { expr, pattern, .. } => vec![expr, pattern],
+            Expression::Function { args, .. } | Expression::Array(args) => args.iter().collect(),
+            Expression::Subscript { expr, index } => vec![expr, index],
+            Expression::Quantified { left, right, .. } => vec![left, right],
+        }
+    }
+
+    /**
+     * Whether two expressions compute the same thing, so a where clause can use an index
+     * over an expression. Subqueries are never the same as anything.
+     */
+    pub fn same_as(&self, other: &Expression) -> bool {
+        let same_node = match (self, other) {
+            (Expression::ColumnValue(l), Expression::ColumnValue(r)) => l == r,
+            // values of different types have different keys, even when they are equal
+            (Expression::Constant(l), Expression::Constant(r)) => std::mem::discriminant(l) == std::mem::discriminant(r) && l == r,
+            (Expression::Binary { operator: l, .. }, Expression::Binary { operator: r, .. }) => l == r,
+            (Expression::Arithmetic { operator: l, .. }, Expression::Arithmetic { operator: r, .. }) => l == r,
+            (Expression::Not(_), Expression::Not(_)) | (Expression::Negate(_), Expression::Negate(_)) => true,
+            (Expression::Cast { to: l, .. }, Expression::Cast { to: r, .. }) => l == r,
+            (Expression::Case { operand: lo, when_clauses: lw, else_result: le }, Expression::Case { operand: ro, when_clauses: rw, else_result: re }) => {
+                lo.is_some() == ro.is_some() && lw.len() == rw.len() && le.is_some() == re.is_some()
+            },
+            (Expression::InList { negated: l, .. }, Expression::InList { negated: r, .. }) => l == r,
+            (Expression::Between { negated: l, .. }, Expression::Between { negated: r, .. }) => l == r,
+            (Expression::Like { negated: ln, case_insensitive: lc, .. }, Expression::Like { negated: rn, case_insensitive: rc, .. }) => ln == rn && lc == rc,
+            (Expression::Function { function: l, .. }, Expression::Function { function: r, .. }) => l == r,
+            (Expression::Array(_), Expression::Array(_)) | (Expression::Subscript { .. }, Expression::Subscript { .. }) => true,
+            (Expression::Quantified { operator: lo, all: la, .. }, Expression::Quantified { operator: ro, all: ra, .. }) => lo == ro && la == ra,
+            _ => false,
+        };
+        let (left, right) = (self.children(), other.children());
+        same_node && left.len() == right.len() && left.iter().zip(right).all(|(l, r)| l.same_as(r))
+    }
+
+    // whether the expression gives the same result whenever it is evaluated against the same row
+    pub fn is_immutable(&self) -> bool {
+        match self {
+            Expression::OuterColumnValue { .. } | Expression::Subquery(_) | Expression::InSubquery { .. } | Expression::Exists(_) => false,
+            Expression::Function { function: Function::NextVal | Function::CurrVal | Function::Now | Function::GenRandomUuid, .. } => false,
+            _ => self.children().iter().all(|e| e.is_immutable()),
+        }
+    }
+}
+
+fn evaluate_function(function: Function, args: &[LiteralValue], ctx: &mut ExecutionContext) -> Result<LiteralValue, TError> {
+    match function {
+        Function::NextVal | Function::CurrVal => {
+            let name = match &args[0] {
+                LiteralValue::StringValue(name) => &normalize_identifier(name),
+                _ => return Ok(LiteralValue::NullValue),
+            };
+            let mut sequence = match find_sequence(ctx.buffer_pool, name)? {
+                Some(sequence) => sequence,
+                None => return Err(TError::ExecutionError(UNDEFINED_TABLE, format!("sequence {:?} does not exist", name))),
+            };
+            let value = match function {
+                Function::NextVal => {
+                    let value = sequence.next_value()?;
+                    update_sequence(ctx.buffer_pool, &sequence)?;
+                    value
+                },
+                _ => sequence.current_value()?,
+            };
+            Ok(LiteralValue::IntValue(value))
+        },
+        Function::Now => Ok(LiteralValue::TimestampValue(now())),
+        Function::GenRandomUuid => Ok(LiteralValue::UuidValue(random_uuid())),
+        Function::Extract | Function::DateTrunc => {
+            let field = match &args[0] {
+                LiteralValue::StringValue(field) => field.to_lowercase(),
+                _ => return Ok(LiteralValue::NullValue),
+            };
+            match function {
+                Function::Extract => extract(&field, &args[1]),
+                _ => date_trunc(&field, &args[1]),
+            }
+        },
+        Function::JsonGet | Function::JsonGetText | Function::JsonPath | Function::JsonPathText | Function::JsonExtract => {
+            let json = match &args[0] {
+                LiteralValue::JsonValue(json) => json,
+                _ => return Ok(LiteralValue::NullValue),
+            };
+            let found = match (function, &args[1]) {
+                (_, LiteralValue::NullValue) => None,
+                (Function::JsonGet | Function::JsonGetText, LiteralValue::IntValue(index)) => json.element(*index),
+                (Function::JsonGet | Function::JsonGetText, key) => json.field(&key.to_string()),
+                (Function::JsonExtract, path) => match parse_json_path(&path.to_string()) {
+                    Some(path) => json.extract(&path),
+                    None => return Err(TError::ExecutionError(INVALID_TEXT_REPRESENTATION, format!("invalid json path {:?}", path.to_string()))),
+                },
+                (_, path) => match parse_text_path(&path.to_string()) {
+                    Some(path) => json.path(&path),
+                    None => return Err(TError::ExecutionError(INVALID_TEXT_REPRESENTATION, format!("malformed array literal: {:?}", path.to_string()))),
+                },
+            };
+            Ok(match (function, found) {
+                (_, None) => LiteralValue::NullValue,
+                (Function::JsonGet | Function::JsonPath, Some(found)) => LiteralValue::JsonValue(found.clone()),
+                // strings lose their quotes and json nulls become sql nulls
+                (_, Some(Json::Null)) => LiteralValue::NullValue,
+                (_, Some(Json::String(s))) => LiteralValue::StringValue(s.clone()),
+                (_, Some(found)) => LiteralValue::StringValue(found.to_string()),
+            })
+        },
+        Function::JsonArrayLength => match &args[0] {
+            LiteralValue::JsonValue(Json::Array(elements)) => Ok(LiteralValue::IntValue(i64::try_from(elements.len()).unwrap())),
+            LiteralValue::JsonValue(_) => Err(TError::ExecutionError(INVALID_PARAMETER_VALUE, String::from("cannot get array length of a non-array"))),
+            _ => Ok(LiteralValue::NullValue),
+        },
+    }
+}
+
+// x IN (...) is true if any value is equal, null if there was no match but a comparison was null
+fn evaluate_in<'v>(value: &LiteralValue, candidates: impl Iterator<Item = &'v LiteralValue>, negated: bool) -> LiteralValue {
+    let mut result = LiteralValue::BooleanValue(false);
+    for candidate in candidates {
+        match evaluate_binary(BinaryOperator::Equal, value, candidate) {
+            LiteralValue::BooleanValue(true) => {
+                result = LiteralValue::BooleanValue(true);
+                break;
+            },
+            LiteralValue::NullValue => result = LiteralValue::NullValue,
+            _ => {}
+        }
+    }
+    negate_if(result, negated)
+}
+
+fn negate_if(value: LiteralValue, negated: bool) -> LiteralValue {
+    match (value, negated) {
+        (LiteralValue::BooleanValue(b), true) => LiteralValue::BooleanValue(!b),
+        (value, _) => value,
+    }
+}
+
+enum PatternElement {
+    AnySequence, // %
+    AnyChar, // _
+    Char(char),
+}
+
+// sql LIKE, % matches any sequence of characters and _ any single one, \ escapes the next character
+pub fn like_match(value: &str, pattern: &str) -> bool {
+    let mut elements = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        elements.push(match c {
+            '%' => PatternElement::AnySequence,
+            '_' => PatternElement::AnyChar,
+            '\\' => PatternElement::Char(chars.next().unwrap_or('\\')),
+            c => PatternElement::Char(c),
+        });
+    }
+    let value: Vec<char> = value.chars().collect();
+
+    // greedy matching, on a mismatch go back to the last % and let it consume one more character
+    let (mut v, mut p) = (0, 0);
+    let mut backtrack = None;
+    while v < value.len() {
+        match elements.get(p) {
+            Some(PatternElement::AnySequence) => {
+                backtrack = Some((v, p));
+                p += 1;
+            },
+            Some(PatternElement::AnyChar) => {
+                v += 1;
+                p += 1;
+            },
+            Some(PatternElement::Char(c)) if *c == value[v] => {
+                v += 1;
+                p += 1;
+            },
+            _ => match backtrack {
+                Some((last_v, last_p)) => {
+                    v = last_v + 1;
+                    p = last_p + 1;
+                    backtrack = Some((v, last_p));
+                },
+                None => return false,
+            },
+        }
+    }
+    elements[p..].iter().all(|e| matches!(e, PatternElement::AnySequence))
+}
+
+// run a subquery with row as its outer row, stopping after limit rows
+fn run_subquery(subquery: &mut dyn Operator, row: &[LiteralValue], ctx: &mut ExecutionContext, limit: usize) -> Result<Vec<Vec<LiteralValue>>, TError> {
+    ctx.outer_rows.push(row.to_vec());
+    let mut rows = Vec::new();
+    let mut result = subquery.init(ctx);
+    while result.is_ok() && rows.len() < limit {
+        match subquery.next(ctx) {
+            Ok(Some(r)) => rows.push(r),
+            Ok(None) => break,
+            Err(e) => result = Err(e),
+        }
+    }
+    ctx.outer_rows.pop();
+    result.map(|_| rows)
+}
+
+pub fn compare(left: &LiteralValue, right: &LiteralValue) -> Option<Ordering> {
+    match (left, right) {
+        (LiteralValue::IntValue(l), LiteralValue::IntValue(r)) => Some(l.cmp(r)),
+        (LiteralValue::BooleanValue(l), LiteralValue::BooleanValue(r)) => Some(l.cmp(r)),
+        (LiteralValue::StringValue(l), LiteralValue::StringValue(r)) => Some(l.cmp(r)),
+        (LiteralValue::BytesValue(l), LiteralValue::BytesValue(r)) => Some(l.cmp(r)),
+        (LiteralValue::UuidValue(l), LiteralValue::UuidValue(r)) => Some(l.cmp(r)),
+        (LiteralValue::JsonValue(l), LiteralValue::JsonValue(r)) => Some(l.compare(r)),
+        (LiteralValue::ArrayValue(l), LiteralValue::ArrayValue(r)) => Some(compare_arrays(l, r)),
+        _ if is_temporal_value(left) => compare_temporal(left, right),
+        _ => compare_numeric(left, right),
+    }
+}
+
+// element by element, null elements are equal to each other and sort after the others,
+// when one array is the start of the other the shorter one comes first
+fn compare_arrays(left: &[LiteralValue], right: &[LiteralValue]) -> Ordering {
+    for (l, r) in left.iter().zip(right) {
+        let ordering = match (l, r) {
+            (LiteralValue::NullValue, LiteralValue::NullValue) => Ordering::Equal,
+            (LiteralValue::NullValue, _) => Ordering::Greater,
+            (_, LiteralValue::NullValue) => Ordering::Less,
+            _ => compare(l, r).unwrap_or(Ordering::Equal),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    left.len().cmp(&right.len())
+}
+
+fn evaluate_binary(operator: BinaryOperator, left: &LiteralValue, right: &LiteralValue) -> LiteralValue {
+    match operator {
+        // three valued logic, null is unknown
+        BinaryOperator::And => match (left, right) {
+            (LiteralValue::BooleanValue(false), _) | (_, LiteralValue::BooleanValue(false)) => LiteralValue::BooleanValue(false),
+            (LiteralValue::BooleanValue(true), LiteralValue::BooleanValue(true)) => LiteralValue::BooleanValue(true),
+            _ => LiteralValue::NullValue,
+        },
+        BinaryOperator::Or => match (left, right) {
+            (LiteralValue::BooleanValue(true), _) | (_, LiteralValue::BooleanValue(true)) => LiteralValue::BooleanValue(true),
+            (LiteralValue::BooleanValue(false), LiteralValue::BooleanValue(false)) => LiteralValue::BooleanValue(false),
+            _ => LiteralValue::NullValue,
+        },
+        _ => {
+            let ordering = match compare(left, right) {
+                Some(ordering) => ordering,
+                None => return LiteralValue::NullValue,
+            };
+            let result = match operator {
+                BinaryOperator::Equal => ordering == Ordering::Equal,
+                BinaryOperator::NotEqual => ordering != Ordering::Equal,
+                BinaryOperator::Less => ordering == Ordering::Less,
+                BinaryOperator::LessEqual => ordering != Ordering::Greater,
+                BinaryOperator::Greater => ordering == Ordering::Greater,
+                BinaryOperator::GreaterEqual => ordering != Ordering::Less,
+                BinaryOperator::And | BinaryOperator::Or => unreachable!(),
+            };
+            LiteralValue::BooleanValue(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::like_match;
+
+    #[test]
+    fn like_patterns() {
+        assert!(like_match("hello", "hello"));
+        assert!(like_match("hello", "h%"));
+        assert!(like_match("hello", "%llo"));
+        assert!(like_match("hello", "h_l%o"));
+        assert!(like_match("", "%"));
+        assert!(like_match("abcabc", "%bc%c"));
+        assert!(like_match("50%", "50\\%"));
+        assert!(!like_match("500", "50\\%"));
+        assert!(!like_match("hello", "h_llo_"));
+        assert!(!like_match("hello", "Hello"));
+        assert!(!like_match("abcab", "%bc%c"));
+    }
+}
@@ -1,29 +1,573 @@
-use crate::{catalog::table_schema::TableSchema, parse::{ast::Expr, token::LiteralValue}};
+use std::{cell::RefCell, collections::{HashMap, HashSet, VecDeque}, rc::Rc};
 
+use crate::{catalog::table_schema::TableSchema, parse::{scanner::TError, token::LiteralValue}, storage::{b_plus_tree::BPlusTree, buffer_pool::{BufferPoolManager, PageId}, overflow::read_overflow, table_page::{RecordId, TablePage, TupleId}}};
+
+use super::expression::Expression;
+
+
+pub struct ExecutionContext<'b, 'a> {
+    pub buffer_pool: &'b mut BufferPoolManager<'a>,
+    // rows of the enclosing queries while a correlated subquery runs, innermost last
+    pub outer_rows: Vec<Vec<LiteralValue>>,
+}
+
+impl <'b, 'a> ExecutionContext<'b, 'a> {
+    pub fn new(buffer_pool: &'b mut BufferPoolManager<'a>) -> ExecutionContext<'b, 'a> {
+        ExecutionContext { buffer_pool, outer_rows: Vec::new() }
+    }
+}
 
 pub trait Operator {
-    fn next(&self) -> Option<Vec<LiteralValue>>;
+    // (re)start producing rows from the beginning
+    fn init(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError>;
+    fn next(&mut self, ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError>;
 }
 
 
 
 pub struct Projection {
-    pub expressions: Vec<Expr>,
+    pub expressions: Vec<Expression>,
     pub child: Box<dyn Operator>,
 }
 
 pub struct SeqScan {
-    pub table: TableSchema
+    pub table: TableSchema,
+    // position of the next tuple to read
+    page_id: Option<u32>,
+    tuple_id: u16,
+}
+
+impl SeqScan {
+    pub fn new(table: TableSchema) -> SeqScan {
+        let page_id = Some(table.first_page_id);
+        SeqScan { table, page_id, tuple_id: 0 }
+    }
 }
 
 impl Operator for SeqScan {
-    fn next(&self) -> Option<Vec<LiteralValue> >{
-        todo!()
+    fn init(&mut self, _ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.page_id = Some(self.table.first_page_id);
+        self.tuple_id = 0;
+        Ok(())
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        Ok(self.next_with_record_id(ctx)?.map(|(_, row)| row))
+    }
+}
+
+impl SeqScan {
+    // the next row along with where it is stored, for statements that modify the rows they read
+    pub fn next_with_record_id(&mut self, ctx: &mut ExecutionContext) -> Result<Option<(RecordId, Vec<LiteralValue>)>, TError> {
+        loop {
+            let page_id = match self.page_id {
+                Some(page_id) => page_id,
+                None => return Ok(None),
+            };
+            let page = ctx.buffer_pool.fetch_page(PageId(page_id.try_into().unwrap()))?;
+            let table_page = TablePage::new(page);
+            if self.tuple_id < table_page.get_num_tuples() {
+                let tuple_id = TupleId(self.tuple_id.into());
+                self.tuple_id += 1;
+                if table_page.is_deleted(&tuple_id) {
+                    continue;
+                }
+                let tuple = table_page.get_tuple(tuple_id.clone())?;
+                let row = self.table.decode_tuple(&tuple, &mut |first_page_id, length| read_overflow(ctx.buffer_pool, first_page_id, length))?;
+                return Ok(Some((RecordId { page_id, tuple_id }, row)));
+            }
+            // done with this page, follow the linked list of pages
+            self.page_id = table_page.get_next_page_id();
+            self.tuple_id = 0;
+        }
+    }
+}
+
+// the rows stored under a key of an index, which is looked up again by every init
+pub struct IndexScan {
+    pub table: TableSchema,
+    root_page_id: u32,
+    key: Vec<u8>,
+    record_ids: Vec<RecordId>,
+    position: usize,
+}
+
+impl IndexScan {
+    pub fn new(table: TableSchema, root_page_id: u32, key: Vec<u8>) -> IndexScan {
+        IndexScan { table, root_page_id, key, record_ids: Vec::new(), position: 0 }
+    }
+}
+
+impl Operator for IndexScan {
+    fn init(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.record_ids = BPlusTree::new(self.root_page_id).get(ctx.buffer_pool, &self.key)?;
+        self.position = 0;
+        Ok(())
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        while let Some(record_id) = self.record_ids.get(self.position) {
+            self.position += 1;
+            let page = ctx.buffer_pool.fetch_page(PageId(record_id.page_id.try_into().unwrap()))?;
+            let table_page = TablePage::new(page);
+            if table_page.is_deleted(&record_id.tuple_id) {
+                continue;
+            }
+            let tuple = table_page.get_tuple(record_id.tuple_id.clone())?;
+            return Ok(Some(self.table.decode_tuple(&tuple, &mut |first_page_id, length| read_overflow(ctx.buffer_pool, first_page_id, length))?));
+        }
+        Ok(None)
     }
 }
 
 impl Operator for Projection {
-    fn next(&self) -> Option<Vec<LiteralValue> >{
-        todo!()
+    fn init(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.child.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        let row = match self.child.next(ctx)? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let mut res = Vec::new();
+        for expr in self.expressions.iter_mut() {
+            res.push(expr.evaluate(&row, ctx)?);
+        }
+        Ok(Some(res))
+    }
+}
+
+// only passes through rows for which the predicate is true
+pub struct Filter {
+    pub predicate: Expression,
+    pub child: Box<dyn Operator>,
+}
+
+impl Operator for Filter {
+    fn init(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.child.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        while let Some(row) = self.child.next(ctx)? {
+            if let LiteralValue::BooleanValue(true) = self.predicate.evaluate(&row, ctx)? {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/**
+ * Semi join: emits the rows from left whose keys are equal to some row of right.
+ * Anti join: emits the rows from left whose keys are not equal to any row of right.
+ *
+ * The rows produced by right are the keys, they are hashed before the first left row is read.
+ * A null aware anti join implements NOT IN, where a null on either side makes the
+ * comparison unknown so the row is filtered out.
+ */
+pub struct HashSemiJoin {
+    pub left: Box<dyn Operator>,
+    pub right: Box<dyn Operator>,
+    pub left_keys: Vec<Expression>,
+    pub anti: bool,
+    pub null_aware: bool,
+    hash_table: Option<HashSet<Vec<LiteralValue>>>,
+    right_has_null: bool,
+}
+
+impl HashSemiJoin {
+    pub fn new(left: Box<dyn Operator>, right: Box<dyn Operator>, left_keys: Vec<Expression>, anti: bool, null_aware: bool) -> HashSemiJoin {
+        HashSemiJoin { left, right, left_keys, anti, null_aware, hash_table: None, right_has_null: false }
+    }
+
+    fn build(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError> {
+        let mut hash_table = HashSet::new();
+        self.right_has_null = false;
+        while let Some(row) = self.right.next(ctx)? {
+            // nulls are never equal to anything so they don't go in the table
+            if row.contains(&LiteralValue::NullValue) {
+                self.right_has_null = true;
+            } else {
+                hash_table.insert(row);
+            }
+        }
+        self.hash_table = Some(hash_table);
+        Ok(())
+    }
+}
+
+impl Operator for HashSemiJoin {
+    fn init(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.hash_table = None;
+        self.left.init(ctx)?;
+        self.right.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        if self.hash_table.is_none() {
+            self.build(ctx)?;
+        }
+        while let Some(row) = self.left.next(ctx)? {
+            let mut keys = Vec::new();
+            for key in self.left_keys.iter_mut() {
+                keys.push(key.evaluate(&row, ctx)?);
+            }
+            let hash_table = self.hash_table.as_ref().unwrap();
+            let key_has_null = keys.contains(&LiteralValue::NullValue);
+            let matched = !key_has_null && hash_table.contains(&keys);
+
+            let emit = if !self.anti {
+                matched
+            } else if self.null_aware {
+                let right_is_empty = hash_table.is_empty() && !self.right_has_null;
+                right_is_empty || !(matched || key_has_null || self.right_has_null)
+            } else {
+                !matched
+            };
+            if emit {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+}
+
+// one row without columns, for a select without from
+pub struct SingleRow {
+    done: bool,
+}
+
+impl SingleRow {
+    pub fn new() -> SingleRow {
+        SingleRow { done: false }
+    }
+}
+
+impl Operator for SingleRow {
+    fn init(&mut self, _ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.done = false;
+        Ok(())
+    }
+
+    fn next(&mut self, _ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        if self.done {
+            return Ok(None);
+        }
+        self.done = true;
+        Ok(Some(Vec::new()))
+    }
+}
+
+// a row for each element of an array, which is evaluated again by every init, a null array has no rows
+pub struct Unnest {
+    array: Expression,
+    elements: Vec<LiteralValue>,
+    position: usize,
+}
+
+impl Unnest {
+    pub fn new(array: Expression) -> Unnest {
+        Unnest { array, elements: Vec::new(), position: 0 }
+    }
+}
+
+impl Operator for Unnest {
+    fn init(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.elements = match self.array.evaluate(&[], ctx)? {
+            LiteralValue::ArrayValue(elements) => elements,
+            _ => Vec::new(),
+        };
+        self.position = 0;
+        Ok(())
+    }
+
+    fn next(&mut self, _ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        let element = self.elements.get(self.position).cloned();
+        self.position += 1;
+        Ok(element.map(|element| vec![element]))
+    }
+}
+
+// all the rows of each child one after the other, UNION ALL
+pub struct Append {
+    pub children: Vec<Box<dyn Operator>>,
+    current: usize,
+}
+
+impl Append {
+    pub fn new(children: Vec<Box<dyn Operator>>) -> Append {
+        Append { children, current: 0 }
     }
-}
\ No newline at end of file
+}
+
+impl Operator for Append {
+    fn init(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.current = 0;
+        for child in self.children.iter_mut() {
+            child.init(ctx)?;
+        }
+        Ok(())
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        while self.current < self.children.len() {
+            if let Some(row) = self.children[self.current].next(ctx)? {
+                return Ok(Some(row));
+            }
+            self.current += 1;
+        }
+        Ok(None)
+    }
+}
+
+// removes duplicate rows, nulls are considered equal to each other
+pub struct HashDistinct {
+    pub child: Box<dyn Operator>,
+    seen: HashSet<Vec<LiteralValue>>,
+}
+
+impl HashDistinct {
+    pub fn new(child: Box<dyn Operator>) -> HashDistinct {
+        HashDistinct { child, seen: HashSet::new() }
+    }
+}
+
+impl Operator for HashDistinct {
+    fn init(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.seen.clear();
+        self.child.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        while let Some(row) = self.child.next(ctx)? {
+            if !self.seen.contains(&row) {
+                self.seen.insert(row.clone());
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SetOperationKind {
+    Intersect,
+    Except,
+}
+
+/**
+ * INTERSECT and EXCEPT. The rows of right are counted in a hash table before the
+ * first left row is read, then each left row is matched against the counts.
+ * With all, duplicates are kept: a row appearing m times in left and n times in right
+ * appears min(m, n) times in INTERSECT ALL and max(m - n, 0) times in EXCEPT ALL.
+ */
+pub struct HashSetOperation {
+    pub left: Box<dyn Operator>,
+    pub right: Box<dyn Operator>,
+    pub kind: SetOperationKind,
+    pub all: bool,
+    counts: Option<HashMap<Vec<LiteralValue>, usize>>,
+    emitted: HashSet<Vec<LiteralValue>>,
+}
+
+impl HashSetOperation {
+    pub fn new(left: Box<dyn Operator>, right: Box<dyn Operator>, kind: SetOperationKind, all: bool) -> HashSetOperation {
+        HashSetOperation { left, right, kind, all, counts: None, emitted: HashSet::new() }
+    }
+}
+
+impl Operator for HashSetOperation {
+    fn init(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.counts = None;
+        self.emitted.clear();
+        self.left.init(ctx)?;
+        self.right.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        if self.counts.is_none() {
+            let mut counts = HashMap::new();
+            while let Some(row) = self.right.next(ctx)? {
+                *counts.entry(row).or_insert(0) += 1;
+            }
+            self.counts = Some(counts);
+        }
+        let counts = self.counts.as_mut().unwrap();
+        while let Some(row) = self.left.next(ctx)? {
+            let count = counts.get_mut(&row);
+            let in_right = count.as_ref().is_some_and(|c| **c > 0);
+            let emit = match (self.kind, self.all) {
+                (SetOperationKind::Intersect, true) | (SetOperationKind::Except, true) => {
+                    // each right row cancels out one left row
+                    if let Some(count) = count {
+                        if *count > 0 {
+                            *count -= 1;
+                        }
+                    }
+                    matches!(self.kind, SetOperationKind::Intersect) == in_right
+                },
+                (SetOperationKind::Intersect, false) => in_right && !self.emitted.contains(&row),
+                (SetOperationKind::Except, false) => !in_right && !self.emitted.contains(&row),
+            };
+            if emit {
+                if !self.all {
+                    self.emitted.insert(row.clone());
+                }
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+}
+
+// the result of a common table expression that is computed once and read by every reference to it
+pub struct MaterializedCte {
+    pub plan: Box<dyn Operator>,
+    pub rows: Vec<Vec<LiteralValue>>,
+}
+
+impl MaterializedCte {
+    pub fn new(plan: Box<dyn Operator>) -> MaterializedCte {
+        MaterializedCte { plan, rows: Vec::new() }
+    }
+}
+
+// root of a query with materialized ctes, computes them before the body starts
+pub struct WithMaterialize {
+    pub ctes: Vec<Rc<RefCell<MaterializedCte>>>,
+    pub body: Box<dyn Operator>,
+}
+
+impl Operator for WithMaterialize {
+    fn init(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError> {
+        // in order, a cte can read the ones defined before it
+        for cte in &self.ctes {
+            let mut cte = cte.borrow_mut();
+            cte.rows.clear();
+            cte.plan.init(ctx)?;
+            while let Some(row) = cte.plan.next(ctx)? {
+                cte.rows.push(row);
+            }
+        }
+        self.body.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        self.body.next(ctx)
+    }
+}
+
+pub struct CteScan {
+    pub cte: Rc<RefCell<MaterializedCte>>,
+    position: usize,
+}
+
+impl CteScan {
+    pub fn new(cte: Rc<RefCell<MaterializedCte>>) -> CteScan {
+        CteScan { cte, position: 0 }
+    }
+}
+
+impl Operator for CteScan {
+    fn init(&mut self, _ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.position = 0;
+        Ok(())
+    }
+
+    fn next(&mut self, _ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        let row = self.cte.borrow().rows.get(self.position).cloned();
+        self.position += 1;
+        Ok(row)
+    }
+}
+
+/**
+ * WITH RECURSIVE name AS (base UNION [ALL] recursive)
+ *
+ * Runs base once, then runs recursive over and over. Each time the working table holds
+ * the rows produced by the previous iteration, references to name inside recursive read
+ * it through a WorkingTableScan. Stops once an iteration produces no new rows.
+ * Without ALL, rows that were already produced are dropped.
+ */
+pub struct RecursiveCte {
+    pub base: Box<dyn Operator>,
+    pub recursive: Box<dyn Operator>,
+    pub working_table: Rc<RefCell<Vec<Vec<LiteralValue>>>>,
+    pub distinct: bool,
+    base_done: bool,
+    pending: VecDeque<Vec<LiteralValue>>,
+    seen: HashSet<Vec<LiteralValue>>,
+}
+
+impl RecursiveCte {
+    pub fn new(base: Box<dyn Operator>, recursive: Box<dyn Operator>, working_table: Rc<RefCell<Vec<Vec<LiteralValue>>>>, distinct: bool) -> RecursiveCte {
+        RecursiveCte { base, recursive, working_table, distinct, base_done: false, pending: VecDeque::new(), seen: HashSet::new() }
+    }
+}
+
+impl Operator for RecursiveCte {
+    fn init(&mut self, ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.base_done = false;
+        self.pending.clear();
+        self.seen.clear();
+        self.working_table.borrow_mut().clear();
+        self.base.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        loop {
+            if let Some(row) = self.pending.pop_front() {
+                return Ok(Some(row));
+            }
+
+            let mut rows = Vec::new();
+            if !self.base_done {
+                self.base_done = true;
+                while let Some(row) = self.base.next(ctx)? {
+                    rows.push(row);
+                }
+            } else {
+                if self.working_table.borrow().is_empty() {
+                    return Ok(None);
+                }
+                self.recursive.init(ctx)?;
+                while let Some(row) = self.recursive.next(ctx)? {
+                    rows.push(row);
+                }
+            }
+
+            if self.distinct {
+                rows.retain(|row| self.seen.insert(row.clone()));
+            }
+            *self.working_table.borrow_mut() = rows.clone();
+            self.pending.extend(rows);
+        }
+    }
+}
+
+// reads the rows of the previous iteration of a RecursiveCte
+pub struct WorkingTableScan {
+    pub working_table: Rc<RefCell<Vec<Vec<LiteralValue>>>>,
+    position: usize,
+}
+
+impl WorkingTableScan {
+    pub fn new(working_table: Rc<RefCell<Vec<Vec<LiteralValue>>>>) -> WorkingTableScan {
+        WorkingTableScan { working_table, position: 0 }
+    }
+}
+
+impl Operator for WorkingTableScan {
+    fn init(&mut self, _ctx: &mut ExecutionContext) -> Result<(), TError> {
+        self.position = 0;
+        Ok(())
+    }
+
+    fn next(&mut self, _ctx: &mut ExecutionContext) -> Result<Option<Vec<LiteralValue>>, TError> {
+        let row = self.working_table.borrow().get(self.position).cloned();
+        self.position += 1;
+        Ok(row)
+    }
+}
@@ -1,9 +1,10 @@
-use std::{fs::{self, File, OpenOptions}, io, path::PathBuf, vec};
+use std::{fs::{self, File, OpenOptions}, vec};
 
 use catalog::{table_schema::TableSchema};
 use config::config::PAGE_SIZE;
-use parse::{parser::Parser, scanner::Scanner};
-use storage::buffer_pool::BufferPoolManager;
+use parse::{parser::Parser, scanner::{is_complete, Scanner, TError}};
+use repl::{completion::complete, line_reader::{Input, LineReader}};
+use storage::{buffer_pool::BufferPoolManager, disk_manager::data_file_path};
 
 use crate::{catalog::catalog::load_catalog, execution::execution::execute, planner::planner::plan};
 
@@ -12,24 +13,25 @@ mod config;
 mod parse;
 mod catalog;
 mod planner;
+mod repl;
 mod execution;
+mod types;
 mod test;
 
 
 
-fn init(buffer_pool: &mut BufferPoolManager) -> std::io::Result<Vec<TableSchema>> {
+fn init(buffer_pool: &mut BufferPoolManager) -> Result<Vec<TableSchema>, TError> {
     println!("init");
     
-    let dir = PathBuf::from(config::config::DATA_DIR);
-    let data_file_path = dir.join(config::config::DATA_FILE);
+    let data_file_path = data_file_path();
     let tables;
     if !data_file_path.exists() {
-        File::create(config::config::DATA_FILE).unwrap();
+        File::create(config::config::DATA_FILE)?;
         tables = vec![];
     } else {
-        let file = OpenOptions::new().read(true).open(data_file_path).unwrap();
-        if file.metadata().unwrap().len() > 0 {
-            tables = load_catalog(buffer_pool);
+        let file = OpenOptions::new().read(true).open(data_file_path)?;
+        if file.metadata()?.len() > 0 {
+            tables = load_catalog(buffer_pool)?;
         } else {
             tables = Vec::new();
         }
@@ -39,61 +41,123 @@ fn init(buffer_pool: &mut BufferPoolManager) -> std::io::Result<Vec<TableSchema>
 }
 
 fn cleanup(buffer_pool: &mut BufferPoolManager) {
-    buffer_pool.flush_all_pages();
-    println!("cleaned up!");
+    match buffer_pool.flush_all_pages() {
+        Ok(()) => println!("cleaned up!"),
+        Err(e) => println!("{}", e.render("")),
+    }
+}
+
+
+// "format sql" prints the statements of the sql the way they are normalized, without running them
+fn format_command(input: &str) -> Option<String> {
+    let input = input.trim_start();
+    let end = input.find(char::is_whitespace).unwrap_or(input.len());
+    if input[..end].eq_ignore_ascii_case("format") {
+        Some(input[end..].to_owned())
+    } else {
+        None
+    }
+}
+
+fn format(scanner: &mut Scanner, parser: &mut Parser, sql: &String) {
+    if let Err(e) = scanner.scan(sql) {
+        println!("{}", e.render(sql));
+        return;
+    }
+    match parser.parse(&scanner.tokens) {
+        Ok(statements) => {
+            for statement in statements {
+                println!("{}", statement);
+            }
+        },
+        Err(_) => {
+            for error in &parser.errors {
+                println!("{}", error.render(sql));
+            }
+            parser.errors.clear();
+        },
+    }
 }
 
+fn run(input: &String, scanner: &mut Scanner, parser: &mut Parser, buffer_pool: &mut BufferPoolManager, tables: &mut Vec<TableSchema>) {
+    if let Some(sql) = format_command(input) {
+        format(scanner, parser, &sql);
+        return;
+    }
+    if let Err(e) = scanner.scan(input) {
+        println!("{}", e.render(input));
+        return;
+    }
+    match parser.parse(&scanner.tokens) {
+        Ok(statements) => {
+            for stmt in statements {
+                let result = plan(tables, stmt).and_then(|plan| execute(buffer_pool, tables, plan));
+                match result {
+                    Ok(Some(result)) => println!("{}", result),
+                    Ok(None) => {},
+                    Err(e) => println!("{}", e.render(input)),
+                }
+            }
+        },
+        Err(_) => {
+            for error in &parser.errors {
+                println!("{}", error.render(input));
+            }
+            parser.errors.clear();
+        },
+    }
+}
 
 fn main() {
     
     let pool_size=4;
     let mut memory = vec![0u8; pool_size * PAGE_SIZE];
-    let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2);
+    let mut buffer_pool = match BufferPoolManager::new(&mut memory, pool_size, 2) {
+        Ok(buffer_pool) => buffer_pool,
+        Err(e) => {
+            println!("{}", e.render(""));
+            return;
+        },
+    };
     let mut scanner = Scanner::new();
     let mut parser = Parser::new();
 
-    let mut tables = init(&mut buffer_pool).unwrap();
+    let mut tables = match init(&mut buffer_pool) {
+        Ok(tables) => tables,
+        Err(e) => {
+            println!("{}", e.render(""));
+            return;
+        },
+    };
 
-    let mut input = String::new();
-    let stdin = io::stdin();
+    let mut reader = LineReader::new();
+    // the lines of a statement that isn't finished yet
+    let mut buffer = String::new();
 
     loop {
-        print!("> ");
-        io::Write::flush(&mut io::stdout()).ok().expect("Couldn't flush stdout");
-        input.clear();
-        let read = stdin.read_line(&mut input);
-        match read {
-            Ok(chars_read) => { 
-                if chars_read == 0 {
-                    break;
-                }
-                match scanner.scan(&input) {
-                    Ok(_) => {
-                        let r = parser.parse(&scanner.tokens);
-                        match r {
-                            Ok(statements) => {
-                                println!("{:?}", statements);
-                                for stmt in statements {
-                                    let plan = plan(&mut tables, stmt).expect("plan");
-                                    execute(&mut buffer_pool, &mut tables, plan);
-                                }
-                            },
-                            Err(_) => {
-                                println!("tokens: {:?}", scanner.tokens);
-                                for error in &parser.errors {
-                                    println!("{:?}", error);
-                                }
-                                parser.errors.clear();
-                            },
-                        }
-                    },
-                    Err(e) => {
-                        println!("error: {:?}", e);
-                    },
-                }
-            }
-            Err(error) => println!("error: {}", error),
+        let prompt = if buffer.is_empty() { "> " } else { "-> " };
+        match reader.read_line(prompt, &|line, cursor| complete(&buffer, line, cursor, &tables)) {
+            Ok(Input::Line(line)) => buffer.push_str(&line),
+            Ok(Input::Interrupted) => {
+                println!();
+                buffer.clear();
+                continue;
+            },
+            Ok(Input::Eof) => break,
+            Err(e) => {
+                println!("error: {}", e);
+                break;
+            },
+        }
+        if !is_complete(&buffer) {
+            continue;
         }
+        run(&buffer, &mut scanner, &mut parser, &mut buffer_pool, &mut tables);
+        buffer.clear();
+    }
+    // what is left at the end of the input is run too, so the error says what is missing
+    if !buffer.trim().is_empty() {
+        run(&buffer, &mut scanner, &mut parser, &mut buffer_pool, &mut tables);
     }
 
     cleanup(&mut buffer_pool);
@@ -1,34 +1,333 @@
-use super::token::{LiteralValue, Token};
+use std::{borrow::Cow, fmt};
 
-#[derive(Debug, Clone)]
+use super::{scanner::quote_identifier, token::{LiteralValue, Span, Token, TokenType}};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     ColumnReference(ColumnReference),
     Literal(Literal),
+    Binary(Binary),
+    Unary(Unary),
+    Subquery(Subquery),
+    InSubquery(InSubquery),
+    Exists(Exists),
+    Case(Case),
+    InList(InList),
+    Between(Between),
+    Like(Like),
+    Function(Function),
+    Cast(Cast),
+    Array(Array),
+    Subscript(Subscript),
+    Quantified(Quantified),
+}
+
+impl Expr {
+    // the source the expression was parsed from
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::ColumnReference(e) => e.span,
+            Expr::Literal(e) => e.span,
+            Expr::Binary(e) => e.span,
+            Expr::Unary(e) => e.span,
+            Expr::Subquery(e) => e.span,
+            Expr::InSubquery(e) => e.span,
+            Expr::Exists(e) => e.span,
+            Expr::Case(e) => e.span,
+            Expr::InList(e) => e.span,
+            Expr::Between(e) => e.span,
+            Expr::Like(e) => e.span,
+            Expr::Function(e) => e.span,
+            Expr::Cast(e) => e.span,
+            Expr::Array(e) => e.span,
+            Expr::Subscript(e) => e.span,
+            Expr::Quantified(e) => e.span,
+        }
+    }
+}
+#[derive(Debug, Clone)]
+pub struct Binary {
+    pub left: Box<Expr>,
+    pub operator: Token,
+    pub right: Box<Expr>,
+    pub span: Span,
+}
+#[derive(Debug, Clone)]
+pub struct Unary {
+    pub operator: Token,
+    pub right: Box<Expr>,
+    pub span: Span,
+}
+// scalar subquery, must return at most one row with one column
+#[derive(Debug, Clone)]
+pub struct Subquery {
+    pub query: Box<Query>,
+    pub span: Span,
+}
+// expr [NOT] IN (SELECT ...)
+#[derive(Debug, Clone)]
+pub struct InSubquery {
+    pub expr: Box<Expr>,
+    pub query: Box<Query>,
+    pub negated: bool,
+    pub span: Span,
+}
+// EXISTS (SELECT ...)
+#[derive(Debug, Clone)]
+pub struct Exists {
+    pub query: Box<Query>,
+    pub span: Span,
+}
+// CASE [operand] WHEN condition THEN result ... [ELSE result] END
+// with an operand each condition is a value compared to it
+#[derive(Debug, Clone)]
+pub struct Case {
+    pub operand: Option<Box<Expr>>,
+    pub when_clauses: Vec<WhenClause>,
+    pub else_result: Option<Box<Expr>>,
+    pub span: Span,
+}
+#[derive(Debug, Clone)]
+pub struct WhenClause {
+    pub condition: Expr,
+    pub result: Expr,
+    pub span: Span,
+}
+// expr [NOT] IN (value, ...)
+#[derive(Debug, Clone)]
+pub struct InList {
+    pub expr: Box<Expr>,
+    pub list: Vec<Expr>,
+    pub negated: bool,
+    pub span: Span,
+}
+// expr [NOT] BETWEEN low AND high
+#[derive(Debug, Clone)]
+pub struct Between {
+    pub expr: Box<Expr>,
+    pub low: Box<Expr>,
+    pub high: Box<Expr>,
+    pub negated: bool,
+    pub span: Span,
+}
+// expr [NOT] LIKE/ILIKE pattern
+#[derive(Debug, Clone)]
+pub struct Like {
+    pub expr: Box<Expr>,
+    pub pattern: Box<Expr>,
+    pub negated: bool,
+    pub case_insensitive: bool,
+    pub span: Span,
+}
+// name(arg, ...)
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: Token,
+    pub args: Vec<Expr>,
+    pub span: Span,
+}
+// CAST(expr AS type) or expr::type
+#[derive(Debug, Clone)]
+pub struct Cast {
+    pub expr: Box<Expr>,
+    pub column_type: ColumnType,
+    pub span: Span,
+}
+// ARRAY[expr, ...]
+#[derive(Debug, Clone)]
+pub struct Array {
+    pub elements: Vec<Expr>,
+    pub span: Span,
+}
+// expr[index], counting from 1
+#[derive(Debug, Clone)]
+pub struct Subscript {
+    pub expr: Box<Expr>,
+    pub index: Box<Expr>,
+    pub span: Span,
+}
+// left op ANY (array) or left op ALL (array)
+#[derive(Debug, Clone)]
+pub struct Quantified {
+    pub left: Box<Expr>,
+    pub operator: Token,
+    pub all: bool,
+    pub right: Box<Expr>,
+    pub span: Span,
 }
 #[derive(Debug, Clone)]
 pub struct Literal {
     pub token: Token,
     pub value: LiteralValue,
+    pub span: Span,
 }
 #[derive(Debug, Clone)]
 pub struct ColumnReference {
+    pub table: Option<String>,
     pub name: String,
+    pub span: Span,
 }
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Statement {
-    SelectStatement(SelectStatement),
+    Query(Query),
     InsertStatement(InsertStatement),
     CreateTableStatement(CreateTableStatement),
+    DropTableStatement(DropTableStatement),
+    TruncateStatement(TruncateStatement),
+    AlterTableStatement(AlterTableStatement),
+    UpdateStatement(UpdateStatement),
+    DeleteStatement(DeleteStatement),
+    CreateSequenceStatement(CreateSequenceStatement),
+    DropSequenceStatement(DropSequenceStatement),
+    CreateIndexStatement(CreateIndexStatement),
+    DropIndexStatement(DropIndexStatement),
 }
-#[derive(Debug)]
+
+impl Statement {
+    // the source of the statement, without the ";"
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Query(s) => s.span(),
+            Statement::InsertStatement(s) => s.span,
+            Statement::CreateTableStatement(s) => s.span,
+            Statement::DropTableStatement(s) => s.span,
+            Statement::TruncateStatement(s) => s.span,
+            Statement::AlterTableStatement(s) => s.span,
+            Statement::UpdateStatement(s) => s.span,
+            Statement::DeleteStatement(s) => s.span,
+            Statement::CreateSequenceStatement(s) => s.span,
+            Statement::DropSequenceStatement(s) => s.span,
+            Statement::CreateIndexStatement(s) => s.span,
+            Statement::DropIndexStatement(s) => s.span,
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Select(Box<SelectStatement>),
+    SetOperation(SetOperation),
+    With(With),
+}
+
+impl Query {
+    // a parenthesized query doesn't include its parentheses
+    pub fn span(&self) -> Span {
+        match self {
+            Query::Select(q) => q.span,
+            Query::SetOperation(q) => q.span,
+            Query::With(q) => q.span,
+        }
+    }
+}
+
+// WITH [RECURSIVE] name [(column, ...)] AS (query), ... body
+#[derive(Debug, Clone)]
+pub struct With {
+    pub recursive: bool,
+    pub ctes: Vec<CommonTableExpression>,
+    pub body: Box<Query>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommonTableExpression {
+    pub name: Token,
+    pub columns: Vec<Token>,
+    pub query: Box<Query>,
+    pub span: Span,
+}
+
+// left UNION/INTERSECT/EXCEPT [ALL] right
+#[derive(Debug, Clone)]
+pub struct SetOperation {
+    pub left: Box<Query>,
+    pub operator: Token,
+    pub all: bool,
+    pub right: Box<Query>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
 pub struct SelectStatement {
-    pub expressions: Vec<Expr>,
-    pub from_item: Table,
+    pub distinct: bool,
+    pub items: Vec<SelectItem>,
+    pub from_item: FromItem,
+    pub where_clause: Option<Expr>,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum FromItem {
+    Table(Table),
+    Subquery(DerivedTable),
+    Unnest(Unnest),
+    // no from clause, the select is evaluated once
+    Nothing,
+}
+
+impl FromItem {
+    // none when there is no from clause
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            FromItem::Table(t) => Some(t.span),
+            FromItem::Subquery(t) => Some(t.span),
+            FromItem::Unnest(t) => Some(t.span),
+            FromItem::Nothing => None,
+        }
+    }
+}
+
+// FROM (SELECT ...) AS alias
+#[derive(Debug, Clone)]
+pub struct DerivedTable {
+    pub query: Box<Query>,
+    pub alias: Token,
+    pub span: Span,
+}
+
+// FROM UNNEST(expr) [[AS] alias [(column)]], a row for each element of an array
+#[derive(Debug, Clone)]
+pub struct Unnest {
+    pub expr: Expr,
+    pub alias: Option<Token>,
+    pub column: Option<Token>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    Wildcard(Wildcard),
+    Expression(SelectExpression),
+}
+
+impl SelectItem {
+    pub fn span(&self) -> Span {
+        match self {
+            SelectItem::Wildcard(w) => w.span,
+            SelectItem::Expression(e) => e.span,
+        }
+    }
+}
+
+// "*" or "t.*"
+#[derive(Debug, Clone)]
+pub struct Wildcard {
+    pub table: Option<Token>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct SelectExpression {
+    pub expr: Expr,
+    pub alias: Option<Token>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
 pub struct Table {
     pub token: Token,
+    pub alias: Option<Token>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -36,19 +335,765 @@ pub struct InsertStatement {
     pub token: Token,
     pub columns: Vec<String>,
     pub values: Vec<Vec<Expr>>, // todo fix this probably
+    pub span: Span,
 }
 #[derive(Debug)]
 pub struct CreateTableStatement {
     pub token: Token,
     pub columns: Vec<Column>,
+    // column constraints end up here too
+    pub constraints: Vec<TableConstraint>,
+    pub span: Span,
+}
+#[derive(Debug, PartialEq)]
+pub enum TableConstraint {
+    PrimaryKey(KeyConstraint),
+    Unique(KeyConstraint),
+    ForeignKey(ForeignKey),
+    Check(Check),
+}
+
+impl TableConstraint {
+    // for a constraint written after a column, the part after the column
+    pub fn span(&self) -> Span {
+        match self {
+            TableConstraint::PrimaryKey(c) | TableConstraint::Unique(c) => c.span,
+            TableConstraint::ForeignKey(c) => c.span,
+            TableConstraint::Check(c) => c.span,
+        }
+    }
+}
+// PRIMARY KEY (columns) or UNIQUE (columns), or either written after a column
+#[derive(Debug)]
+pub struct KeyConstraint {
+    pub columns: Vec<Token>,
+    pub span: Span,
+}
+// CHECK (expr), column is set when it was written after a column
+#[derive(Debug)]
+pub struct Check {
+    pub column: Option<Token>,
+    pub expr: StoredExpr,
+    pub span: Span,
+}
+// FOREIGN KEY (columns) REFERENCES table [(columns)] [ON DELETE action] [ON UPDATE action]
+#[derive(Debug)]
+pub struct ForeignKey {
+    pub columns: Vec<Token>,
+    pub table: Token,
+    // empty when the primary key of the referenced table is meant
+    pub referenced_columns: Vec<Token>,
+    pub on_delete: ReferentialAction,
+    pub on_update: ReferentialAction,
+    pub span: Span,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReferentialAction {
+    Restrict,
+    Cascade,
+    SetNull,
+}
+// UPDATE name SET column = expr [, ...] [WHERE expr]
+#[derive(Debug)]
+pub struct UpdateStatement {
+    pub token: Token,
+    pub assignments: Vec<Assignment>,
+    pub where_clause: Option<Expr>,
+    pub span: Span,
+}
+// DELETE FROM name [WHERE expr]
+#[derive(Debug)]
+pub struct DeleteStatement {
+    pub token: Token,
+    pub where_clause: Option<Expr>,
+    pub span: Span,
+}
+#[derive(Debug)]
+pub struct Assignment {
+    pub column: Token,
+    pub value: Expr,
+    pub span: Span,
+}
+// DROP TABLE [IF EXISTS] name
+#[derive(Debug)]
+pub struct DropTableStatement {
+    pub token: Token,
+    pub if_exists: bool,
+    pub span: Span,
+}
+// CREATE SEQUENCE name [START [WITH] n] [INCREMENT [BY] n]
+#[derive(Debug)]
+pub struct CreateSequenceStatement {
+    pub token: Token,
+    pub options: SequenceOptions,
+    pub span: Span,
+}
+#[derive(Debug, Default, PartialEq)]
+pub struct SequenceOptions {
+    pub start: Option<i64>,
+    pub increment: Option<i64>,
+}
+// DROP SEQUENCE [IF EXISTS] name
+#[derive(Debug)]
+pub struct DropSequenceStatement {
+    pub token: Token,
+    pub if_exists: bool,
+    pub span: Span,
+}
+// CREATE [UNIQUE] INDEX name ON table (column | expr, ...)
+#[derive(Debug)]
+pub struct CreateIndexStatement {
+    pub token: Token,
+    pub table: Token,
+    pub unique: bool,
+    pub elements: Vec<StoredExpr>,
+    pub span: Span,
+}
+// DROP INDEX [IF EXISTS] name
+#[derive(Debug)]
+pub struct DropIndexStatement {
+    pub token: Token,
+    pub if_exists: bool,
+    pub span: Span,
+}
+// TRUNCATE [TABLE] name
+#[derive(Debug)]
+pub struct TruncateStatement {
+    pub token: Token,
+    pub span: Span,
+}
+// ALTER TABLE name action
+#[derive(Debug)]
+pub struct AlterTableStatement {
+    pub token: Token,
+    pub action: AlterTableAction,
+    pub span: Span,
+}
+#[derive(Debug, PartialEq)]
+pub enum AlterTableAction {
+    AddColumn(Box<AddColumn>),
+    DropColumn(DropColumn),
+    RenameColumn(RenameColumn),
+    RenameTable(RenameTable),
+}
+
+impl AlterTableAction {
+    pub fn span(&self) -> Span {
+        match self {
+            AlterTableAction::AddColumn(a) => a.span,
+            AlterTableAction::DropColumn(a) => a.span,
+            AlterTableAction::RenameColumn(a) => a.span,
+            AlterTableAction::RenameTable(a) => a.span,
+        }
+    }
+}
+// ADD [COLUMN] column [DEFAULT expr]
+#[derive(Debug)]
+pub struct AddColumn {
+    pub column: Column,
+    pub span: Span,
+}
+// DROP [COLUMN] name
+#[derive(Debug)]
+pub struct DropColumn {
+    pub token: Token,
+    pub span: Span,
+}
+// RENAME [COLUMN] name TO new_name
+#[derive(Debug)]
+pub struct RenameColumn {
+    pub token: Token,
+    pub new_name: Token,
+    pub span: Span,
+}
+// RENAME TO new_name
+#[derive(Debug)]
+pub struct RenameTable {
+    pub new_name: Token,
+    pub span: Span,
 }
 #[derive(Debug)]
 pub struct Column {
     pub token: Token,
-    pub column_type: ColumnType
+    pub column_type: ColumnType,
+    pub default: Option<StoredExpr>,
+    pub identity: Option<Identity>,
+    pub span: Span,
 }
+// GENERATED {ALWAYS | BY DEFAULT} AS IDENTITY [(options)], or the SERIAL type
 #[derive(Debug)]
+pub struct Identity {
+    // explicit values can't be written to the column
+    pub always: bool,
+    pub options: SequenceOptions,
+    pub span: Span,
+}
+// an expression kept in the catalog, which stores the source it was parsed from
+#[derive(Debug, Clone)]
+pub struct StoredExpr {
+    pub expr: Expr,
+    pub source: String,
+}
+#[derive(Debug, Clone, PartialEq)]
 pub enum ColumnType {
     Bool,
-    Int
-}
\ No newline at end of file
+    SmallInt,
+    Int,
+    BigInt,
+    Real,
+    Double,
+    // precision and scale
+    Decimal(Option<(u8, u8)>),
+    Date,
+    Time,
+    Timestamp,
+    Interval,
+    Text,
+    Bytea,
+    Json,
+    Uuid,
+    // one dimensional arrays of any of the other types
+    Array(Box<ColumnType>),
+}
+// how tightly each level of the grammar binds, from loosest to tightest
+// the source of a stored expression is how it was written, it isn't part of the expression
+impl PartialEq for StoredExpr {
+    fn eq(&self, other: &StoredExpr) -> bool {
+        self.expr == other.expr
+    }
+}
+
+// nodes are equal when they have the same syntax, wherever in the source they were written.
+// every field but the span has to be listed, so a new field can't be left out of the comparison
+macro_rules! eq_ignoring_span {
+    ($($name:ident { $($field:ident),* })*) => {
+        $(impl PartialEq for $name {
+            fn eq(&self, other: &$name) -> bool {
+                let $name { $($field,)* span: _ } = self;
+                $(*$field == other.$field &&)* true
+            }
+        })*
+    };
+}
+
+eq_ignoring_span! {
+    Binary { left, operator, right }
+    Unary { operator, right }
+    Subquery { query }
+    InSubquery { expr, query, negated }
+    Exists { query }
+    Case { operand, when_clauses, else_result }
+    WhenClause { condition, result }
+    InList { expr, list, negated }
+    Between { expr, low, high, negated }
+    Like { expr, pattern, negated, case_insensitive }
+    Function { name, args }
+    Cast { expr, column_type }
+    Array { elements }
+    Subscript { expr, index }
+    Quantified { left, operator, all, right }
+    Literal { token, value }
+    ColumnReference { table, name }
+    With { recursive, ctes, body }
+    CommonTableExpression { name, columns, query }
+    SetOperation { left, operator, all, right }
+    SelectStatement { distinct, items, from_item, where_clause }
+    DerivedTable { query, alias }
+    Unnest { expr, alias, column }
+    Wildcard { table }
+    SelectExpression { expr, alias }
+    Table { token, alias }
+    InsertStatement { token, columns, values }
+    CreateTableStatement { token, columns, constraints }
+    KeyConstraint { columns }
+    Check { column, expr }
+    ForeignKey { columns, table, referenced_columns, on_delete, on_update }
+    UpdateStatement { token, assignments, where_clause }
+    DeleteStatement { token, where_clause }
+    Assignment { column, value }
+    DropTableStatement { token, if_exists }
+    CreateSequenceStatement { token, options }
+    DropSequenceStatement { token, if_exists }
+    CreateIndexStatement { token, table, unique, elements }
+    DropIndexStatement { token, if_exists }
+    TruncateStatement { token }
+    AlterTableStatement { token, action }
+    AddColumn { column }
+    DropColumn { token }
+    RenameColumn { token, new_name }
+    RenameTable { new_name }
+    Column { token, column_type, default, identity }
+    Identity { always, options }
+}
+
+const OR: u8 = 1;
+const AND: u8 = 2;
+const NOT: u8 = 3;
+const COMPARISON: u8 = 4;
+const JSON_ACCESS: u8 = 5;
+const TERM: u8 = 6;
+const FACTOR: u8 = 7;
+const UNARY: u8 = 8;
+const POSTFIX: u8 = 9;
+const PRIMARY: u8 = 10;
+
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Binary(binary) => binary_precedence(&binary.operator),
+        Expr::Unary(unary) if matches!(unary.operator.token_type, TokenType::Not) => NOT,
+        Expr::Unary(_) => UNARY,
+        Expr::InSubquery(_) | Expr::InList(_) | Expr::Between(_) | Expr::Like(_) | Expr::Quantified(_) => COMPARISON,
+        Expr::Cast(_) | Expr::Subscript(_) => POSTFIX,
+        // negative numbers are written with their sign
+        Expr::Literal(literal) if literal_source(literal).starts_with('-') => UNARY,
+        _ => PRIMARY,
+    }
+}
+
+fn binary_precedence(operator: &Token) -> u8 {
+    match operator.token_type {
+        TokenType::Or => OR,
+        TokenType::And => AND,
+        TokenType::Arrow | TokenType::LongArrow | TokenType::HashArrow | TokenType::HashLongArrow => JSON_ACCESS,
+        TokenType::Plus | TokenType::Minus => TERM,
+        TokenType::Star | TokenType::Slash | TokenType::Percent => FACTOR,
+        _ => COMPARISON,
+    }
+}
+
+// an operand that is put in parentheses when it binds looser than its position allows
+struct Operand<'a>(&'a Expr, u8);
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if precedence(self.0) < self.1 {
+            write!(f, "({})", self.0)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+// a literal as it was written, a minus folded into a number is put back in front of it
+fn literal_source(literal: &Literal) -> Cow<'_, str> {
+    let lexeme = &literal.token.lexeme;
+    match (&literal.token.token_type, &literal.value) {
+        (TokenType::IntLiteral | TokenType::DecimalLiteral, value) if literal.token.literal.as_ref() == Some(value) => Cow::Borrowed(lexeme),
+        (TokenType::IntLiteral | TokenType::DecimalLiteral, _) => Cow::Owned(format!("-{}", lexeme)),
+        (TokenType::StringLiteral, LiteralValue::StringValue(_)) => Cow::Borrowed(lexeme),
+        (TokenType::StringLiteral, LiteralValue::DateValue(_)) => Cow::Owned(format!("date {}", lexeme)),
+        (TokenType::StringLiteral, LiteralValue::TimeValue(_)) => Cow::Owned(format!("time {}", lexeme)),
+        (TokenType::StringLiteral, LiteralValue::TimestampValue(_)) => Cow::Owned(format!("timestamp {}", lexeme)),
+        (TokenType::StringLiteral, LiteralValue::IntervalValue(_)) => Cow::Owned(format!("interval {}", lexeme)),
+        (TokenType::True | TokenType::False | TokenType::Null, _) => Cow::Borrowed(lexeme),
+        (_, LiteralValue::StringValue(s)) => Cow::Owned(format!("'{}'", s.replace('\'', "''"))),
+        (_, value) => Cow::Owned(value.to_string()),
+    }
+}
+
+// items separated by commas
+struct List<'a, T>(&'a [T]);
+
+impl<T: fmt::Display> fmt::Display for List<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+// identifiers written the way they have to be scanned again
+struct Names<'a>(&'a [Token]);
+
+impl fmt::Display for Names<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.0.iter().map(|t| t.source()).collect();
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+/**
+ * Display renders nodes as canonical sql, which parses back to the same tree: keywords are
+ * lowercase, identifiers are quoted only when they have to be, and parentheses are only
+ * written where the precedence of the operators needs them.
+ */
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::ColumnReference(c) => match &c.table {
+                Some(table) => write!(f, "{}.{}", quote_identifier(table), quote_identifier(&c.name)),
+                None => write!(f, "{}", quote_identifier(&c.name)),
+            },
+            Expr::Literal(literal) => write!(f, "{}", literal_source(literal)),
+            Expr::Binary(b) => {
+                let level = binary_precedence(&b.operator);
+                // comparisons don't chain, the others are left associative
+                let left = if level == COMPARISON { level + 1 } else { level };
+                write!(f, "{} {} {}", Operand(&b.left, left), b.operator.lexeme, Operand(&b.right, level + 1))
+            },
+            Expr::Unary(u) => match u.operator.token_type {
+                TokenType::Not => write!(f, "not {}", Operand(&u.right, NOT)),
+                _ => {
+                    // two minuses in a row would start a comment
+                    let right = Operand(&u.right, UNARY).to_string();
+                    let space = if right.starts_with('-') { " " } else { "" };
+                    write!(f, "{}{}{}", u.operator.lexeme, space, right)
+                },
+            },
+            Expr::Subquery(s) => write!(f, "({})", s.query),
+            Expr::InSubquery(i) => write!(f, "{} {}in ({})", Operand(&i.expr, JSON_ACCESS), not(i.negated), i.query),
+            Expr::Exists(e) => write!(f, "exists ({})", e.query),
+            Expr::Case(c) => {
+                write!(f, "case")?;
+                if let Some(operand) = &c.operand {
+                    write!(f, " {}", operand)?;
+                }
+                for when in &c.when_clauses {
+                    write!(f, " when {} then {}", when.condition, when.result)?;
+                }
+                if let Some(else_result) = &c.else_result {
+                    write!(f, " else {}", else_result)?;
+                }
+                write!(f, " end")
+            },
+            Expr::InList(i) => write!(f, "{} {}in ({})", Operand(&i.expr, JSON_ACCESS), not(i.negated), List(&i.list)),
+            Expr::Between(b) => write!(f, "{} {}between {} and {}", Operand(&b.expr, JSON_ACCESS), not(b.negated), Operand(&b.low, JSON_ACCESS), Operand(&b.high, JSON_ACCESS)),
+            Expr::Like(l) => {
+                let operator = if l.case_insensitive { "ilike" } else { "like" };
+                write!(f, "{} {}{} {}", Operand(&l.expr, JSON_ACCESS), not(l.negated), operator, Operand(&l.pattern, JSON_ACCESS))
+            },
+            Expr::Function(function) => match (&function.name.token_type, function.args.as_slice()) {
+                // extract(field from value) is parsed into a call with the field as a string
+                (TokenType::Extract, [Expr::Literal(field), value]) => write!(f, "extract({} from {})", field.token.source(), value),
+                _ => write!(f, "{}({})", function.name.source(), List(&function.args)),
+            },
+            Expr::Cast(c) => write!(f, "{}::{}", Operand(&c.expr, POSTFIX), c.column_type),
+            Expr::Array(a) => write!(f, "array[{}]", List(&a.elements)),
+            Expr::Subscript(s) => match s.expr.as_ref() {
+                // the brackets would be read as part of the type
+                Expr::Cast(_) => write!(f, "({})[{}]", s.expr, s.index),
+                _ => write!(f, "{}[{}]", Operand(&s.expr, POSTFIX), s.index),
+            },
+            Expr::Quantified(q) => {
+                let quantifier = if q.all { "all" } else { "any" };
+                write!(f, "{} {} {} ({})", Operand(&q.left, JSON_ACCESS), q.operator.lexeme, quantifier, q.right)
+            },
+        }
+    }
+}
+
+fn not(negated: bool) -> &'static str {
+    if negated { "not " } else { "" }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Query(query) => write!(f, "{};", query),
+            Statement::InsertStatement(s) => {
+                let columns: Vec<_> = s.columns.iter().map(|c| quote_identifier(c)).collect();
+                let rows: Vec<_> = s.values.iter().map(|row| format!("({})", List(row))).collect();
+                write!(f, "insert into {} ({}) values {};", s.token.source(), columns.join(", "), rows.join(", "))
+            },
+            Statement::CreateTableStatement(s) => write!(f, "{}", s),
+            Statement::DropTableStatement(s) => write!(f, "drop table {}{};", if_exists(s.if_exists), s.token.source()),
+            Statement::TruncateStatement(s) => write!(f, "truncate table {};", s.token.source()),
+            Statement::AlterTableStatement(s) => {
+                write!(f, "alter table {} ", s.token.source())?;
+                match &s.action {
+                    AlterTableAction::AddColumn(a) => write!(f, "add column {};", a.column),
+                    AlterTableAction::DropColumn(d) => write!(f, "drop column {};", d.token.source()),
+                    AlterTableAction::RenameColumn(r) => write!(f, "rename column {} to {};", r.token.source(), r.new_name.source()),
+                    AlterTableAction::RenameTable(r) => write!(f, "rename to {};", r.new_name.source()),
+                }
+            },
+            Statement::UpdateStatement(s) => {
+                let assignments: Vec<_> = s.assignments.iter().map(|a| format!("{} = {}", a.column.source(), a.value)).collect();
+                write!(f, "update {} set {}{};", s.token.source(), assignments.join(", "), Where(&s.where_clause))
+            },
+            Statement::DeleteStatement(s) => write!(f, "delete from {}{};", s.token.source(), Where(&s.where_clause)),
+            Statement::CreateSequenceStatement(s) => {
+                write!(f, "create sequence {}", s.token.source())?;
+                if s.options.start.is_some() || s.options.increment.is_some() {
+                    write!(f, " {}", s.options)?;
+                }
+                write!(f, ";")
+            },
+            Statement::DropSequenceStatement(s) => write!(f, "drop sequence {}{};", if_exists(s.if_exists), s.token.source()),
+            Statement::CreateIndexStatement(s) => {
+                let unique = if s.unique { "unique " } else { "" };
+                write!(f, "create {}index {} on {} ({});", unique, s.token.source(), s.table.source(), List(&s.elements))
+            },
+            Statement::DropIndexStatement(s) => write!(f, "drop index {}{};", if_exists(s.if_exists), s.token.source()),
+        }
+    }
+}
+
+fn if_exists(if_exists: bool) -> &'static str {
+    if if_exists { "if exists " } else { "" }
+}
+
+// " where expr" when there is a condition
+struct Where<'a>(&'a Option<Expr>);
+
+impl fmt::Display for Where<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(condition) => write!(f, " where {}", condition),
+            None => Ok(()),
+        }
+    }
+}
+
+// with binds loosest, intersect tighter than union and except
+fn query_precedence(query: &Query) -> u8 {
+    match query {
+        Query::With(_) => 0,
+        Query::SetOperation(s) if matches!(s.operator.token_type, TokenType::Intersect) => 2,
+        Query::SetOperation(_) => 1,
+        Query::Select(_) => 3,
+    }
+}
+
+struct QueryOperand<'a>(&'a Query, u8);
+
+impl fmt::Display for QueryOperand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if query_precedence(self.0) < self.1 {
+            write!(f, "({})", self.0)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Query::Select(select) => write!(f, "{}", select),
+            Query::SetOperation(s) => {
+                let level = query_precedence(self);
+                let all = if s.all { " all" } else { "" };
+                write!(f, "{} {}{} {}", QueryOperand(&s.left, level), s.operator.lexeme, all, QueryOperand(&s.right, level + 1))
+            },
+            Query::With(w) => {
+                write!(f, "with {}{} {}", if w.recursive { "recursive " } else { "" }, List(&w.ctes), QueryOperand(&w.body, 1))
+            },
+        }
+    }
+}
+
+impl fmt::Display for CommonTableExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name.source())?;
+        if !self.columns.is_empty() {
+            write!(f, " ({})", Names(&self.columns))?;
+        }
+        write!(f, " as ({})", self.query)
+    }
+}
+
+impl fmt::Display for SelectStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "select {}{}", if self.distinct { "distinct " } else { "" }, List(&self.items))?;
+        match &self.from_item {
+            FromItem::Nothing => {},
+            from_item => write!(f, " from {}", from_item)?,
+        }
+        write!(f, "{}", Where(&self.where_clause))
+    }
+}
+
+impl fmt::Display for FromItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FromItem::Table(table) => write!(f, "{}{}", table.token.source(), Alias(&table.alias)),
+            FromItem::Subquery(derived) => write!(f, "({}) as {}", derived.query, derived.alias.source()),
+            FromItem::Unnest(unnest) => {
+                write!(f, "unnest({}){}", unnest.expr, Alias(&unnest.alias))?;
+                match &unnest.column {
+                    Some(column) => write!(f, " ({})", column.source()),
+                    None => Ok(()),
+                }
+            },
+            FromItem::Nothing => Ok(()),
+        }
+    }
+}
+
+// " as alias" when there is one
+struct Alias<'a>(&'a Option<Token>);
+
+impl fmt::Display for Alias<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(alias) => write!(f, " as {}", alias.source()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for SelectItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectItem::Wildcard(Wildcard { table: Some(table), .. }) => write!(f, "{}.*", table.source()),
+            SelectItem::Wildcard(Wildcard { table: None, .. }) => write!(f, "*"),
+            SelectItem::Expression(e) => write!(f, "{}{}", e.expr, Alias(&e.alias)),
+        }
+    }
+}
+
+impl fmt::Display for CreateTableStatement {
+    /**
+     * A constraint on a single column is written after the column when that keeps the constraints
+     * in order, otherwise it's written as a table constraint where it was in the list of elements.
+     * Checks written after a column stay there, since they are named after it.
+     */
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = |name: &Token| self.columns.iter().position(|c| c.token.lexeme == name.lexeme);
+        let mut elements: Vec<String> = Vec::new();
+        // the number of columns written, and whether the last element is the last of them
+        let mut written = 0;
+        let mut after_column = false;
+        for constraint in &self.constraints {
+            let column = match constraint {
+                TableConstraint::Check(check) => check.column.as_ref().and_then(position),
+                TableConstraint::PrimaryKey(key) | TableConstraint::Unique(key) if key.columns.len() == 1 => position(&key.columns[0]),
+                TableConstraint::ForeignKey(foreign_key) if foreign_key.columns.len() == 1 => position(&foreign_key.columns[0]),
+                _ => None,
+            }.filter(|&i| i >= written || (after_column && i + 1 == written));
+            match column {
+                Some(i) => {
+                    while written <= i {
+                        elements.push(self.columns[written].to_string());
+                        written += 1;
+                    }
+                    let element = elements.last_mut().unwrap();
+                    match constraint {
+                        TableConstraint::PrimaryKey(_) => element.push_str(" primary key"),
+                        TableConstraint::Unique(_) => element.push_str(" unique"),
+                        TableConstraint::ForeignKey(foreign_key) => element.push_str(&format!(" {}", References(foreign_key))),
+                        TableConstraint::Check(check) => element.push_str(&format!(" check ({})", check.expr)),
+                    }
+                    after_column = true;
+                },
+                None => {
+                    elements.push(match constraint {
+                        TableConstraint::PrimaryKey(key) => format!("primary key ({})", Names(&key.columns)),
+                        TableConstraint::Unique(key) => format!("unique ({})", Names(&key.columns)),
+                        TableConstraint::ForeignKey(foreign_key) => format!("foreign key ({}) {}", Names(&foreign_key.columns), References(foreign_key)),
+                        TableConstraint::Check(check) => format!("check ({})", check.expr),
+                    });
+                    after_column = false;
+                },
+            }
+        }
+        elements.extend(self.columns[written..].iter().map(|c| c.to_string()));
+        write!(f, "create table {} ({});", self.token.source(), elements.join(", "))
+    }
+}
+
+// references table [(columns)] [on delete action] [on update action]
+struct References<'a>(&'a ForeignKey);
+
+impl fmt::Display for References<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let foreign_key = self.0;
+        write!(f, "references {}", foreign_key.table.source())?;
+        if !foreign_key.referenced_columns.is_empty() {
+            write!(f, " ({})", Names(&foreign_key.referenced_columns))?;
+        }
+        if !matches!(foreign_key.on_delete, ReferentialAction::Restrict) {
+            write!(f, " on delete {}", foreign_key.on_delete)?;
+        }
+        if !matches!(foreign_key.on_update, ReferentialAction::Restrict) {
+            write!(f, " on update {}", foreign_key.on_update)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ReferentialAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReferentialAction::Restrict => write!(f, "restrict"),
+            ReferentialAction::Cascade => write!(f, "cascade"),
+            ReferentialAction::SetNull => write!(f, "set null"),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.token.source())?;
+        let identity = match &self.identity {
+            // an int column generated by default with no options is what serial stands for
+            Some(Identity { always: false, options: SequenceOptions { start: None, increment: None }, .. }) if matches!(self.column_type, ColumnType::Int) => {
+                write!(f, " serial")?;
+                None
+            },
+            identity => {
+                write!(f, " {}", self.column_type)?;
+                identity.as_ref()
+            },
+        };
+        if let Some(default) = &self.default {
+            write!(f, " default {}", default)?;
+        }
+        if let Some(identity) = identity {
+            write!(f, " generated {} as identity", if identity.always { "always" } else { "by default" })?;
+            if identity.options.start.is_some() || identity.options.increment.is_some() {
+                write!(f, " ({})", identity.options)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SequenceOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut options = Vec::new();
+        if let Some(start) = self.start {
+            options.push(format!("start with {}", start));
+        }
+        if let Some(increment) = self.increment {
+            options.push(format!("increment by {}", increment));
+        }
+        write!(f, "{}", options.join(" "))
+    }
+}
+
+// the expression rather than its source, so it comes out canonical too
+impl fmt::Display for StoredExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr)
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnType::Bool => write!(f, "bool"),
+            ColumnType::SmallInt => write!(f, "smallint"),
+            ColumnType::Int => write!(f, "integer"),
+            ColumnType::BigInt => write!(f, "bigint"),
+            ColumnType::Real => write!(f, "real"),
+            ColumnType::Double => write!(f, "double precision"),
+            ColumnType::Decimal(Some((precision, scale))) => write!(f, "decimal({},{})", precision, scale),
+            ColumnType::Decimal(None) => write!(f, "decimal"),
+            ColumnType::Date => write!(f, "date"),
+            ColumnType::Time => write!(f, "time"),
+            ColumnType::Timestamp => write!(f, "timestamp"),
+            ColumnType::Interval => write!(f, "interval"),
+            ColumnType::Text => write!(f, "text"),
+            ColumnType::Bytea => write!(f, "bytea"),
+            ColumnType::Json => write!(f, "json"),
+            ColumnType::Uuid => write!(f, "uuid"),
+            ColumnType::Array(element_type) => write!(f, "{}[]", element_type),
+        }
+    }
+}
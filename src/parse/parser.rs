@@ -1,6 +1,8 @@
 use std::{fmt::format, iter::Peekable, slice::Iter};
 
-use super::{ast::{Column, ColumnType, CreateTableStatement, Expr, InsertStatement, Literal, SelectStatement, Statement, Table}, scanner::TError, token::{Token, TokenType}};
+use crate::types::{datetime::{parse_date, parse_time, parse_timestamp, Interval}, decimal::MAX_PRECISION};
+
+use super::{ast::{AddColumn, AlterTableAction, AlterTableStatement, Between, Binary, Case, Cast, Column, ColumnReference, CommonTableExpression, ColumnType, CreateTableStatement, DerivedTable, DropColumn, DropTableStatement, Exists, Expr, FromItem, InList, InSubquery, Like, Literal, Query, RenameColumn, RenameTable, SelectExpression, SelectItem, SelectStatement, SetOperation, Statement, Subquery, Table, TableConstraint, Check, StoredExpr, ForeignKey, ReferentialAction, DeleteStatement, TruncateStatement, Unary, UpdateStatement, Assignment, WhenClause, Wildcard, With, InsertStatement, KeyConstraint, Function, CreateSequenceStatement, DropSequenceStatement, SequenceOptions, Identity, CreateIndexStatement, DropIndexStatement, Array, Subscript, Quantified, Unnest}, scanner::{TError, INVALID_DATETIME_FORMAT}, token::{LiteralValue, Span, Token, TokenType}};
 
 
 
@@ -15,6 +17,14 @@ impl Parser {
         Parser { errors: Vec::new() }
     }
 
+    // a single expression followed by EOF, used for expressions stored in the catalog
+    pub fn parse_expr(&self, tokens: &[Token]) -> Result<Expr, TError> {
+        let mut tokens = tokens.iter().peekable();
+        let expr = self.expr(&mut tokens)?;
+        self.consume(&mut tokens, TokenType::EOF, "end of expression")?;
+        Ok(expr)
+    }
+
     // query -> statement* EOF ;
     pub fn parse(&mut self, tokens: & Vec<Token>) -> Result<Vec<Statement>, ()> {
         let mut tokens = tokens.iter().peekable();
@@ -76,8 +86,14 @@ impl Parser {
                         Some(peeked) => {
                             match peeked.token_type {
                                 TokenType::Select => break,
+                                TokenType::With => break,
                                 TokenType::Insert => break,
                                 TokenType::Create => break,
+                                TokenType::Drop => break,
+                                TokenType::Truncate => break,
+                                TokenType::Alter => break,
+                                TokenType::Update => break,
+                                TokenType::Delete => break,
                                 _ => {}
                             }
                         },
@@ -96,79 +112,373 @@ impl Parser {
     // statement -> select | insert | create_table
     fn statement(&mut self, tokens: &mut Peekable<Iter<Token>>) -> Result<Statement, TError> {
         match &tokens.peek().unwrap().token_type {
-            TokenType::Select => {
+            TokenType::Select | TokenType::With => {
                 self.select(tokens)
             },
             TokenType::Insert => {
                 self.insert(tokens)
             },
             TokenType::Create => {
-                self.create_table(tokens)
-            }
+                let mut lookahead = tokens.clone();
+                lookahead.next();
+                let kind = lookahead.peek().unwrap();
+                match kind.token_type {
+                    TokenType::Unique => self.create_index(tokens),
+                    _ if is_word(kind, "index") => self.create_index(tokens),
+                    _ if is_word(kind, "sequence") => self.create_sequence(tokens),
+                    _ => self.create_table(tokens),
+                }
+            },
+            TokenType::Drop => {
+                self.drop_table(tokens)
+            },
+            TokenType::Truncate => {
+                self.truncate(tokens)
+            },
+            TokenType::Alter => {
+                self.alter_table(tokens)
+            },
+            TokenType::Update => {
+                self.update(tokens)
+            },
+            TokenType::Delete => {
+                self.delete(tokens)
+            },
             _ => {
                 let token = tokens.peek().unwrap();
-                Err(TError::ParseError(
-                
-                format!("found unexpected token {:?} at line {}", token, token.line)
-            ))
+                Err(unexpected(token, "a statement"))
             }
         }
     }
     
-    // select -> "select" expr ("," expr)* "from" identifier ";"
+    // select -> query ";"
     fn select(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Statement, TError> {
-        tokens.next(); // consume "select"
-        let mut expressions = Vec::new();
+        let query = self.query(tokens)?;
+
+        match tokens.peek().unwrap().token_type {
+            TokenType::Semicolon => {
+                tokens.next(); // consume ";"
+            },
+            _ => {
+                let token = tokens.peek().unwrap();
+                return Err(unexpected(token, "';' after select"))
+            }
+        };
+
+        Ok(Statement::Query(query))
+    }
+
+    // query -> ("with" "recursive"? cte ("," cte)*)? union
+    fn query(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Query, TError> {
+        let start = tokens.clone();
+        match tokens.peek().unwrap().token_type {
+            TokenType::With => {
+                tokens.next(); // consume "with"
+            },
+            _ => return self.union(tokens),
+        };
+        let recursive = match tokens.peek().unwrap().token_type {
+            TokenType::Recursive => {
+                tokens.next(); // consume "recursive"
+                true
+            },
+            _ => false
+        };
+        let mut ctes = vec![self.cte(tokens)?];
+        while let TokenType::Comma = tokens.peek().unwrap().token_type {
+            tokens.next(); // consume ","
+            ctes.push(self.cte(tokens)?);
+        }
+        let body = self.union(tokens)?;
+        Ok(Query::With(With { recursive, ctes, body: Box::new(body), span: span_since(&start, tokens) }))
+    }
+
+    // cte -> identifier ("(" identifier ("," identifier)* ")")? "as" "(" query ")"
+    fn cte(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<CommonTableExpression, TError> {
+        let start = tokens.clone();
+        let name;
+        match tokens.peek().unwrap().token_type {
+            TokenType::Identifier => {
+                name = tokens.next().unwrap(); // consume identifier
+            },
+            _ => {
+                let token = tokens.peek().unwrap();
+                return Err(unexpected(token, "name of common table expression"))
+            }
+        };
+
+        let mut columns = Vec::new();
+        if let TokenType::LeftParen = tokens.peek().unwrap().token_type {
+            tokens.next(); // consume "("
+            loop {
+                match tokens.peek().unwrap().token_type {
+                    TokenType::Identifier => {
+                        columns.push(tokens.next().unwrap().clone()); // consume identifier
+                    },
+                    _ => {
+                        let token = tokens.peek().unwrap();
+                        return Err(unexpected(token, "column name"))
+                    }
+                };
+                match tokens.peek().unwrap().token_type {
+                    TokenType::Comma => {
+                        tokens.next(); // consume ","
+                    },
+                    _ => break
+                };
+            }
+            self.right_paren(tokens)?;
+        }
+
+        match tokens.peek().unwrap().token_type {
+            TokenType::As => {
+                tokens.next(); // consume "as"
+            },
+            _ => {
+                let token = tokens.peek().unwrap();
+                return Err(unexpected(token, "'as'"))
+            }
+        };
+        let query = self.parenthesized_query(tokens)?;
+        Ok(CommonTableExpression { name: name.clone(), columns, query: Box::new(query), span: span_since(&start, tokens) })
+    }
+
+    // union -> intersect (("union" | "except") "all"? intersect)*
+    fn union(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Query, TError> {
+        let start = tokens.clone();
+        let mut query = self.intersect(tokens)?;
+        while let TokenType::Union | TokenType::Except = tokens.peek().unwrap().token_type {
+            let operator = tokens.next().unwrap(); // consume "union" or "except"
+            let all = self.set_quantifier(tokens);
+            let right = self.intersect(tokens)?;
+            let span = span_since(&start, tokens);
+            query = Query::SetOperation(SetOperation { left: Box::new(query), operator: operator.clone(), all, right: Box::new(right), span });
+        }
+        Ok(query)
+    }
+
+    // intersect -> simple_query ("intersect" "all"? simple_query)*
+    fn intersect(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Query, TError> {
+        let start = tokens.clone();
+        let mut query = self.simple_query(tokens)?;
+        while let TokenType::Intersect = tokens.peek().unwrap().token_type {
+            let operator = tokens.next().unwrap(); // consume "intersect"
+            let all = self.set_quantifier(tokens);
+            let right = self.simple_query(tokens)?;
+            let span = span_since(&start, tokens);
+            query = Query::SetOperation(SetOperation { left: Box::new(query), operator: operator.clone(), all, right: Box::new(right), span });
+        }
+        Ok(query)
+    }
+
+    fn set_quantifier(&self, tokens: &mut Peekable<Iter<Token>>) -> bool {
+        match tokens.peek().unwrap().token_type {
+            TokenType::All => {
+                tokens.next(); // consume "all"
+                true
+            },
+            _ => false
+        }
+    }
+
+    // simple_query -> select_core | "(" query ")"
+    fn simple_query(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Query, TError> {
+        match tokens.peek().unwrap().token_type {
+            TokenType::LeftParen => self.parenthesized_query(tokens),
+            _ => Ok(Query::Select(Box::new(self.select_core(tokens)?))),
+        }
+    }
+
+    // select_core -> "select" "distinct"? select_item ("," select_item)* ("from" table_reference)? ("where" expr)?
+    fn select_core(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<SelectStatement, TError> {
+        let start = tokens.clone();
+        match tokens.peek().unwrap().token_type {
+            TokenType::Select => {
+                tokens.next(); // consume "select"
+            },
+            _ => {
+                let token = tokens.peek().unwrap();
+                return Err(unexpected(token, "'select'"))
+            }
+        };
+        let distinct = match tokens.peek().unwrap().token_type {
+            TokenType::Distinct => {
+                tokens.next(); // consume "distinct"
+                true
+            },
+            _ => false
+        };
+        let mut items = Vec::new();
         loop {
-            let expr = self.expr(tokens)?;
-            expressions.push(expr);
+            let item = self.select_item(tokens)?;
+            items.push(item);
             match tokens.peek().unwrap().token_type {
-                    TokenType::Comma => {
-                        tokens.next(); //consume ","
-                    }
-                    TokenType::From => {
-                        tokens.next(); //consume "from"
-                        break;
+                TokenType::Comma => {
+                    tokens.next(); //consume ","
+                }
+                _ => break,
+            }
+        };
+
+        let from_item = match tokens.peek().unwrap().token_type {
+            TokenType::From => {
+                tokens.next(); //consume "from"
+                self.table_reference(tokens)?
+            },
+            _ => FromItem::Nothing,
+        };
+
+        let where_clause = match tokens.peek().unwrap().token_type {
+            TokenType::Where => {
+                tokens.next(); // consume "where"
+                Some(self.expr(tokens)?)
+            },
+            _ => None
+        };
+
+        Ok(SelectStatement {distinct, items, from_item, where_clause, span: span_since(&start, tokens)})
+    }
+
+    // select_item -> "*" | identifier "." "*" | expr alias?
+    fn select_item(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<SelectItem, TError> {
+        let start = tokens.clone();
+        match tokens.peek().unwrap().token_type {
+            TokenType::Star => {
+                let star = tokens.next().unwrap(); // consume "*"
+                return Ok(SelectItem::Wildcard(Wildcard { table: None, span: star.span }));
+            },
+            TokenType::Identifier => {
+                // need two tokens of lookahead to tell "t.*" apart from "t.column"
+                let mut lookahead = tokens.clone();
+                lookahead.next();
+                if matches!(lookahead.next().unwrap().token_type, TokenType::Dot)
+                    && matches!(lookahead.peek().unwrap().token_type, TokenType::Star) {
+                    let table = tokens.next().unwrap(); // consume identifier
+                    tokens.next(); // consume "."
+                    let star = tokens.next().unwrap(); // consume "*"
+                    return Ok(SelectItem::Wildcard(Wildcard { table: Some(table.clone()), span: table.span.to(star.span) }));
+                }
+            },
+            _ => {}
+        };
+
+        let expr = self.expr(tokens)?;
+        let alias = self.alias(tokens)?;
+        Ok(SelectItem::Expression(SelectExpression { expr, alias, span: span_since(&start, tokens) }))
+    }
+
+    // alias -> ("as" identifier | identifier)
+    fn alias(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Option<Token>, TError> {
+        match tokens.peek().unwrap().token_type {
+            TokenType::As => {
+                tokens.next(); // consume "as"
+                match tokens.peek().unwrap().token_type {
+                    TokenType::Identifier => {
+                        Ok(Some(tokens.next().unwrap().clone())) // consume identifier
                     },
                     _ => {
-
+                        let token = tokens.peek().unwrap();
+                        Err(unexpected(token, "identifier after 'as'"))
                     }
                 }
-        };
+            },
+            TokenType::Identifier => {
+                Ok(Some(tokens.next().unwrap().clone())) // consume identifier
+            },
+            _ => Ok(None)
+        }
+    }
 
-        let identifier;
+    // table_reference -> identifier alias? | "(" query ")" alias | unnest
+    fn table_reference(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<FromItem, TError> {
+        let start = tokens.clone();
+        // unnest is only a function when it's called, otherwise it can be a table name
+        let mut lookahead = tokens.clone();
+        let token = lookahead.next().unwrap();
+        if matches!(token.token_type, TokenType::Identifier) && token.lexeme == "unnest" && matches!(lookahead.peek().unwrap().token_type, TokenType::LeftParen) {
+            return self.unnest(tokens);
+        }
         match tokens.peek().unwrap().token_type {
             TokenType::Identifier => {
-                identifier = tokens.next().unwrap(); // consume identifier
+                let identifier = tokens.next().unwrap(); // consume identifier
+                let alias = self.alias(tokens)?;
+                Ok(FromItem::Table(Table {token: identifier.clone(), alias, span: span_since(&start, tokens)}))
+            },
+            TokenType::LeftParen => {
+                tokens.next(); // consume "("
+                let query = self.query(tokens)?;
+                self.right_paren(tokens)?;
+                match self.alias(tokens)? {
+                    Some(alias) => Ok(FromItem::Subquery(DerivedTable { query: Box::new(query), alias, span: span_since(&start, tokens) })),
+                    None => {
+                        let token = tokens.peek().unwrap();
+                        Err(TError::ParseError("subquery in from must have an alias".to_owned()).at(token.span))
+                    }
+                }
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected identifier", token, token.line)
-                ))
+                Err(unexpected(token, "identifier"))
             }
+        }
+    }
+
+    // unnest -> "unnest" "(" expr ")" (alias ("(" identifier ")")?)?
+    fn unnest(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<FromItem, TError> {
+        let start = tokens.clone();
+        tokens.next(); // consume "unnest"
+        tokens.next(); // consume "("
+        let expr = self.expr(tokens)?;
+        self.right_paren(tokens)?;
+        let alias = self.alias(tokens)?;
+        let column = match (&alias, &tokens.peek().unwrap().token_type) {
+            (Some(_), TokenType::LeftParen) => {
+                tokens.next(); // consume "("
+                let column = self.consume(tokens, TokenType::Identifier, "column name")?;
+                self.right_paren(tokens)?;
+                Some(column.clone())
+            },
+            _ => None,
         };
+        Ok(FromItem::Unnest(Unnest { expr, alias, column, span: span_since(&start, tokens) }))
+    }
 
+    // the span of the ")"
+    fn right_paren(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Span, TError> {
         match tokens.peek().unwrap().token_type {
-            TokenType::Semicolon => {
-                tokens.next(); // consume ";"
+            TokenType::RightParen => {
+                Ok(tokens.next().unwrap().span) // consume ")"
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected ';' after table name", token, token.line)
-                ))
+                Err(unexpected(token, "')'"))
             }
-        };
-
-        let from_item = Table {token: identifier.clone()};
+        }
+    }
 
-        Ok(Statement::SelectStatement(SelectStatement {expressions, from_item}))
+    // consume a token of the given type, what describes it for the error message
+    fn consume<'t>(&self, tokens: &mut Peekable<Iter<'t, Token>>, token_type: TokenType, what: &str) -> Result<&'t Token, TError> {
+        let token = tokens.peek().unwrap();
+        if std::mem::discriminant(&token.token_type) == std::mem::discriminant(&token_type) {
+            Ok(tokens.next().unwrap())
+        } else {
+            Err(unexpected(token, what))
+        }
+    }
 
+    // consume a non-reserved keyword, which the scanner leaves an identifier
+    fn consume_word<'t>(&self, tokens: &mut Peekable<Iter<'t, Token>>, word: &str, what: &str) -> Result<&'t Token, TError> {
+        let token = tokens.peek().unwrap();
+        if is_word(token, word) {
+            Ok(tokens.next().unwrap())
+        } else {
+            Err(unexpected(token, what))
+        }
     }
-    
+
     // insert -> "insert" "into" identifier "(" identifier ("," identifier)*  ")" "values" value+ ";"
     fn insert(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Statement, TError> {
+        let start = tokens.clone();
         tokens.next(); // consume "insert"
 
         match tokens.peek().unwrap().token_type {
@@ -177,9 +487,7 @@ impl Parser {
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected 'into' after insert", token, token.line)
-                ))
+                return Err(unexpected(token, "'into' after insert"))
             }
         };
 
@@ -190,9 +498,7 @@ impl Parser {
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected identifier", token, token.line)
-                ))
+                return Err(unexpected(token, "identifier"))
             }
         };
 
@@ -202,9 +508,7 @@ impl Parser {
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected '('", token, token.line)
-                ))
+                return Err(unexpected(token, "'('"))
             }
         };
 
@@ -230,9 +534,7 @@ impl Parser {
                 
                 _ => {
                     let token = tokens.peek().unwrap();
-                    return Err(TError::ParseError(
-                        format!("found unexpected {:?} at line {}. expected identifier", token, token.line)
-                    ))
+                    return Err(unexpected(token, "identifier"))
                 }
             };
         };
@@ -243,13 +545,12 @@ impl Parser {
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected 'values'", token, token.line)
-                ))
+                return Err(unexpected(token, "'values'"))
             }
         };
 
         let values = self.values(tokens)?;
+        let span = span_since(&start, tokens);
 
         match tokens.peek().unwrap().token_type {
             TokenType::Semicolon => {
@@ -257,13 +558,11 @@ impl Parser {
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected ';' after values", token, token.line)
-                ))
+                return Err(unexpected(token, "';' after values"))
             }
         };
 
-        Ok(Statement::InsertStatement(InsertStatement { token: identifier.clone(), columns, values}))
+        Ok(Statement::InsertStatement(InsertStatement { token: identifier.clone(), columns, values, span }))
 
     }
     
@@ -277,9 +576,7 @@ impl Parser {
                 }
                 _ => {
                     let token = tokens.peek().unwrap();
-                    return Err(TError::ParseError(
-                        format!("found unexpected {:?} at line {}. expected identifier", token, token.line)
-                    ))
+                    return Err(unexpected(token, "identifier"))
                 }
             };
             let mut value = Vec::new();
@@ -313,27 +610,399 @@ impl Parser {
         Ok(res)
     }
 
-    // expr -> literal | column_reference
+    // expr -> or
     fn expr(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        self.or(tokens)
+    }
+
+    // or -> and ("or" and)*
+    fn or(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let mut expr = self.and(tokens)?;
+        while let TokenType::Or = tokens.peek().unwrap().token_type {
+            let operator = tokens.next().unwrap(); // consume "or"
+            let right = self.and(tokens)?;
+            expr = binary(expr, operator, right);
+        }
+        Ok(expr)
+    }
+
+    // and -> not ("and" not)*
+    fn and(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let mut expr = self.not(tokens)?;
+        while let TokenType::And = tokens.peek().unwrap().token_type {
+            let operator = tokens.next().unwrap(); // consume "and"
+            let right = self.not(tokens)?;
+            expr = binary(expr, operator, right);
+        }
+        Ok(expr)
+    }
+
+    // not -> "not" not | comparison
+    fn not(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
         match tokens.peek().unwrap().token_type {
-            TokenType::IntLiteral | TokenType::False | TokenType::True | TokenType::Null => {
+            TokenType::Not => {
+                let operator = tokens.next().unwrap(); // consume "not"
+                let right = self.not(tokens)?;
+                let span = operator.span.to(right.span());
+                Ok(Expr::Unary(Unary { operator: operator.clone(), right: Box::new(right), span }))
+            },
+            _ => self.comparison(tokens)
+        }
+    }
+
+    // comparison -> json_access ( ("=" | "<>" | "!=" | "<" | "<=" | ">" | ">=") (json_access | ("any" | "all") "(" expr ")")
+    //                            | "not"? "in" "(" (query | expr ("," expr)*) ")"
+    //                            | "not"? "between" json_access "and" json_access
+    //                            | "not"? ("like" | "ilike") json_access )?
+    fn comparison(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let expr = self.json_access(tokens)?;
+        match tokens.peek().unwrap().token_type {
+            TokenType::Equal | TokenType::NotEqual | TokenType::Less | TokenType::LessEqual | TokenType::Greater | TokenType::GreaterEqual => {
+                let operator = tokens.next().unwrap(); // consume operator
+                if let TokenType::Any | TokenType::All = tokens.peek().unwrap().token_type {
+                    let all = matches!(tokens.next().unwrap().token_type, TokenType::All); // consume "any" or "all"
+                    self.consume(tokens, TokenType::LeftParen, "'(' after 'any' or 'all'")?;
+                    let right = self.expr(tokens)?;
+                    let span = expr.span().to(self.right_paren(tokens)?);
+                    return Ok(Expr::Quantified(Quantified { left: Box::new(expr), operator: operator.clone(), all, right: Box::new(right), span }));
+                }
+                let right = self.json_access(tokens)?;
+                Ok(binary(expr, operator, right))
+            },
+            TokenType::Not | TokenType::In | TokenType::Between | TokenType::Like | TokenType::Ilike => {
+                let negated = matches!(tokens.peek().unwrap().token_type, TokenType::Not);
+                if negated {
+                    tokens.next(); // consume "not"
+                }
+                let expr = Box::new(expr);
+                match tokens.peek().unwrap().token_type {
+                    TokenType::In => {
+                        tokens.next(); // consume "in"
+                        self.in_predicate(tokens, expr, negated)
+                    },
+                    TokenType::Between => {
+                        tokens.next(); // consume "between"
+                        let low = Box::new(self.json_access(tokens)?);
+                        self.consume(tokens, TokenType::And, "'and' after lower bound of between")?;
+                        let high = Box::new(self.json_access(tokens)?);
+                        let span = expr.span().to(high.span());
+                        Ok(Expr::Between(Between { expr, low, high, negated, span }))
+                    },
+                    TokenType::Like | TokenType::Ilike => {
+                        let operator = tokens.next().unwrap(); // consume "like" or "ilike"
+                        let case_insensitive = matches!(operator.token_type, TokenType::Ilike);
+                        let pattern = Box::new(self.json_access(tokens)?);
+                        let span = expr.span().to(pattern.span());
+                        Ok(Expr::Like(Like { expr, pattern, negated, case_insensitive, span }))
+                    },
+                    _ => {
+                        let token = tokens.peek().unwrap();
+                        Err(unexpected(token, "'in', 'between' or 'like' after 'not'"))
+                    }
+                }
+            },
+            _ => Ok(expr)
+        }
+    }
+
+    // json_access -> term (("->" | "->>" | "#>" | "#>>") term)*
+    fn json_access(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let mut expr = self.term(tokens)?;
+        while let TokenType::Arrow | TokenType::LongArrow | TokenType::HashArrow | TokenType::HashLongArrow = tokens.peek().unwrap().token_type {
+            let operator = tokens.next().unwrap(); // consume operator
+            let right = self.term(tokens)?;
+            expr = binary(expr, operator, right);
+        }
+        Ok(expr)
+    }
+
+    // term -> factor (("+" | "-") factor)*
+    fn term(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let mut expr = self.factor(tokens)?;
+        while let TokenType::Plus | TokenType::Minus = tokens.peek().unwrap().token_type {
+            let operator = tokens.next().unwrap(); // consume operator
+            let right = self.factor(tokens)?;
+            expr = binary(expr, operator, right);
+        }
+        Ok(expr)
+    }
+
+    // factor -> unary (("*" | "/" | "%") unary)*
+    fn factor(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let mut expr = self.unary(tokens)?;
+        while let TokenType::Star | TokenType::Slash | TokenType::Percent = tokens.peek().unwrap().token_type {
+            let operator = tokens.next().unwrap(); // consume operator
+            let right = self.unary(tokens)?;
+            expr = binary(expr, operator, right);
+        }
+        Ok(expr)
+    }
+
+    // unary -> ("-" | "+") unary | postfix
+    fn unary(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        match tokens.peek().unwrap().token_type {
+            TokenType::Minus => {
+                let operator = tokens.next().unwrap(); // consume "-"
+                let right = self.unary(tokens)?;
+                // a negative number is a literal, so the smallest bigint is one too
+                let span = operator.span.to(right.span());
+                if let Expr::Literal(literal) = &right {
+                    if let Some(value) = negate_literal(literal) {
+                        return Ok(Expr::Literal(Literal { token: literal.token.clone(), value, span }));
+                    }
+                }
+                Ok(Expr::Unary(Unary { operator: operator.clone(), right: Box::new(right), span }))
+            },
+            TokenType::Plus => {
+                tokens.next(); // consume "+"
+                self.unary(tokens)
+            },
+            _ => self.postfix(tokens),
+        }
+    }
+
+    // postfix -> primary ("::" column_type | "[" expr "]")*
+    fn postfix(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let start = tokens.clone();
+        let mut expr = self.primary(tokens)?;
+        loop {
+            match tokens.peek().unwrap().token_type {
+                TokenType::DoubleColon => {
+                    tokens.next(); // consume "::"
+                    let column_type = self.column_type(tokens)?;
+                    expr = Expr::Cast(Cast { expr: Box::new(expr), column_type, span: span_since(&start, tokens) });
+                },
+                TokenType::LeftBracket => {
+                    tokens.next(); // consume "["
+                    let index = self.expr(tokens)?;
+                    let span = expr.span().to(self.consume(tokens, TokenType::RightBracket, "']' after array index")?.span);
+                    expr = Expr::Subscript(Subscript { expr: Box::new(expr), index: Box::new(index), span });
+                },
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    // the part of an in predicate after "in", either a subquery or a list of values
+    fn in_predicate(&self, tokens: &mut Peekable<Iter<Token>>, expr: Box<Expr>, negated: bool) -> Result<Expr, TError> {
+        let mut lookahead = tokens.clone();
+        lookahead.next();
+        if matches!(lookahead.peek().unwrap().token_type, TokenType::Select | TokenType::With) {
+            let start = tokens.clone();
+            let query = self.parenthesized_query(tokens)?;
+            let span = expr.span().to(span_since(&start, tokens));
+            return Ok(Expr::InSubquery(InSubquery { expr, query: Box::new(query), negated, span }));
+        }
+        self.consume(tokens, TokenType::LeftParen, "'(' after 'in'")?;
+        let mut list = vec![self.expr(tokens)?];
+        while let TokenType::Comma = tokens.peek().unwrap().token_type {
+            tokens.next(); // consume ","
+            list.push(self.expr(tokens)?);
+        }
+        let span = expr.span().to(self.right_paren(tokens)?);
+        Ok(Expr::InList(InList { expr, list, negated, span }))
+    }
+
+    // case -> "case" expr? ("when" expr "then" expr)+ ("else" expr)? "end"
+    fn case(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let start = tokens.next().unwrap().span; // consume "case"
+        let operand = match tokens.peek().unwrap().token_type {
+            TokenType::When => None,
+            _ => Some(Box::new(self.expr(tokens)?)),
+        };
+        let mut when_clauses = Vec::new();
+        while let TokenType::When = tokens.peek().unwrap().token_type {
+            let when = tokens.next().unwrap(); // consume "when"
+            let condition = self.expr(tokens)?;
+            self.consume(tokens, TokenType::Then, "'then' after condition")?;
+            let result = self.expr(tokens)?;
+            let span = when.span.to(result.span());
+            when_clauses.push(WhenClause { condition, result, span });
+        }
+        if when_clauses.is_empty() {
+            let token = tokens.peek().unwrap();
+            return Err(unexpected(token, "'when'"));
+        }
+        let else_result = match tokens.peek().unwrap().token_type {
+            TokenType::Else => {
+                tokens.next(); // consume "else"
+                Some(Box::new(self.expr(tokens)?))
+            },
+            _ => None
+        };
+        let span = start.to(self.consume(tokens, TokenType::End, "'end' after case")?.span);
+        Ok(Expr::Case(Case { operand, when_clauses, else_result, span }))
+    }
+
+    // primary -> literal | typed_literal | column_reference | function | extract | cast | case | array
+    //          | "exists" "(" query ")" | "(" query ")" | "(" expr ")"
+    fn primary(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        match tokens.peek().unwrap().token_type {
+            TokenType::IntLiteral | TokenType::DecimalLiteral | TokenType::StringLiteral | TokenType::False | TokenType::True | TokenType::Null => {
                 let token = tokens.next().unwrap();
-                Ok(Expr::Literal(Literal { token: token.clone(), value: token.literal.as_ref().unwrap().clone() }))
+                Ok(Expr::Literal(Literal { token: token.clone(), value: token.literal.as_ref().unwrap().clone(), span: token.span }))
             }
             TokenType::Identifier => {
-                todo!()
+                let token = *tokens.peek().unwrap();
+                let mut lookahead = tokens.clone();
+                lookahead.next();
+                match lookahead.peek().unwrap().token_type {
+                    TokenType::LeftParen => self.function(tokens),
+                    // the type names are only a literal's type in front of a string, otherwise they are columns
+                    TokenType::StringLiteral if ["date", "time", "timestamp", "interval"].iter().any(|word| is_word(token, word)) => self.typed_literal(tokens),
+                    _ => self.column_reference(tokens),
+                }
+            },
+            TokenType::Extract => self.extract(tokens),
+            TokenType::Cast => self.cast(tokens),
+            TokenType::Case => self.case(tokens),
+            TokenType::Array => self.array(tokens),
+            TokenType::Exists => {
+                let start = tokens.clone();
+                tokens.next(); // consume "exists"
+                let query = self.parenthesized_query(tokens)?;
+                Ok(Expr::Exists(Exists { query: Box::new(query), span: span_since(&start, tokens) }))
+            },
+            TokenType::LeftParen => {
+                let mut lookahead = tokens.clone();
+                lookahead.next();
+                if matches!(lookahead.peek().unwrap().token_type, TokenType::Select | TokenType::With) {
+                    let start = tokens.clone();
+                    let query = self.parenthesized_query(tokens)?;
+                    return Ok(Expr::Subquery(Subquery { query: Box::new(query), span: span_since(&start, tokens) }));
+                }
+                tokens.next(); // consume "("
+                let expr = self.expr(tokens)?;
+                self.right_paren(tokens)?;
+                Ok(expr)
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected literal", token, token.line)
-                ))
+                Err(unexpected(token, "expression"))
+            }
+        }
+    }
+
+    // array -> "array" "[" (expr ("," expr)*)? "]"
+    fn array(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let start = tokens.next().unwrap().span; // consume "array"
+        self.consume(tokens, TokenType::LeftBracket, "'[' after array")?;
+        let mut elements = Vec::new();
+        if !matches!(tokens.peek().unwrap().token_type, TokenType::RightBracket) {
+            elements.push(self.expr(tokens)?);
+            while let TokenType::Comma = tokens.peek().unwrap().token_type {
+                tokens.next(); // consume ","
+                elements.push(self.expr(tokens)?);
             }
         }
+        let span = start.to(self.consume(tokens, TokenType::RightBracket, "']' after array elements")?.span);
+        Ok(Expr::Array(Array { elements, span }))
+    }
+
+    // "(" query ")"
+    fn parenthesized_query(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Query, TError> {
+        match tokens.peek().unwrap().token_type {
+            TokenType::LeftParen => {
+                tokens.next(); // consume "("
+            },
+            _ => {
+                let token = tokens.peek().unwrap();
+                return Err(unexpected(token, "'(' before subquery"))
+            }
+        };
+        let query = self.query(tokens)?;
+        self.right_paren(tokens)?;
+        Ok(query)
+    }
+
+    // column_reference -> identifier ("." identifier)?
+    // function -> identifier "(" (expr ("," expr)*)? ")"
+    fn function(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let name = tokens.next().unwrap().clone(); // consume identifier
+        tokens.next(); // consume "("
+        let mut args = Vec::new();
+        if !matches!(tokens.peek().unwrap().token_type, TokenType::RightParen) {
+            args.push(self.expr(tokens)?);
+            while let TokenType::Comma = tokens.peek().unwrap().token_type {
+                tokens.next(); // consume ","
+                args.push(self.expr(tokens)?);
+            }
+        }
+        let span = name.span.to(self.right_paren(tokens)?);
+        Ok(Expr::Function(Function { name, args, span }))
+    }
+
+    // typed_literal -> ("date" | "time" | "timestamp" | "interval") string_literal
+    fn typed_literal(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let type_token = tokens.next().unwrap(); // consume type
+        let token = self.consume(tokens, TokenType::StringLiteral, "string after type name")?;
+        let text = match &token.literal {
+            Some(LiteralValue::StringValue(text)) => text,
+            _ => unreachable!(),
+        };
+        let value = match type_token.lexeme.as_str() {
+            "date" => parse_date(text).map(LiteralValue::DateValue),
+            "time" => parse_time(text).map(LiteralValue::TimeValue),
+            "timestamp" => parse_timestamp(text).map(LiteralValue::TimestampValue),
+            _ => Interval::parse(text).map(LiteralValue::IntervalValue),
+        };
+        match value {
+            Some(value) => Ok(Expr::Literal(Literal { token: token.clone(), value, span: type_token.span.to(token.span) })),
+            // the literal parses, it's the value in it that is wrong
+            None => Err(TError::ExecutionError(
+                INVALID_DATETIME_FORMAT,
+                format!("invalid input syntax for type {}: {:?} at line {}, column {}", type_token.lexeme, text, token.line, token.column)
+            ).at(token.span)),
+        }
+    }
+
+    // extract -> "extract" "(" identifier "from" expr ")"
+    // it becomes a call of the function extract with the field as a string
+    fn extract(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let name = tokens.next().unwrap().clone(); // consume "extract"
+        self.consume(tokens, TokenType::LeftParen, "'(' after extract")?;
+        let field = self.consume(tokens, TokenType::Identifier, "field to extract")?;
+        self.consume(tokens, TokenType::From, "'from' after field")?;
+        let value = self.expr(tokens)?;
+        let span = name.span.to(self.right_paren(tokens)?);
+        let field = Expr::Literal(Literal { token: field.clone(), value: LiteralValue::StringValue(field.lexeme.to_lowercase()), span: field.span });
+        Ok(Expr::Function(Function { name, args: vec![field, value], span }))
+    }
+
+    // cast -> "cast" "(" expr "as" column_type ")"
+    fn cast(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let start = tokens.next().unwrap().span; // consume "cast"
+        self.consume(tokens, TokenType::LeftParen, "'(' after cast")?;
+        let expr = self.expr(tokens)?;
+        self.consume(tokens, TokenType::As, "'as' after expression")?;
+        let column_type = self.column_type(tokens)?;
+        let span = start.to(self.right_paren(tokens)?);
+        Ok(Expr::Cast(Cast { expr: Box::new(expr), column_type, span }))
+    }
+
+    fn column_reference(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Expr, TError> {
+        let identifier = tokens.next().unwrap(); // consume identifier
+        match tokens.peek().unwrap().token_type {
+            TokenType::Dot => {
+                tokens.next(); // consume "."
+                match tokens.peek().unwrap().token_type {
+                    TokenType::Identifier => {
+                        let column = tokens.next().unwrap(); // consume identifier
+                        Ok(Expr::ColumnReference(ColumnReference { table: Some(identifier.lexeme.clone()), name: column.lexeme.clone(), span: identifier.span.to(column.span) }))
+                    },
+                    _ => {
+                        let token = tokens.peek().unwrap();
+                        Err(unexpected(token, "column name after '.'"))
+                    }
+                }
+            },
+            _ => Ok(Expr::ColumnReference(ColumnReference { table: None, name: identifier.lexeme.clone(), span: identifier.span }))
+        }
     }
     
-    // create_table -> "CREATE" "TABLE" identifier "(" Column+ ")" ";"
+    // create_table -> "CREATE" "TABLE" identifier "(" table_element ("," table_element)* ")" ";"
     fn create_table(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Statement, TError> {
+        let start = tokens.clone();
         tokens.next(); // consume "create"
 
         match tokens.peek().unwrap().token_type {
@@ -342,9 +1011,7 @@ impl Parser {
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected 'table' after create", token, token.line)
-                ))
+                return Err(unexpected(token, "'table' after create"))
             }
         };
 
@@ -355,9 +1022,7 @@ impl Parser {
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected identifier", token, token.line)
-                ))
+                return Err(unexpected(token, "identifier"))
             }
         };
 
@@ -367,16 +1032,14 @@ impl Parser {
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected '('", token, token.line)
-                ))
+                return Err(unexpected(token, "'(' after table name"))
             }
         };
 
         let mut columns: Vec<Column> = Vec::new();
+        let mut constraints = Vec::new();
 
-        let first_column: Column = self.column(tokens)?;
-        columns.push(first_column);
+        self.table_element(tokens, &mut columns, &mut constraints)?;
 
         loop {
             
@@ -395,34 +1058,428 @@ impl Parser {
                 },
                 _ => {
                     let token = tokens.peek().unwrap();
-                    return Err(TError::ParseError(
-                        format!("found unexpected {:?} at line {}. expected ','", token, token.line)
-                    ))
+                    return Err(unexpected(token, "',' or ')' after column definition"))
                 }
             };
 
-            let column: Column = self.column(tokens)?;
-            columns.push(column);
-
-            
+            self.table_element(tokens, &mut columns, &mut constraints)?;
         }
+        let span = span_since(&start, tokens);
         match tokens.peek().unwrap().token_type {
             TokenType::Semicolon => {
                 tokens.next(); // consume ";"
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected ';'", token, token.line)
-                ))
+                return Err(unexpected(token, "';'"))
+            }
+        };
+
+        Ok(Statement::CreateTableStatement(CreateTableStatement {token: identifier.clone(), columns, constraints, span}))
+    }
+
+    // table_element -> column ("primary" "key" | "unique" | references | "default" expr | check | identity)*
+    //                | "primary" "key" column_list
+    //                | "unique" column_list
+    //                | "foreign" "key" column_list references
+    //                | check
+    fn table_element(&self, tokens: &mut Peekable<Iter<Token>>, columns: &mut Vec<Column>, constraints: &mut Vec<TableConstraint>) -> Result<(), TError> {
+        let start = tokens.clone();
+        match tokens.peek().unwrap().token_type {
+            TokenType::Primary => {
+                tokens.next(); // consume "primary"
+                self.consume_word(tokens, "key", "'key' after 'primary'")?;
+                let columns = self.column_list(tokens)?;
+                constraints.push(TableConstraint::PrimaryKey(KeyConstraint { columns, span: span_since(&start, tokens) }));
+            },
+            TokenType::Unique => {
+                tokens.next(); // consume "unique"
+                let columns = self.column_list(tokens)?;
+                constraints.push(TableConstraint::Unique(KeyConstraint { columns, span: span_since(&start, tokens) }));
+            },
+            TokenType::Foreign => {
+                tokens.next(); // consume "foreign"
+                self.consume_word(tokens, "key", "'key' after 'foreign'")?;
+                let columns = self.column_list(tokens)?;
+                let foreign_key = self.references(tokens, columns)?;
+                constraints.push(TableConstraint::ForeignKey(ForeignKey { span: span_since(&start, tokens), ..foreign_key }));
+            },
+            TokenType::Check => {
+                let expr = self.check(tokens)?;
+                constraints.push(TableConstraint::Check(Check { column: None, expr, span: span_since(&start, tokens) }));
+            },
+            _ => {
+                let mut column = self.column(tokens)?;
+                loop {
+                    // a constraint after the column spans from its first keyword
+                    let start = tokens.clone();
+                    let token = tokens.peek().unwrap();
+                    match token.token_type {
+                        TokenType::Primary => {
+                            tokens.next(); // consume "primary"
+                            self.consume_word(tokens, "key", "'key' after 'primary'")?;
+                            constraints.push(TableConstraint::PrimaryKey(KeyConstraint { columns: vec![column.token.clone()], span: span_since(&start, tokens) }));
+                        },
+                        TokenType::Unique => {
+                            tokens.next(); // consume "unique"
+                            constraints.push(TableConstraint::Unique(KeyConstraint { columns: vec![column.token.clone()], span: span_since(&start, tokens) }));
+                        },
+                        TokenType::References => {
+                            constraints.push(TableConstraint::ForeignKey(self.references(tokens, vec![column.token.clone()])?));
+                        },
+                        TokenType::Default => {
+                            tokens.next(); // consume "default"
+                            let default = self.stored_expr(tokens)?;
+                            column.span = column.span.to(default.expr.span());
+                            column.default = Some(default);
+                        },
+                        _ if is_word(token, "generated") => {
+                            let identity = self.identity(tokens)?;
+                            column.span = column.span.to(identity.span);
+                            column.identity = Some(identity);
+                        },
+                        TokenType::Check => {
+                            let expr = self.check(tokens)?;
+                            constraints.push(TableConstraint::Check(Check { column: Some(column.token.clone()), expr, span: span_since(&start, tokens) }));
+                        },
+                        _ => break
+                    }
+                }
+                columns.push(column);
+            },
+        }
+        Ok(())
+    }
+
+    // references -> "references" identifier column_list? ("on" ("delete" | "update") referential_action)*
+    fn references(&self, tokens: &mut Peekable<Iter<Token>>, columns: Vec<Token>) -> Result<ForeignKey, TError> {
+        let start = tokens.clone();
+        self.consume(tokens, TokenType::References, "'references'")?;
+        let table = self.consume(tokens, TokenType::Identifier, "table name")?.clone();
+        let referenced_columns = match tokens.peek().unwrap().token_type {
+            TokenType::LeftParen => self.column_list(tokens)?,
+            _ => Vec::new(),
+        };
+        let mut on_delete = ReferentialAction::Restrict;
+        let mut on_update = ReferentialAction::Restrict;
+        while let TokenType::On = tokens.peek().unwrap().token_type {
+            tokens.next(); // consume "on"
+            let event = tokens.next().unwrap(); // consume "delete" or "update"
+            let action = self.referential_action(tokens)?;
+            match event.token_type {
+                TokenType::Delete => on_delete = action,
+                TokenType::Update => on_update = action,
+                _ => return Err(unexpected(event, "'delete' or 'update' after 'on'")),
+            }
+        }
+        Ok(ForeignKey { columns, table, referenced_columns, on_delete, on_update, span: span_since(&start, tokens) })
+    }
+
+    // referential_action -> "restrict" | "cascade" | "set" "null"
+    fn referential_action(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<ReferentialAction, TError> {
+        let token = tokens.next().unwrap();
+        match token.token_type {
+            _ if is_word(token, "restrict") => Ok(ReferentialAction::Restrict),
+            _ if is_word(token, "cascade") => Ok(ReferentialAction::Cascade),
+            TokenType::Set => {
+                self.consume(tokens, TokenType::Null, "'null' after 'set'")?;
+                Ok(ReferentialAction::SetNull)
+            },
+            _ => Err(unexpected(token, "'restrict', 'cascade' or 'set null'")),
+        }
+    }
+
+    // check -> "check" "(" expr ")"
+    fn check(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<StoredExpr, TError> {
+        self.consume(tokens, TokenType::Check, "'check'")?;
+        self.consume(tokens, TokenType::LeftParen, "'(' after check")?;
+        let expr = self.stored_expr(tokens)?;
+        self.consume(tokens, TokenType::RightParen, "')'")?;
+        Ok(expr)
+    }
+
+    // an expression along with its source, which is the lexemes of its tokens
+    fn stored_expr(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<StoredExpr, TError> {
+        let start = tokens.clone();
+        let expr = self.expr(tokens)?;
+        let end = tokens.peek().unwrap().id;
+        let lexemes: Vec<_> = start.take_while(|t| t.id != end).map(|t| t.source()).collect();
+        Ok(StoredExpr { expr, source: lexemes.join(" ") })
+    }
+
+    // column_list -> "(" identifier ("," identifier)* ")"
+    fn column_list(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Vec<Token>, TError> {
+        self.consume(tokens, TokenType::LeftParen, "'('")?;
+        let mut res = vec![self.consume(tokens, TokenType::Identifier, "column name")?.clone()];
+        while let TokenType::Comma = tokens.peek().unwrap().token_type {
+            tokens.next(); // consume ","
+            res.push(self.consume(tokens, TokenType::Identifier, "column name")?.clone());
+        }
+        self.consume(tokens, TokenType::RightParen, "')'")?;
+        Ok(res)
+    }
+
+    // update -> "update" identifier "set" identifier "=" expr ("," identifier "=" expr)* ("where" expr)? ";"
+    fn update(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Statement, TError> {
+        let start = tokens.clone();
+        tokens.next(); // consume "update"
+        let token = self.consume(tokens, TokenType::Identifier, "table name")?.clone();
+        self.consume(tokens, TokenType::Set, "'set' after table name")?;
+        let mut assignments = Vec::new();
+        loop {
+            let column = self.consume(tokens, TokenType::Identifier, "column name")?.clone();
+            self.consume(tokens, TokenType::Equal, "'=' after column name")?;
+            let value = self.expr(tokens)?;
+            let span = column.span.to(value.span());
+            assignments.push(Assignment { column, value, span });
+            match tokens.peek().unwrap().token_type {
+                TokenType::Comma => { tokens.next(); }, // consume ","
+                _ => break
+            }
+        }
+        let where_clause = match tokens.peek().unwrap().token_type {
+            TokenType::Where => {
+                tokens.next(); // consume "where"
+                Some(self.expr(tokens)?)
+            },
+            _ => None
+        };
+        let span = span_since(&start, tokens);
+        self.consume(tokens, TokenType::Semicolon, "';' after update")?;
+        Ok(Statement::UpdateStatement(UpdateStatement { token, assignments, where_clause, span }))
+    }
+
+    // delete -> "delete" "from" identifier ("where" expr)? ";"
+    fn delete(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Statement, TError> {
+        let start = tokens.clone();
+        tokens.next(); // consume "delete"
+        self.consume(tokens, TokenType::From, "'from' after delete")?;
+        let token = self.consume(tokens, TokenType::Identifier, "table name")?.clone();
+        let where_clause = match tokens.peek().unwrap().token_type {
+            TokenType::Where => {
+                tokens.next(); // consume "where"
+                Some(self.expr(tokens)?)
+            },
+            _ => None
+        };
+        let span = span_since(&start, tokens);
+        self.consume(tokens, TokenType::Semicolon, "';' after delete")?;
+        Ok(Statement::DeleteStatement(DeleteStatement { token, where_clause, span }))
+    }
+
+    // drop_table -> "drop" ("table" | "sequence" | "index") ("if" "exists")? identifier ";"
+    fn drop_table(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Statement, TError> {
+        let start = tokens.clone();
+        tokens.next(); // consume "drop"
+        let kind = *tokens.peek().unwrap();
+        if !(matches!(kind.token_type, TokenType::Table) || is_word(kind, "sequence") || is_word(kind, "index")) {
+            return Err(unexpected(kind, "'table', 'sequence' or 'index' after drop"));
+        }
+        tokens.next(); // consume "table", "sequence" or "index"
+        let if_exists = match tokens.peek().unwrap().token_type {
+            TokenType::If => {
+                tokens.next(); // consume "if"
+                self.consume(tokens, TokenType::Exists, "'exists' after 'if'")?;
+                true
+            },
+            _ => false
+        };
+        if is_word(kind, "sequence") {
+            let token = self.consume(tokens, TokenType::Identifier, "sequence name")?.clone();
+            let span = span_since(&start, tokens);
+            self.consume(tokens, TokenType::Semicolon, "';' after drop sequence")?;
+            return Ok(Statement::DropSequenceStatement(DropSequenceStatement { token, if_exists, span }));
+        }
+        if is_word(kind, "index") {
+            let token = self.consume(tokens, TokenType::Identifier, "index name")?.clone();
+            let span = span_since(&start, tokens);
+            self.consume(tokens, TokenType::Semicolon, "';' after drop index")?;
+            return Ok(Statement::DropIndexStatement(DropIndexStatement { token, if_exists, span }));
+        }
+        let token = self.consume(tokens, TokenType::Identifier, "table name")?.clone();
+        let span = span_since(&start, tokens);
+        self.consume(tokens, TokenType::Semicolon, "';' after drop table")?;
+        Ok(Statement::DropTableStatement(DropTableStatement { token, if_exists, span }))
+    }
+
+    // create_index -> "create" "unique"? "index" identifier "on" identifier "(" expr ("," expr)* ")" ";"
+    // where an expr that is a column name indexes that column
+    fn create_index(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Statement, TError> {
+        let start = tokens.clone();
+        tokens.next(); // consume "create"
+        let unique = matches!(tokens.peek().unwrap().token_type, TokenType::Unique);
+        if unique {
+            tokens.next(); // consume "unique"
+        }
+        self.consume_word(tokens, "index", "'index' after create")?;
+        let token = self.consume(tokens, TokenType::Identifier, "index name")?.clone();
+        self.consume(tokens, TokenType::On, "'on' after index name")?;
+        let table = self.consume(tokens, TokenType::Identifier, "table name")?.clone();
+        self.consume(tokens, TokenType::LeftParen, "'(' after table name")?;
+        let mut elements = vec![self.stored_expr(tokens)?];
+        while let TokenType::Comma = tokens.peek().unwrap().token_type {
+            tokens.next(); // consume ","
+            elements.push(self.stored_expr(tokens)?);
+        }
+        self.consume(tokens, TokenType::RightParen, "')'")?;
+        let span = span_since(&start, tokens);
+        self.consume(tokens, TokenType::Semicolon, "';' after create index")?;
+        Ok(Statement::CreateIndexStatement(CreateIndexStatement { token, table, unique, elements, span }))
+    }
+
+    // create_sequence -> "create" "sequence" identifier sequence_options ";"
+    fn create_sequence(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Statement, TError> {
+        let start = tokens.clone();
+        tokens.next(); // consume "create"
+        self.consume_word(tokens, "sequence", "'sequence' after create")?;
+        let token = self.consume(tokens, TokenType::Identifier, "sequence name")?.clone();
+        let options = self.sequence_options(tokens)?;
+        let span = span_since(&start, tokens);
+        self.consume(tokens, TokenType::Semicolon, "';' after create sequence")?;
+        Ok(Statement::CreateSequenceStatement(CreateSequenceStatement { token, options, span }))
+    }
+
+    // sequence_options -> ("start" "with"? integer | "increment" "by"? integer)*
+    fn sequence_options(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<SequenceOptions, TError> {
+        let mut options = SequenceOptions::default();
+        loop {
+            let token = tokens.peek().unwrap();
+            match token.token_type {
+                _ if is_word(token, "start") => {
+                    tokens.next(); // consume "start"
+                    if let TokenType::With = tokens.peek().unwrap().token_type {
+                        tokens.next(); // consume "with"
+                    }
+                    options.start = Some(self.integer(tokens)?);
+                },
+                _ if is_word(token, "increment") => {
+                    tokens.next(); // consume "increment"
+                    if let TokenType::By = tokens.peek().unwrap().token_type {
+                        tokens.next(); // consume "by"
+                    }
+                    options.increment = Some(self.integer(tokens)?);
+                },
+                _ => return Ok(options),
+            }
+        }
+    }
+
+    // integer -> "-"? int_literal
+    fn integer(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<i64, TError> {
+        let negative = matches!(tokens.peek().unwrap().token_type, TokenType::Minus);
+        if negative {
+            tokens.next(); // consume "-"
+        }
+        match self.consume(tokens, TokenType::IntLiteral, "integer")?.literal {
+            Some(LiteralValue::IntValue(value)) if negative => Ok(-value),
+            Some(LiteralValue::IntValue(value)) => Ok(value),
+            _ => unreachable!(),
+        }
+    }
+
+    // identity -> "generated" ("always" | "by" "default") "as" "identity" ("(" sequence_options ")")?
+    fn identity(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Identity, TError> {
+        let start = tokens.clone();
+        self.consume_word(tokens, "generated", "'generated'")?;
+        let always = match tokens.peek().unwrap() {
+            token if is_word(token, "always") => {
+                tokens.next(); // consume "always"
+                true
+            },
+            _ => {
+                self.consume(tokens, TokenType::By, "'always' or 'by default' after generated")?;
+                self.consume(tokens, TokenType::Default, "'default' after 'by'")?;
+                false
             }
         };
+        self.consume(tokens, TokenType::As, "'as'")?;
+        self.consume_word(tokens, "identity", "'identity'")?;
+        let options = match tokens.peek().unwrap().token_type {
+            TokenType::LeftParen => {
+                tokens.next(); // consume "("
+                let options = self.sequence_options(tokens)?;
+                self.consume(tokens, TokenType::RightParen, "')'")?;
+                options
+            },
+            _ => SequenceOptions::default(),
+        };
+        Ok(Identity { always, options, span: span_since(&start, tokens) })
+    }
+
+    // truncate -> "truncate" "table"? identifier ";"
+    fn truncate(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Statement, TError> {
+        let start = tokens.clone();
+        tokens.next(); // consume "truncate"
+        if let TokenType::Table = tokens.peek().unwrap().token_type {
+            tokens.next(); // consume "table"
+        }
+        let token = self.consume(tokens, TokenType::Identifier, "table name")?.clone();
+        let span = span_since(&start, tokens);
+        self.consume(tokens, TokenType::Semicolon, "';' after truncate")?;
+        Ok(Statement::TruncateStatement(TruncateStatement { token, span }))
+    }
 
-        Ok(Statement::CreateTableStatement(CreateTableStatement {token: identifier.clone(), columns}))
+    // alter_table -> "alter" "table" identifier alter_table_action ";"
+    fn alter_table(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Statement, TError> {
+        let start = tokens.clone();
+        tokens.next(); // consume "alter"
+        self.consume(tokens, TokenType::Table, "'table' after alter")?;
+        let token = self.consume(tokens, TokenType::Identifier, "table name")?.clone();
+        let action = self.alter_table_action(tokens)?;
+        let span = span_since(&start, tokens);
+        self.consume(tokens, TokenType::Semicolon, "';' after alter table")?;
+        Ok(Statement::AlterTableStatement(AlterTableStatement { token, action, span }))
     }
 
-    // column -> identifier ("int" | "bool")
+    // alter_table_action -> "add" "column"? column ("default" expr)?
+    //                     | "drop" "column"? identifier
+    //                     | "rename" "column"? identifier "to" identifier
+    //                     | "rename" "to" identifier
+    fn alter_table_action(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<AlterTableAction, TError> {
+        let start = tokens.clone();
+        let keyword = tokens.next().unwrap(); // consume "add", "drop" or "rename"
+        match keyword.token_type {
+            _ if is_word(keyword, "add") => {
+                self.optional_column_keyword(tokens);
+                let mut column = self.column(tokens)?;
+                if let TokenType::Default = tokens.peek().unwrap().token_type {
+                    tokens.next(); // consume "default"
+                    let default = self.stored_expr(tokens)?;
+                    column.span = column.span.to(default.expr.span());
+                    column.default = Some(default);
+                }
+                Ok(AlterTableAction::AddColumn(Box::new(AddColumn { column, span: span_since(&start, tokens) })))
+            },
+            TokenType::Drop => {
+                self.optional_column_keyword(tokens);
+                let token = self.consume(tokens, TokenType::Identifier, "column name")?.clone();
+                Ok(AlterTableAction::DropColumn(DropColumn { token, span: span_since(&start, tokens) }))
+            },
+            _ if is_word(keyword, "rename") => {
+                if let TokenType::To = tokens.peek().unwrap().token_type {
+                    tokens.next(); // consume "to"
+                    let new_name = self.consume(tokens, TokenType::Identifier, "new table name")?.clone();
+                    return Ok(AlterTableAction::RenameTable(RenameTable { new_name, span: span_since(&start, tokens) }));
+                }
+                self.optional_column_keyword(tokens);
+                let token = self.consume(tokens, TokenType::Identifier, "column name")?.clone();
+                self.consume(tokens, TokenType::To, "'to' after column name")?;
+                let new_name = self.consume(tokens, TokenType::Identifier, "new column name")?.clone();
+                Ok(AlterTableAction::RenameColumn(RenameColumn { token, new_name, span: span_since(&start, tokens) }))
+            },
+            _ => Err(unexpected(keyword, "'add', 'drop' or 'rename'"))
+        }
+    }
+
+    fn optional_column_keyword(&self, tokens: &mut Peekable<Iter<Token>>) {
+        if let TokenType::Column = tokens.peek().unwrap().token_type {
+            tokens.next(); // consume "column"
+        }
+    }
+
+    // column -> identifier (column_type | "serial")
     fn column(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<Column, TError> {
+        let start = tokens.clone();
         let identifier;
         match tokens.peek().unwrap().token_type {
             TokenType::Identifier => {
@@ -430,34 +1487,370 @@ impl Parser {
             },
             _ => {
                 let token = tokens.peek().unwrap();
-                return Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected identifier", token, token.line)
-                ))
+                return Err(unexpected(token, "identifier"))
             }
         };
 
+        // serial is an int column with a sequence behind it
+        if is_word(tokens.peek().unwrap(), "serial") {
+            let serial = tokens.next().unwrap(); // consume "serial"
+            let identity = Identity { always: false, options: SequenceOptions::default(), span: serial.span };
+            let span = identifier.span.to(serial.span);
+            return Ok(Column {token: identifier.clone(), column_type: ColumnType::Int, default: None, identity: Some(identity), span});
+        }
         let column_t = self.column_type(tokens)?;
-        Ok(Column {token: identifier.clone(), column_type: column_t})
+        let span = span_since(&start, tokens);
+        Ok(Column {token: identifier.clone(), column_type: column_t, default: None, identity: None, span})
         
     }
 
+    // column_type -> "smallint" | "int" | "integer" | "bigint" | "real" | "double" "precision" | "float"
+    //              | ("decimal" | "numeric") ("(" integer ("," integer)? ")")?
+    //              | "date" | "time" | "timestamp" | "interval" | "bool" | "text" | "bytea" | "json" | "uuid"
+    //              | column_type "[" "]"
     fn column_type(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<ColumnType, TError> {
-        match tokens.peek().unwrap().token_type {
-            TokenType::Int => {
-                tokens.next().unwrap(); // consume "int"
-                Ok(ColumnType::Int)
+        let column_type = self.scalar_type(tokens)?;
+        if !matches!(tokens.peek().unwrap().token_type, TokenType::LeftBracket) {
+            return Ok(column_type);
+        }
+        tokens.next(); // consume "["
+        self.consume(tokens, TokenType::RightBracket, "']' after '['")?;
+        if let TokenType::LeftBracket = tokens.peek().unwrap().token_type {
+            let token = tokens.peek().unwrap();
+            return Err(TError::ParseError("arrays have only one dimension".to_owned()).at(token.span));
+        }
+        Ok(ColumnType::Array(Box::new(column_type)))
+    }
+
+    fn scalar_type(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<ColumnType, TError> {
+        let token = tokens.peek().unwrap();
+        // type names aren't reserved, so columns can still be called that
+        let name = if is_word(token, &token.lexeme) { token.lexeme.as_str() } else { "" };
+        let column_type = match name {
+            "smallint" => ColumnType::SmallInt,
+            "int" | "integer" => ColumnType::Int,
+            "bigint" => ColumnType::BigInt,
+            "real" => ColumnType::Real,
+            "double" => {
+                tokens.next(); // consume "double"
+                self.consume_word(tokens, "precision", "'precision' after double")?;
+                return Ok(ColumnType::Double);
             },
-            TokenType::Bool => {
-                tokens.next().unwrap(); // consume "bool"
-                Ok(ColumnType::Int)
-            }
+            "float" => ColumnType::Double,
+            "decimal" | "numeric" => {
+                tokens.next(); // consume "decimal"
+                return self.decimal_params(tokens);
+            },
+            "date" => ColumnType::Date,
+            "time" => ColumnType::Time,
+            "timestamp" => ColumnType::Timestamp,
+            "interval" => ColumnType::Interval,
+            "bool" => ColumnType::Bool,
+            "text" => ColumnType::Text,
+            "bytea" | "blob" => ColumnType::Bytea,
+            "json" | "jsonb" => ColumnType::Json,
+            "uuid" => ColumnType::Uuid,
             _ => {
-                let token = tokens.peek().unwrap();
-                Err(TError::ParseError(
-                    format!("found unexpected {:?} at line {}. expected valid column type", token, token.line)
-                ))
+                return Err(unexpected(token, "valid column type"))
             }
+        };
+        tokens.next(); // consume type
+        Ok(column_type)
+    }
+
+    // the optional precision and scale of a decimal, the scale defaults to 0
+    fn decimal_params(&self, tokens: &mut Peekable<Iter<Token>>) -> Result<ColumnType, TError> {
+        if !matches!(tokens.peek().unwrap().token_type, TokenType::LeftParen) {
+            return Ok(ColumnType::Decimal(None));
+        }
+        tokens.next(); // consume "("
+        let start = tokens.clone();
+        let line = tokens.peek().unwrap().line;
+        let precision = self.integer(tokens)?;
+        let scale = match tokens.peek().unwrap().token_type {
+            TokenType::Comma => {
+                tokens.next(); // consume ","
+                self.integer(tokens)?
+            },
+            _ => 0,
+        };
+        let span = span_since(&start, tokens);
+        self.right_paren(tokens)?;
+        if !(1..=i64::from(MAX_PRECISION)).contains(&precision) {
+            return Err(TError::ParseError(format!("decimal precision {} must be between 1 and {} at line {}", precision, MAX_PRECISION, line)).at(span));
+        }
+        if !(0..=precision).contains(&scale) {
+            return Err(TError::ParseError(format!("decimal scale {} must be between 0 and precision {} at line {}", scale, precision, line)).at(span));
         }
+        Ok(ColumnType::Decimal(Some((u8::try_from(precision).unwrap(), u8::try_from(scale).unwrap()))))
     }
 }
 
+// whether a token is the non-reserved keyword, written without quotes, since a quoted name is never a keyword
+fn is_word(token: &Token, word: &str) -> bool {
+    matches!(token.token_type, TokenType::Identifier) && token.lexeme == word && token.span.end - token.span.start == word.len()
+}
+
+// an error at a token the grammar doesn't allow there
+fn unexpected(token: &Token, expected: &str) -> TError {
+    let found = match token.token_type {
+        TokenType::EOF => "end of input".to_owned(),
+        _ => format!("'{}'", token.source()),
+    };
+    TError::ParseError(format!("expected {}, found {} at line {}, column {}", expected, found, token.line, token.column)).at(token.span)
+}
+
+fn binary(left: Expr, operator: &Token, right: Expr) -> Expr {
+    let span = left.span().to(right.span());
+    Expr::Binary(Binary { left: Box::new(left), operator: operator.clone(), right: Box::new(right), span })
+}
+
+// the span of the tokens consumed since the iterator was where start is
+fn span_since(start: &Peekable<Iter<Token>>, tokens: &mut Peekable<Iter<Token>>) -> Span {
+    let next = tokens.peek().map(|t| t.id);
+    let mut consumed = start.clone().take_while(|t| Some(t.id) != next);
+    let first = consumed.next().map_or(Span::default(), |t| t.span);
+    consumed.last().map_or(first, |t| first.to(t.span))
+}
+
+fn negate_literal(literal: &Literal) -> Option<LiteralValue> {
+    match &literal.value {
+        LiteralValue::IntValue(i) => i.checked_neg().map(LiteralValue::IntValue),
+        // the smallest bigint doesn't fit without its sign, so it was scanned as a decimal
+        LiteralValue::DecimalValue(_) if literal.token.lexeme == i64::MIN.unsigned_abs().to_string() => Some(LiteralValue::IntValue(i64::MIN)),
+        LiteralValue::DecimalValue(d) => d.checked_neg().map(LiteralValue::DecimalValue),
+        LiteralValue::FloatValue(f) => Some(LiteralValue::FloatValue(-f)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse::{ast::{Expr, Query, SelectItem, Statement}, scanner::Scanner, token::Span}, test::Random};
+
+    use super::Parser;
+
+    #[test]
+    fn select_wildcards_and_aliases() {
+        let mut scanner = Scanner::new();
+        scanner.scan(&"select *, t.*, t.a as x, b y from t;".to_owned()).unwrap();
+        let statements = Parser::new().parse(&scanner.tokens).unwrap();
+        let select = match &statements[0] {
+            Statement::Query(Query::Select(s)) => s,
+            _ => panic!("expected select"),
+        };
+        assert_eq!(select.items.len(), 4);
+        assert!(matches!(&select.items[0], SelectItem::Wildcard(w) if w.table.is_none()));
+        assert!(matches!(&select.items[1], SelectItem::Wildcard(w) if w.table.as_ref().unwrap().lexeme == "t"));
+        match &select.items[2] {
+            SelectItem::Expression(e) => {
+                assert!(matches!(&e.expr, Expr::ColumnReference(c) if c.table.as_deref() == Some("t") && c.name == "a"));
+                assert_eq!(e.alias.as_ref().unwrap().lexeme, "x");
+            },
+            _ => panic!("expected expression"),
+        }
+        assert!(matches!(&select.items[3], SelectItem::Expression(e) if e.alias.as_ref().unwrap().lexeme == "y"));
+    }
+
+    #[test]
+    fn spans_and_diagnostics() {
+        let source = "select -1, t.a, cast(b as int)[1], case when c then 2 end\nfrom t where d not between 1 and 2 * e;";
+        let mut scanner = Scanner::new();
+        scanner.scan(&source.to_owned()).unwrap();
+        let statements = Parser::new().parse(&scanner.tokens).unwrap();
+        let select = match &statements[0] {
+            Statement::Query(Query::Select(s)) => s,
+            _ => panic!("expected select"),
+        };
+        let text = |expr: &Expr| &source[expr.span().start..expr.span().end];
+        let items: Vec<_> = select.items.iter().map(|item| match item {
+            SelectItem::Expression(e) => text(&e.expr),
+            _ => panic!("expected expression"),
+        }).collect();
+        assert_eq!(items, ["-1", "t.a", "cast(b as int)[1]", "case when c then 2 end"]);
+        assert_eq!(text(select.where_clause.as_ref().unwrap()), "d not between 1 and 2 * e");
+        let text = |span: Span| &source[span.start..span.end];
+        assert_eq!(text(statements[0].span()), &source[..source.len() - 1]);
+        assert_eq!(text(select.from_item.span().unwrap()), "t");
+        assert_eq!(text(select.items[2].span()), "cast(b as int)[1]");
+
+        // statement and clause nodes have spans too
+        let source = "create table u (a int default 1 primary key, b text check (b <> ''), unique (a, b));\n\
+            alter table u add column c int;\nupdate u set b = 'x' where a = 1;\nselect * from (select 1) s union select 2;";
+        scanner.scan(&source.to_owned()).unwrap();
+        let statements = Parser::new().parse(&scanner.tokens).unwrap();
+        let text = |span: Span| &source[span.start..span.end];
+        let spans: Vec<_> = statements.iter().map(|s| text(s.span())).collect();
+        assert_eq!(spans, ["create table u (a int default 1 primary key, b text check (b <> ''), unique (a, b))",
+            "alter table u add column c int", "update u set b = 'x' where a = 1", "select * from (select 1) s union select 2"]);
+        let Statement::CreateTableStatement(create) = &statements[0] else { panic!("expected create table") };
+        let columns: Vec<_> = create.columns.iter().map(|c| text(c.span)).collect();
+        assert_eq!(columns, ["a int default 1", "b text"]);
+        let constraints: Vec<_> = create.constraints.iter().map(|c| text(c.span())).collect();
+        assert_eq!(constraints, ["primary key", "check (b <> '')", "unique (a, b)"]);
+        let Statement::AlterTableStatement(alter) = &statements[1] else { panic!("expected alter table") };
+        assert_eq!(text(alter.action.span()), "add column c int");
+        let Statement::UpdateStatement(update) = &statements[2] else { panic!("expected update") };
+        assert_eq!(text(update.assignments[0].span), "b = 'x'");
+        let Statement::Query(Query::SetOperation(union)) = &statements[3] else { panic!("expected union") };
+        let Query::Select(left) = union.left.as_ref() else { panic!("expected select") };
+        assert_eq!((text(left.span), text(left.from_item.span().unwrap())), ("select * from (select 1) s", "(select 1) s"));
+
+        let source = "select a from t;\ncreate table u (a int, b text;\nselect (1;";
+        scanner.scan(&source.to_owned()).unwrap();
+        let mut parser = Parser::new();
+        assert!(parser.parse(&scanner.tokens).is_err());
+        let rendered: Vec<_> = parser.errors.iter().map(|e| e.render(source)).collect();
+        assert_eq!(rendered, [
+            "error[42601]: expected ',' or ')' after column definition, found ';' at line 2, column 30\nLINE 2: create table u (a int, b text;\n                                     ^",
+            "error[42601]: expected ')', found ';' at line 3, column 10\nLINE 3: select (1;\n                 ^",
+        ]);
+    }
+
+    #[test]
+    fn keywords_and_quoted_identifiers() {
+        let mut scanner = Scanner::new();
+        scanner.scan(&"SeLeCT _x$1, \"Select\", \"a\"\"b\" FROM T;".to_owned()).unwrap();
+        let statements = Parser::new().parse(&scanner.tokens).unwrap();
+        let select = match &statements[0] {
+            Statement::Query(Query::Select(s)) => s,
+            _ => panic!("expected select"),
+        };
+        let names: Vec<_> = select.items.iter().map(|item| match item {
+            SelectItem::Expression(e) => match &e.expr {
+                Expr::ColumnReference(c) => c.name.clone(),
+                _ => panic!("expected column"),
+            },
+            _ => panic!("expected expression"),
+        }).collect();
+        assert_eq!(names, vec!["_x$1", "Select", "a\"b"]);
+        let sources: Vec<_> = scanner.tokens.iter().map(|t| t.source().into_owned()).collect();
+        assert_eq!(sources[..7], ["select", "_x$1", ",", "\"Select\"", ",", "\"a\"\"b\"", "from"]);
+
+        for invalid in ["select \"a;", "select \"\" from t;"] {
+            assert!(Scanner::new().scan(&invalid.to_owned()).is_err(), "{}", invalid);
+        }
+
+        // the non-reserved keywords are names too, but a quoted name is never the keyword
+        assert_round_trip("create table index (key int primary key, date date default date '2024-01-31', text text, start serial); select key, date + interval '1 day', text from index where time = time '10:00';");
+        for invalid in ["create table t (a int primary \"key\");", "create table t (a \"int\");", "create \"sequence\" s;", "select \"date\" '2024-01-31';"] {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    fn parse(sql: &str) -> Result<Vec<Statement>, ()> {
+        let mut scanner = Scanner::new();
+        scanner.scan(&sql.to_owned()).map_err(|_| ())?;
+        Parser::new().parse(&scanner.tokens)
+    }
+
+    #[test]
+    fn equality_ignores_positions() {
+        let statements = parse("select a + 1 from t where b;").unwrap();
+        assert!(statements == parse("select  a+1\nfrom t where (b);").unwrap());
+        for different in ["select a + 2 from t where b;", "select a - 1 from t where b;", "select a + 1 from u where b;",
+            "select a + 1 as a from t where b;", "select a + 1 from t;", "select \"A\" + 1 from t where b;"] {
+            assert!(statements != parse(different).unwrap(), "{}", different);
+        }
+        let create = parse("create table t (a int check (a > 0));").unwrap();
+        assert!(create == parse("create table t (a int check (a>0));").unwrap());
+        assert!(create != parse("create table t (a int check (a > 1));").unwrap());
+    }
+
+    // printing the statements gives sql that parses to the same statements, and prints the same again
+    fn assert_round_trip(sql: &str) -> String {
+        let statements = parse(sql).unwrap_or_else(|_| panic!("{} should parse", sql));
+        let printed = statements.iter().map(|s| s.to_string()).collect::<Vec<_>>().join("\n");
+        let reparsed = parse(&printed).unwrap_or_else(|_| panic!("{} printed as {}, which doesn't parse", sql, printed));
+        // the nodes compare their syntax, not where it was written
+        assert!(statements == reparsed, "{} printed as {}, which parses differently:\n{:?}\n{:?}", sql, printed, statements, reparsed);
+        assert_eq!(reparsed.iter().map(|s| s.to_string()).collect::<Vec<_>>().join("\n"), printed);
+        printed
+    }
+
+    #[test]
+    fn display_is_canonical_sql() {
+        let cases = [
+            ("SELECT  a+b*2 AS \"X\", (a+b)*c, -1::INT, (-1)::int FROM t WHERE NOT (a = 1 OR b IN (1,2));",
+                "select a + b * 2 as \"X\", (a + b) * c, -1::integer, (-1)::integer from t where not (a = 1 or b in (1, 2));"),
+            ("select a - (b - c), (a - b) - c, a = (b = c), - -a, - (-1), x from \"T\" u;",
+                "select a - (b - c), a - b - c, a = (b = c), - -a, 1, x from \"T\" as u;"),
+            ("select (select 1) union ((select 2) intersect select 3) except all (select 4 union select 5);",
+                "select (select 1) union select 2 intersect select 3 except all (select 4 union select 5);"),
+            ("create table t (a int, b serial, primary key (a), c numeric(5) references s on delete cascade, check (a > 0));",
+                "create table t (a integer primary key, b serial, c decimal(5,0) references s on delete cascade, check (a > 0));"),
+            // the constraint on b comes before the one on a, so a can't have it
+            ("create table t (a int, b int unique, primary key (a));",
+                "create table t (a integer, b integer unique, primary key (a));"),
+            ("select EXTRACT(Year FROM d), \"Select\"(1), CAST(x AS numeric) FROM t;",
+                "select extract(year from d), \"Select\"(1), x::decimal from t;"),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(assert_round_trip(sql), expected);
+        }
+    }
+
+    #[test]
+    fn display_round_trips_every_statement() {
+        for sql in [
+            "select distinct t.*, a as x, b y from t as u where a between 1 and 2 and b not like 'x%' or c ilike 'y' and d not between -1 and 1 + 1;",
+            "with recursive r (n) as (select 1 union all select n + 1 from r where n < 5), s as (select 2) select * from r;",
+            "select * from (select a from t except select b from s) as d where a in (select b from s) and not exists (select 1 from s);",
+            "select x from unnest(array[1, 2]) as u (x); select * from unnest(array[]::int[]); select * from unnest;",
+            "select case a when 1 then 'one' else 'it''s' end, case when a > 1 then 2 end, extract(YEAR from date '2024-01-31'), cast(a as decimal(5,2)), a::text[], (a::int[])[1], b[2][3];",
+            "select a = any (array[1]), a <> all (b), a != b, (select 1), a not in (1, 2), - -9223372036854775808, j -> 'k' ->> 'l', j #> '{a}' #>> '{b}', -9223372036854775808, not not true, null, false;",
+            "select interval '1 day', time '10:00', timestamp '2024-01-01 10:00', 1.50, 2.5e-3, -0, -(1.5), 9223372036854775808, lower(\"Mixed Case\"), \"select\".\"from\", now();",
+            "select 1 intersect (select 2 union select 3); select 1 union (with w as (select 1) select * from w);",
+            "insert into t (a, \"B\") values (1, 'x'), (-2, null);",
+            "create table t (id int generated always as identity (start with 10 increment by -1) primary key, a decimal(10,2) default 0 check (a >= 0) unique, b int references s (x) on delete cascade on update set null, c bool, d double precision, e float, f smallint, g bigint, h real, i uuid, j json, k bytea, l date[], m time, n timestamp, o interval, p text, q int generated by default as identity, unique (id, c), foreign key (a, b) references u on delete set null, check (a < b));",
+            "alter table t add column c int default 1 + 1; alter table t add d serial; alter table t drop c; alter table t rename column a to b; alter table t rename to u;",
+            "update t set a = a + 1, b = 'x' where c; update t set a = 1; delete from t where a in (1); delete from t;",
+            "drop table if exists t; drop table t; drop sequence s; drop sequence if exists s; drop index if exists i; drop index i; truncate t; truncate table t;",
+            "create sequence s start 5 increment by 2; create sequence s; create sequence s increment -1; create unique index i on t (a, lower(b)); create index i on t (a);",
+        ] {
+            assert_round_trip(sql);
+        }
+    }
+
+    // a random expression, where an operand is put in parentheses half the time, so not all of them parse
+    fn random_expr(random: &mut Random, depth: usize) -> String {
+        if depth == 0 || random.below(4) == 0 {
+            return random.pick(&["a", "t.b", "\"Mixed\"", "1", "2.5", "'it''s'", "null", "true", "date '2024-01-31'", "interval '1 day'", "9223372036854775808", "array[1, 2]"]).to_owned();
+        }
+        let operand = |random: &mut Random| {
+            let expr = random_expr(random, depth - 1);
+            if random.below(2) == 0 { format!("({})", expr) } else { expr }
+        };
+        let (x, y, z) = (operand(random), operand(random), operand(random));
+        match random.below(14) {
+            0 => format!("{} {} {}", x, random.pick(&["or", "and", "=", "<>", "!=", "<", ">=", "->", "->>", "#>", "#>>", "+", "-", "*", "/", "%"]), y),
+            1 => format!("not {}", x),
+            2 => format!("- {}", x),
+            3 => format!("{}::{}", x, random.pick(&["int", "text[]", "decimal(5,2)", "bool"])),
+            4 => format!("{}[{}]", x, y),
+            5 => format!("{} {}in ({}, {})", x, random.pick(&["", "not "]), y, z),
+            6 => format!("{} {}between {} and {}", x, random.pick(&["", "not "]), y, z),
+            7 => format!("{} {} {}", x, random.pick(&["like", "not ilike"]), y),
+            8 => format!("{} = {} ({})", x, random.pick(&["any", "all"]), y),
+            9 => format!("case {} when {} then {} else {} end", x, y, z, x),
+            10 => format!("f({}, {})", x, y),
+            11 => format!("extract(day from {})", x),
+            12 => format!("exists (select {} from t where {})", x, y),
+            _ => format!("cast({} as text)", x),
+        }
+    }
+
+    #[test]
+    fn display_round_trips_random_expressions() {
+        let mut random = Random::new();
+        let mut parsed = 0;
+        for _ in 0..2000 {
+            let sql = format!("select {} as x from t where {};", random_expr(&mut random, 4), random_expr(&mut random, 3));
+            if parse(&sql).is_ok() {
+                assert_round_trip(&sql);
+                parsed += 1;
+            }
+        }
+        assert!(parsed > 500, "only {} of the expressions parsed", parsed);
+    }
+}
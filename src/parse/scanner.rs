@@ -1,6 +1,8 @@
-use std::{iter::Peekable, str::Chars};
+use std::{borrow::Cow, io, iter::Peekable, str::Chars};
 
-use super::token::{LiteralValue, Token, TokenType};
+use crate::types::decimal::Decimal;
+
+use super::token::{LiteralValue, Span, Token, TokenType};
 
 
 pub struct Scanner {
@@ -8,19 +10,149 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: i32,
+    // byte offset where the line of current starts
+    line_start: usize,
+    // line and column of start
+    start_line: i32,
+    start_column: i32,
     next_id: u32,
 }
 
+// the errors of every layer, from scanning a statement to writing its pages to disk
 #[derive(Debug)]
 pub enum TError {
     ScanError(String),
     ParseError(String),
-    PlanError(String),
+    // a statement that can't be planned against the tables there are, with the SQLSTATE of what is wrong with it
+    PlanError(&'static str, String),
+    // a value that can't be computed or stored, with the SQLSTATE telling what was wrong with it
+    ExecutionError(&'static str, String),
+    // a write that would break a table constraint, with the SQLSTATE of the kind of constraint
+    ConstraintError(&'static str, String),
+    // data on disk that can't be read back
+    StorageError(String),
+    // a buffer pool without room for another page
+    ResourceError(String),
+    // reading or writing the data file failed
+    IoError(String),
+    // one of the others, caused by the part of the source the span covers
+    Spanned(Box<TError>, Span),
+}
+
+// the SQLSTATEs of plan, execution and constraint errors, the codes postgres uses for them
+pub const FEATURE_NOT_SUPPORTED: &str = "0A000";
+pub const DATA_EXCEPTION: &str = "22000";
+pub const NUMERIC_VALUE_OUT_OF_RANGE: &str = "22003";
+pub const INVALID_DATETIME_FORMAT: &str = "22007";
+pub const DATETIME_FIELD_OVERFLOW: &str = "22008";
+pub const DIVISION_BY_ZERO: &str = "22012";
+pub const INVALID_PARAMETER_VALUE: &str = "22023";
+pub const SEQUENCE_GENERATOR_LIMIT_EXCEEDED: &str = "2200H";
+pub const INVALID_TEXT_REPRESENTATION: &str = "22P02";
+pub const CARDINALITY_VIOLATION: &str = "21000";
+pub const NOT_NULL_VIOLATION: &str = "23502";
+pub const FOREIGN_KEY_VIOLATION: &str = "23503";
+pub const UNIQUE_VIOLATION: &str = "23505";
+pub const CHECK_VIOLATION: &str = "23514";
+pub const DEPENDENT_OBJECTS_STILL_EXIST: &str = "2BP01";
+pub const SYNTAX_ERROR: &str = "42601";
+pub const NAME_TOO_LONG: &str = "42622";
+pub const DUPLICATE_COLUMN: &str = "42701";
+pub const AMBIGUOUS_COLUMN: &str = "42702";
+pub const UNDEFINED_COLUMN: &str = "42703";
+pub const UNDEFINED_OBJECT: &str = "42704";
+pub const DUPLICATE_ALIAS: &str = "42712";
+pub const DATATYPE_MISMATCH: &str = "42804";
+pub const INVALID_FOREIGN_KEY: &str = "42830";
+pub const CANNOT_COERCE: &str = "42846";
+pub const UNDEFINED_FUNCTION: &str = "42883";
+pub const GENERATED_ALWAYS: &str = "428C9";
+pub const UNDEFINED_TABLE: &str = "42P01";
+pub const DUPLICATE_TABLE: &str = "42P07";
+pub const INVALID_COLUMN_REFERENCE: &str = "42P10";
+pub const INVALID_TABLE_DEFINITION: &str = "42P16";
+pub const INVALID_OBJECT_DEFINITION: &str = "42P17";
+pub const INVALID_RECURSION: &str = "42P19";
+pub const INSUFFICIENT_RESOURCES: &str = "53000";
+pub const PROGRAM_LIMIT_EXCEEDED: &str = "54000";
+pub const OBJECT_NOT_IN_PREREQUISITE_STATE: &str = "55000";
+pub const INTERNAL_ERROR: &str = "XX000";
+
+impl From<io::Error> for TError {
+    fn from(e: io::Error) -> TError {
+        TError::IoError(e.to_string())
+    }
+}
+
+impl TError {
+    // points the error at a part of the source, unless it already points somewhere more precise
+    pub fn at(self, span: Span) -> TError {
+        match self {
+            TError::Spanned(..) => self,
+            e => TError::Spanned(Box::new(e), span),
+        }
+    }
+
+    // the error without the part of the source it points at
+    pub fn unspanned(self) -> TError {
+        match self {
+            TError::Spanned(e, _) => *e,
+            e => e,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            TError::ScanError(m) | TError::ParseError(m) | TError::PlanError(_, m) | TError::ExecutionError(_, m)
+                | TError::ConstraintError(_, m) | TError::StorageError(m) | TError::ResourceError(m) | TError::IoError(m) => m,
+            TError::Spanned(e, _) => e.message(),
+        }
+    }
+
+    // the SQLSTATE of the error, like postgres reports them
+    pub fn sqlstate(&self) -> &'static str {
+        match self {
+            TError::ScanError(_) | TError::ParseError(_) => SYNTAX_ERROR,
+            TError::PlanError(code, _) | TError::ExecutionError(code, _) | TError::ConstraintError(code, _) => code,
+            // data_corrupted
+            TError::StorageError(_) => "XX001",
+            TError::ResourceError(_) => INSUFFICIENT_RESOURCES,
+            // io_error
+            TError::IoError(_) => "58030",
+            TError::Spanned(e, _) => e.sqlstate(),
+        }
+    }
+
+    /**
+     * The error for showing to a user, followed by the line of the source it points at and
+     * carets under the part it is about, like
+     *   error[42601]: expected ')' after column list, found ';'
+     *   LINE 1: create table t (a int;
+     *                                ^
+     */
+    pub fn render(&self, source: &str) -> String {
+        let mut res = format!("error[{}]: {}", self.sqlstate(), self.message());
+        if let TError::Spanned(_, span) = self {
+            let start = span.start.min(source.len());
+            if !source.is_char_boundary(start) {
+                return res;
+            }
+            let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = source[start..].find('\n').map_or(source.len(), |i| start + i);
+            let line_number = source[..start].matches('\n').count() + 1;
+            let prefix = format!("LINE {}: ", line_number);
+            let line = &source[line_start..line_end];
+            let indent = prefix.chars().count() + source[line_start..start].chars().count();
+            let width = source.get(start..span.end.min(line_end)).map_or(0, |s| s.chars().count()).max(1);
+            res.push_str(&format!("\n{}{}\n{}{}", prefix, line.trim_end_matches('\r'), " ".repeat(indent), "^".repeat(width)));
+        }
+        res
+    }
 }
 
 impl<'a> Scanner {
     pub fn new() -> Scanner {
-        Scanner { tokens: Vec::<Token>::new(), start: 0, current: 0, line: 1, next_id: 0 }
+        Scanner { tokens: Vec::<Token>::new(), start: 0, current: 0, line: 1, line_start: 0, start_line: 1, start_column: 1, next_id: 0 }
     }
 
     pub fn scan(&mut self, source: &'a String) -> Result<(), TError> {
@@ -28,15 +160,18 @@ impl<'a> Scanner {
         self.start = 0;
         self.current = 0;
         self.line = 1;
+        self.line_start = 0;
 
         let mut chars = source.chars().peekable();
 
         loop {
+            self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = column(source, self.line_start, self.start);
             if chars.peek().is_none() {
                 break;
             }
             self.scan_token(&mut chars, source)?;
-            self.start = self.current;
         }
         self.add_token(TokenType::EOF, "".to_owned(), None);
 
@@ -50,18 +185,80 @@ impl<'a> Scanner {
             ';' => self.add_simple_token(TokenType::Semicolon, source),
             '(' => self.add_simple_token(TokenType::LeftParen, source),
             ')' => self.add_simple_token(TokenType::RightParen, source),
+            '[' => self.add_simple_token(TokenType::LeftBracket, source),
+            ']' => self.add_simple_token(TokenType::RightBracket, source),
+            '*' => self.add_simple_token(TokenType::Star, source),
+            '+' => self.add_simple_token(TokenType::Plus, source),
+            '-' if self.match_next(chars, '-') => {
+                // a comment runs to the end of the line
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    self.advance(chars);
+                }
+            },
+            '-' => {
+                let token_type = if self.match_next(chars, '>') {
+                    if self.match_next(chars, '>') { TokenType::LongArrow } else { TokenType::Arrow }
+                } else {
+                    TokenType::Minus
+                };
+                self.add_simple_token(token_type, source)
+            },
+            '#' => {
+                if self.match_next(chars, '>') {
+                    let token_type = if self.match_next(chars, '>') { TokenType::HashLongArrow } else { TokenType::HashArrow };
+                    self.add_simple_token(token_type, source)
+                } else {
+                    return Err(self.error(format!("Unrecognized symbol {}", s)));
+                }
+            },
+            '/' if self.match_next(chars, '*') => return self.skip_block_comment(chars),
+            '/' => self.add_simple_token(TokenType::Slash, source),
+            '%' => self.add_simple_token(TokenType::Percent, source),
+            '.' => self.add_simple_token(TokenType::Dot, source),
+            '=' => self.add_simple_token(TokenType::Equal, source),
+            '<' => {
+                let token_type = if self.match_next(chars, '=') {
+                    TokenType::LessEqual
+                } else if self.match_next(chars, '>') {
+                    TokenType::NotEqual
+                } else {
+                    TokenType::Less
+                };
+                self.add_simple_token(token_type, source)
+            },
+            '>' => {
+                let token_type = if self.match_next(chars, '=') { TokenType::GreaterEqual } else { TokenType::Greater };
+                self.add_simple_token(token_type, source)
+            },
+            ':' => {
+                if self.match_next(chars, ':') {
+                    self.add_simple_token(TokenType::DoubleColon, source)
+                } else {
+                    return Err(self.error(format!("Unrecognized symbol {}", s)));
+                }
+            },
+            '!' => {
+                if self.match_next(chars, '=') {
+                    self.add_simple_token(TokenType::NotEqual, source)
+                } else {
+                    return Err(self.error(format!("Unrecognized symbol {}", s)));
+                }
+            },
 
             ' ' | '\t' | '\r' | '\n' => {},
 
+            '\'' => return self.scan_string(chars, source),
+            '"' => return self.scan_quoted_identifier(chars),
+
             '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
                 return self.scan_number(chars, source)
             }
 
             _ => {
-                if s.is_alphabetic() {
+                if s.is_alphabetic() || s == '_' {
                     return self.scan_alphabetic(chars, source);
                 } else {
-                    return Err(TError::ScanError(format!("Unrecognized symbol {}", s)));
+                    return Err(self.error(format!("Unrecognized symbol {}", s)));
                 }
             }
         };
@@ -73,48 +270,47 @@ impl<'a> Scanner {
         self.add_token(token_type, lexeme.to_owned(), None);
     }
 
+    // consume the next char only if it is the expected one
+    fn match_next(&mut self, chars: &mut Peekable<Chars<'_>>, expected: char) -> bool {
+        if chars.peek() == Some(&expected) {
+            self.advance(chars);
+            true
+        } else {
+            false
+        }
+    }
+
     fn advance(&mut self, chars: &mut Peekable<Chars<'_>>) -> Option<char> {
-        self.current += 1;
-        chars.next()
+        let c = chars.next();
+        // start and current are byte offsets into source
+        self.current += c.map_or(0, char::len_utf8);
+        if c == Some('\n') {
+            self.line += 1;
+            self.line_start = self.current;
+        }
+        c
     }
-    
+
+    // errors point at where the token being scanned starts
+    fn error(&self, message: String) -> TError {
+        let span = Span { start: self.start, end: self.current };
+        TError::ScanError(format!("{} at line {}, column {}", message, self.start_line, self.start_column)).at(span)
+    }
+
     fn add_token(&mut self, token_type: TokenType, lexeme: String, literal: Option<LiteralValue>) {
-        let t = Token {token_type, lexeme, literal, line: self.line, id: self.next_id};
+        let span = Span { start: self.start, end: self.current };
+        let t = Token {token_type, lexeme, literal, line: self.start_line, column: self.start_column, span, id: self.next_id};
         self.next_id += 1;
         self.tokens.push(t);
     }
 
+    // keywords and identifiers are case insensitive, both are lowercased
     fn scan_alphabetic(&mut self, chars: &mut Peekable<Chars<'_>>, source: &'a String) -> Result<(), TError> {
-        loop {
-            if let Some(possible_alphabetic) = chars.peek() {
-                if possible_alphabetic.is_alphanumeric() {
-                    self.advance(chars);
-                } else {
-                    break;
-                }
-            } else {
-                break;
-            }
-        };
-        let lexeme = &source[self.start..self.current];
-        let token_type = match lexeme {
-            "select" => TokenType::Select,
-            "from" => TokenType::From,
-            "insert" => TokenType::Insert,
-            "into" => TokenType::Into,
-            "values" => TokenType::Values,
-            "create" => TokenType::Create,
-            "table" => TokenType::Table,
-
-            "int" => TokenType::Int,
-            "bool" => TokenType::Bool,
-
-            "false" => TokenType::False,
-            "true" => TokenType::True,
-            "null" => TokenType::Null,
-            
-            _ => TokenType::Identifier
-        };
+        while chars.peek().is_some_and(|c| is_identifier_char(*c)) {
+            self.advance(chars);
+        }
+        let lexeme = source[self.start..self.current].to_lowercase();
+        let token_type = keyword(&lexeme).unwrap_or(TokenType::Identifier);
         let literal = match &token_type {
             TokenType::False => Some(LiteralValue::BooleanValue(false)),
             TokenType::True => Some(LiteralValue::BooleanValue(true)),
@@ -125,31 +321,308 @@ impl<'a> Scanner {
         Ok(())
     }
 
-    fn scan_number(&mut self, chars: &mut Peekable<Chars<'_>>, source: &'a String) -> Result<(), TError> {
+    // 'characters', the opening quote is already consumed and '' stands for a quote
+    fn scan_string(&mut self, chars: &mut Peekable<Chars<'_>>, source: &'a String) -> Result<(), TError> {
+        let mut value = String::new();
         loop {
-            if let Some(next) = chars.peek() {
-                match next {
-                    '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                        self.advance(chars);
-                    },
-                    _ => {
-                        break;
-                    }
+            match self.advance(chars) {
+                Some('\'') if self.match_next(chars, '\'') => value.push('\''),
+                Some('\'') => break,
+                Some(c) => value.push(c),
+                None => return Err(self.error("Unterminated string".to_owned())),
+            }
+        }
+        let lexeme = &source[self.start..self.current];
+        self.add_token(TokenType::StringLiteral, lexeme.to_owned(), Some(LiteralValue::StringValue(value)));
+        Ok(())
+    }
+
+    // "identifier", the opening quote is already consumed and "" stands for a quote
+    // quoted identifiers keep their case and can be keywords
+    fn scan_quoted_identifier(&mut self, chars: &mut Peekable<Chars<'_>>) -> Result<(), TError> {
+        let mut name = String::new();
+        loop {
+            match self.advance(chars) {
+                Some('"') if self.match_next(chars, '"') => name.push('"'),
+                Some('"') => break,
+                Some(c) => name.push(c),
+                None => return Err(self.error("Unterminated quoted identifier".to_owned())),
+            }
+        }
+        if name.is_empty() {
+            return Err(self.error("Zero-length quoted identifier".to_owned()));
+        }
+        self.add_token(TokenType::Identifier, name, None);
+        Ok(())
+    }
+
+    // 12, 1.5, 1. and 2.5e-3, the sign is a separate token
+    // ints that don't fit in a bigint become decimals, like any number with a point or an exponent,
+    // numbers with an exponent that don't fit in a decimal either become doubles
+    fn scan_number(&mut self, chars: &mut Peekable<Chars<'_>>, source: &'a String) -> Result<(), TError> {
+        self.scan_digits(chars);
+        let mut is_decimal = false;
+        let mut has_exponent = false;
+        if self.match_next(chars, '.') {
+            is_decimal = true;
+            self.scan_digits(chars);
+        }
+        if matches!(chars.peek(), Some('e' | 'E')) {
+            // only an exponent if digits follow, otherwise the e starts the next token
+            let mut lookahead = chars.clone();
+            lookahead.next();
+            if matches!(lookahead.peek(), Some('+' | '-')) {
+                lookahead.next();
+            }
+            if lookahead.peek().is_some_and(char::is_ascii_digit) {
+                is_decimal = true;
+                has_exponent = true;
+                self.advance(chars);
+                if !self.match_next(chars, '+') {
+                    self.match_next(chars, '-');
                 }
-            } else {
-                break;
+                self.scan_digits(chars);
             }
         }
 
         let lexeme = &source[self.start..self.current];
-        let number_conversion = lexeme.parse::<i64>();
-        if let Ok(number) = number_conversion {
-            let literal = Some(LiteralValue::IntValue(number));
-            self.add_token(TokenType::IntLiteral, lexeme.to_owned(), literal);
-            Ok(())
-        } else {
-            Err(TError::ScanError(format!("Failed to parse number {:?}", number_conversion)))
+        if !is_decimal {
+            if let Ok(number) = lexeme.parse::<i64>() {
+                self.add_token(TokenType::IntLiteral, lexeme.to_owned(), Some(LiteralValue::IntValue(number)));
+                return Ok(());
+            }
         }
+        let value = match Decimal::parse(lexeme) {
+            Some(number) => LiteralValue::DecimalValue(number),
+            None if has_exponent => match lexeme.parse::<f64>() {
+                // zeros are decimals, so a zero here is a number too small for a double
+                Ok(number) if number.is_finite() && number != 0.0 => LiteralValue::FloatValue(number),
+                _ => return Err(self.error(format!("Number {} out of range", lexeme))),
+            },
+            None => return Err(self.error(format!("Number {} out of range", lexeme))),
+        };
+        self.add_token(TokenType::DecimalLiteral, lexeme.to_owned(), Some(value));
+        Ok(())
     }
 
-}
\ No newline at end of file
+    fn scan_digits(&mut self, chars: &mut Peekable<Chars<'_>>) {
+        while chars.peek().is_some_and(char::is_ascii_digit) {
+            self.advance(chars);
+        }
+    }
+
+    // /* comment */, the opening is already consumed, comments nest like in postgres
+    fn skip_block_comment(&mut self, chars: &mut Peekable<Chars<'_>>) -> Result<(), TError> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.advance(chars) {
+                Some('*') if self.match_next(chars, '/') => depth -= 1,
+                Some('/') if self.match_next(chars, '*') => depth += 1,
+                Some(_) => {},
+                None => return Err(self.error("Unterminated comment".to_owned())),
+            }
+        }
+        Ok(())
+    }
+
+}
+
+// columns count characters from 1
+fn column(source: &str, line_start: usize, offset: usize) -> i32 {
+    i32::try_from(source[line_start..offset].chars().count()).unwrap_or(i32::MAX - 1) + 1
+}
+
+// identifiers start with a letter or an underscore, after that digits and dollar signs are allowed too
+pub fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+// a name as it has to be written to be scanned as an identifier again, quoted when it isn't lowercase or is a keyword
+pub fn quote_identifier(name: &str) -> Cow<'_, str> {
+    let plain = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(is_identifier_char)
+        && name.to_lowercase() == name
+        && keyword(name).is_none();
+    if plain {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(format!("\"{}\"", name.replace('"', "\"\"")))
+    }
+}
+
+// a name written in a string, like the sequence of nextval('name'), is read like an identifier
+pub fn normalize_identifier(text: &str) -> String {
+    match text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        Some(quoted) if !quoted.is_empty() => quoted.replace("\"\"", "\""),
+        _ => text.to_lowercase(),
+    }
+}
+
+// whether the input ends a statement, with a ";" that isn't in a string, quoted identifier or comment,
+// input that is only whitespace and comments has nothing left to wait for either
+pub fn is_complete(source: &str) -> bool {
+    let mut chars = source.chars().peekable();
+    let mut last = None;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                // a doubled quote stands for one and doesn't close it
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c && chars.peek() == Some(&c) => { chars.next(); },
+                        Some(q) if q == c => break,
+                        Some(_) => {},
+                        None => return false,
+                    }
+                }
+                last = Some(c);
+            },
+            '-' if chars.peek() == Some(&'-') => {
+                while chars.next().is_some_and(|c| c != '\n') {}
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('/') if chars.peek() == Some(&'*') => { chars.next(); depth += 1; },
+                        Some('*') if chars.peek() == Some(&'/') => { chars.next(); depth -= 1; },
+                        Some(_) => {},
+                        None => return false,
+                    }
+                }
+            },
+            c if c.is_whitespace() => {},
+            c => last = Some(c),
+        }
+    }
+    matches!(last, None | Some(';'))
+}
+
+// the keywords and the tokens they are, which are also what the shell completes
+pub const KEYWORDS: &[(&str, TokenType)] = &[
+    ("select", TokenType::Select),
+    ("from", TokenType::From),
+    ("insert", TokenType::Insert),
+    ("into", TokenType::Into),
+    ("values", TokenType::Values),
+    ("create", TokenType::Create),
+    ("table", TokenType::Table),
+    ("as", TokenType::As),
+    ("where", TokenType::Where),
+    ("and", TokenType::And),
+    ("or", TokenType::Or),
+    ("not", TokenType::Not),
+    ("in", TokenType::In),
+    ("exists", TokenType::Exists),
+    ("union", TokenType::Union),
+    ("intersect", TokenType::Intersect),
+    ("except", TokenType::Except),
+    ("all", TokenType::All),
+    ("with", TokenType::With),
+    ("recursive", TokenType::Recursive),
+    ("distinct", TokenType::Distinct),
+    ("case", TokenType::Case),
+    ("when", TokenType::When),
+    ("then", TokenType::Then),
+    ("else", TokenType::Else),
+    ("end", TokenType::End),
+    ("between", TokenType::Between),
+    ("like", TokenType::Like),
+    ("ilike", TokenType::Ilike),
+    ("drop", TokenType::Drop),
+    ("if", TokenType::If),
+    ("truncate", TokenType::Truncate),
+    ("alter", TokenType::Alter),
+    ("column", TokenType::Column),
+    ("to", TokenType::To),
+    ("default", TokenType::Default),
+    ("primary", TokenType::Primary),
+    ("unique", TokenType::Unique),
+    ("update", TokenType::Update),
+    ("set", TokenType::Set),
+    ("references", TokenType::References),
+    ("foreign", TokenType::Foreign),
+    ("on", TokenType::On),
+    ("delete", TokenType::Delete),
+    ("check", TokenType::Check),
+    ("by", TokenType::By),
+    ("array", TokenType::Array),
+    ("any", TokenType::Any),
+    ("extract", TokenType::Extract),
+    ("cast", TokenType::Cast),
+
+    ("false", TokenType::False),
+    ("true", TokenType::True),
+    ("null", TokenType::Null),
+];
+
+// keywords that are only keywords where the grammar expects them, anywhere else they are names,
+// so the scanner leaves them identifiers and the parser matches them by their text
+pub const NON_RESERVED_KEYWORDS: &[&str] = &[
+    "add", "rename", "key", "restrict", "cascade", "sequence", "start", "increment",
+    "generated", "always", "identity", "serial", "index",
+    "smallint", "int", "integer", "bigint", "real", "double", "precision", "float", "decimal", "numeric",
+    "date", "time", "timestamp", "interval", "bool", "text", "bytea", "blob", "json", "jsonb", "uuid",
+];
+
+// the keyword a lowercase word is, if it is one
+fn keyword(word: &str) -> Option<TokenType> {
+    KEYWORDS.iter()
+        .find(|(keyword, _)| *keyword == word)
+        .map(|(_, token_type)| token_type.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::token::{LiteralValue, Span, TokenType};
+
+    use super::{is_complete, Scanner, TError};
+
+    #[test]
+    fn comments_strings_and_positions() {
+        let mut scanner = Scanner::new();
+        let source = "select a, -- the first column\n  'it''s' /* a /* nested */ comment */ <> b.*\n-- only a comment\n;";
+        scanner.scan(&source.to_owned()).unwrap();
+        let tokens: Vec<_> = scanner.tokens.iter().map(|t| (t.lexeme.as_str(), t.line, t.column)).collect();
+        assert_eq!(tokens, vec![("select", 1, 1), ("a", 1, 8), (",", 1, 9), ("'it''s'", 2, 3), ("<>", 2, 40), ("b", 2, 43), (".", 2, 44), ("*", 2, 45), (";", 4, 1), ("", 4, 2)]);
+        assert!(matches!(&scanner.tokens[3].literal, Some(LiteralValue::StringValue(s)) if s == "it's"));
+
+        scanner.scan(&"1-2 - -3 a->'x' 6/2 /**/ ä <= != >= < >".to_owned()).unwrap();
+        let types: Vec<_> = scanner.tokens.iter().map(|t| format!("{:?}", t.token_type)).collect();
+        assert_eq!(types, ["IntLiteral", "Minus", "IntLiteral", "Minus", "Minus", "IntLiteral", "Identifier", "Arrow", "StringLiteral", "IntLiteral", "Slash", "IntLiteral",
+            "Identifier", "LessEqual", "NotEqual", "GreaterEqual", "Less", "Greater", "EOF"]);
+        // columns count characters, spans bytes
+        assert_eq!((scanner.tokens[12].column, scanner.tokens[12].span), (26, Span { start: 25, end: 27 }));
+        assert!(matches!(scanner.tokens.last().unwrap().token_type, TokenType::EOF));
+
+        let invalid = [
+            ("select 'abc", "Unterminated string at line 1, column 8", 7),
+            ("select\n /* /* */", "Unterminated comment at line 2, column 2", 8),
+            ("a ? b", "Unrecognized symbol ? at line 1, column 3", 2),
+        ];
+        for (source, message, start) in invalid {
+            match Scanner::new().scan(&source.to_owned()) {
+                Err(TError::Spanned(e, span)) => {
+                    assert!(matches!(*e, TError::ScanError(ref m) if m == message), "{:?}", e);
+                    assert_eq!(span.start, start);
+                },
+                _ => panic!("expected a scan error for {}", source),
+            }
+        }
+        let error = Scanner::new().scan(&"select 1,\n  'abc".to_owned()).unwrap_err();
+        assert_eq!(error.render("select 1,\n  'abc"), "error[42601]: Unterminated string at line 2, column 3\nLINE 2:   'abc\n          ^^^^");
+    }
+
+    #[test]
+    fn statements_end_at_a_semicolon() {
+        for complete in ["", "  \n", "-- nothing yet\n", "select 1;", "select 1;\n", "select 1; -- done", "select ';' || \"a;\" ; /* c; */",
+            "create table t (\n  a int\n);", "select 'it''s;';", "select /* /* */ */ 1;"] {
+            assert!(is_complete(complete), "{:?}", complete);
+        }
+        for incomplete in ["select 1", "create table t (\n", "select 1; select 2", "select ';", "select \"a;", "select 1 -- ;", "select 1; /* ;",
+            "select /* /* */ ; */", "select 'it'';"] {
+            assert!(!is_complete(incomplete), "{:?}", incomplete);
+        }
+    }
+}
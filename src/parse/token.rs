@@ -1,14 +1,52 @@
+use std::{borrow::Cow, cmp::Ordering, fmt, hash::{Hash, Hasher}};
+
+use super::scanner::quote_identifier;
+use crate::types::{array::DisplayArray, bytea::DisplayBytea, datetime::{compare_temporal, date_to_timestamp, is_temporal_value, DisplayDate, DisplayTime, DisplayTimestamp, Interval}, decimal::Decimal, json::Json, numeric::{compare_numeric, normalize_float, to_f64}, uuid::DisplayUuid};
+
 #[derive(Debug, Clone)]
 pub struct Token { 
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<LiteralValue>,
     pub line: i32,
+    pub column: i32,
+    pub span: Span,
     pub id: u32, // used for resolving names
 }
 
+// the bytes of the source something was parsed from, end is exclusive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
 
-#[derive(Debug, Clone)]
+impl Span {
+    // from the start of one to the end of the other
+    pub fn to(self, other: Span) -> Span {
+        Span { start: self.start.min(other.start), end: self.end.max(other.end) }
+    }
+}
+
+
+// tokens are equal when they were written the same, wherever that was
+impl PartialEq for Token {
+    fn eq(&self, other: &Token) -> bool {
+        self.token_type == other.token_type && self.lexeme == other.lexeme && self.literal == other.literal
+    }
+}
+
+impl Token {
+    // the token as it has to be written to be scanned again, identifiers are lowercased by the scanner
+    pub fn source(&self) -> Cow<'_, str> {
+        match self.token_type {
+            TokenType::Identifier => quote_identifier(&self.lexeme),
+            _ => Cow::Borrowed(&self.lexeme),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     EOF,
     Comma,
@@ -19,22 +57,198 @@ pub enum TokenType {
     False,
     True,
     Null,
-    Int,
     IntLiteral,
     Insert,
     Into,
     Values,
-    Bool,
     Create,
     Table,
     LeftParen,
     RightParen,
+    Star,
+    Dot,
+    As,
+    Where,
+    And,
+    Or,
+    Not,
+    In,
+    Exists,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Union,
+    Intersect,
+    Except,
+    All,
+    With,
+    Recursive,
+    Distinct,
+    Case,
+    When,
+    Then,
+    Else,
+    End,
+    Between,
+    Like,
+    Ilike,
+    StringLiteral,
+    Drop,
+    If,
+    Truncate,
+    Alter,
+    Column,
+    To,
+    Default,
+    Primary,
+    Unique,
+    Update,
+    Set,
+    References,
+    Foreign,
+    On,
+    Delete,
+    Check,
+    By,
+    Plus,
+    Minus,
+    Slash,
+    Percent,
+    DecimalLiteral,
+    Extract,
+    Cast,
+    DoubleColon,
+    Arrow,
+    LongArrow,
+    HashArrow,
+    HashLongArrow,
+    LeftBracket,
+    RightBracket,
+    Array,
+    Any,
 }
 
+// ints of every width are IntValue, reals and doubles are FloatValue
+// dates, times and timestamps are as described in types::datetime, bytea values are BytesValue
+// json documents JsonValue, uuids UuidValue and arrays ArrayValue, whose elements all have the same type
 #[derive(Debug, Clone)]
 pub enum LiteralValue {
     IntValue(i64),
+    FloatValue(f64),
+    DecimalValue(Decimal),
+    DateValue(i32),
+    TimeValue(i64),
+    TimestampValue(i64),
+    IntervalValue(Interval),
     StringValue(String),
+    BytesValue(Vec<u8>),
+    JsonValue(Json),
+    ArrayValue(Vec<LiteralValue>),
+    UuidValue([u8; 16]),
     BooleanValue(bool),
     NullValue
-}
\ No newline at end of file
+}
+
+// numbers are equal when their values are, whatever their type, so rows hash the same
+// in joins and distinct, and so are a date and a timestamp at its midnight.
+// nulls are equal to each other here, unlike in sql comparisons
+impl PartialEq for LiteralValue {
+    fn eq(&self, other: &LiteralValue) -> bool {
+        match (self, other) {
+            (LiteralValue::StringValue(l), LiteralValue::StringValue(r)) => l == r,
+            (LiteralValue::BytesValue(l), LiteralValue::BytesValue(r)) => l == r,
+            (LiteralValue::BytesValue(_), _) | (_, LiteralValue::BytesValue(_)) => false,
+            (LiteralValue::JsonValue(l), LiteralValue::JsonValue(r)) => l == r,
+            (LiteralValue::JsonValue(_), _) | (_, LiteralValue::JsonValue(_)) => false,
+            (LiteralValue::ArrayValue(l), LiteralValue::ArrayValue(r)) => l == r,
+            (LiteralValue::ArrayValue(_), _) | (_, LiteralValue::ArrayValue(_)) => false,
+            (LiteralValue::UuidValue(l), LiteralValue::UuidValue(r)) => l == r,
+            (LiteralValue::UuidValue(_), _) | (_, LiteralValue::UuidValue(_)) => false,
+            (LiteralValue::BooleanValue(l), LiteralValue::BooleanValue(r)) => l == r,
+            (LiteralValue::NullValue, LiteralValue::NullValue) => true,
+            _ if is_temporal_value(self) => compare_temporal(self, other) == Some(Ordering::Equal),
+            _ => compare_numeric(self, other) == Some(Ordering::Equal),
+        }
+    }
+}
+
+impl Eq for LiteralValue {}
+
+impl Hash for LiteralValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            LiteralValue::IntValue(_) | LiteralValue::FloatValue(_) | LiteralValue::DecimalValue(_) => {
+                0.hash(state);
+                // equal numbers have the same double, numbers that are close can collide
+                normalize_float(to_f64(self).unwrap()).to_bits().hash(state);
+            },
+            LiteralValue::StringValue(s) => {
+                1.hash(state);
+                s.hash(state);
+            },
+            LiteralValue::BooleanValue(b) => {
+                2.hash(state);
+                b.hash(state);
+            },
+            LiteralValue::NullValue => 3.hash(state),
+            LiteralValue::DateValue(d) => {
+                4.hash(state);
+                date_to_timestamp(*d).hash(state);
+            },
+            LiteralValue::TimestampValue(t) => {
+                4.hash(state);
+                t.hash(state);
+            },
+            LiteralValue::TimeValue(t) => {
+                5.hash(state);
+                t.hash(state);
+            },
+            LiteralValue::IntervalValue(i) => {
+                6.hash(state);
+                i.hash(state);
+            },
+            LiteralValue::BytesValue(b) => {
+                7.hash(state);
+                b.hash(state);
+            },
+            LiteralValue::JsonValue(j) => {
+                8.hash(state);
+                j.to_bytes().hash(state);
+            },
+            LiteralValue::ArrayValue(elements) => {
+                9.hash(state);
+                elements.hash(state);
+            },
+            LiteralValue::UuidValue(u) => {
+                10.hash(state);
+                u.hash(state);
+            },
+        }
+    }
+}
+
+impl fmt::Display for LiteralValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LiteralValue::IntValue(i) => write!(f, "{}", i),
+            LiteralValue::FloatValue(x) if x.is_nan() => write!(f, "NaN"),
+            LiteralValue::FloatValue(x) if x.is_infinite() => write!(f, "{}Infinity", if *x < 0.0 { "-" } else { "" }),
+            LiteralValue::FloatValue(x) => write!(f, "{}", x),
+            LiteralValue::DecimalValue(d) => write!(f, "{}", d),
+            LiteralValue::DateValue(d) => write!(f, "{}", DisplayDate(*d)),
+            LiteralValue::TimeValue(t) => write!(f, "{}", DisplayTime(*t)),
+            LiteralValue::TimestampValue(t) => write!(f, "{}", DisplayTimestamp(*t)),
+            LiteralValue::IntervalValue(i) => write!(f, "{}", i),
+            LiteralValue::StringValue(s) => write!(f, "{}", s),
+            LiteralValue::BytesValue(b) => write!(f, "{}", DisplayBytea(b)),
+            LiteralValue::JsonValue(j) => write!(f, "{}", j),
+            LiteralValue::ArrayValue(elements) => write!(f, "{}", DisplayArray(elements)),
+            LiteralValue::UuidValue(u) => write!(f, "{}", DisplayUuid(u)),
+            LiteralValue::BooleanValue(b) => write!(f, "{}", b),
+            LiteralValue::NullValue => write!(f, "NULL"),
+        }
+    }
+}
@@ -1,2 +1,5 @@
 pub mod query_plan;
-pub mod planner;
\ No newline at end of file
+pub mod planner;
+mod decorrelate;
+mod cte;
+mod index_scan;
\ No newline at end of file
@@ -0,0 +1,196 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{catalog::table_schema::ColumnType, execution::operators::{CteScan, MaterializedCte, Operator, RecursiveCte, WithMaterialize, WorkingTableScan}, parse::{ast::{CommonTableExpression, Expr, FromItem, Query, SelectItem, With}, scanner::{TError, DATATYPE_MISMATCH, DUPLICATE_ALIAS, INVALID_COLUMN_REFERENCE, INVALID_RECURSION, SYNTAX_ERROR}, token::{LiteralValue, TokenType}}};
+
+use super::planner::{cast_columns, common_value_type, plan_query, type_name, types_compatible, PlanContext, PlannedQuery, ScopeColumn};
+
+
+pub struct CteDefinition {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub types: Vec<Option<ColumnType>>,
+    pub source: CteSource,
+}
+
+pub enum CteSource {
+    // planned again at the reference, with the ctes that were visible where it was defined
+    Inline { query: Box<Query>, ctes: Vec<Rc<CteDefinition>> },
+    Materialized(Rc<RefCell<MaterializedCte>>),
+    // a recursive cte referencing itself from its recursive term
+    WorkingTable(Rc<RefCell<Vec<Vec<LiteralValue>>>>),
+}
+
+/**
+ * A cte is inlined when it is referenced at most once and doesn't use columns of an enclosing
+ * query, it is then planned like a subquery in from wherever it is used. Otherwise it is
+ * materialized once when the query starts, which recursive ctes always are.
+ */
+pub fn plan_with(ctx: &PlanContext, with: &With, outer: &[&[ScopeColumn]]) -> Result<PlannedQuery, TError> {
+    let mut body_ctx = PlanContext { tables: ctx.tables, ctes: ctx.ctes.clone() };
+    let mut materialized = Vec::new();
+
+    for (index, cte) in with.ctes.iter().enumerate() {
+        let name = &cte.name.lexeme;
+        if with.ctes[..index].iter().any(|c| c.name.lexeme == *name) {
+            return Err(TError::PlanError(DUPLICATE_ALIAS, format!("cte name {:?} specified more than once", name)).at(cte.span));
+        }
+
+        let later_references: usize = with.ctes[index + 1..].iter().map(|c| count_references(&c.query, name)).sum();
+        let references = later_references + count_references(&with.body, name);
+        let self_referencing = with.recursive && count_references(&cte.query, name) > 0;
+
+        let definition = if self_referencing {
+            let query = plan_recursive_cte(&body_ctx, cte, outer)?;
+            let plan = Rc::new(RefCell::new(MaterializedCte::new(query.root)));
+            materialized.push(Rc::clone(&plan));
+            CteDefinition { name: name.clone(), columns: query.columns, types: query.types, source: CteSource::Materialized(plan) }
+        } else {
+            let inline = references <= 1 && plan_query(&body_ctx, &cte.query, &[]).is_ok();
+            let query = plan_query(&body_ctx, &cte.query, outer)?;
+            let columns = rename_columns(cte, query.columns)?;
+            let source = if inline {
+                CteSource::Inline { query: cte.query.clone(), ctes: body_ctx.ctes.clone() }
+            } else {
+                let plan = Rc::new(RefCell::new(MaterializedCte::new(query.root)));
+                materialized.push(Rc::clone(&plan));
+                CteSource::Materialized(plan)
+            };
+            CteDefinition { name: name.clone(), columns, types: query.types, source }
+        };
+        body_ctx.ctes.push(Rc::new(definition));
+    }
+
+    let body = plan_query(&body_ctx, &with.body, outer)?;
+    if materialized.is_empty() {
+        return Ok(body);
+    }
+    Ok(PlannedQuery { root: Box::new(WithMaterialize { ctes: materialized, body: body.root }), columns: body.columns, types: body.types })
+}
+
+// the operator that produces the rows of a cte where it is referenced in a from clause
+pub fn plan_cte_reference(ctx: &PlanContext, cte: &CteDefinition) -> Result<Box<dyn Operator>, TError> {
+    match &cte.source {
+        CteSource::Inline { query, ctes } => {
+            let cte_ctx = PlanContext { tables: ctx.tables, ctes: ctes.clone() };
+            Ok(plan_query(&cte_ctx, query, &[])?.root)
+        },
+        CteSource::Materialized(plan) => Ok(Box::new(CteScan::new(Rc::clone(plan)))),
+        CteSource::WorkingTable(working_table) => Ok(Box::new(WorkingTableScan::new(Rc::clone(working_table)))),
+    }
+}
+
+// name AS (base UNION [ALL] recursive), only recursive can reference name
+fn plan_recursive_cte(ctx: &PlanContext, cte: &CommonTableExpression, outer: &[&[ScopeColumn]]) -> Result<PlannedQuery, TError> {
+    let name = &cte.name.lexeme;
+    let set_operation = match cte.query.as_ref() {
+        Query::SetOperation(set_operation) if matches!(set_operation.operator.token_type, TokenType::Union) => set_operation,
+        _ => return Err(TError::PlanError(INVALID_RECURSION, format!("recursive query {:?} does not have the form base UNION [ALL] recursive", name)).at(cte.span)),
+    };
+    if count_references(&set_operation.left, name) > 0 {
+        return Err(TError::PlanError(INVALID_RECURSION, format!("recursive reference to query {:?} must not appear within its non-recursive term", name)).at(cte.span));
+    }
+
+    let base = plan_query(ctx, &set_operation.left, outer)?;
+    let columns = rename_columns(cte, base.columns)?;
+
+    let working_table = Rc::new(RefCell::new(Vec::new()));
+    let mut recursive_ctx = PlanContext { tables: ctx.tables, ctes: ctx.ctes.clone() };
+    recursive_ctx.ctes.push(Rc::new(CteDefinition {
+        name: name.clone(),
+        columns: columns.clone(),
+        types: base.types.clone(),
+        source: CteSource::WorkingTable(Rc::clone(&working_table)),
+    }));
+    let recursive = plan_query(&recursive_ctx, &set_operation.right, outer)?;
+
+    if recursive.types.len() != base.types.len() {
+        return Err(TError::PlanError(SYNTAX_ERROR, format!("each UNION query in {:?} must have the same number of columns", name)).at(set_operation.span));
+    }
+    // the working table has the types of the base, which the recursive rows are converted to,
+    // a wider type can't be used for the rows that were already produced
+    let mut types = Vec::new();
+    for (base_type, recursive_type) in base.types.iter().zip(&recursive.types) {
+        let mismatch = |overall: &Option<ColumnType>| TError::PlanError(DATATYPE_MISMATCH, format!(
+            "recursive query {:?} column has type {} in non-recursive term but {} overall", name, type_name(base_type), type_name(overall)
+        )).at(set_operation.span);
+        if !types_compatible(base_type, recursive_type) {
+            return Err(mismatch(recursive_type));
+        }
+        let overall = match base_type == recursive_type {
+            true => base_type.clone(),
+            false => common_value_type(base_type.clone(), recursive_type.clone()),
+        };
+        if base_type.is_some() && *base_type != overall {
+            return Err(mismatch(&overall));
+        }
+        types.push(overall);
+    }
+    let recursive_root = cast_columns(recursive.root, &recursive.types, &types);
+
+    let plan = RecursiveCte::new(base.root, recursive_root, working_table, !set_operation.all);
+    Ok(PlannedQuery { root: Box::new(plan), columns, types })
+}
+
+// apply the optional column list, name(a, b) AS (...)
+fn rename_columns(cte: &CommonTableExpression, mut columns: Vec<String>) -> Result<Vec<String>, TError> {
+    if cte.columns.len() > columns.len() {
+        return Err(TError::PlanError(INVALID_COLUMN_REFERENCE, format!("{:?} has {} columns available but {} columns specified", cte.name.lexeme, columns.len(), cte.columns.len())).at(cte.span));
+    }
+    for (column, name) in columns.iter_mut().zip(&cte.columns) {
+        *column = name.lexeme.clone();
+    }
+    Ok(columns)
+}
+
+// how many times a from clause in query refers to name
+fn count_references(query: &Query, name: &str) -> usize {
+    match query {
+        Query::Select(select) => {
+            let from = match &select.from_item {
+                FromItem::Table(table) => usize::from(table.token.lexeme == name),
+                FromItem::Subquery(derived) => count_references(&derived.query, name),
+                FromItem::Unnest(unnest) => count_expr_references(&unnest.expr, name),
+                FromItem::Nothing => 0,
+            };
+            let items: usize = select.items.iter().map(|item| match item {
+                SelectItem::Expression(select_expr) => count_expr_references(&select_expr.expr, name),
+                SelectItem::Wildcard(_) => 0,
+            }).sum();
+            let where_clause = select.where_clause.as_ref().map_or(0, |e| count_expr_references(e, name));
+            from + items + where_clause
+        },
+        Query::SetOperation(set_operation) => count_references(&set_operation.left, name) + count_references(&set_operation.right, name),
+        Query::With(with) => {
+            // a nested cte with the same name hides this one
+            if with.ctes.iter().any(|c| c.name.lexeme == name) {
+                return 0;
+            }
+            with.ctes.iter().map(|c| count_references(&c.query, name)).sum::<usize>() + count_references(&with.body, name)
+        },
+    }
+}
+
+fn count_expr_references(expr: &Expr, name: &str) -> usize {
+    match expr {
+        Expr::ColumnReference(_) | Expr::Literal(_) => 0,
+        Expr::Binary(binary) => count_expr_references(&binary.left, name) + count_expr_references(&binary.right, name),
+        Expr::Unary(unary) => count_expr_references(&unary.right, name),
+        Expr::Subquery(subquery) => count_references(&subquery.query, name),
+        Expr::InSubquery(in_subquery) => count_expr_references(&in_subquery.expr, name) + count_references(&in_subquery.query, name),
+        Expr::Exists(exists) => count_references(&exists.query, name),
+        Expr::Case(case) => {
+            let operand = case.operand.as_ref().map_or(0, |e| count_expr_references(e, name));
+            let when_clauses: usize = case.when_clauses.iter().map(|w| count_expr_references(&w.condition, name) + count_expr_references(&w.result, name)).sum();
+            let else_result = case.else_result.as_ref().map_or(0, |e| count_expr_references(e, name));
+            operand + when_clauses + else_result
+        },
+        Expr::InList(in_list) => count_expr_references(&in_list.expr, name) + in_list.list.iter().map(|e| count_expr_references(e, name)).sum::<usize>(),
+        Expr::Between(between) => [&between.expr, &between.low, &between.high].iter().map(|e| count_expr_references(e, name)).sum(),
+        Expr::Like(like) => count_expr_references(&like.expr, name) + count_expr_references(&like.pattern, name),
+        Expr::Function(function) => function.args.iter().map(|e| count_expr_references(e, name)).sum(),
+        Expr::Cast(cast) => count_expr_references(&cast.expr, name),
+        Expr::Array(array) => array.elements.iter().map(|e| count_expr_references(e, name)).sum(),
+        Expr::Subscript(subscript) => count_expr_references(&subscript.expr, name) + count_expr_references(&subscript.index, name),
+        Expr::Quantified(quantified) => count_expr_references(&quantified.left, name) + count_expr_references(&quantified.right, name),
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{execution::{expression::Expression, operators::{Filter, Operator, Projection}}, parse::{ast::{Expr, Query, SelectItem, SelectStatement}, scanner::TError, token::TokenType}};
+
+use super::planner::{bind_expr, is_bool, plan_from, split_conjuncts, types_compatible, PlanContext, ScopeColumn};
+
+
+// the right side of a semi/anti join that replaces a subquery in a where clause
+pub struct SubqueryJoin {
+    pub right: Box<dyn Operator>,
+    pub left_keys: Vec<Expression>,
+    pub anti: bool,
+    pub null_aware: bool,
+}
+
+/**
+ * Try to turn a conjunct of a where clause into a semi or anti join:
+ *   x IN (SELECT y FROM s WHERE ...)         => semi join on x = y
+ *   x NOT IN (SELECT y FROM s WHERE ...)     => null aware anti join on x = y (uncorrelated only)
+ *   EXISTS (SELECT ... FROM s WHERE s.a = t.b AND ...)      => semi join on t.b = s.a
+ *   NOT EXISTS (SELECT ... FROM s WHERE s.a = t.b AND ...)  => anti join on t.b = s.a
+ *
+ * The subquery's where clause has to be a conjunction of predicates that only use the
+ * subquery's own columns, and equalities between one of its columns and an expression over
+ * the outer query's columns. Returns None when the subquery doesn't have that shape, it
+ * is then planned as a correlated subquery instead.
+ */
+pub fn decorrelate(ctx: &PlanContext, scopes: &[&[ScopeColumn]], conjunct: &Expr) -> Result<Option<SubqueryJoin>, TError> {
+    let (query, in_expr, anti) = match conjunct {
+        Expr::InSubquery(in_subquery) => (&in_subquery.query, Some(&in_subquery.expr), in_subquery.negated),
+        Expr::Exists(exists) => (&exists.query, None, false),
+        Expr::Unary(unary) if matches!(unary.operator.token_type, TokenType::Not) => match unary.right.as_ref() {
+            Expr::Exists(exists) => (&exists.query, None, true),
+            _ => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    let query = match query.as_ref() {
+        Query::Select(query) => query,
+        Query::SetOperation(_) | Query::With(_) => return Ok(None),
+    };
+
+    // the right side of the join is run once, so it can't depend on any outer query
+    let (mut right, inner_scope) = match plan_from(ctx, &query.from_item, &[]) {
+        Ok(planned) => planned,
+        Err(_) => return Ok(None),
+    };
+    let inner_scopes: [&[ScopeColumn]; 1] = [&inner_scope];
+
+    let mut left_keys = Vec::new();
+    let mut right_keys = Vec::new();
+
+    if let Some(in_expr) = in_expr {
+        let item = match single_expression(query) {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+        let (left, left_type) = bind_expr(ctx, scopes, in_expr)?;
+        let (right_key, right_type) = match bind_expr(ctx, &inner_scopes, item) {
+            Ok(bound) => bound,
+            Err(_) => return Ok(None),
+        };
+        if !types_compatible(&left_type, &right_type) {
+            return Ok(None);
+        }
+        left_keys.push(left);
+        right_keys.push(right_key);
+    }
+
+    let mut local_predicates = Vec::new();
+    if let Some(where_clause) = &query.where_clause {
+        for conjunct in split_conjuncts(where_clause) {
+            if let Ok((predicate, predicate_type)) = bind_expr(ctx, &inner_scopes, conjunct) {
+                if !is_bool(&predicate_type) {
+                    return Ok(None);
+                }
+                local_predicates.push(predicate);
+                continue;
+            }
+            match correlation_keys(ctx, scopes, &inner_scopes, conjunct) {
+                Some((left, right_key)) => {
+                    left_keys.push(left);
+                    right_keys.push(right_key);
+                },
+                None => return Ok(None),
+            }
+        }
+    }
+
+    // NOT IN is only null aware against the whole subquery result,
+    // a correlated NOT IN would need that per group of correlation keys
+    let null_aware = anti && in_expr.is_some();
+    if null_aware && left_keys.len() > 1 {
+        return Ok(None);
+    }
+
+    for predicate in local_predicates {
+        right = Box::new(Filter { predicate, child: right });
+    }
+    let right = Box::new(Projection { expressions: right_keys, child: right });
+
+    Ok(Some(SubqueryJoin { right, left_keys, anti, null_aware }))
+}
+
+fn single_expression(query: &SelectStatement) -> Option<&Expr> {
+    match query.items.as_slice() {
+        [SelectItem::Expression(select_expr)] => Some(&select_expr.expr),
+        _ => None,
+    }
+}
+
+// split inner_col = outer_expr (or the other way around) into (outer key, inner key)
+fn correlation_keys(ctx: &PlanContext, outer_scopes: &[&[ScopeColumn]], inner_scopes: &[&[ScopeColumn]], conjunct: &Expr) -> Option<(Expression, Expression)> {
+    let binary = match conjunct {
+        Expr::Binary(binary) if matches!(binary.operator.token_type, TokenType::Equal) => binary,
+        _ => return None,
+    };
+    for (inner, outer) in [(&binary.left, &binary.right), (&binary.right, &binary.left)] {
+        if let Ok((inner_key, inner_type)) = bind_expr(ctx, inner_scopes, inner) {
+            if let Ok((outer_key, outer_type)) = bind_expr(ctx, outer_scopes, outer) {
+                if types_compatible(&inner_type, &outer_type) {
+                    return Some((outer_key, inner_key));
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{catalog::table_schema::{Column, ColumnType, TableSchema}, parse::{ast::{Query, Statement}, parser::Parser, scanner::Scanner}, planner::planner::{plan_from, split_conjuncts, PlanContext, ScopeColumn}};
+
+    use super::decorrelate;
+
+    // whether each conjunct of the where clause of sql is turned into a join
+    fn decorrelated(sql: &str) -> Vec<bool> {
+        let tables = vec![
+            TableSchema::new(String::from("t"), vec![Column::new(String::from("a"), ColumnType::Int)], 1),
+            TableSchema::new(String::from("s"), vec![Column::new(String::from("x"), ColumnType::Int)], 2),
+        ];
+        let mut scanner = Scanner::new();
+        scanner.scan(&sql.to_owned()).unwrap();
+        let stmt = match Parser::new().parse(&scanner.tokens).unwrap().pop().unwrap() {
+            Statement::Query(Query::Select(stmt)) => stmt,
+            _ => panic!("expected select"),
+        };
+        let ctx = PlanContext { tables: &tables, ctes: Vec::new() };
+        let (_, scope) = plan_from(&ctx, &stmt.from_item, &[]).unwrap();
+        let scopes: [&[ScopeColumn]; 1] = [&scope];
+        split_conjuncts(stmt.where_clause.as_ref().unwrap()).into_iter().map(|c| decorrelate(&ctx, &scopes, c).unwrap().is_some()).collect()
+    }
+
+    #[test]
+    fn common_cases_become_joins() {
+        assert_eq!(decorrelated("select a from t where a in (select x from s) and not exists (select x from s where x = a);"), vec![true, true]);
+        assert_eq!(decorrelated("select a from t where a not in (select x from s where x > 1);"), vec![true]);
+        assert_eq!(decorrelated("select a from t where exists (select x from s where t.a = s.x and x > 1);"), vec![true]);
+    }
+
+    #[test]
+    fn other_cases_stay_correlated() {
+        // only equality correlations
+        assert_eq!(decorrelated("select a from t where exists (select x from s where x > a);"), vec![false]);
+        // correlated NOT IN
+        assert_eq!(decorrelated("select a from t where a not in (select x from s where x = a);"), vec![false]);
+        // not a top level conjunct
+        assert_eq!(decorrelated("select a from t where a = 1 or a in (select x from s);"), vec![false]);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{catalog::table_schema::{encode_key, ColumnType}, execution::{expression::Expression, operators::{IndexScan, Operator}}, parse::{ast::{Expr, FromItem, SelectStatement}, scanner::TError, token::{LiteralValue, TokenType}}, types::cast::{cast, coercion, CoercionContext}};
+
+use super::planner::{bind_expr, bind_stored_expr, split_conjuncts, type_of_literal, PlanContext, ScopeColumn};
+
+
+/**
+ * An index scan for a select from a single table whose where clause makes every element
+ * of an index equal to a constant, like a = 1, or doc ->> 'name' = 'ann' with an index on
+ * that expression. The whole where clause is still applied to the rows it returns.
+ */
+pub fn plan_index_scan(ctx: &PlanContext, stmt: &SelectStatement, scopes: &[&[ScopeColumn]]) -> Result<Option<Box<dyn Operator>>, TError> {
+    let (table, where_clause) = match (&stmt.from_item, &stmt.where_clause) {
+        (FromItem::Table(table), Some(where_clause)) => (table, where_clause),
+        _ => return Ok(None),
+    };
+    let table_name = &table.token.lexeme;
+    if ctx.ctes.iter().any(|c| c.name == *table_name) {
+        return Ok(None);
+    }
+    let schema = match ctx.tables.iter().find(|t| t.name == *table_name) {
+        Some(schema) => schema,
+        None => return Ok(None),
+    };
+
+    // the expressions the where clause makes equal to a constant
+    let mut equalities = Vec::new();
+    for conjunct in split_conjuncts(where_clause) {
+        if !matches!(conjunct, Expr::Binary(binary) if matches!(binary.operator.token_type, TokenType::Equal)) {
+            continue;
+        }
+        if let (Expression::Binary { left, right, .. }, _) = bind_expr(ctx, scopes, conjunct)? {
+            match (*left, *right) {
+                (Expression::Constant(value), expr) | (expr, Expression::Constant(value)) => equalities.push((expr, value)),
+                _ => {},
+            }
+        }
+    }
+
+    for index in &schema.indexes {
+        let mut elements = Vec::new();
+        for id in &index.columns {
+            let column = schema.columns.iter().find(|c| c.id == *id).unwrap();
+            elements.push((Expression::ColumnValue(schema.column_position(*id)), Some(column.column_type.clone())));
+        }
+        for source in &index.exprs {
+            elements.push(bind_stored_expr(Some(schema), source)?);
+        }
+        let values: Vec<LiteralValue> = elements.iter().map_while(|(element, element_type)| {
+            let (_, value) = equalities.iter().find(|(expr, _)| expr.same_as(element))?;
+            key_value(value, element_type)
+        }).collect();
+        if values.len() < elements.len() {
+            continue;
+        }
+        if let Some(key) = encode_key(&values.iter().collect::<Vec<_>>()) {
+            return Ok(Some(Box::new(IndexScan::new(schema.clone(), index.root_page_id, key))));
+        }
+    }
+    Ok(None)
+}
+
+// the constant as a value of the indexed type, so its key is the one of the equal stored values
+fn key_value(value: &LiteralValue, element_type: &Option<ColumnType>) -> Option<LiteralValue> {
+    let value_type = type_of_literal(value)?;
+    let element_type = element_type.as_ref()?;
+    match coercion(&value_type, element_type) {
+        Some(CoercionContext::Implicit) => cast(value, element_type).ok(),
+        _ => None,
+    }
+}
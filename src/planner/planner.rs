@@ -1,126 +1,1481 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, rc::Rc};
 
-use crate::{catalog::table_schema::{self, ColumnType, TableSchema}, execution::operators::{Projection, SeqScan}, parse::{ast::{Expr, InsertStatement, SelectStatement, Statement}, scanner::TError}, storage::buffer_pool::BufferPoolManager};
+use crate::{catalog::{sequence_schema::SequenceSchema, table_schema::{self, CheckSchema, Column, ColumnType, ForeignKeySchema, IndexSchema, ReferentialAction, TableSchema}}, execution::{expression::{BinaryOperator, Expression, Function}, operators::{Append, Filter, HashDistinct, HashSemiJoin, HashSetOperation, Operator, Projection, SeqScan, SetOperationKind, SingleRow, Unnest}}, parse::{ast::{self, AlterTableAction, AlterTableStatement, CreateIndexStatement, CreateTableStatement, DeleteStatement, DropIndexStatement, Expr, FromItem, InsertStatement, Query, SelectItem, SelectStatement, SetOperation, Statement, StoredExpr, TableConstraint, UpdateStatement}, parser::Parser, scanner::{quote_identifier, Scanner, TError, AMBIGUOUS_COLUMN, CANNOT_COERCE, DATATYPE_MISMATCH, DEPENDENT_OBJECTS_STILL_EXIST, DUPLICATE_COLUMN, DUPLICATE_TABLE, FEATURE_NOT_SUPPORTED, GENERATED_ALWAYS, INVALID_FOREIGN_KEY, INVALID_OBJECT_DEFINITION, INVALID_PARAMETER_VALUE, INVALID_TABLE_DEFINITION, SYNTAX_ERROR, UNDEFINED_COLUMN, UNDEFINED_FUNCTION, UNDEFINED_OBJECT, UNDEFINED_TABLE}, token::{LiteralValue, Token, TokenType}}, storage::buffer_pool::BufferPoolManager, types::{cast::{cast, coercion, CoercionContext}, datetime::{extract_fields, is_temporal, temporal_result_type, DATE_TRUNC_FIELDS}, numeric::{common_type, is_integer, is_numeric, ArithmeticOperator}}};
 
-use super::query_plan::{CreateTablePlan, InsertPlan, QueryPlan, SelectPlan};
+use super::{cte::{plan_cte_reference, plan_with, CteDefinition}, decorrelate::decorrelate, index_scan::plan_index_scan, query_plan::{AlterTablePlan, CreateIndexPlan, CreateSequencePlan, DropIndexPlan, CreateTablePlan, DropSequencePlan, DeletePlan, DropTablePlan, InsertPlan, QueryPlan, SelectPlan, TruncatePlan, UpdatePlan}};
 
 
 
 
+// errors that don't point at a part of the statement point at all of it
 pub fn plan(tables: &mut Vec<TableSchema>, statement: Statement) -> Result<QueryPlan, TError> {
+    let span = statement.span();
+    plan_statement(tables, statement).map_err(|e| e.at(span))
+}
+
+fn plan_statement(tables: &mut Vec<TableSchema>, statement: Statement) -> Result<QueryPlan, TError> {
     match statement {
-        Statement::SelectStatement(stmt) => {
+        Statement::Query(stmt) => {
             plan_select(tables, stmt)
         }
         Statement::InsertStatement(stmt) => {
             plan_insert(tables, stmt)
         },
         Statement::CreateTableStatement(stmt) => {
-            Ok(QueryPlan::CreateTablePlan(CreateTablePlan {stmt} ))
+            plan_create_table(tables, stmt)
+        },
+        Statement::DropTableStatement(stmt) => {
+            let table = stmt.token.lexeme;
+            if !stmt.if_exists && !tables.iter().any(|t| t.name == table) {
+                return Err(TError::PlanError(UNDEFINED_TABLE, format!("table {:?} not found", table)).at(stmt.token.span));
+            }
+            check_not_referenced(tables, &table, "drop")?;
+            Ok(QueryPlan::DropTablePlan(DropTablePlan { table, if_exists: stmt.if_exists }))
+        },
+        Statement::TruncateStatement(stmt) => {
+            let table = stmt.token.lexeme;
+            if !tables.iter().any(|t| t.name == table) {
+                return Err(TError::PlanError(UNDEFINED_TABLE, format!("table {:?} not found", table)));
+            }
+            check_not_referenced(tables, &table, "truncate")?;
+            Ok(QueryPlan::TruncatePlan(TruncatePlan { table }))
+        },
+        Statement::AlterTableStatement(stmt) => {
+            plan_alter_table(tables, stmt)
+        },
+        Statement::UpdateStatement(stmt) => {
+            plan_update(tables, stmt)
+        },
+        Statement::DeleteStatement(stmt) => {
+            plan_delete(tables, stmt)
+        },
+        // sequences aren't known until execution, which checks the name is free
+        Statement::CreateSequenceStatement(stmt) => {
+            let name = stmt.token.lexeme;
+            if tables.iter().any(|t| t.name == name) {
+                return Err(TError::PlanError(DUPLICATE_TABLE, format!("relation {:?} already exists", name)));
+            }
+            if stmt.options.increment == Some(0) {
+                return Err(TError::PlanError(INVALID_PARAMETER_VALUE, String::from("increment must not be zero")));
+            }
+            let sequence = SequenceSchema::new(name, stmt.options.start, stmt.options.increment, None);
+            Ok(QueryPlan::CreateSequencePlan(CreateSequencePlan { sequence }))
+        },
+        Statement::DropSequenceStatement(stmt) => {
+            Ok(QueryPlan::DropSequencePlan(DropSequencePlan { name: stmt.token.lexeme, if_exists: stmt.if_exists }))
+        },
+        Statement::CreateIndexStatement(stmt) => {
+            plan_create_index(tables, stmt)
+        },
+        Statement::DropIndexStatement(stmt) => {
+            plan_drop_index(tables, stmt)
+        },
+    }
+}
+
+/**
+ * An index over columns when every element is a column name, otherwise over expressions,
+ * which are stored as their source. Rows already in the table are indexed when the plan
+ * is executed.
+ */
+fn plan_create_index(tables: &[TableSchema], stmt: CreateIndexStatement) -> Result<QueryPlan, TError> {
+    let table = stmt.table.lexeme;
+    let schema = match tables.iter().find(|t| t.name == table) {
+        Some(schema) => schema,
+        None => return Err(TError::PlanError(UNDEFINED_TABLE, format!("table {:?} not found", table)).at(stmt.table.span)),
+    };
+    let name = stmt.token.lexeme;
+    if tables.iter().any(|t| t.name == name || t.indexes.iter().any(|i| i.name == name)) {
+        return Err(TError::PlanError(DUPLICATE_TABLE, format!("relation {:?} already exists", name)));
+    }
+    let mut columns = Vec::new();
+    for element in &stmt.elements {
+        let (expr, _) = bind_stored_expr(Some(schema), &element.source)?;
+        if !expr.is_immutable() {
+            return Err(TError::PlanError(INVALID_OBJECT_DEFINITION, format!("index expression {:?} can't use subqueries, sequences or now", element.source)));
+        }
+        if let Expr::ColumnReference(ast::ColumnReference { table: None, name, .. }) = &element.expr {
+            columns.push(schema.column_id(name).unwrap());
         }
     }
+    let (columns, exprs) = match columns.len() == stmt.elements.len() {
+        true => (columns, Vec::new()),
+        false => (Vec::new(), stmt.elements.into_iter().map(|e| e.source).collect()),
+    };
+    let index = IndexSchema { name, columns, exprs, unique: stmt.unique, primary: false, root_page_id: 0 };
+    Ok(QueryPlan::CreateIndexPlan(CreateIndexPlan { table, index }))
 }
 
-fn plan_select(tables: &mut Vec<TableSchema>, stmt: SelectStatement) -> Result<QueryPlan, TError> {
-    let table_name = stmt.from_item.token.lexeme;
-    let mut table_schema = None;
-    for table in tables {
-        if table_name == table.name {
-            
-            table_schema = Some(table);
-            break;
+// the index of a primary key can't be dropped, nor a unique index a foreign key looks up referenced rows in
+fn plan_drop_index(tables: &[TableSchema], stmt: DropIndexStatement) -> Result<QueryPlan, TError> {
+    let name = stmt.token.lexeme;
+    let (schema, index) = match tables.iter().find_map(|t| t.indexes.iter().find(|i| i.name == name).map(|i| (t, i))) {
+        Some(found) => found,
+        None if stmt.if_exists => return Ok(QueryPlan::DropIndexPlan(DropIndexPlan { table: None, name })),
+        None => return Err(TError::PlanError(UNDEFINED_OBJECT, format!("index {:?} does not exist", name))),
+    };
+    if index.primary {
+        return Err(TError::PlanError(DEPENDENT_OBJECTS_STILL_EXIST, format!("cannot drop index {:?}, the primary key of table {:?} needs it", name, schema.name)));
+    }
+    let replaced = schema.indexes.iter().any(|i| i.name != name && i.unique && i.columns == index.columns);
+    for other in tables {
+        let foreign_key = other.foreign_keys.iter().find(|f| f.table == schema.name && f.referenced_columns == index.columns);
+        if let Some(foreign_key) = foreign_key.filter(|_| index.unique && !replaced) {
+            return Err(TError::PlanError(DEPENDENT_OBJECTS_STILL_EXIST, format!("cannot drop index {:?}, foreign key {:?} of table {:?} needs it", name, foreign_key.name, other.name)));
         }
+    }
+    Ok(QueryPlan::DropIndexPlan(DropIndexPlan { table: Some(schema.name.clone()), name }))
+}
+
+// tables referenced by a foreign key of another table can't be dropped or truncated
+fn check_not_referenced(tables: &[TableSchema], table: &str, action: &str) -> Result<(), TError> {
+    for other in tables.iter().filter(|t| t.name != table) {
+        if let Some(foreign_key) = other.foreign_keys.iter().find(|f| f.table == table) {
+            return Err(TError::PlanError(DEPENDENT_OBJECTS_STILL_EXIST, format!(
+                "cannot {} table {:?}, foreign key {:?} of table {:?} references it", action, table, foreign_key.name, other.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+// a column visible to expressions in a query, in the order it appears in the input row
+pub struct ScopeColumn {
+    pub table: String,
+    pub name: String,
+    pub column_type: Option<ColumnType>, // None if the type is unknown, ex. a null literal
+}
+
+// what the from clauses of a query can refer to
+pub struct PlanContext<'t> {
+    pub tables: &'t Vec<TableSchema>,
+    // common table expressions in scope, innermost last
+    pub ctes: Vec<Rc<CteDefinition>>,
+}
+
+// the output of planning a select, before it is wrapped into a QueryPlan
+pub struct PlannedQuery {
+    pub root: Box<dyn Operator>,
+    pub columns: Vec<String>,
+    pub types: Vec<Option<ColumnType>>,
+}
+
+fn plan_select(tables: &mut Vec<TableSchema>, query: Query) -> Result<QueryPlan, TError> {
+    let ctx = PlanContext { tables, ctes: Vec::new() };
+    let query = plan_query(&ctx, &query, &[])?;
+    Ok(QueryPlan::SelectPlan(SelectPlan {root: query.root, columns: query.columns}))
+}
+
+// outer holds the scopes of the enclosing queries when planning a subquery, innermost last
+pub fn plan_query(ctx: &PlanContext, query: &Query, outer: &[&[ScopeColumn]]) -> Result<PlannedQuery, TError> {
+    let res = match query {
+        Query::Select(stmt) => plan_select_core(ctx, stmt, outer),
+        Query::SetOperation(set_operation) => plan_set_operation(ctx, set_operation, outer),
+        Query::With(with) => plan_with(ctx, with, outer),
     };
-    if table_schema.is_none() {
-        return Err(TError::PlanError(format!("table {:?} not found", table_name)));
+    res.map_err(|e| e.at(query.span()))
+}
+
+fn plan_set_operation(ctx: &PlanContext, set_operation: &SetOperation, outer: &[&[ScopeColumn]]) -> Result<PlannedQuery, TError> {
+    let left = plan_query(ctx, &set_operation.left, outer)?;
+    let right = plan_query(ctx, &set_operation.right, outer)?;
+    let operator = &set_operation.operator.lexeme;
+
+    if left.columns.len() != right.columns.len() {
+        return Err(TError::PlanError(SYNTAX_ERROR, format!("each {} query must have the same number of columns", operator)).at(set_operation.span));
+    }
+    let mut types = Vec::new();
+    for (index, (left_type, right_type)) in left.types.iter().zip(&right.types).enumerate() {
+        if !types_compatible(left_type, right_type) {
+            return Err(TError::PlanError(DATATYPE_MISMATCH, format!("{} types {} and {} cannot be matched in column {}", operator, type_name(left_type), type_name(right_type), index + 1)).at(set_operation.span));
+        }
+        types.push(match left_type == right_type {
+            true => left_type.clone(),
+            false => common_value_type(left_type.clone(), right_type.clone()),
+        });
+    }
+    let left_root = cast_columns(left.root, &left.types, &types);
+    let right_root = cast_columns(right.root, &right.types, &types);
+
+    // the result takes its column names from the left query
+    let root: Box<dyn Operator> = match (&set_operation.operator.token_type, set_operation.all) {
+        (TokenType::Union, true) => Box::new(Append::new(vec![left_root, right_root])),
+        (TokenType::Union, false) => Box::new(HashDistinct::new(Box::new(Append::new(vec![left_root, right_root])))),
+        (TokenType::Intersect, all) => Box::new(HashSetOperation::new(left_root, right_root, SetOperationKind::Intersect, all)),
+        (TokenType::Except, all) => Box::new(HashSetOperation::new(left_root, right_root, SetOperationKind::Except, all)),
+        _ => return Err(TError::PlanError(FEATURE_NOT_SUPPORTED, format!("unsupported set operation {:?}", operator))),
     };
-    let table_schema = table_schema.unwrap();
+    Ok(PlannedQuery { root, columns: left.columns, types })
+}
+
+// converts the rows of a query to the types of the columns, where one of them has another type
+pub fn cast_columns(root: Box<dyn Operator>, from: &[Option<ColumnType>], to: &[Option<ColumnType>]) -> Box<dyn Operator> {
+    if from == to {
+        return root;
+    }
+    let expressions = from.iter().zip(to).enumerate().map(|(index, (from, to))| match (from, to) {
+        // nulls don't need to be converted
+        (Some(from), Some(to)) if from != to => Expression::Cast { expr: Box::new(Expression::ColumnValue(index)), to: to.clone() },
+        _ => Expression::ColumnValue(index),
+    }).collect();
+    Box::new(Projection { expressions, child: root })
+}
+
+fn plan_select_core(ctx: &PlanContext, stmt: &SelectStatement, outer: &[&[ScopeColumn]]) -> Result<PlannedQuery, TError> {
+    let (mut root, scope) = plan_from(ctx, &stmt.from_item, outer)?;
+
+    let mut scopes = outer.to_vec();
+    scopes.push(&scope);
+
+    if let Some(scan) = plan_index_scan(ctx, stmt, &scopes)? {
+        root = scan;
+    }
+
+    if let Some(where_clause) = &stmt.where_clause {
+        let mut remaining = Vec::new();
+        for conjunct in split_conjuncts(where_clause) {
+            // rewrite IN and EXISTS subqueries into joins when we can,
+            // otherwise they are evaluated again for every row
+            match decorrelate(ctx, &scopes, conjunct)? {
+                Some(join) => {
+                    root = Box::new(HashSemiJoin::new(root, join.right, join.left_keys, join.anti, join.null_aware));
+                },
+                None => remaining.push(conjunct),
+            }
+        }
+        let mut predicate = None;
+        for conjunct in remaining {
+            let (expr, expr_type) = bind_expr(ctx, &scopes, conjunct)?;
+            if !is_bool(&expr_type) {
+                return Err(TError::PlanError(DATATYPE_MISMATCH, format!("argument of where must be a bool, not {}", type_name(&expr_type))).at(conjunct.span()));
+            }
+            predicate = Some(match predicate {
+                Some(left) => Expression::Binary { left: Box::new(left), operator: BinaryOperator::And, right: Box::new(expr) },
+                None => expr,
+            });
+        }
+        if let Some(predicate) = predicate {
+            root = Box::new(Filter { predicate, child: root });
+        }
+    }
+
+    let mut expressions = Vec::new();
+    let mut columns = Vec::new();
+    let mut types = Vec::new();
+    for item in &stmt.items {
+        match item {
+            SelectItem::Wildcard(wildcard) => {
+                if let Some(table) = &wildcard.table {
+                    if !scope.iter().any(|c| c.table == table.lexeme) {
+                        return Err(TError::PlanError(UNDEFINED_TABLE, format!("table {:?} not found in from clause", table.lexeme)).at(item.span()));
+                    }
+                }
+                for (index, col) in scope.iter().enumerate() {
+                    let table_matches = match &wildcard.table {
+                        Some(table) => table.lexeme == col.table,
+                        None => true,
+                    };
+                    if table_matches {
+                        expressions.push(Expression::ColumnValue(index));
+                        columns.push(col.name.clone());
+                        types.push(col.column_type.clone());
+                    }
+                }
+            },
+            SelectItem::Expression(select_expr) => {
+                let (expr, expr_type) = bind_expr(ctx, &scopes, &select_expr.expr)?;
+                expressions.push(expr);
+                types.push(expr_type);
+                let name = match (&select_expr.alias, &select_expr.expr) {
+                    (Some(alias), _) => alias.lexeme.clone(),
+                    (None, Expr::ColumnReference(col)) => col.name.clone(),
+                    (None, Expr::Exists(_)) => String::from("exists"),
+                    (None, _) => String::from("?column?"),
+                };
+                columns.push(name);
+            },
+        }
+    };
+
+    let mut root: Box<dyn Operator> = Box::new(Projection { expressions, child: root});
+    if stmt.distinct {
+        root = Box::new(HashDistinct::new(root));
+    }
+    Ok(PlannedQuery { root, columns, types })
+}
+
+pub fn plan_from(ctx: &PlanContext, from_item: &FromItem, outer: &[&[ScopeColumn]]) -> Result<(Box<dyn Operator>, Vec<ScopeColumn>), TError> {
+    let res = plan_from_item(ctx, from_item, outer);
+    match from_item.span() {
+        Some(span) => res.map_err(|e| e.at(span)),
+        None => res,
+    }
+}
+
+fn plan_from_item(ctx: &PlanContext, from_item: &FromItem, outer: &[&[ScopeColumn]]) -> Result<(Box<dyn Operator>, Vec<ScopeColumn>), TError> {
+    match from_item {
+        FromItem::Table(table) => {
+            let table_name = &table.token.lexeme;
+            // columns can be qualified by the alias if there is one, otherwise the table name
+            let qualifier = match &table.alias {
+                Some(alias) => alias.lexeme.clone(),
+                None => table_name.clone(),
+            };
+
+            // ctes hide tables with the same name
+            if let Some(cte) = ctx.ctes.iter().rev().find(|c| c.name == *table_name) {
+                let root = plan_cte_reference(ctx, cte)?;
+                let scope = cte.columns.iter().zip(&cte.types).map(|(name, column_type)| ScopeColumn { table: qualifier.clone(), name: name.clone(), column_type: column_type.clone() }).collect();
+                return Ok((root, scope));
+            }
+
+            let table_schema = match ctx.tables.iter().find(|t| t.name == *table_name) {
+                Some(table_schema) => table_schema,
+                None => return Err(TError::PlanError(UNDEFINED_TABLE, format!("table {:?} not found", table_name)).at(table.token.span)),
+            };
+
+            let scope = table_schema.columns.iter().map(|c| ScopeColumn { table: qualifier.clone(), name: c.name.clone(), column_type: Some(c.column_type.clone()) }).collect();
+
+            // plan_select_core uses an index instead when the where clause allows it
+            Ok((Box::new(SeqScan::new(table_schema.clone())), scope))
+        },
+        FromItem::Subquery(derived) => {
+            // a subquery in from can't see the other from items of its query,
+            // only the enclosing queries
+            let query = plan_query(ctx, &derived.query, outer)?;
+            let scope = query.columns.into_iter().zip(query.types).map(|(name, column_type)| ScopeColumn { table: derived.alias.lexeme.clone(), name, column_type }).collect();
+            Ok((query.root, scope))
+        },
+        FromItem::Unnest(unnest) => {
+            // like a subquery in from the array can only refer to the enclosing queries
+            let mut scopes = outer.to_vec();
+            scopes.push(&[]);
+            let (array, array_type) = bind_expr(ctx, &scopes, &unnest.expr)?;
+            let element_type = match array_type {
+                Some(ColumnType::Array(element_type)) => Some(*element_type),
+                Some(t) => return Err(TError::PlanError(UNDEFINED_FUNCTION, format!("function unnest({}) does not exist", t))),
+                None => None,
+            };
+            // the column is named by the alias when it isn't given a name of its own
+            let table = unnest.alias.as_ref().map_or(String::from("unnest"), |alias| alias.lexeme.clone());
+            let name = unnest.column.as_ref().map_or(table.clone(), |column| column.lexeme.clone());
+            Ok((Box::new(Unnest::new(array)), vec![ScopeColumn { table, name, column_type: element_type }]))
+        },
+        FromItem::Nothing => Ok((Box::new(SingleRow::new()), Vec::new())),
+    }
+}
 
-    for expr in &stmt.expressions {
-        match expr {
-            Expr::ColumnReference(col) => {
-                let mut found = false;
-                for col_def in &table_schema.columns {
-                    if col.name == col_def.name {
-                        found = true;
-                        break;
+// flatten a chain of ANDs into the list of expressions that all have to be true
+pub fn split_conjuncts(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Binary(binary) if matches!(binary.operator.token_type, TokenType::And) => {
+            let mut res = split_conjuncts(&binary.left);
+            res.extend(split_conjuncts(&binary.right));
+            res
+        },
+        _ => vec![expr],
+    }
+}
+
+// scopes holds the scope of the query the expression is in last, preceded by the enclosing queries
+pub fn bind_expr(ctx: &PlanContext, scopes: &[&[ScopeColumn]], expr: &Expr) -> Result<(Expression, Option<ColumnType>), TError> {
+    // errors point at the innermost expression they are about
+    bind_expr_at(ctx, scopes, expr).map_err(|e| e.at(expr.span()))
+}
+
+fn bind_expr_at(ctx: &PlanContext, scopes: &[&[ScopeColumn]], expr: &Expr) -> Result<(Expression, Option<ColumnType>), TError> {
+    match expr {
+        Expr::ColumnReference(col) => {
+            // the innermost query that has a matching column wins
+            for (level, scope) in scopes.iter().rev().enumerate() {
+                let mut found = None;
+                for (index, col_def) in scope.iter().enumerate() {
+                    let table_matches = match &col.table {
+                        Some(table) => *table == col_def.table,
+                        None => true,
+                    };
+                    if table_matches && col.name == col_def.name {
+                        if found.is_some() {
+                            return Err(TError::PlanError(AMBIGUOUS_COLUMN, format!("column reference {:?} is ambiguous", col.name)));
+                        }
+                        found = Some(index);
                     }
                 }
-                if !found {
-                    return Err(TError::PlanError(format!("column {:?} not found", col.name)));
+                if let Some(index) = found {
+                    let column_type = scope[index].column_type.clone();
+                    if level == 0 {
+                        return Ok((Expression::ColumnValue(index), column_type));
+                    }
+                    return Ok((Expression::OuterColumnValue { level, index }, column_type));
                 }
             }
-            Expr::Literal(_) => {},
+            Err(TError::PlanError(UNDEFINED_COLUMN, format!("column {:?} not found", col.name)))
         }
+        Expr::Literal(lit) => Ok((Expression::Constant(lit.value.clone()), type_of_literal(&lit.value))),
+        Expr::Binary(binary) => {
+            let (mut left, mut left_type) = bind_expr(ctx, scopes, &binary.left)?;
+            let (mut right, mut right_type) = bind_expr(ctx, scopes, &binary.right)?;
+            if let Some(function) = json_operator(&binary.operator.token_type) {
+                return bind_json_operator(function, &binary.operator.lexeme, (left, left_type), (right, right_type));
+            }
+            let arithmetic_operator = match binary.operator.token_type {
+                TokenType::Plus => Some(ArithmeticOperator::Add),
+                TokenType::Minus => Some(ArithmeticOperator::Subtract),
+                TokenType::Star => Some(ArithmeticOperator::Multiply),
+                TokenType::Slash => Some(ArithmeticOperator::Divide),
+                TokenType::Percent => Some(ArithmeticOperator::Modulo),
+                _ => None,
+            };
+            if let Some(operator) = arithmetic_operator {
+                if left_type.as_ref().is_some_and(is_temporal) || right_type.as_ref().is_some_and(is_temporal) {
+                    let result_type = match (&left_type, &right_type) {
+                        (Some(l), Some(r)) => match temporal_result_type(operator, l, r) {
+                            Some(result_type) => Some(result_type),
+                            None => return Err(TError::PlanError(UNDEFINED_FUNCTION, format!("operator does not exist: {} {} {}", l, binary.operator.lexeme, r))),
+                        },
+                        _ => None,
+                    };
+                    return Ok((Expression::Arithmetic { left: Box::new(left), operator, right: Box::new(right) }, result_type));
+                }
+                for t in [&left_type, &right_type] {
+                    if !t.as_ref().is_none_or(is_numeric) {
+                        return Err(TError::PlanError(DATATYPE_MISMATCH, format!("arguments of {} must be numbers, not {}", binary.operator.lexeme, type_name(t))));
+                    }
+                }
+                let result_type = numeric_result_type(&left_type, &right_type).or(left_type).or(right_type);
+                let expr = Expression::Arithmetic { left: Box::new(left), operator, right: Box::new(right) };
+                return Ok((narrow(expr, &result_type), result_type));
+            }
+            let operator = match binary.operator.token_type {
+                TokenType::And => BinaryOperator::And,
+                TokenType::Or => BinaryOperator::Or,
+                TokenType::Equal => BinaryOperator::Equal,
+                TokenType::NotEqual => BinaryOperator::NotEqual,
+                TokenType::Less => BinaryOperator::Less,
+                TokenType::LessEqual => BinaryOperator::LessEqual,
+                TokenType::Greater => BinaryOperator::Greater,
+                TokenType::GreaterEqual => BinaryOperator::GreaterEqual,
+                _ => return Err(TError::PlanError(FEATURE_NOT_SUPPORTED, format!("unsupported operator {:?}", binary.operator.lexeme))),
+            };
+            match operator {
+                BinaryOperator::And | BinaryOperator::Or => {
+                    if !is_bool(&left_type) || !is_bool(&right_type) {
+                        return Err(TError::PlanError(DATATYPE_MISMATCH, format!("arguments of {} must be bool", binary.operator.lexeme)));
+                    }
+                },
+                _ => {
+                    (left, left_type) = coerce_literal(left, left_type, &right_type)?;
+                    (right, right_type) = coerce_literal(right, right_type, &left_type)?;
+                    if !types_compatible(&left_type, &right_type) {
+                        return Err(TError::PlanError(DATATYPE_MISMATCH, format!("cannot compare {} {} {}", type_name(&left_type), binary.operator.lexeme, type_name(&right_type))));
+                    }
+                }
+            };
+            Ok((Expression::Binary { left: Box::new(left), operator, right: Box::new(right) }, Some(ColumnType::Bool)))
+        },
+        Expr::Unary(unary) if matches!(unary.operator.token_type, TokenType::Minus) => {
+            let (right, right_type) = bind_expr(ctx, scopes, &unary.right)?;
+            if !right_type.as_ref().is_none_or(|t| is_numeric(t) || *t == ColumnType::Interval) {
+                return Err(TError::PlanError(DATATYPE_MISMATCH, format!("argument of - must be a number or an interval, not {}", type_name(&right_type))));
+            }
+            Ok((narrow(Expression::Negate(Box::new(right)), &right_type), right_type))
+        },
+        Expr::Unary(unary) => {
+            let (right, right_type) = bind_expr(ctx, scopes, &unary.right)?;
+            if !is_bool(&right_type) {
+                return Err(TError::PlanError(DATATYPE_MISMATCH, format!("argument of not must be bool, not {}", type_name(&right_type))));
+            }
+            Ok((Expression::Not(Box::new(right)), Some(ColumnType::Bool)))
+        },
+        Expr::Subquery(subquery) => {
+            let query = plan_query(ctx, &subquery.query, scopes)?;
+            if query.columns.len() != 1 {
+                return Err(TError::PlanError(SYNTAX_ERROR, String::from("subquery must return only one column")));
+            }
+            Ok((Expression::Subquery(query.root), query.types[0].clone()))
+        },
+        Expr::InSubquery(in_subquery) => {
+            let (left, left_type) = bind_expr(ctx, scopes, &in_subquery.expr)?;
+            let query = plan_query(ctx, &in_subquery.query, scopes)?;
+            if query.columns.len() != 1 {
+                return Err(TError::PlanError(SYNTAX_ERROR, String::from("subquery has too many columns")));
+            }
+            if !types_compatible(&left_type, &query.types[0]) {
+                return Err(TError::PlanError(DATATYPE_MISMATCH, format!("cannot compare {} with subquery of {}", type_name(&left_type), type_name(&query.types[0]))));
+            }
+            Ok((Expression::InSubquery { expr: Box::new(left), subquery: query.root, negated: in_subquery.negated }, Some(ColumnType::Bool)))
+        },
+        Expr::Exists(exists) => {
+            let query = plan_query(ctx, &exists.query, scopes)?;
+            Ok((Expression::Exists(query.root), Some(ColumnType::Bool)))
+        },
+        Expr::Case(case) => {
+            let operand = match &case.operand {
+                Some(operand) => Some(bind_expr(ctx, scopes, operand)?),
+                None => None,
+            };
+            let mut when_clauses = Vec::new();
+            let mut result_type = None;
+            for when_clause in &case.when_clauses {
+                let (mut condition, mut condition_type) = bind_expr(ctx, scopes, &when_clause.condition)?;
+                match &operand {
+                    Some((_, operand_type)) => {
+                        (condition, condition_type) = coerce_literal(condition, condition_type, operand_type)?;
+                        if !types_compatible(operand_type, &condition_type) {
+                            return Err(TError::PlanError(DATATYPE_MISMATCH, format!("cannot compare case operand of {} with {}", type_name(operand_type), type_name(&condition_type))).at(when_clause.span));
+                        }
+                    },
+                    None => {
+                        if !is_bool(&condition_type) {
+                            return Err(TError::PlanError(DATATYPE_MISMATCH, format!("argument of when must be bool, not {}", type_name(&condition_type))).at(when_clause.span));
+                        }
+                    },
+                }
+                let (result, this_type) = bind_expr(ctx, scopes, &when_clause.result)?;
+                result_type = case_result_type(result_type, this_type)?;
+                when_clauses.push((condition, result));
+            }
+            let else_result = match &case.else_result {
+                Some(else_result) => {
+                    let (else_result, this_type) = bind_expr(ctx, scopes, else_result)?;
+                    result_type = case_result_type(result_type, this_type)?;
+                    Some(Box::new(else_result))
+                },
+                None => None,
+            };
+            let operand = operand.map(|(operand, _)| Box::new(operand));
+            Ok((Expression::Case { operand, when_clauses, else_result }, result_type))
+        },
+        Expr::InList(in_list) => {
+            let (left, left_type) = bind_expr(ctx, scopes, &in_list.expr)?;
+            let mut list = Vec::new();
+            for item in &in_list.list {
+                let (item, item_type) = bind_expr(ctx, scopes, item)?;
+                let (item, item_type) = coerce_literal(item, item_type, &left_type)?;
+                if !types_compatible(&left_type, &item_type) {
+                    return Err(TError::PlanError(DATATYPE_MISMATCH, format!("cannot compare {} with {} in in list", type_name(&left_type), type_name(&item_type))));
+                }
+                list.push(item);
+            }
+            Ok((Expression::InList { expr: Box::new(left), list, negated: in_list.negated }, Some(ColumnType::Bool)))
+        },
+        Expr::Between(between) => {
+            let (expr, expr_type) = bind_expr(ctx, scopes, &between.expr)?;
+            let (low, low_type) = bind_expr(ctx, scopes, &between.low)?;
+            let (low, low_type) = coerce_literal(low, low_type, &expr_type)?;
+            let (high, high_type) = bind_expr(ctx, scopes, &between.high)?;
+            let (high, high_type) = coerce_literal(high, high_type, &expr_type)?;
+            for bound_type in [&low_type, &high_type] {
+                if !types_compatible(&expr_type, bound_type) {
+                    return Err(TError::PlanError(DATATYPE_MISMATCH, format!("cannot compare {} with {} in between", type_name(&expr_type), type_name(bound_type))));
+                }
+            }
+            let between = Expression::Between { expr: Box::new(expr), low: Box::new(low), high: Box::new(high), negated: between.negated };
+            Ok((between, Some(ColumnType::Bool)))
+        },
+        Expr::Function(function) => {
+            let name = &function.name.lexeme;
+            let mut args = Vec::new();
+            let mut arg_types = Vec::new();
+            for arg in &function.args {
+                let (arg, arg_type) = bind_expr(ctx, scopes, arg)?;
+                args.push(arg);
+                arg_types.push(arg_type);
+            }
+            // json functions read a string literal document as json
+            if name.starts_with("json_") && !args.is_empty() {
+                let (arg, arg_type) = coerce_literal(args.remove(0), arg_types.remove(0), &Some(ColumnType::Json))?;
+                args.insert(0, arg);
+                arg_types.insert(0, arg_type);
+            }
+            let (function, return_type) = bind_function(name, &args, &arg_types)?;
+            Ok((Expression::Function { function, args }, Some(return_type)))
+        },
+        Expr::Like(like) => {
+            let (expr, expr_type) = bind_expr(ctx, scopes, &like.expr)?;
+            let (pattern, pattern_type) = bind_expr(ctx, scopes, &like.pattern)?;
+            for t in [&expr_type, &pattern_type] {
+                if !types_compatible(t, &Some(ColumnType::Text)) {
+                    return Err(TError::PlanError(DATATYPE_MISMATCH, format!("arguments of like must be text, not {}", type_name(t))));
+                }
+            }
+            let like = Expression::Like { expr: Box::new(expr), pattern: Box::new(pattern), negated: like.negated, case_insensitive: like.case_insensitive };
+            Ok((like, Some(ColumnType::Bool)))
+        },
+        // invalid values, like text that isn't a number, are only found when the cast is evaluated
+        Expr::Cast(ast_cast) => {
+            let (expr, expr_type) = bind_expr(ctx, scopes, &ast_cast.expr)?;
+            let to = to_column_type(&ast_cast.column_type);
+            if let Some(from) = &expr_type {
+                if coercion(from, &to).is_none() {
+                    return Err(TError::PlanError(CANNOT_COERCE, format!("cannot cast type {} to {}", from, to)));
+                }
+            }
+            Ok((Expression::Cast { expr: Box::new(expr), to: to.clone() }, Some(to)))
+        },
+        Expr::Array(array) => {
+            let mut elements = Vec::new();
+            for element in &array.elements {
+                elements.push(bind_expr(ctx, scopes, element)?);
+            }
+            // string literals take the type of the other elements, like in comparisons
+            let is_string = |e: &Expression| matches!(e, Expression::Constant(LiteralValue::StringValue(_)));
+            let mut element_type = None;
+            for (_, this_type) in elements.iter().filter(|(e, _)| !is_string(e)) {
+                if !types_compatible(&element_type, this_type) {
+                    return Err(TError::PlanError(DATATYPE_MISMATCH, format!("array elements of types {} and {} cannot be matched", type_name(&element_type), type_name(this_type))));
+                }
+                element_type = common_value_type(element_type, this_type.clone());
+            }
+            if element_type.is_none() && elements.iter().any(|(e, _)| is_string(e)) {
+                element_type = Some(ColumnType::Text);
+            }
+            if let Some(ColumnType::Array(_)) = element_type {
+                return Err(TError::PlanError(FEATURE_NOT_SUPPORTED, String::from("arrays have only one dimension")));
+            }
+            let mut cast_elements = Vec::new();
+            for (element, this_type) in elements {
+                let (element, this_type) = coerce_literal(element, this_type, &element_type)?;
+                cast_elements.push(match (&this_type, &element_type) {
+                    (Some(this_type), Some(to)) if this_type != to => Expression::Cast { expr: Box::new(element), to: to.clone() },
+                    _ => element,
+                });
+            }
+            Ok((Expression::Array(cast_elements), element_type.map(|t| ColumnType::Array(Box::new(t)))))
+        },
+        Expr::Subscript(subscript) => {
+            let (expr, expr_type) = bind_expr(ctx, scopes, &subscript.expr)?;
+            let (index, index_type) = bind_expr(ctx, scopes, &subscript.index)?;
+            if !index_type.as_ref().is_none_or(is_integer) {
+                return Err(TError::PlanError(DATATYPE_MISMATCH, format!("array subscript must have type integer, not {}", type_name(&index_type))));
+            }
+            let element_type = match expr_type {
+                Some(ColumnType::Array(element_type)) => Some(*element_type),
+                Some(t) => return Err(TError::PlanError(DATATYPE_MISMATCH, format!("cannot subscript type {} because it is not an array", t))),
+                None => None,
+            };
+            Ok((Expression::Subscript { expr: Box::new(expr), index: Box::new(index) }, element_type))
+        },
+        Expr::Quantified(quantified) => {
+            let (left, left_type) = bind_expr(ctx, scopes, &quantified.left)?;
+            let (right, right_type) = bind_expr(ctx, scopes, &quantified.right)?;
+            // a string literal on the right is read as an array of the type on the left
+            let (right, right_type) = coerce_literal(right, right_type, &left_type.clone().map(|t| ColumnType::Array(Box::new(t))))?;
+            let quantifier = if quantified.all { "all" } else { "any" };
+            let element_type = match right_type {
+                Some(ColumnType::Array(element_type)) => Some(*element_type),
+                Some(t) => return Err(TError::PlanError(DATATYPE_MISMATCH, format!("op {} (array) requires an array on the right, not {}", quantifier, t))),
+                None => None,
+            };
+            let (left, left_type) = coerce_literal(left, left_type, &element_type)?;
+            if !types_compatible(&left_type, &element_type) {
+                return Err(TError::PlanError(DATATYPE_MISMATCH, format!("cannot compare {} {} {} with elements of {}", type_name(&left_type), quantified.operator.lexeme, quantifier, type_name(&element_type))));
+            }
+            let operator = match quantified.operator.token_type {
+                TokenType::Equal => BinaryOperator::Equal,
+                TokenType::NotEqual => BinaryOperator::NotEqual,
+                TokenType::Less => BinaryOperator::Less,
+                TokenType::LessEqual => BinaryOperator::LessEqual,
+                TokenType::Greater => BinaryOperator::Greater,
+                TokenType::GreaterEqual => BinaryOperator::GreaterEqual,
+                _ => return Err(TError::PlanError(FEATURE_NOT_SUPPORTED, format!("unsupported operator {:?}", quantified.operator.lexeme))),
+            };
+            let all = quantified.all;
+            Ok((Expression::Quantified { left: Box::new(left), operator, all, right: Box::new(right) }, Some(ColumnType::Bool)))
+        },
+    }
+}
+
+// checks the arguments of a function, returning it and its result type
+fn bind_function(name: &str, args: &[Expression], arg_types: &[Option<ColumnType>]) -> Result<(Function, ColumnType), TError> {
+    let takes = |types: &[ColumnType]| {
+        arg_types.len() == types.len() && arg_types.iter().zip(types).all(|(arg_type, t)| types_compatible(arg_type, &Some(t.clone())))
     };
-    // for now always do a sequential scan
-    let scan = SeqScan { table: table_schema.clone() };
-    
-    let projection = Projection { expressions: stmt.expressions.clone(), child: Box::new(scan)};
-    Ok(QueryPlan::SelectPlan(SelectPlan {projection}))
+    // the fields of extract and date_trunc are checked here when they are constants
+    let field_valid = |fields: &[&str]| match &args[0] {
+        Expression::Constant(LiteralValue::StringValue(field)) => fields.contains(&field.as_str()),
+        _ => true,
+    };
+    match name {
+        "nextval" | "currval" => {
+            if !takes(&[ColumnType::Text]) {
+                return Err(TError::PlanError(DATATYPE_MISMATCH, format!("{} takes the name of a sequence", name)));
+            }
+            let function = if name == "nextval" { Function::NextVal } else { Function::CurrVal };
+            Ok((function, ColumnType::BigInt))
+        },
+        "now" => {
+            if !args.is_empty() {
+                return Err(TError::PlanError(UNDEFINED_FUNCTION, String::from("now takes no arguments")));
+            }
+            Ok((Function::Now, ColumnType::Timestamp))
+        },
+        "gen_random_uuid" => {
+            if !args.is_empty() {
+                return Err(TError::PlanError(UNDEFINED_FUNCTION, String::from("gen_random_uuid takes no arguments")));
+            }
+            Ok((Function::GenRandomUuid, ColumnType::Uuid))
+        },
+        "extract" => {
+            let value_type = match arg_types.get(1) {
+                Some(Some(t)) if is_temporal(t) => t,
+                _ => return Err(TError::PlanError(UNDEFINED_FUNCTION, String::from("extract takes a date, time, timestamp or interval"))),
+            };
+            if !field_valid(extract_fields(value_type)) {
+                return Err(TError::PlanError(INVALID_PARAMETER_VALUE, format!("unit {} not supported for type {}", args_field(args), value_type)));
+            }
+            Ok((Function::Extract, ColumnType::Double))
+        },
+        "date_trunc" => {
+            if !takes(&[ColumnType::Text, ColumnType::Timestamp]) {
+                return Err(TError::PlanError(UNDEFINED_FUNCTION, String::from("date_trunc takes a unit and a timestamp")));
+            }
+            if !field_valid(&DATE_TRUNC_FIELDS) {
+                return Err(TError::PlanError(INVALID_PARAMETER_VALUE, format!("unit {} not recognized by date_trunc", args_field(args))));
+            }
+            Ok((Function::DateTrunc, ColumnType::Timestamp))
+        },
+        "json_extract" => {
+            if !takes(&[ColumnType::Json, ColumnType::Text]) {
+                return Err(TError::PlanError(UNDEFINED_FUNCTION, String::from("json_extract takes a json document and a path")));
+            }
+            Ok((Function::JsonExtract, ColumnType::Text))
+        },
+        "json_array_length" => {
+            if !takes(&[ColumnType::Json]) {
+                return Err(TError::PlanError(UNDEFINED_FUNCTION, String::from("json_array_length takes a json array")));
+            }
+            Ok((Function::JsonArrayLength, ColumnType::Int))
+        },
+        _ => Err(TError::PlanError(UNDEFINED_FUNCTION, format!("function {:?} does not exist", name))),
+    }
 }
 
-fn plan_insert(tables: &mut Vec<TableSchema>, stmt: InsertStatement) -> Result<QueryPlan, TError> {
-    let table_name = stmt.token.lexeme;
-    let mut table_schema = None;
-    for table in tables {
-        if table_name == table.name {
-            
-            table_schema = Some(table);
-            break;
+fn json_operator(token_type: &TokenType) -> Option<Function> {
+    match token_type {
+        TokenType::Arrow => Some(Function::JsonGet),
+        TokenType::LongArrow => Some(Function::JsonGetText),
+        TokenType::HashArrow => Some(Function::JsonPath),
+        TokenType::HashLongArrow => Some(Function::JsonPathText),
+        _ => None,
+    }
+}
+
+// the left side is a json document, a string literal is read as one, -> and ->> take a key or an index and #> and #>> a path
+fn bind_json_operator(function: Function, lexeme: &str, left: (Expression, Option<ColumnType>), right: (Expression, Option<ColumnType>)) -> Result<(Expression, Option<ColumnType>), TError> {
+    let (left, left_type) = coerce_literal(left.0, left.1, &Some(ColumnType::Json))?;
+    let (right, right_type) = right;
+    let takes_index = matches!(function, Function::JsonGet | Function::JsonGetText);
+    let valid = left_type.as_ref().is_none_or(|t| *t == ColumnType::Json)
+        && right_type.as_ref().is_none_or(|t| *t == ColumnType::Text || (takes_index && is_integer(t)));
+    if !valid {
+        let name = |t: &Option<ColumnType>| t.as_ref().map_or(String::from("unknown"), |t| t.to_string());
+        return Err(TError::PlanError(UNDEFINED_FUNCTION, format!("operator does not exist: {} {} {}", name(&left_type), lexeme, name(&right_type))));
+    }
+    let result_type = match function {
+        Function::JsonGet | Function::JsonPath => ColumnType::Json,
+        _ => ColumnType::Text,
+    };
+    Ok((Expression::Function { function, args: vec![left, right] }, Some(result_type)))
+}
+
+fn args_field(args: &[Expression]) -> String {
+    match &args[0] {
+        Expression::Constant(field) => format!("{:?}", field.to_string()),
+        _ => String::new(),
+    }
+}
+
+// the type both types convert to implicitly, the wider one for numbers, like for the elements of an array
+// or the columns of a set operation
+pub fn common_value_type(left: Option<ColumnType>, right: Option<ColumnType>) -> Option<ColumnType> {
+    match (left, right) {
+        (Some(l), Some(r)) if is_numeric(&l) && is_numeric(&r) => Some(common_type(&l, &r)),
+        (Some(l), Some(r)) if coercion(&l, &r) == Some(CoercionContext::Implicit) => Some(r),
+        (l, r) => l.or(r),
+    }
+}
+
+// all results of a case have to have the same type, nulls take the type of the others
+fn case_result_type(result_type: Option<ColumnType>, this_type: Option<ColumnType>) -> Result<Option<ColumnType>, TError> {
+    if !types_compatible(&result_type, &this_type) {
+        return Err(TError::PlanError(DATATYPE_MISMATCH, format!("case types {} and {} cannot be matched", type_name(&result_type), type_name(&this_type))));
+    }
+    Ok(numeric_result_type(&result_type, &this_type).or(result_type).or(this_type))
+}
+
+/**
+ * Arithmetic on ints is done on bigints and on reals with doubles, this converts the
+ * result back to the narrower type, which fails if it doesn't fit.
+ */
+fn narrow(expr: Expression, result_type: &Option<ColumnType>) -> Expression {
+    match result_type {
+        Some(to @ (ColumnType::SmallInt | ColumnType::Int | ColumnType::Real)) => Expression::Cast { expr: Box::new(expr), to: to.clone() },
+        _ => expr,
+    }
+}
+
+/**
+ * A string literal where a value of another type is expected is read as one, so
+ * dates can be written '2026-10-19'. This happens while planning, but bad input is still
+ * the error about the value it would be when the cast ran.
+ */
+fn coerce_literal(expr: Expression, expr_type: Option<ColumnType>, to: &Option<ColumnType>) -> Result<(Expression, Option<ColumnType>), TError> {
+    match (&expr, to) {
+        (Expression::Constant(value @ LiteralValue::StringValue(_)), Some(to)) if *to != ColumnType::Text => {
+            let value = cast(value, to)?;
+            Ok((Expression::Constant(value), Some(to.clone())))
+        },
+        _ => Ok((expr, expr_type)),
+    }
+}
+
+// whether the coercion table allows writing a value to a column, string literals can be written to any
+fn assignable(value: &Expression, value_type: &Option<ColumnType>, column_type: &ColumnType) -> bool {
+    match value_type {
+        _ if matches!(value, Expression::Constant(LiteralValue::StringValue(_))) => true,
+        Some(value_type) => coercion(value_type, column_type).is_some_and(|c| c <= CoercionContext::Assignment),
+        None => true,
+    }
+}
+
+// values written to a column are converted to its type, values of another type or numbers of another precision are cast
+fn cast_to_column(expr: Expression, value_type: &Option<ColumnType>, column_type: &ColumnType) -> Result<Expression, TError> {
+    let (expr, value_type) = coerce_literal(expr, value_type.clone(), &Some(column_type.clone()))?;
+    let needs_cast = value_type.as_ref() != Some(column_type) || matches!(column_type, ColumnType::Decimal(Some(_)));
+    match value_type {
+        Some(_) if needs_cast => Ok(Expression::Cast { expr: Box::new(expr), to: column_type.clone() }),
+        _ => Ok(expr),
+    }
+}
+
+// the common type when both are numbers
+fn numeric_result_type(left: &Option<ColumnType>, right: &Option<ColumnType>) -> Option<ColumnType> {
+    match (left, right) {
+        (Some(left), Some(right)) if is_numeric(left) && is_numeric(right) => Some(common_type(left, right)),
+        _ => None,
+    }
+}
+
+pub fn type_of_literal(value: &LiteralValue) -> Option<ColumnType> {
+    match value {
+        LiteralValue::IntValue(i) if i32::try_from(*i).is_ok() => Some(ColumnType::Int),
+        LiteralValue::IntValue(_) => Some(ColumnType::BigInt),
+        LiteralValue::FloatValue(_) => Some(ColumnType::Double),
+        LiteralValue::DecimalValue(_) => Some(ColumnType::Decimal(None)),
+        LiteralValue::DateValue(_) => Some(ColumnType::Date),
+        LiteralValue::TimeValue(_) => Some(ColumnType::Time),
+        LiteralValue::TimestampValue(_) => Some(ColumnType::Timestamp),
+        LiteralValue::IntervalValue(_) => Some(ColumnType::Interval),
+        LiteralValue::BooleanValue(_) => Some(ColumnType::Bool),
+        LiteralValue::StringValue(_) => Some(ColumnType::Text),
+        LiteralValue::BytesValue(_) => Some(ColumnType::Bytea),
+        LiteralValue::JsonValue(_) => Some(ColumnType::Json),
+        LiteralValue::UuidValue(_) => Some(ColumnType::Uuid),
+        // the type of an array is known from its elements, unless they are all null
+        LiteralValue::ArrayValue(elements) => {
+            let element_type = elements.iter().find_map(type_of_literal)?;
+            Some(ColumnType::Array(Box::new(element_type)))
+        },
+        LiteralValue::NullValue => None,
+    }
+}
+
+// unknown types (nulls) are compatible with everything, others if the coercion table converts one to the other implicitly
+pub fn types_compatible(left: &Option<ColumnType>, right: &Option<ColumnType>) -> bool {
+    match (left, right) {
+        (Some(left), Some(right)) => [coercion(left, right), coercion(right, left)].contains(&Some(CoercionContext::Implicit)),
+        _ => true,
+    }
+}
+
+// a type as messages name it, nulls have no type yet and are unknown like in postgres
+pub fn type_name(t: &Option<ColumnType>) -> String {
+    t.as_ref().map_or(String::from("unknown"), |t| t.to_string())
+}
+
+pub fn is_bool(t: &Option<ColumnType>) -> bool {
+    types_compatible(t, &Some(ColumnType::Bool))
+}
+
+fn plan_alter_table(tables: &[TableSchema], stmt: AlterTableStatement) -> Result<QueryPlan, TError> {
+    let table = stmt.token.lexeme;
+    let mut schema = match tables.iter().find(|t| t.name == table) {
+        Some(schema) => schema.clone(),
+        None => return Err(TError::PlanError(UNDEFINED_TABLE, format!("table {:?} not found", table)).at(stmt.token.span)),
+    };
+    let column_exists = |schema: &TableSchema, name: &str| schema.columns.iter().any(|c| c.name == name);
+
+    let span = stmt.action.span();
+    match stmt.action {
+        AlterTableAction::AddColumn(add) => {
+            let name = add.column.token.lexeme;
+            if column_exists(&schema, &name) {
+                return Err(TError::PlanError(DUPLICATE_COLUMN, format!("column {:?} of table {:?} already exists", name, table)).at(span));
+            }
+            if add.column.identity.is_some() {
+                return Err(TError::PlanError(FEATURE_NOT_SUPPORTED, format!("cannot add identity column {:?} to an existing table", name)).at(span));
+            }
+            let column_type = to_column_type(&add.column.column_type);
+            // the default is also the value of the column in the rows already there
+            let (default, default_expr) = match add.column.default {
+                Some(StoredExpr { expr: Expr::Literal(lit), source }) => {
+                    let value = Expression::Constant(lit.value.clone());
+                    if !assignable(&value, &type_of_literal(&lit.value), &column_type) {
+                        return Err(TError::PlanError(DATATYPE_MISMATCH, format!("default for column {:?} must be a {}", name, column_type)).at(span));
+                    }
+                    // stored as a value of the column, so it has to be converted now
+                    let default = cast(&lit.value, &column_type)?;
+                    (Some(default), Some(source))
+                },
+                Some(_) => return Err(TError::PlanError(FEATURE_NOT_SUPPORTED, format!("default for column {:?} must be a constant", name)).at(span)),
+                None => (None, None),
+            };
+            schema.add_column(name, column_type, default, default_expr);
+        },
+        AlterTableAction::DropColumn(drop) => {
+            let name = drop.token.lexeme;
+            let id = match schema.column_id(&name) {
+                Some(id) => id,
+                None => return Err(TError::PlanError(UNDEFINED_COLUMN, format!("column {:?} of table {:?} does not exist", name, table)).at(span)),
+            };
+            if let Some(index) = schema.indexes.iter().find(|i| i.columns.contains(&id)) {
+                return Err(TError::PlanError(DEPENDENT_OBJECTS_STILL_EXIST, format!("cannot drop column {:?} of table {:?}, it is part of {:?}", name, table, index.name)).at(span));
+            }
+            if let Some(foreign_key) = schema.foreign_keys.iter().find(|f| f.columns.contains(&id)) {
+                return Err(TError::PlanError(DEPENDENT_OBJECTS_STILL_EXIST, format!("cannot drop column {:?} of table {:?}, it is part of {:?}", name, table, foreign_key.name)).at(span));
+            }
+            schema.drop_column(&name);
+            // checks and index expressions using the column don't bind anymore
+            if let Some(check) = schema.checks.iter().find(|c| bind_stored_expr(Some(&schema), &c.expr).is_err()) {
+                return Err(TError::PlanError(DEPENDENT_OBJECTS_STILL_EXIST, format!("cannot drop column {:?} of table {:?}, it is used by {:?}", name, table, check.name)).at(span));
+            }
+            if let Some(index) = schema.indexes.iter().find(|i| i.exprs.iter().any(|e| bind_stored_expr(Some(&schema), e).is_err())) {
+                return Err(TError::PlanError(DEPENDENT_OBJECTS_STILL_EXIST, format!("cannot drop column {:?} of table {:?}, it is used by {:?}", name, table, index.name)).at(span));
+            }
+        },
+        AlterTableAction::RenameColumn(rename) => {
+            let name = rename.token.lexeme;
+            let new_name = rename.new_name.lexeme;
+            if column_exists(&schema, &new_name) {
+                return Err(TError::PlanError(DUPLICATE_COLUMN, format!("column {:?} of table {:?} already exists", new_name, table)).at(span));
+            }
+            for check in &mut schema.checks {
+                check.expr = rename_in_source(&check.expr, &name, &new_name, false)?;
+            }
+            for index in &mut schema.indexes {
+                for expr in &mut index.exprs {
+                    *expr = rename_in_source(expr, &name, &new_name, false)?;
+                }
+            }
+            match schema.columns.iter_mut().find(|c| c.name == name) {
+                Some(column) => column.name = new_name,
+                None => return Err(TError::PlanError(UNDEFINED_COLUMN, format!("column {:?} of table {:?} does not exist", name, table)).at(span)),
+            }
+        },
+        AlterTableAction::RenameTable(rename) => {
+            let new_name = rename.new_name.lexeme;
+            if tables.iter().any(|t| t.name == new_name) {
+                return Err(TError::PlanError(DUPLICATE_TABLE, format!("table {:?} already exists", new_name)).at(span));
+            }
+            // foreign keys of other tables are renamed when the plan is executed
+            for foreign_key in schema.foreign_keys.iter_mut().filter(|f| f.table == table) {
+                foreign_key.table = new_name.clone();
+            }
+            for check in &mut schema.checks {
+                check.expr = rename_in_source(&check.expr, &table, &new_name, true)?;
+            }
+            for index in &mut schema.indexes {
+                for expr in &mut index.exprs {
+                    *expr = rename_in_source(expr, &table, &new_name, true)?;
+                }
+            }
+            schema.name = new_name;
+        },
+    }
+    Ok(QueryPlan::AlterTablePlan(AlterTablePlan { table, schema }))
+}
+
+// works out the indexes backing the primary key and unique constraints, and the foreign keys
+fn plan_create_table(tables: &[TableSchema], mut stmt: CreateTableStatement) -> Result<QueryPlan, TError> {
+    let sequences = plan_identity_columns(&mut stmt)?;
+    let table = &stmt.token.lexeme;
+    let mut indexes: Vec<IndexSchema> = Vec::new();
+    for constraint in &stmt.constraints {
+        let (columns, primary) = match constraint {
+            TableConstraint::PrimaryKey(key) => (&key.columns, true),
+            TableConstraint::Unique(key) => (&key.columns, false),
+            TableConstraint::ForeignKey(_) | TableConstraint::Check(_) => continue,
+        };
+        if primary && indexes.iter().any(|i| i.primary) {
+            return Err(TError::PlanError(INVALID_TABLE_DEFINITION, format!("multiple primary keys for table {:?} are not allowed", table)).at(constraint.span()));
+        }
+        let mut ids = Vec::new();
+        for column in columns {
+            // columns get their position as id when the table is created
+            let id = match stmt.columns.iter().position(|c| c.token.lexeme == column.lexeme) {
+                Some(id) => u16::try_from(id).unwrap(),
+                None => return Err(TError::PlanError(UNDEFINED_COLUMN, format!("column {:?} named in key does not exist", column.lexeme)).at(column.span)),
+            };
+            if ids.contains(&id) {
+                return Err(TError::PlanError(DUPLICATE_COLUMN, format!("column {:?} appears twice in key", column.lexeme)).at(constraint.span()));
+            }
+            ids.push(id);
+        }
+
+        let name = if primary {
+            format!("{}_pkey", table)
+        } else {
+            constraint_name(table, columns, "key", |name| indexes.iter().any(|i| i.name == name))
+        };
+        indexes.push(IndexSchema { name, columns: ids, exprs: Vec::new(), unique: true, primary, root_page_id: 0 });
+    }
+
+    let mut foreign_keys: Vec<ForeignKeySchema> = Vec::new();
+    for constraint in &stmt.constraints {
+        if let TableConstraint::ForeignKey(constraint) = constraint {
+            let mut foreign_key = plan_foreign_key(tables, &stmt, &indexes, constraint).map_err(|e| e.at(constraint.span))?;
+            foreign_key.name = constraint_name(table, &constraint.columns, "fkey", |name| {
+                indexes.iter().any(|i| i.name == name) || foreign_keys.iter().any(|f| f.name == name)
+            });
+            foreign_keys.push(foreign_key);
+        }
+    }
+
+    for column in &stmt.columns {
+        if let Some(default) = &column.default {
+            let (value, default_type) = bind_stored_expr(None, &default.source).map_err(|e| e.at(default.expr.span()))?;
+            let column_type = to_column_type(&column.column_type);
+            if !assignable(&value, &default_type, &column_type) {
+                return Err(TError::PlanError(DATATYPE_MISMATCH, format!("default for column {:?} must be a {}", column.token.lexeme, column_type)).at(column.span));
+            }
+            cast_to_column(value, &default_type, &column_type).map_err(|e| e.at(column.span))?;
+        }
+    }
+
+    // checks are bound against the new table to see they are valid
+    let columns = stmt.columns.iter().map(|c| Column::new(c.token.lexeme.clone(), to_column_type(&c.column_type))).collect();
+    let schema = TableSchema::new(table.clone(), columns, 0);
+    let mut checks: Vec<CheckSchema> = Vec::new();
+    for constraint in &stmt.constraints {
+        if let TableConstraint::Check(check) = constraint {
+            let (_, check_type) = bind_stored_expr(Some(&schema), &check.expr.source).map_err(|e| e.at(check.span))?;
+            if !is_bool(&check_type) {
+                return Err(TError::PlanError(DATATYPE_MISMATCH, format!("argument of check must be a bool, not {}", type_name(&check_type))).at(check.span));
+            }
+            let columns: Vec<Token> = check.column.iter().cloned().collect();
+            let name = constraint_name(table, &columns, "check", |name| checks.iter().any(|c| c.name == name));
+            checks.push(CheckSchema { name, expr: check.expr.source.clone() });
+        }
+    }
+    Ok(QueryPlan::CreateTablePlan(CreateTablePlan { stmt, indexes, foreign_keys, checks, sequences }))
+}
+
+// identity columns get a sequence of their own, named table_column_seq, which their default takes values from
+fn plan_identity_columns(stmt: &mut CreateTableStatement) -> Result<Vec<SequenceSchema>, TError> {
+    let table = &stmt.token.lexeme;
+    let mut sequences = Vec::new();
+    for column in &mut stmt.columns {
+        let identity = match &column.identity {
+            Some(identity) => identity,
+            None => continue,
+        };
+        let name = &column.token.lexeme;
+        if !is_integer(&to_column_type(&column.column_type)) {
+            return Err(TError::PlanError(INVALID_TABLE_DEFINITION, format!("identity column {:?} must be a smallint, int or bigint", name)).at(column.span));
+        }
+        if column.default.is_some() {
+            return Err(TError::PlanError(INVALID_TABLE_DEFINITION, format!("both default and identity specified for column {:?}", name)).at(column.span));
+        }
+        if identity.options.increment == Some(0) {
+            return Err(TError::PlanError(INVALID_PARAMETER_VALUE, String::from("increment must not be zero")).at(identity.span));
+        }
+        let sequence = SequenceSchema::new(format!("{}_{}_seq", table, name), identity.options.start, identity.options.increment, Some(table.clone()));
+        let source = format!("nextval ( '{}' )", quote_identifier(&sequence.name).replace('\'', "''"));
+        let mut scanner = Scanner::new();
+        scanner.scan(&source)?;
+        column.default = Some(StoredExpr { expr: Parser::new().parse_expr(&scanner.tokens)?, source });
+        sequences.push(sequence);
+    }
+    Ok(sequences)
+}
+
+// table_columns_suffix, with a number added if the name is taken
+fn constraint_name(table: &str, columns: &[Token], suffix: &str, taken: impl Fn(&str) -> bool) -> String {
+    let mut parts = vec![table];
+    parts.extend(columns.iter().map(|c| c.lexeme.as_str()));
+    parts.push(suffix);
+    let base_name = parts.join("_");
+    let mut name = base_name.clone();
+    let mut number = 1;
+    while taken(&name) {
+        name = format!("{}{}", base_name, number);
+        number += 1;
+    }
+    name
+}
+
+// the referenced table can be the one being created, then its columns and indexes come from the statement
+fn plan_foreign_key(tables: &[TableSchema], stmt: &CreateTableStatement, indexes: &[IndexSchema], foreign_key: &ast::ForeignKey) -> Result<ForeignKeySchema, TError> {
+    let referenced = &foreign_key.table.lexeme;
+    let (referenced_columns, referenced_indexes): (Vec<(String, u16, ColumnType)>, &[IndexSchema]) = if *referenced == stmt.token.lexeme {
+        let columns = stmt.columns.iter().enumerate()
+            .map(|(id, c)| (c.token.lexeme.clone(), u16::try_from(id).unwrap(), to_column_type(&c.column_type)))
+            .collect();
+        (columns, indexes)
+    } else {
+        match tables.iter().find(|t| t.name == *referenced) {
+            Some(t) => (t.columns.iter().map(|c| (c.name.clone(), c.id, c.column_type.clone())).collect(), &t.indexes),
+            None => return Err(TError::PlanError(UNDEFINED_TABLE, format!("referenced table {:?} not found", referenced))),
+        }
+    };
+
+    let mut ids = Vec::new();
+    for column in &foreign_key.columns {
+        match stmt.columns.iter().position(|c| c.token.lexeme == column.lexeme) {
+            Some(id) => ids.push(u16::try_from(id).unwrap()),
+            None => return Err(TError::PlanError(UNDEFINED_COLUMN, format!("column {:?} named in foreign key does not exist", column.lexeme))),
+        }
+    }
+    // no referenced columns means the primary key
+    let referenced_ids: Vec<u16> = if foreign_key.referenced_columns.is_empty() {
+        match referenced_indexes.iter().find(|i| i.primary) {
+            Some(index) => index.columns.clone(),
+            None => return Err(TError::PlanError(INVALID_FOREIGN_KEY, format!("table {:?} has no primary key to reference", referenced))),
+        }
+    } else {
+        let mut referenced_ids = Vec::new();
+        for column in &foreign_key.referenced_columns {
+            match referenced_columns.iter().find(|(name, _, _)| *name == column.lexeme) {
+                Some((_, id, _)) => referenced_ids.push(*id),
+                None => return Err(TError::PlanError(UNDEFINED_COLUMN, format!("column {:?} of table {:?} does not exist", column.lexeme, referenced))),
+            }
+        }
+        referenced_ids
+    };
+    if ids.len() != referenced_ids.len() {
+        return Err(TError::PlanError(INVALID_FOREIGN_KEY, String::from("number of referencing and referenced columns for foreign key disagree")));
+    }
+    for (id, referenced_id) in ids.iter().zip(&referenced_ids) {
+        let column = &stmt.columns[usize::from(*id)];
+        let column_type = to_column_type(&column.column_type);
+        let (name, _, referenced_type) = referenced_columns.iter().find(|(_, id, _)| id == referenced_id).unwrap();
+        // cascaded values are copied as they are, so they have to fit in the same type
+        if !same_storage(&column_type, referenced_type) {
+            return Err(TError::PlanError(DATATYPE_MISMATCH, format!(
+                "foreign key column {:?} is a {} but {:?} of table {:?} is a {}", column.token.lexeme, column_type, name, referenced, referenced_type
+            )));
         }
+    }
+
+    let is_match = |index: &&IndexSchema| {
+        index.unique && index.columns.len() == referenced_ids.len() && index.columns.iter().all(|id| referenced_ids.contains(id))
     };
-    if table_schema.is_none() {
-        return Err(TError::PlanError(format!("table {:?} not found", table_name)));
+    let index = match referenced_indexes.iter().find(is_match) {
+        Some(index) => index,
+        None => return Err(TError::PlanError(INVALID_FOREIGN_KEY, format!("there is no unique constraint matching the referenced columns of table {:?}", referenced))),
     };
+    // put the columns in the order of the index, so rows can be looked up in it
+    let columns = index.columns.iter().map(|referenced_id| {
+        ids[referenced_ids.iter().position(|id| id == referenced_id).unwrap()]
+    }).collect();
 
-    let table_schema = table_schema.unwrap();
+    Ok(ForeignKeySchema {
+        name: String::new(),
+        columns,
+        table: referenced.clone(),
+        referenced_columns: index.columns.clone(),
+        on_delete: to_referential_action(foreign_key.on_delete),
+        on_update: to_referential_action(foreign_key.on_update),
+    })
+}
+
+fn same_storage(left: &ColumnType, right: &ColumnType) -> bool {
+    match (left, right) {
+        (ColumnType::Decimal(_), ColumnType::Decimal(_)) => true,
+        _ => left == right,
+    }
+}
+
+fn to_referential_action(action: ast::ReferentialAction) -> ReferentialAction {
+    match action {
+        ast::ReferentialAction::Restrict => ReferentialAction::Restrict,
+        ast::ReferentialAction::Cascade => ReferentialAction::Cascade,
+        ast::ReferentialAction::SetNull => ReferentialAction::SetNull,
+    }
+}
+
+// the columns of a table, for expressions evaluated against its rows
+fn table_scope(schema: &TableSchema) -> Vec<ScopeColumn> {
+    schema.columns.iter()
+        .map(|c| ScopeColumn { table: schema.name.clone(), name: c.name.clone(), column_type: Some(c.column_type.clone()) })
+        .collect()
+}
+
+// the where clause of an update or delete
+fn bind_predicate(ctx: &PlanContext, scope: &[ScopeColumn], where_clause: &Option<Expr>) -> Result<Option<Expression>, TError> {
+    match where_clause {
+        Some(where_clause) => {
+            let (predicate, predicate_type) = bind_expr(ctx, &[scope], where_clause)?;
+            if !is_bool(&predicate_type) {
+                return Err(TError::PlanError(DATATYPE_MISMATCH, format!("argument of where must be a bool, not {}", type_name(&predicate_type))));
+            }
+            Ok(Some(predicate))
+        },
+        None => Ok(None),
+    }
+}
+
+fn plan_update(tables: &Vec<TableSchema>, stmt: UpdateStatement) -> Result<QueryPlan, TError> {
+    let table = stmt.token.lexeme;
+    let schema = match tables.iter().find(|t| t.name == table) {
+        Some(schema) => schema,
+        None => return Err(TError::PlanError(UNDEFINED_TABLE, format!("table {:?} not found", table)).at(stmt.token.span)),
+    };
+    let ctx = PlanContext { tables, ctes: Vec::new() };
+    let scope = table_scope(schema);
+    let predicate = bind_predicate(&ctx, &scope, &stmt.where_clause)?;
+
+    let mut assignments: Vec<(usize, Expression)> = Vec::new();
+    for assignment in &stmt.assignments {
+        let name = &assignment.column.lexeme;
+        let index = match schema.columns.iter().position(|c| &c.name == name) {
+            Some(index) => index,
+            None => return Err(TError::PlanError(UNDEFINED_COLUMN, format!("column {:?} of table {:?} does not exist", name, table)).at(assignment.span)),
+        };
+        if assignments.iter().any(|(i, _)| *i == index) {
+            return Err(TError::PlanError(SYNTAX_ERROR, format!("multiple assignments to column {:?}", name)).at(assignment.span));
+        }
+        if schema.columns[index].generated_always {
+            return Err(TError::PlanError(GENERATED_ALWAYS, format!("column {:?} is an identity column defined as generated always and can't be updated", name)).at(assignment.span));
+        }
+        let column_type = &schema.columns[index].column_type;
+        let (value, value_type) = bind_expr(&ctx, &[&scope], &assignment.value)?;
+        if !assignable(&value, &value_type, column_type) {
+            return Err(TError::PlanError(DATATYPE_MISMATCH, format!("column {:?} is a {} but the expression is a {}", name, column_type, type_name(&value_type))).at(assignment.span));
+        }
+        assignments.push((index, cast_to_column(value, &value_type, column_type)?));
+    }
+    Ok(QueryPlan::UpdatePlan(UpdatePlan { table, assignments, predicate }))
+}
+
+fn plan_delete(tables: &Vec<TableSchema>, stmt: DeleteStatement) -> Result<QueryPlan, TError> {
+    let table = stmt.token.lexeme;
+    let schema = match tables.iter().find(|t| t.name == table) {
+        Some(schema) => schema,
+        None => return Err(TError::PlanError(UNDEFINED_TABLE, format!("table {:?} not found", table)).at(stmt.token.span)),
+    };
+    let ctx = PlanContext { tables, ctes: Vec::new() };
+    let predicate = bind_predicate(&ctx, &table_scope(schema), &stmt.where_clause)?;
+    Ok(QueryPlan::DeletePlan(DeletePlan { table, predicate }))
+}
+
+pub fn to_column_type(column_type: &ast::ColumnType) -> ColumnType {
+    match column_type {
+        ast::ColumnType::Bool => ColumnType::Bool,
+        ast::ColumnType::SmallInt => ColumnType::SmallInt,
+        ast::ColumnType::Int => ColumnType::Int,
+        ast::ColumnType::BigInt => ColumnType::BigInt,
+        ast::ColumnType::Real => ColumnType::Real,
+        ast::ColumnType::Double => ColumnType::Double,
+        ast::ColumnType::Decimal(params) => ColumnType::Decimal(*params),
+        ast::ColumnType::Date => ColumnType::Date,
+        ast::ColumnType::Time => ColumnType::Time,
+        ast::ColumnType::Timestamp => ColumnType::Timestamp,
+        ast::ColumnType::Interval => ColumnType::Interval,
+        ast::ColumnType::Text => ColumnType::Text,
+        ast::ColumnType::Bytea => ColumnType::Bytea,
+        ast::ColumnType::Json => ColumnType::Json,
+        ast::ColumnType::Uuid => ColumnType::Uuid,
+        ast::ColumnType::Array(element_type) => ColumnType::Array(Box::new(to_column_type(element_type))),
+    }
+}
+
+fn plan_insert(tables: &mut Vec<TableSchema>, stmt: InsertStatement) -> Result<QueryPlan, TError> {
+    let table_name = stmt.token.lexeme;
+    let table_schema = match tables.iter().find(|t| t.name == table_name) {
+        Some(table_schema) => table_schema,
+        None => return Err(TError::PlanError(UNDEFINED_TABLE, format!("table {:?} not found", table_name)).at(stmt.token.span)),
+    };
 
     // check columns
-    let name_to_type = table_schema.columns.iter().map(|c| (c.name.clone(), c.column_type.clone())).collect::<HashMap<String, ColumnType>>();
-    
-    for c in &stmt.columns {
-        if name_to_type.get(c).is_none() {
-            return Err(TError::PlanError(format!("no column called {:?} found for table {:?}", c, table_name)));
+    for (i, c) in stmt.columns.iter().enumerate() {
+        if !table_schema.columns.iter().any(|column| column.name == *c) {
+            return Err(TError::PlanError(UNDEFINED_COLUMN, format!("no column called {:?} found for table {:?}", c, table_name)));
+        }
+        if stmt.columns[..i].contains(c) {
+            return Err(TError::PlanError(DUPLICATE_COLUMN, format!("column {:?} specified more than once", c)));
+        }
+        if table_schema.columns.iter().any(|column| column.name == *c && column.generated_always) {
+            return Err(TError::PlanError(GENERATED_ALWAYS, format!("cannot insert into column {:?}, it is an identity column defined as generated always", c)));
         }
     }
 
-    let mut ordered_values: Vec<Vec<Expr>> = Vec::new();
+    let ctx = PlanContext { tables, ctes: Vec::new() };
+    let mut ordered_values: Vec<Vec<Expression>> = Vec::new();
 
     let num_cols = stmt.columns.len();
-    // this is kinda dumb
     for row in &stmt.values {
         if row.len() != num_cols {
-            return Err(TError::PlanError(format!("expected {:?} values for row {:?}", num_cols, table_name)));
+            return Err(TError::PlanError(SYNTAX_ERROR, format!("expected {:?} values for row {:?}", num_cols, table_name)));
         }
-        let mut col_to_expr = HashMap::new();
-        for (value_col, col_name) in row.iter().zip(stmt.columns.iter()) {
-            let expected_type = name_to_type.get(col_name).unwrap();
-            let expr_type = &type_of(value_col);
-            if std::mem::discriminant(expected_type) != std::mem::discriminant(expr_type) {
-                return Err(TError::PlanError(format!("expected a {:?} got a {:?}", expected_type, expr_type)));
-            }
-            col_to_expr.insert(col_name, value_col);
+        let mut ordered_value = Vec::new();
+        for column in &table_schema.columns {
+            let value = match stmt.columns.iter().position(|c| *c == column.name) {
+                Some(position) => {
+                    let (value, value_type) = bind_expr(&ctx, &[], &row[position])?;
+                    if !assignable(&value, &value_type, &column.column_type) {
+                        return Err(TError::PlanError(DATATYPE_MISMATCH, format!("expected a {} got a {}", column.column_type, type_name(&value_type))));
+                    }
+                    cast_to_column(value, &value_type, &column.column_type)?
+                },
+                // columns left out get their default, or null
+                None => match &column.default_expr {
+                    Some(source) => {
+                        let (value, value_type) = bind_stored_expr(None, source)?;
+                        cast_to_column(value, &value_type, &column.column_type)?
+                    },
+                    None => Expression::Constant(LiteralValue::NullValue),
+                },
+            };
+            ordered_value.push(value);
         }
-
-        let ordered_value = table_schema.columns.iter().map(|c| (*(*col_to_expr.get(&c.name).unwrap())).clone() ).collect::<Vec<Expr>>();
-        ordered_values.push(
-            ordered_value
-        );
-        
+        ordered_values.push(ordered_value);
     }
     Ok(QueryPlan::InsertPlan(InsertPlan {table: table_name, values: ordered_values}))
+}
 
+/**
+ * Expressions in the catalog are stored as their source, this parses and binds one again,
+ * against the columns of the table or against no columns at all for defaults.
+ */
+pub fn bind_stored_expr(schema: Option<&TableSchema>, source: &str) -> Result<(Expression, Option<ColumnType>), TError> {
+    let mut scanner = Scanner::new();
+    // spans would point into the stored source rather than the statement being planned
+    scanner.scan(&source.to_owned()).map_err(TError::unspanned)?;
+    let expr = Parser::new().parse_expr(&scanner.tokens).map_err(TError::unspanned)?;
+    let tables = Vec::new();
+    let ctx = PlanContext { tables: &tables, ctes: Vec::new() };
+    let res = match schema {
+        Some(schema) => bind_expr(&ctx, &[&table_scope(schema)], &expr),
+        None => bind_expr(&ctx, &[], &expr),
+    };
+    res.map_err(TError::unspanned)
 }
 
-fn type_of(expr: &Expr) -> ColumnType {
-    match expr {
-        Expr::ColumnReference(_) => todo!(),
-        Expr::Literal(lit) => match lit.value {
-            crate::parse::token::LiteralValue::IntValue(_) => ColumnType::Int,
-            crate::parse::token::LiteralValue::StringValue(_) => todo!(),
-            crate::parse::token::LiteralValue::BooleanValue(_) => ColumnType::Bool,
-            crate::parse::token::LiteralValue::NullValue => todo!(),
+// replaces an identifier in the source of a stored expression, table names are the ones followed by a '.'
+// and function names the ones followed by a '('
+fn rename_in_source(source: &str, old: &str, new: &str, table: bool) -> Result<String, TError> {
+    let mut scanner = Scanner::new();
+    scanner.scan(&source.to_owned())?;
+    let tokens = &scanner.tokens;
+    let lexemes: Vec<Cow<str>> = tokens.iter().enumerate()
+        .filter(|(_, token)| !matches!(token.token_type, TokenType::EOF))
+        .map(|(i, token)| {
+            let next = tokens.get(i + 1).map(|t| &t.token_type);
+            let followed_by_dot = matches!(next, Some(TokenType::Dot));
+            let function = matches!(next, Some(TokenType::LeftParen));
+            // the field of extract ( field from ... ) isn't a column either
+            let field = i >= 2 && matches!(tokens[i - 2].token_type, TokenType::Extract);
+            if matches!(token.token_type, TokenType::Identifier) && token.lexeme == old && followed_by_dot == table && !function && !field {
+                quote_identifier(new)
+            } else {
+                token.source()
+            }
+        })
+        .collect();
+    Ok(lexemes.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{catalog::table_schema::{Column, ColumnType, TableSchema}, parse::{parser::Parser, scanner::{Scanner, TError}}};
+
+    use super::plan;
+
+    fn plan_sql(sql: &str) -> Result<(), TError> {
+        let mut tables = vec![
+            TableSchema::new(String::from("t"), vec![Column::new(String::from("a"), ColumnType::Int), Column::new(String::from("b"), ColumnType::Bool)], 1),
+        ];
+        let mut scanner = Scanner::new();
+        scanner.scan(&sql.to_owned()).unwrap();
+        let stmt = Parser::new().parse(&scanner.tokens).unwrap().pop().unwrap();
+        plan(&mut tables, stmt).map(|_| ()).map_err(TError::unspanned)
+    }
+
+    // the part of the statement an error points at
+    fn error_source(sql: &str) -> String {
+        let mut tables = vec![
+            TableSchema::new(String::from("t"), vec![Column::new(String::from("a"), ColumnType::Int), Column::new(String::from("b"), ColumnType::Bool)], 1),
+        ];
+        let mut scanner = Scanner::new();
+        scanner.scan(&sql.to_owned()).unwrap();
+        let stmt = Parser::new().parse(&scanner.tokens).unwrap().pop().unwrap();
+        match plan(&mut tables, stmt) {
+            Err(TError::Spanned(_, span)) => sql[span.start..span.end].to_owned(),
+            _ => panic!("expected an error with a position: {}", sql),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn errors_point_at_the_clause() {
+        assert_eq!(error_source("select a from t union select a, b from t;"), "select a from t union select a, b from t");
+        assert_eq!(error_source("select 1 from (select a from t union select b from t) s;"), "select a from t union select b from t");
+        assert_eq!(error_source("select case when a then 1 end from t;"), "when a then 1");
+        assert_eq!(error_source("create table u (a int, b int generated always as identity (increment 0));"), "generated always as identity (increment 0)");
+        assert_eq!(error_source("create table u (a int, check (a + 1));"), "check (a + 1)");
+        assert_eq!(error_source("create table u (a int references v);"), "references v");
+        assert_eq!(error_source("alter table t rename column c to d;"), "rename column c to d");
+        assert_eq!(error_source("update t set a = 1, a = 2;"), "a = 2");
+        assert_eq!(error_source("truncate v;"), "truncate v");
+    }
+
+    #[test]
+    fn set_operation_column_checks() {
+        assert!(plan_sql("select a, b from t union select a, null from t;").is_ok());
+        assert!(matches!(plan_sql("select a, b from t union select a from t;"), Err(TError::PlanError(..))));
+        assert!(matches!(plan_sql("select a from t except select b from t;"), Err(TError::PlanError(..))));
+    }
+
+    #[test]
+    fn cte_checks() {
+        assert!(plan_sql("with x as (select a from t), y as (select a from x) select a from y;").is_ok());
+        // a cte can't reference itself without recursive, or ones defined after it
+        assert!(matches!(plan_sql("with x as (select a from x) select a from x;"), Err(TError::PlanError(..))));
+        assert!(matches!(plan_sql("with x as (select a from y), y as (select a from t) select a from x;"), Err(TError::PlanError(..))));
+        assert!(matches!(plan_sql("with x as (select a from t), x as (select a from t) select a from x;"), Err(TError::PlanError(..))));
+        assert!(matches!(plan_sql("with recursive x as (select a from x union select a from t) select a from x;"), Err(TError::PlanError(..))));
+        assert!(matches!(plan_sql("with recursive x as (select a from t union select b from x) select a from x;"), Err(TError::PlanError(..))));
+    }
+
+    #[test]
+    fn expression_type_checks() {
+        assert!(plan_sql("select distinct case when b then 'yes' when a > 1 then null else 'no' end from t where a in (1, 2) and a between 0 and 3 and 'x' like 'x%';").is_ok());
+        assert!(plan_sql("select case a when 1 then true else b end from t;").is_ok());
+        assert!(matches!(plan_sql("select case when a then 1 end from t;"), Err(TError::PlanError(..))));
+        assert!(matches!(plan_sql("select case a when true then 1 end from t;"), Err(TError::PlanError(..))));
+        assert!(matches!(plan_sql("select case when b then 1 else 'no' end from t;"), Err(TError::PlanError(..))));
+        assert!(matches!(plan_sql("select a from t where a in (1, true);"), Err(TError::PlanError(..))));
+        assert!(matches!(plan_sql("select a from t where a between 1 and b;"), Err(TError::PlanError(..))));
+        assert!(matches!(plan_sql("select a from t where a like '1';"), Err(TError::PlanError(..))));
+    }
+
+    #[test]
+    fn errors_point_at_the_source() {
+        let error_at = |sql: &str| {
+            let mut tables = vec![TableSchema::new(String::from("t"), vec![Column::new(String::from("a"), ColumnType::Int)], 1)];
+            let mut scanner = Scanner::new();
+            scanner.scan(&sql.to_owned()).unwrap();
+            let stmt = Parser::new().parse(&scanner.tokens).unwrap().pop().unwrap();
+            match plan(&mut tables, stmt) {
+                Err(TError::Spanned(_, span)) => sql[span.start..span.end].to_owned(),
+                _ => panic!("expected an error with a span for {}", sql),
+            }
+        };
+        assert_eq!(error_at("select a + nope from t;"), "nope");
+        assert_eq!(error_at("select a from t where a = 1 and (a + 'x') > 2;"), "a + 'x'");
+        assert_eq!(error_at("select nope(a) from t;"), "nope(a)");
+        assert_eq!(error_at("select a from u;"), "u");
+        assert_eq!(error_at("update u set a = 1;"), "u");
+    }
+}
@@ -1,21 +1,86 @@
-use crate::{catalog::table_schema::TableSchema, execution::operators::Projection, parse::ast::{CreateTableStatement, Expr}};
+use crate::{catalog::{sequence_schema::SequenceSchema, table_schema::{CheckSchema, ForeignKeySchema, IndexSchema, TableSchema}}, execution::{expression::Expression, operators::Operator}, parse::ast::CreateTableStatement};
 
 
 pub enum QueryPlan {
     CreateTablePlan(CreateTablePlan),
     InsertPlan(InsertPlan),
     SelectPlan(SelectPlan),
+    DropTablePlan(DropTablePlan),
+    TruncatePlan(TruncatePlan),
+    AlterTablePlan(AlterTablePlan),
+    UpdatePlan(UpdatePlan),
+    DeletePlan(DeletePlan),
+    CreateSequencePlan(CreateSequencePlan),
+    DropSequencePlan(DropSequencePlan),
+    CreateIndexPlan(CreateIndexPlan),
+    DropIndexPlan(DropIndexPlan),
 }
 
 pub struct CreateTablePlan {
-    pub stmt: CreateTableStatement
+    pub stmt: CreateTableStatement,
+    // one per primary key and unique constraint, their pages are allocated when the table is created
+    pub indexes: Vec<IndexSchema>,
+    pub foreign_keys: Vec<ForeignKeySchema>,
+    pub checks: Vec<CheckSchema>,
+    // behind the identity columns
+    pub sequences: Vec<SequenceSchema>,
 }
 
 pub struct InsertPlan {
     pub table: String,
-    pub values: Vec<Vec<Expr>>,
+    // a value for every column of the table, in order
+    pub values: Vec<Vec<Expression>>,
+}
+
+pub struct DropTablePlan {
+    pub table: String,
+    pub if_exists: bool,
+}
+
+pub struct CreateSequencePlan {
+    pub sequence: SequenceSchema,
+}
+
+pub struct DropSequencePlan {
+    pub name: String,
+    pub if_exists: bool,
+}
+
+pub struct CreateIndexPlan {
+    pub table: String,
+    // its pages are allocated when the index is created
+    pub index: IndexSchema,
+}
+
+pub struct DropIndexPlan {
+    // None when the index doesn't exist and the statement said if exists
+    pub table: Option<String>,
+    pub name: String,
+}
+
+pub struct TruncatePlan {
+    pub table: String,
+}
+
+pub struct AlterTablePlan {
+    pub table: String,
+    // the schema after the change
+    pub schema: TableSchema,
+}
+
+pub struct UpdatePlan {
+    pub table: String,
+    // position of the column in the table and its new value, evaluated against the old row
+    pub assignments: Vec<(usize, Expression)>,
+    pub predicate: Option<Expression>,
+}
+
+pub struct DeletePlan {
+    pub table: String,
+    pub predicate: Option<Expression>,
 }
 
 pub struct SelectPlan {
-    pub projection: Projection
+    pub root: Box<dyn Operator>,
+    pub columns: Vec<String>, // names for the result set header
 }
\ No newline at end of file
@@ -0,0 +1,5 @@
+pub mod completion;
+pub mod editor;
+pub mod history;
+pub mod line_reader;
+pub mod terminal;
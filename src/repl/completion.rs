@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use crate::{catalog::table_schema::TableSchema, parse::{scanner::{is_identifier_char, quote_identifier, Scanner, KEYWORDS, NON_RESERVED_KEYWORDS}, token::{Token, TokenType}}};
+
+// what tab can replace the word before the cursor with
+#[derive(Debug, PartialEq)]
+pub struct Completion {
+    // byte offset in the line where the word starts
+    pub start: usize,
+    pub candidates: Vec<String>,
+}
+
+/**
+ * Completes the word that ends at the cursor, where statement is the text of the earlier lines of the
+ * same statement. After "name." the candidates are the columns of that table or alias, where a table
+ * is expected they are the tables, and elsewhere the columns of the tables the statement mentions.
+ * Keywords are offered once a letter of the word is typed.
+ */
+pub fn complete(statement: &str, line: &str, cursor: usize, tables: &[TableSchema]) -> Completion {
+    let mut start = line[..cursor].char_indices().rev()
+        .take_while(|(_, c)| is_identifier_char(*c))
+        .last()
+        .map_or(cursor, |(i, _)| i);
+    // an odd number of quotes means the word is the start of a quoted identifier
+    let quoted = line[..start].matches('"').count() % 2 == 1 && line[..start].ends_with('"');
+    if quoted {
+        start -= 1;
+    }
+    let word = line[start..cursor].trim_start_matches('"');
+
+    let before = format!("{}{}", statement, &line[..start]);
+    let mut scanner = Scanner::new();
+    // in a string or a comment there is nothing to complete
+    if scanner.scan(&before).is_err() || ends_in_comment(&before) {
+        return Completion { start, candidates: Vec::new() };
+    }
+    let tokens = &scanner.tokens[..scanner.tokens.len() - 1];
+
+    let whole = format!("{}{}", statement, line);
+    let mut whole_scanner = Scanner::new();
+    let mentioned = match whole_scanner.scan(&whole) {
+        Ok(()) => mentioned_tables(&whole_scanner.tokens, tables),
+        Err(_) => mentioned_tables(tokens, tables),
+    };
+
+    let table_names = || tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+    let (names, keywords) = match tokens {
+        [] => (Vec::new(), true),
+        [.., qualifier, dot] if matches!(dot.token_type, TokenType::Dot) => {
+            let table = mentioned.get(qualifier.lexeme.as_str()).copied()
+                .or_else(|| tables.iter().find(|t| t.name == qualifier.lexeme));
+            (table.map(column_names).unwrap_or_default(), false)
+        },
+        [.., last] if matches!(last.token_type, TokenType::From | TokenType::Into | TokenType::Update | TokenType::Table
+            | TokenType::Truncate | TokenType::References) => (table_names(), false),
+        [.., last] if matches!(last.token_type, TokenType::Comma) && in_from_list(tokens) => (table_names(), false),
+        // ON of CREATE INDEX is followed by a table, ON of a foreign key by DELETE
+        [.., last] if matches!(last.token_type, TokenType::On) => (table_names(), !word.is_empty()),
+        _ => {
+            let mut names = Vec::new();
+            let mut seen = Vec::new();
+            // before FROM is typed, every column could be meant
+            let referenced: Vec<&TableSchema> = if mentioned.is_empty() { tables.iter().collect() } else { mentioned.values().copied().collect() };
+            for table in referenced {
+                if seen.iter().any(|t: &&TableSchema| t.name == table.name) {
+                    continue;
+                }
+                seen.push(table);
+                names.extend(column_names(table));
+            }
+            (names, !word.is_empty())
+        },
+    };
+
+    let mut candidates: Vec<String> = names.into_iter()
+        .filter(|name| if quoted { name.starts_with(word) } else { name.to_lowercase().starts_with(&word.to_lowercase()) })
+        .map(|name| if quoted { format!("\"{}\"", name.replace('"', "\"\"")) } else { quote_identifier(name).into_owned() })
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+    if keywords && !quoted {
+        let upper = word.chars().any(char::is_uppercase) && !word.chars().any(char::is_lowercase);
+        let mut matching: Vec<String> = KEYWORDS.iter()
+            .map(|(keyword, _)| *keyword)
+            .chain(NON_RESERVED_KEYWORDS.iter().copied())
+            .filter(|keyword| keyword.starts_with(&word.to_lowercase()))
+            .map(|keyword| if upper { keyword.to_uppercase() } else { keyword.to_owned() })
+            .collect();
+        matching.sort();
+        candidates.extend(matching);
+    }
+    Completion { start, candidates }
+}
+
+fn column_names(table: &TableSchema) -> Vec<&str> {
+    table.columns.iter().map(|c| c.name.as_str()).collect()
+}
+
+// the tables named in the statement, by their name and by the alias that follows them
+fn mentioned_tables<'t>(tokens: &[Token], tables: &'t [TableSchema]) -> HashMap<String, &'t TableSchema> {
+    let mut mentioned = HashMap::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if !matches!(token.token_type, TokenType::Identifier) || (i > 0 && matches!(tokens[i - 1].token_type, TokenType::Dot)) {
+            continue;
+        }
+        let Some(table) = tables.iter().find(|t| t.name == token.lexeme) else { continue };
+        mentioned.insert(table.name.clone(), table);
+        let alias = match tokens.get(i + 1..i + 3) {
+            Some([as_, alias]) if matches!(as_.token_type, TokenType::As) => Some(alias),
+            _ => tokens.get(i + 1),
+        };
+        if let Some(alias) = alias.filter(|a| matches!(a.token_type, TokenType::Identifier)) {
+            mentioned.insert(alias.lexeme.clone(), table);
+        }
+    }
+    mentioned
+}
+
+// whether the last token is in the list of tables after FROM, like "FROM a, b,"
+fn in_from_list(tokens: &[Token]) -> bool {
+    let mut depth = 0;
+    for token in tokens.iter().rev() {
+        match token.token_type {
+            TokenType::RightParen => depth += 1,
+            TokenType::LeftParen if depth == 0 => return false,
+            TokenType::LeftParen => depth -= 1,
+            _ if depth > 0 => {},
+            TokenType::From => return true,
+            TokenType::Identifier | TokenType::Comma | TokenType::As => {},
+            _ => return false,
+        }
+    }
+    false
+}
+
+// the scanner skips comments, so a "--" on the last line has to be found here
+fn ends_in_comment(source: &str) -> bool {
+    let last_line = source.rsplit('\n').next().unwrap_or("");
+    let mut in_string = false;
+    let mut chars = last_line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => in_string = !in_string,
+            '-' if !in_string && chars.peek() == Some(&'-') => return true,
+            _ => {},
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::catalog::table_schema::{Column, ColumnType, TableSchema};
+
+    use super::complete;
+
+    fn tables() -> Vec<TableSchema> {
+        let columns = |names: &[&str]| names.iter().map(|n| Column::new(n.to_string(), ColumnType::Int)).collect();
+        vec![
+            TableSchema::new("users".to_owned(), columns(&["id", "name", "email"]), 1),
+            TableSchema::new("orders".to_owned(), columns(&["id", "user_id", "total"]), 2),
+            TableSchema::new("Events".to_owned(), columns(&["id", "Kind"]), 3),
+        ]
+    }
+
+    fn candidates(statement: &str, line: &str) -> Vec<String> {
+        complete(statement, line, line.len(), &tables()).candidates
+    }
+
+    #[test]
+    fn completes_from_context() {
+        assert_eq!(candidates("", "SEL"), ["SELECT"]);
+        assert_eq!(candidates("", "sel"), ["select"]);
+        assert_eq!(candidates("", "select * from "), ["\"Events\"", "orders", "users"]);
+        assert_eq!(candidates("", "select * from u"), ["users"]);
+        assert_eq!(candidates("", "insert into o"), ["orders"]);
+        assert_eq!(candidates("", "select * from users, o"), ["orders"]);
+        assert_eq!(candidates("", "select * from \"E"), ["\"Events\""]);
+        // columns of the tables the statement mentions, also on a later line
+        assert_eq!(candidates("", "select * from users where e"), ["email", "else", "end", "except", "exists", "extract"]);
+        assert_eq!(candidates("select total\n", "from ord"), ["orders"]);
+        assert_eq!(candidates("select \n", "from orders where t"), ["total", "table", "text", "then", "time", "timestamp", "to", "true", "truncate"]);
+        // after a dot only the columns of that table or alias
+        assert_eq!(complete("", "select u. from users u", 9, &tables()).candidates, ["email", "id", "name"]);
+        assert_eq!(complete("", "select o.u from orders o", 10, &tables()).candidates, ["user_id"]);
+        assert_eq!(candidates("", "select * from \"Events\" where \"Events\".k"), ["\"Kind\""]);
+        // nothing in a string or a comment
+        assert_eq!(candidates("", "select 'sel"), Vec::<String>::new());
+        assert_eq!(candidates("", "select 1 -- sel"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn completion_replaces_the_word() {
+        let completion = complete("", "select na", 9, &tables());
+        assert_eq!((completion.start, completion.candidates), (7, vec!["name".to_owned()]));
+        let completion = complete("", "select \"Ki from \"Events\"", 10, &tables());
+        assert_eq!((completion.start, completion.candidates), (7, vec!["\"Kind\"".to_owned()]));
+    }
+}
@@ -0,0 +1,306 @@
+use std::io::{self, Read};
+
+use super::completion::Completion;
+
+// a key press, as far as the line editor tells them apart
+#[derive(Debug, PartialEq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    // ctrl-u, ctrl-k and ctrl-w
+    KillToStart,
+    KillToEnd,
+    KillWord,
+    // ctrl-l
+    Clear,
+    // ctrl-c
+    Interrupt,
+    // ctrl-d
+    EndOfInput,
+    // function keys and other sequences that do nothing here
+    Other,
+}
+
+// reads one key from a terminal in raw mode, none at the end of the input
+pub fn read_key(input: &mut impl Read) -> io::Result<Option<Key>> {
+    let Some(first) = read_byte(input)? else { return Ok(None) };
+    let key = match first {
+        1 => Key::Home,
+        2 => Key::Left,
+        3 => Key::Interrupt,
+        4 => Key::EndOfInput,
+        5 => Key::End,
+        6 => Key::Right,
+        8 | 127 => Key::Backspace,
+        b'\t' => Key::Tab,
+        b'\r' | b'\n' => Key::Enter,
+        11 => Key::KillToEnd,
+        12 => Key::Clear,
+        14 => Key::Down,
+        16 => Key::Up,
+        21 => Key::KillToStart,
+        23 => Key::KillWord,
+        27 => read_escape(input)?,
+        b if b < 0x20 => Key::Other,
+        b if b < 0x80 => Key::Char(b as char),
+        b => read_utf8(input, b)?,
+    };
+    Ok(Some(key))
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut byte = [0u8];
+    loop {
+        match input.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// ESC [ params final, or ESC O final, which is what some terminals send for home and end
+fn read_escape(input: &mut impl Read) -> io::Result<Key> {
+    match read_byte(input)? {
+        Some(b'[') => {
+            let mut params = Vec::new();
+            while let Some(b) = read_byte(input)? {
+                if (0x40..=0x7e).contains(&b) {
+                    return Ok(escape_key(&params, b));
+                }
+                params.push(b);
+            }
+            Ok(Key::Other)
+        },
+        Some(b'O') => Ok(read_byte(input)?.map_or(Key::Other, |b| escape_key(&[], b))),
+        _ => Ok(Key::Other),
+    }
+}
+
+// modifiers like the 5 of ctrl-right, ESC[1;5C, are ignored
+fn escape_key(params: &[u8], last: u8) -> Key {
+    match (params, last) {
+        (_, b'A') => Key::Up,
+        (_, b'B') => Key::Down,
+        (_, b'C') => Key::Right,
+        (_, b'D') => Key::Left,
+        (_, b'H') => Key::Home,
+        (_, b'F') => Key::End,
+        (b"1" | b"7", b'~') => Key::Home,
+        (b"4" | b"8", b'~') => Key::End,
+        (b"3", b'~') => Key::Delete,
+        _ => Key::Other,
+    }
+}
+
+fn read_utf8(input: &mut impl Read, first: u8) -> io::Result<Key> {
+    let len = match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Ok(Key::Other),
+    };
+    let mut bytes = vec![first];
+    for _ in 1..len {
+        match read_byte(input)? {
+            Some(b) => bytes.push(b),
+            None => return Ok(Key::Other),
+        }
+    }
+    Ok(std::str::from_utf8(&bytes).ok().and_then(|s| s.chars().next()).map_or(Key::Other, Key::Char))
+}
+
+// the line being edited, the cursor counts chars
+#[derive(Debug, Default)]
+pub struct Line {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl Line {
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    // the cursor as a byte offset in the text
+    pub fn byte_cursor(&self) -> usize {
+        self.chars[..self.cursor].iter().map(|c| c.len_utf8()).sum()
+    }
+
+    // replaces the text and puts the cursor at its end
+    pub fn set(&mut self, text: &str) {
+        self.chars = text.chars().collect();
+        self.cursor = self.chars.len();
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        for c in text.chars() {
+            self.chars.insert(self.cursor, c);
+            self.cursor += 1;
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.chars.len());
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.chars.len();
+    }
+
+    pub fn kill_to_start(&mut self) {
+        self.chars.drain(..self.cursor);
+        self.cursor = 0;
+    }
+
+    pub fn kill_to_end(&mut self) {
+        self.chars.truncate(self.cursor);
+    }
+
+    // the whitespace before the cursor and the word before that
+    pub fn kill_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        self.chars.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    /**
+     * What tab does: the only candidate replaces the word followed by a space, several replace it with
+     * the start they have in common if that is longer than the word. Returns whether the candidates
+     * should be listed instead, because nothing could be added to the word.
+     */
+    pub fn complete(&mut self, completion: &Completion) -> bool {
+        let text = self.text();
+        let typed = &text[completion.start..self.byte_cursor()];
+        let replacement = match completion.candidates.as_slice() {
+            [] => return false,
+            [only] => format!("{} ", only),
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(first.as_str(), |common, candidate| {
+                    let len = common.char_indices()
+                        .zip(candidate.chars())
+                        .find(|((_, a), b)| a != b)
+                        .map_or(common.len().min(candidate.len()), |((i, _), _)| i);
+                    &common[..len]
+                });
+                if common.chars().count() <= typed.chars().count() {
+                    return true;
+                }
+                common.to_owned()
+            },
+        };
+        let start = text[..completion.start].chars().count();
+        self.chars.drain(start..self.cursor);
+        self.cursor = start;
+        self.insert(&replacement);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repl::completion::Completion;
+
+    use super::{read_key, Key, Line};
+
+    #[test]
+    fn keys_are_decoded() {
+        let mut input: &[u8] = b"a\x1b[A\x1b[1;5C\x1b[3~\x1bOH\x1b[4~\x01\x03\x04\x7f\r\t\xc3\xa9\x1b[15~";
+        let mut keys = Vec::new();
+        while let Some(key) = read_key(&mut input).unwrap() {
+            keys.push(key);
+        }
+        assert_eq!(keys, [Key::Char('a'), Key::Up, Key::Right, Key::Delete, Key::Home, Key::End, Key::Home, Key::Interrupt,
+            Key::EndOfInput, Key::Backspace, Key::Enter, Key::Tab, Key::Char('é'), Key::Other]);
+    }
+
+    #[test]
+    fn line_editing() {
+        let mut line = Line::default();
+        line.insert("select é from t");
+        line.home();
+        line.right();
+        line.delete();
+        line.backspace();
+        assert_eq!((line.text(), line.cursor()), ("lect é from t".to_owned(), 0));
+        line.end();
+        line.kill_word();
+        line.kill_word();
+        assert_eq!(line.text(), "lect é ");
+        line.left();
+        line.left();
+        assert_eq!(line.byte_cursor(), 5);
+        line.kill_to_end();
+        line.insert("x");
+        line.left();
+        line.kill_to_start();
+        assert_eq!((line.text(), line.cursor()), ("x".to_owned(), 0));
+    }
+
+    #[test]
+    fn tab_inserts_what_the_candidates_share() {
+        let candidates = |names: &[&str]| Completion { start: 7, candidates: names.iter().map(|n| n.to_string()).collect() };
+        let mut line = Line::default();
+        line.set("select na");
+        assert!(!line.complete(&candidates(&["name"])));
+        assert_eq!(line.text(), "select name ");
+
+        line.set("select u");
+        assert!(!line.complete(&candidates(&["user_id", "user_name"])));
+        assert_eq!(line.text(), "select user_");
+        assert!(line.complete(&candidates(&["user_id", "user_name"])));
+        assert_eq!(line.text(), "select user_");
+
+        line.set("select e from t");
+        line.home();
+        (0..8).for_each(|_| line.right());
+        assert!(!line.complete(&candidates(&["email"])));
+        assert_eq!((line.text(), line.cursor()), ("select email  from t".to_owned(), 13));
+        assert!(!line.complete(&candidates(&[])));
+    }
+}
@@ -0,0 +1,81 @@
+use std::{fs::{self, OpenOptions}, io::{self, Write}, path::PathBuf};
+
+// how many lines are kept, older ones are dropped from the file when it is loaded
+const MAX_ENTRIES: usize = 1000;
+
+// the lines typed in this and earlier sessions, oldest first, one per line of the file
+pub struct History {
+    entries: Vec<String>,
+    path: PathBuf,
+}
+
+impl History {
+    // a file that can't be read is an empty history, it shouldn't keep the shell from starting
+    pub fn load(path: PathBuf) -> History {
+        let mut entries: Vec<String> = fs::read_to_string(&path)
+            .map(|text| text.lines().map(str::to_owned).collect())
+            .unwrap_or_default();
+        if entries.len() > MAX_ENTRIES {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+            let _ = fs::write(&path, entries.iter().map(|e| format!("{}\n", e)).collect::<String>());
+        }
+        History { entries, path }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    // remembers a line unless it is blank or the same as the one before it
+    pub fn add(&mut self, line: &str) {
+        let line = line.trim_end();
+        if line.trim().is_empty() || self.entries.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.entries.push(line.to_owned());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        // history is a convenience, not being able to write it isn't worth an error
+        let _ = self.append(line);
+    }
+
+    fn append(&self, line: &str) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{History, MAX_ENTRIES};
+
+    #[test]
+    fn history_is_kept_across_sessions() {
+        let dir = env::temp_dir().join(format!("toy-sql-db-history-{}", std::process::id()));
+        let path = dir.join("history");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut history = History::load(path.clone());
+        assert!(history.entries().is_empty());
+        history.add("select 1;\n");
+        history.add("select 1;\n");
+        history.add("   \n");
+        history.add("select 2;\n");
+        let history = History::load(path.clone());
+        assert_eq!(history.entries(), ["select 1;", "select 2;"]);
+
+        // a long file is cut down to the newest lines
+        let lines: String = (0..MAX_ENTRIES + 10).map(|i| format!("select {};\n", i)).collect();
+        fs::write(&path, lines).unwrap();
+        let history = History::load(path.clone());
+        assert_eq!((history.entries().len(), history.entries()[0].as_str()), (MAX_ENTRIES, "select 10;"));
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), MAX_ENTRIES);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
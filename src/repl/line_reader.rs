@@ -0,0 +1,197 @@
+use std::{io::{self, BufRead, IsTerminal, Read, Write}, path::PathBuf};
+
+use crate::config::config::{DATA_DIR, HISTORY_FILE};
+
+use super::{completion::Completion, editor::{read_key, Key, Line}, history::History, terminal::RawMode};
+
+
+pub enum Input {
+    Line(String),
+    // ctrl-c was pressed while the line was being typed
+    Interrupted,
+    Eof,
+}
+
+/**
+ * Reads lines from stdin, where ctrl-c interrupts the line instead of ending the process.
+ * In a terminal the line is edited in raw mode, with history and tab completion. Otherwise
+ * a handler for SIGINT that does nothing makes the read that is waiting fail with EINTR,
+ * which read_line of std would retry, so lines are read from the buffer of stdin here.
+ * The handler is only there while a line is read, ctrl-c still ends a statement that is running.
+ */
+pub struct LineReader {
+    stdin: io::Stdin,
+    // only lines typed in a terminal are remembered
+    history: Option<History>,
+}
+
+impl LineReader {
+    pub fn new() -> LineReader {
+        let stdin = io::stdin();
+        let history = stdin.is_terminal().then(|| History::load(PathBuf::from(DATA_DIR).join(HISTORY_FILE)));
+        LineReader { stdin, history }
+    }
+
+    // complete is called with the line and the byte offset of the cursor when tab is pressed
+    pub fn read_line(&mut self, prompt: &str, complete: &dyn Fn(&str, usize) -> Completion) -> io::Result<Input> {
+        if let Some(history) = &mut self.history {
+            if let Ok(_raw) = RawMode::enable() {
+                return edit_line(&mut self.stdin.lock(), history, prompt, complete);
+            }
+        }
+        self.read_buffered(prompt)
+    }
+
+    fn read_buffered(&mut self, prompt: &str) -> io::Result<Input> {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        let mut line = Vec::new();
+        let mut stdin = self.stdin.lock();
+        let _interrupts = CatchInterrupts::install();
+        loop {
+            let available = match stdin.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => return Ok(Input::Interrupted),
+                Err(e) => return Err(e),
+            };
+            if available.is_empty() {
+                if line.is_empty() {
+                    return Ok(Input::Eof);
+                }
+                break;
+            }
+            match available.iter().position(|b| *b == b'\n') {
+                Some(end) => {
+                    line.extend_from_slice(&available[..=end]);
+                    stdin.consume(end + 1);
+                    break;
+                },
+                None => {
+                    let len = available.len();
+                    line.extend_from_slice(available);
+                    stdin.consume(len);
+                },
+            }
+        }
+        Ok(Input::Line(String::from_utf8_lossy(&line).into_owned()))
+    }
+}
+
+fn edit_line(stdin: &mut impl Read, history: &mut History, prompt: &str, complete: &dyn Fn(&str, usize) -> Completion) -> io::Result<Input> {
+    let mut out = io::stdout();
+    let mut line = Line::default();
+    // the entry of the history that is shown, and what was typed before going up to it
+    let mut index = history.entries().len();
+    let mut typed = String::new();
+    redraw(&mut out, prompt, &line)?;
+    while let Some(key) = read_key(stdin)? {
+        match key {
+            Key::Enter => {
+                let text = line.text();
+                writeln!(out)?;
+                history.add(&text);
+                return Ok(Input::Line(text + "\n"));
+            },
+            Key::Interrupt => {
+                write!(out, "^C")?;
+                out.flush()?;
+                return Ok(Input::Interrupted);
+            },
+            Key::EndOfInput if line.is_empty() => {
+                writeln!(out)?;
+                return Ok(Input::Eof);
+            },
+            Key::EndOfInput | Key::Delete => line.delete(),
+            Key::Char(c) => line.insert(c.encode_utf8(&mut [0; 4])),
+            Key::Tab => {
+                let completion = complete(&line.text(), line.byte_cursor());
+                if completion.candidates.is_empty() {
+                    write!(out, "\x07")?;
+                } else if line.complete(&completion) {
+                    writeln!(out)?;
+                    writeln!(out, "{}", completion.candidates.join("  "))?;
+                }
+            },
+            Key::Up if index > 0 => {
+                if index == history.entries().len() {
+                    typed = line.text();
+                }
+                index -= 1;
+                line.set(&history.entries()[index]);
+            },
+            Key::Down if index < history.entries().len() => {
+                index += 1;
+                line.set(history.entries().get(index).unwrap_or(&typed));
+            },
+            Key::Backspace => line.backspace(),
+            Key::Left => line.left(),
+            Key::Right => line.right(),
+            Key::Home => line.home(),
+            Key::End => line.end(),
+            Key::KillToStart => line.kill_to_start(),
+            Key::KillToEnd => line.kill_to_end(),
+            Key::KillWord => line.kill_word(),
+            Key::Clear => write!(out, "\x1b[H\x1b[2J")?,
+            Key::Up | Key::Down | Key::Other => {},
+        }
+        redraw(&mut out, prompt, &line)?;
+    }
+    // the input ended in the middle of the line
+    writeln!(out)?;
+    Ok(if line.is_empty() { Input::Eof } else { Input::Line(line.text()) })
+}
+
+// writes the prompt and the line over the one on the screen and moves the cursor to where it is in the line
+fn redraw(out: &mut impl Write, prompt: &str, line: &Line) -> io::Result<()> {
+    let mut frame = format!("\r{}{}\x1b[0K\r", prompt, line.text());
+    let column = prompt.chars().count() + line.cursor();
+    if column > 0 {
+        frame.push_str(&format!("\x1b[{}C", column));
+    }
+    out.write_all(frame.as_bytes())?;
+    out.flush()
+}
+
+// SIGINT is caught until this is dropped, then it ends the process again
+struct CatchInterrupts;
+
+impl CatchInterrupts {
+    fn install() -> CatchInterrupts {
+        sys::catch_interrupts(true);
+        CatchInterrupts
+    }
+}
+
+impl Drop for CatchInterrupts {
+    fn drop(&mut self) {
+        sys::catch_interrupts(false);
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    const SIGINT: i32 = 2;
+    const SIG_DFL: usize = 0;
+    extern "C" {
+        fn signal(signum: i32, handler: usize) -> usize;
+        fn siginterrupt(signum: i32, flag: i32) -> i32;
+    }
+    extern "C" fn on_interrupt(_signum: i32) {}
+
+    pub fn catch_interrupts(catch: bool) {
+        // without siginterrupt the read would be restarted after the handler
+        unsafe {
+            if catch {
+                signal(SIGINT, on_interrupt as extern "C" fn(i32) as usize);
+                siginterrupt(SIGINT, 1);
+            } else {
+                signal(SIGINT, SIG_DFL);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    pub fn catch_interrupts(_catch: bool) {}
+}
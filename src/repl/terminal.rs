@@ -0,0 +1,93 @@
+use std::io;
+
+/**
+ * The terminal of stdin in raw mode, so keys arrive one at a time without being echoed or turned into
+ * signals, until this is dropped. termios is declared here as glibc lays it out on linux, with the flag
+ * values of the architectures that share them, elsewhere enabling it fails and lines are read without editing.
+ */
+pub struct RawMode {
+    #[cfg(all(target_os = "linux", target_env = "gnu", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64", target_arch = "riscv64")))]
+    original: sys::Termios,
+}
+
+impl RawMode {
+    #[cfg(all(target_os = "linux", target_env = "gnu", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64", target_arch = "riscv64")))]
+    pub fn enable() -> io::Result<RawMode> {
+        let original = sys::get()?;
+        let mut raw = original;
+        raw.c_iflag &= !(sys::BRKINT | sys::ICRNL | sys::INPCK | sys::ISTRIP | sys::IXON);
+        raw.c_cflag |= sys::CS8;
+        raw.c_lflag &= !(sys::ECHO | sys::ICANON | sys::IEXTEN | sys::ISIG);
+        raw.c_cc[sys::VMIN] = 1;
+        raw.c_cc[sys::VTIME] = 0;
+        sys::set(&raw)?;
+        Ok(RawMode { original })
+    }
+
+    #[cfg(not(all(target_os = "linux", target_env = "gnu", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64", target_arch = "riscv64"))))]
+    pub fn enable() -> io::Result<RawMode> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        #[cfg(all(target_os = "linux", target_env = "gnu", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64", target_arch = "riscv64")))]
+        let _ = sys::set(&self.original);
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64", target_arch = "riscv64")))]
+mod sys {
+    use std::io;
+
+    pub const BRKINT: u32 = 0o2;
+    pub const INPCK: u32 = 0o20;
+    pub const ISTRIP: u32 = 0o40;
+    pub const ICRNL: u32 = 0o400;
+    pub const IXON: u32 = 0o2000;
+    pub const CS8: u32 = 0o60;
+    pub const ISIG: u32 = 0o1;
+    pub const ICANON: u32 = 0o2;
+    pub const ECHO: u32 = 0o10;
+    pub const IEXTEN: u32 = 0o100000;
+    pub const VTIME: usize = 5;
+    pub const VMIN: usize = 6;
+    // applies the change once the output is written, keeping input that wasn't read like the rest of a paste
+    const TCSADRAIN: i32 = 1;
+    const STDIN: i32 = 0;
+
+    // struct termios of glibc, not the kernel's, which has fewer control characters and no speeds
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct Termios {
+        pub c_iflag: u32,
+        pub c_oflag: u32,
+        pub c_cflag: u32,
+        pub c_lflag: u32,
+        pub c_line: u8,
+        pub c_cc: [u8; 32],
+        pub c_ispeed: u32,
+        pub c_ospeed: u32,
+    }
+
+    extern "C" {
+        fn tcgetattr(fd: i32, termios: *mut Termios) -> i32;
+        fn tcsetattr(fd: i32, optional_actions: i32, termios: *const Termios) -> i32;
+    }
+
+    pub fn get() -> io::Result<Termios> {
+        let mut termios = Termios { c_iflag: 0, c_oflag: 0, c_cflag: 0, c_lflag: 0, c_line: 0, c_cc: [0; 32], c_ispeed: 0, c_ospeed: 0 };
+        if unsafe { tcgetattr(STDIN, &mut termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(termios)
+    }
+
+    pub fn set(termios: &Termios) -> io::Result<()> {
+        if unsafe { tcsetattr(STDIN, TCSADRAIN, termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
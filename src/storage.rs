@@ -1,4 +1,6 @@
+pub mod b_plus_tree;
 pub mod buffer_pool;
 pub mod disk_manager;
 mod lru_k_replacer;
+pub mod overflow;
 pub mod table_page;
\ No newline at end of file
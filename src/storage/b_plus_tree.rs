@@ -0,0 +1,349 @@
+use crate::{config::config::PAGE_SIZE, parse::scanner::{INTERNAL_ERROR, PROGRAM_LIMIT_EXCEEDED, TError}};
+
+use super::{buffer_pool::{BufferPoolManager, PageId}, table_page::{RecordId, TupleId}};
+
+
+/**
+ * B+ tree mapping byte string keys to the location of a tuple. Keys compare bytewise and
+ * the same key can be inserted more than once, it is up to the user to check uniqueness.
+ *
+ * Node page format:
+ *  | is leaf (1) | num entries (2) | next leaf page id (4) | entries ... |
+ *
+ *  leaf entry:
+ *  | key length (2) | key | page id (4) | tuple id (2) |
+ *
+ *  internal nodes start with the page id of their first child (4), followed by entries
+ *  | key length (2) | key | child page id (4) |
+ *  where every key in the child is >= the entry's key and <= the next entry's key
+ *
+ * The root never moves, when it splits both halves go to new pages and the root becomes
+ * their parent, so the catalog can keep referring to a tree by its root page id.
+ * Removing entries doesn't merge nodes.
+ */
+const NODE_HEADER_SIZE: usize = 7;
+
+// makes sure a split node always fits in its pages
+pub const MAX_KEY_SIZE: usize = PAGE_SIZE / 4;
+
+pub struct BPlusTree {
+    pub root_page_id: u32,
+}
+
+enum Node {
+    Leaf { entries: Vec<(Vec<u8>, RecordId)>, next: Option<u32> },
+    Internal { first_child: u32, entries: Vec<(Vec<u8>, u32)> },
+}
+
+impl BPlusTree {
+    pub fn new(root_page_id: u32) -> BPlusTree {
+        BPlusTree { root_page_id }
+    }
+
+    // allocate the root page of an empty tree
+    pub fn create(buffer_pool: &mut BufferPoolManager) -> Result<BPlusTree, TError> {
+        let root_page_id = allocate_page(buffer_pool)?;
+        write_node(buffer_pool, root_page_id, &Node::Leaf { entries: Vec::new(), next: None })?;
+        Ok(BPlusTree { root_page_id })
+    }
+
+    pub fn insert(&self, buffer_pool: &mut BufferPoolManager, key: &[u8], record_id: RecordId) -> Result<(), TError> {
+        if key.len() > MAX_KEY_SIZE {
+            return Err(TError::ExecutionError(PROGRAM_LIMIT_EXCEEDED, format!("index key of {} bytes exceeds the maximum of {}", key.len(), MAX_KEY_SIZE)));
+        }
+        self.insert_into(buffer_pool, self.root_page_id, key, record_id)?;
+        Ok(())
+    }
+
+    // returns the separator and page of the new right sibling if the node was split
+    fn insert_into(&self, buffer_pool: &mut BufferPoolManager, page_id: u32, key: &[u8], record_id: RecordId) -> Result<Option<(Vec<u8>, u32)>, TError> {
+        let mut node = read_node(buffer_pool, page_id)?;
+        match &mut node {
+            Node::Leaf { entries, .. } => {
+                let position = entries.partition_point(|(k, _)| k.as_slice() <= key);
+                entries.insert(position, (key.to_vec(), record_id));
+            },
+            Node::Internal { first_child, entries } => {
+                let position = entries.partition_point(|(k, _)| k.as_slice() <= key);
+                let child = if position == 0 { *first_child } else { entries[position - 1].1 };
+                match self.insert_into(buffer_pool, child, key, record_id)? {
+                    Some(split) => entries.insert(position, split),
+                    None => return Ok(None),
+                }
+            },
+        }
+
+        if node_size(&node) <= PAGE_SIZE {
+            write_node(buffer_pool, page_id, &node)?;
+            return Ok(None);
+        }
+
+        let (mut left, separator, mut right) = split(node);
+        if page_id == self.root_page_id {
+            let left_page_id = allocate_page(buffer_pool)?;
+            let right_page_id = allocate_page(buffer_pool)?;
+            if let Node::Leaf { next, .. } = &mut left {
+                *next = Some(right_page_id);
+            }
+            write_node(buffer_pool, left_page_id, &left)?;
+            write_node(buffer_pool, right_page_id, &right)?;
+            let root = Node::Internal { first_child: left_page_id, entries: vec![(separator, right_page_id)] };
+            write_node(buffer_pool, page_id, &root)?;
+            return Ok(None);
+        }
+
+        let right_page_id = allocate_page(buffer_pool)?;
+        if let (Node::Leaf { next: left_next, .. }, Node::Leaf { next: right_next, .. }) = (&mut left, &mut right) {
+            *right_next = *left_next;
+            *left_next = Some(right_page_id);
+        }
+        write_node(buffer_pool, page_id, &left)?;
+        write_node(buffer_pool, right_page_id, &right)?;
+        Ok(Some((separator, right_page_id)))
+    }
+
+    // every record stored under key
+    pub fn get(&self, buffer_pool: &mut BufferPoolManager, key: &[u8]) -> Result<Vec<RecordId>, TError> {
+        let mut res = Vec::new();
+        let mut next = Some(self.find_leaf(buffer_pool, key)?);
+        while let Some(page_id) = next {
+            let (entries, next_leaf) = match read_node(buffer_pool, page_id)? {
+                Node::Leaf { entries, next } => (entries, next),
+                Node::Internal { .. } => return Err(TError::StorageError(format!("index page {} is not a leaf", page_id))),
+            };
+            for (k, record_id) in entries {
+                match k.as_slice().cmp(key) {
+                    std::cmp::Ordering::Less => {},
+                    std::cmp::Ordering::Equal => res.push(record_id),
+                    std::cmp::Ordering::Greater => return Ok(res),
+                }
+            }
+            next = next_leaf;
+        }
+        Ok(res)
+    }
+
+    // returns whether the entry was found
+    pub fn remove(&self, buffer_pool: &mut BufferPoolManager, key: &[u8], record_id: &RecordId) -> Result<bool, TError> {
+        let mut next = Some(self.find_leaf(buffer_pool, key)?);
+        while let Some(page_id) = next {
+            let (mut entries, next_leaf) = match read_node(buffer_pool, page_id)? {
+                Node::Leaf { entries, next } => (entries, next),
+                Node::Internal { .. } => return Err(TError::StorageError(format!("index page {} is not a leaf", page_id))),
+            };
+            if let Some(position) = entries.iter().position(|(k, r)| k.as_slice() == key && r == record_id) {
+                entries.remove(position);
+                write_node(buffer_pool, page_id, &Node::Leaf { entries, next: next_leaf })?;
+                return Ok(true);
+            }
+            if entries.last().is_some_and(|(k, _)| k.as_slice() > key) {
+                return Ok(false);
+            }
+            next = next_leaf;
+        }
+        Ok(false)
+    }
+
+    // the leftmost leaf that can hold key
+    fn find_leaf(&self, buffer_pool: &mut BufferPoolManager, key: &[u8]) -> Result<u32, TError> {
+        let mut page_id = self.root_page_id;
+        loop {
+            match read_node(buffer_pool, page_id)? {
+                Node::Internal { first_child, entries } => {
+                    let position = entries.partition_point(|(k, _)| k.as_slice() < key);
+                    page_id = if position == 0 { first_child } else { entries[position - 1].1 };
+                },
+                Node::Leaf { .. } => return Ok(page_id),
+            }
+        }
+    }
+
+    // delete every page of the tree, including the root
+    pub fn free_pages(&self, buffer_pool: &mut BufferPoolManager) -> Result<(), TError> {
+        let mut pages = vec![self.root_page_id];
+        while let Some(page_id) = pages.pop() {
+            if let Node::Internal { first_child, entries } = read_node(buffer_pool, page_id)? {
+                pages.push(first_child);
+                pages.extend(entries.iter().map(|(_, child)| *child));
+            }
+            if !buffer_pool.delete_page(&PageId(page_id.try_into().unwrap())) {
+                return Err(TError::ExecutionError(INTERNAL_ERROR, format!("page {} is still in use", page_id)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn allocate_page(buffer_pool: &mut BufferPoolManager) -> Result<u32, TError> {
+    let page = buffer_pool.new_page()?;
+    let page_id = page.borrow().get_page_id().unwrap().0;
+    u32::try_from(page_id).map_err(|_| TError::StorageError(format!("page id {} is out of range", page_id)))
+}
+
+// split an overflowing node in two halves of about the same size, and the key that separates them
+fn split(node: Node) -> (Node, Vec<u8>, Node) {
+    match node {
+        Node::Leaf { mut entries, next } => {
+            let mid = split_point(entries.iter().map(|(k, _)| k.len() + 8));
+            let right = entries.split_off(mid);
+            let separator = right[0].0.clone();
+            (Node::Leaf { entries, next }, separator, Node::Leaf { entries: right, next: None })
+        },
+        Node::Internal { first_child, mut entries } => {
+            let mid = split_point(entries.iter().map(|(k, _)| k.len() + 6));
+            let mut right = entries.split_off(mid);
+            // the middle key moves up to the parent, its child becomes the right node's first
+            let (separator, right_first_child) = right.remove(0);
+            (Node::Internal { first_child, entries }, separator, Node::Internal { first_child: right_first_child, entries: right })
+        },
+    }
+}
+
+fn split_point(sizes: impl Iterator<Item = usize>) -> usize {
+    let sizes: Vec<usize> = sizes.collect();
+    let total: usize = sizes.iter().sum();
+    let mut size = 0;
+    for (index, entry_size) in sizes.iter().enumerate() {
+        size += entry_size;
+        if size * 2 >= total {
+            return (index + 1).clamp(1, sizes.len() - 1);
+        }
+    }
+    sizes.len() - 1
+}
+
+fn node_size(node: &Node) -> usize {
+    match node {
+        Node::Leaf { entries, .. } => NODE_HEADER_SIZE + entries.iter().map(|(k, _)| 2 + k.len() + 6).sum::<usize>(),
+        Node::Internal { entries, .. } => NODE_HEADER_SIZE + 4 + entries.iter().map(|(k, _)| 2 + k.len() + 4).sum::<usize>(),
+    }
+}
+
+// a node whose entries run past the end of its page is a storage error
+fn read_node(buffer_pool: &mut BufferPoolManager, page_id: u32) -> Result<Node, TError> {
+    let page = buffer_pool.fetch_page(PageId(page_id.try_into().unwrap()))?;
+    let page = page.borrow();
+    let data = &page.data;
+    let read = |offset: usize, len: usize| data.get(offset..offset + len)
+        .ok_or_else(|| TError::StorageError(format!("index page {} is truncated", page_id)));
+    let read_u16 = |offset: usize| Ok::<_, TError>(usize::from(u16::from_le_bytes(read(offset, 2)?.try_into().unwrap())));
+    let read_u32 = |offset: usize| Ok::<_, TError>(u32::from_le_bytes(read(offset, 4)?.try_into().unwrap()));
+
+    let is_leaf = read(0, 1)?[0] != 0;
+    let num_entries = read_u16(1)?;
+    let mut offset = NODE_HEADER_SIZE;
+    if is_leaf {
+        let next = match read_u32(3)? {
+            0 => None,
+            next => Some(next),
+        };
+        let mut entries = Vec::new();
+        for _ in 0..num_entries {
+            let key_length = read_u16(offset)?;
+            let key = read(offset + 2, key_length)?.to_vec();
+            offset += 2 + key_length;
+            let record_id = RecordId { page_id: read_u32(offset)?, tuple_id: TupleId(read_u16(offset + 4)?) };
+            offset += 6;
+            entries.push((key, record_id));
+        }
+        Ok(Node::Leaf { entries, next })
+    } else {
+        let first_child = read_u32(offset)?;
+        offset += 4;
+        let mut entries = Vec::new();
+        for _ in 0..num_entries {
+            let key_length = read_u16(offset)?;
+            let key = read(offset + 2, key_length)?.to_vec();
+            offset += 2 + key_length;
+            entries.push((key, read_u32(offset)?));
+            offset += 4;
+        }
+        Ok(Node::Internal { first_child, entries })
+    }
+}
+
+fn write_node(buffer_pool: &mut BufferPoolManager, page_id: u32, node: &Node) -> Result<(), TError> {
+    // see read_node
+    let mut res = Vec::new();
+    match node {
+        Node::Leaf { entries, next } => {
+            res.push(1);
+            res.extend(u16::try_from(entries.len()).unwrap().to_le_bytes());
+            res.extend(next.unwrap_or(0).to_le_bytes());
+            for (key, record_id) in entries {
+                res.extend(u16::try_from(key.len()).unwrap().to_le_bytes());
+                res.extend(key);
+                res.extend(record_id.page_id.to_le_bytes());
+                res.extend(u16::try_from(record_id.tuple_id.0).unwrap().to_le_bytes());
+            }
+        },
+        Node::Internal { first_child, entries } => {
+            res.push(0);
+            res.extend(u16::try_from(entries.len()).unwrap().to_le_bytes());
+            res.extend(0u32.to_le_bytes());
+            res.extend(first_child.to_le_bytes());
+            for (key, child) in entries {
+                res.extend(u16::try_from(key.len()).unwrap().to_le_bytes());
+                res.extend(key);
+                res.extend(child.to_le_bytes());
+            }
+        },
+    }
+    let page = buffer_pool.fetch_page(PageId(page_id.try_into().unwrap()))?;
+    page.borrow_mut().data[..res.len()].copy_from_slice(&res);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::config::PAGE_SIZE, parse::scanner::TError, storage::{buffer_pool::{BufferPoolManager, PageId}, table_page::{RecordId, TupleId}}, test::TestSetup};
+
+    use super::{BPlusTree, NODE_HEADER_SIZE};
+
+    fn record(i: usize) -> RecordId {
+        RecordId { page_id: 1, tuple_id: TupleId(i) }
+    }
+
+    #[test]
+    fn insert_get_and_remove() {
+        let _setup = TestSetup;
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+        let tree = BPlusTree::create(&mut buffer_pool).unwrap();
+
+        // enough big keys for a few levels of splits, in an order that isn't sorted
+        let key = |i: usize| format!("{:0>200}", (i * 7919) % 1000).into_bytes();
+        for i in 0..1000 {
+            tree.insert(&mut buffer_pool, &key(i), record(i)).unwrap();
+        }
+        tree.insert(&mut buffer_pool, &key(10), record(1010)).unwrap();
+
+        for i in 0..1000 {
+            let expected = if i == 10 { vec![record(10), record(1010)] } else { vec![record(i)] };
+            assert_eq!(tree.get(&mut buffer_pool, &key(i)).unwrap(), expected);
+        }
+        assert!(tree.get(&mut buffer_pool, b"missing").unwrap().is_empty());
+
+        assert!(tree.remove(&mut buffer_pool, &key(10), &record(10)).unwrap());
+        assert!(!tree.remove(&mut buffer_pool, &key(10), &record(10)).unwrap());
+        assert_eq!(tree.get(&mut buffer_pool, &key(10)).unwrap(), vec![record(1010)]);
+
+        tree.free_pages(&mut buffer_pool).unwrap();
+    }
+
+    #[test]
+    fn corrupt_nodes() {
+        let _setup = TestSetup;
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+        let tree = BPlusTree::create(&mut buffer_pool).unwrap();
+        tree.insert(&mut buffer_pool, b"key", record(1)).unwrap();
+
+        // a key length past the end of the page
+        let page = buffer_pool.fetch_page(PageId(tree.root_page_id.try_into().unwrap())).unwrap();
+        page.borrow_mut().data[NODE_HEADER_SIZE..NODE_HEADER_SIZE + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(matches!(tree.get(&mut buffer_pool, b"key"), Err(TError::StorageError(_))));
+    }
+}
@@ -1,4 +1,4 @@
-use std::cell::RefCell;
+use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
@@ -7,6 +7,7 @@ use std::path::Path;
 use std::rc::Rc;
 
 use crate::config::config::PAGE_SIZE;
+use crate::parse::scanner::TError;
 
 use super::disk_manager::DiskManager;
 use super::lru_k_replacer::LRUKReplacer;
@@ -15,7 +16,6 @@ use super::lru_k_replacer::LRUKReplacer;
 
 pub struct PageTableEntry<'a> {
     page_id: Option<PageId>,
-    pin_count: i64,
     is_dirty: bool,
     frame_id: FrameId,
     pub data: &'a mut [u8],
@@ -25,7 +25,7 @@ pub struct PageTableEntry<'a> {
 
 impl  <'a> PageTableEntry<'a> {
     pub fn new(frame_id: FrameId, data: &'a mut [u8]) -> PageTableEntry<'a> {
-        PageTableEntry {page_id: None, pin_count: 0, is_dirty: false, frame_id, data }
+        PageTableEntry {page_id: None, is_dirty: false, frame_id, data }
     }
 
     pub fn get_page_id(&self) -> Option<PageId> {
@@ -33,6 +33,46 @@ impl  <'a> PageTableEntry<'a> {
     }
 }
 
+/**
+ * A pinned page, handed out by fetch_page and new_page. The page can't be evicted or deleted
+ * while a guard of it is around, dropping the last one unpins it. Writing through borrow_mut
+ * marks the page dirty so it is written back when it is evicted.
+ */
+pub struct PageGuard<'a> {
+    page: Rc<RefCell<PageTableEntry<'a>>>,
+    // shared with the buffer pool, which keeps it outside of the page so it can be read while the page is borrowed
+    pin_count: Rc<Cell<usize>>,
+}
+
+impl <'a> PageGuard<'a> {
+    pub fn new(page: Rc<RefCell<PageTableEntry<'a>>>, pin_count: Rc<Cell<usize>>) -> PageGuard<'a> {
+        pin_count.set(pin_count.get() + 1);
+        PageGuard { page, pin_count }
+    }
+
+    pub fn borrow(&self) -> Ref<'_, PageTableEntry<'a>> {
+        self.page.borrow()
+    }
+
+    pub fn borrow_mut(&self) -> RefMut<'_, PageTableEntry<'a>> {
+        let mut pte = self.page.borrow_mut();
+        pte.is_dirty = true;
+        pte
+    }
+}
+
+impl Clone for PageGuard<'_> {
+    fn clone(&self) -> Self {
+        PageGuard::new(Rc::clone(&self.page), Rc::clone(&self.pin_count))
+    }
+}
+
+impl Drop for PageGuard<'_> {
+    fn drop(&mut self) {
+        self.pin_count.set(self.pin_count.get() - 1);
+    }
+}
+
 #[derive(Eq, Hash, PartialEq, Clone)]
 pub struct PageId(pub usize);
 
@@ -56,104 +96,115 @@ impl From<usize> for FrameId {
 pub struct BufferPoolManager<'a> {
     replacer: LRUKReplacer,
     page_table: Vec<Rc<RefCell<PageTableEntry<'a>>>>,
+    // the number of guards of the page in each frame
+    pin_counts: Vec<Rc<Cell<usize>>>,
     page_to_frame: HashMap<PageId, FrameId>,
     disk_manager: DiskManager,
-    // temp
-    next_page_id: usize,
 }
 
 impl <'a> BufferPoolManager<'a> {
-    pub fn new(memory: &'a mut Vec<u8>,pool_size: usize, k: usize) -> BufferPoolManager<'a> {
+    pub fn new(memory: &'a mut Vec<u8>,pool_size: usize, k: usize) -> Result<BufferPoolManager<'a>, TError> {
         
-        BufferPoolManager {replacer: LRUKReplacer::new( pool_size, k),
+        Ok(BufferPoolManager {replacer: LRUKReplacer::new( pool_size, k),
         page_table: memory.chunks_exact_mut(PAGE_SIZE).enumerate().map(|(index, memory)| 
         Rc::from(RefCell::from(PageTableEntry::new(FrameId::from(index), memory)))
     ).collect(),
+        pin_counts: (0..pool_size).map(|_| Rc::default()).collect(),
         page_to_frame: HashMap::new(),
-        disk_manager: DiskManager::new(),
-        next_page_id: 1,
-        }
+        disk_manager: DiskManager::new()?,
+        })
     }
 
-    pub fn get_catalog_page(&mut self) -> Option<Rc<RefCell<PageTableEntry<'a>>>> {
+    pub fn get_catalog_page(&mut self) -> Result<PageGuard<'a>, TError> {
         // first page is hardcoded to be the catalog page
         self.fetch_page(PageId(0))
     }
 
-    pub fn fetch_page (&mut self, page_id: PageId) -> Option<Rc<RefCell<PageTableEntry<'a>>>> {
-        // return none if no page is available in the free list and all other pages are currently pinned
-        match self.page_to_frame.get(&page_id) {
-            Some(frame_id) => {
-                let mut pte = self.page_table[frame_id.0].borrow_mut();
-                self.replacer.record_access(*frame_id);
-                // TODO set not evictable?
-                pte.page_id = Some(page_id);
-                Some(Rc::clone(&self.page_table[frame_id.0]))
-            },
-            None => {
-                // TODO use a better datastructure?
-                // find first free frame
-                
-                match self.find_free_frame() {
-                    Some(frame_id) => {
-                        let mut pte = self.page_table[frame_id.0].borrow_mut();
-                        
-                        let buf = self.disk_manager.read_page(&page_id);
-                        pte.data.copy_from_slice(&buf);
-
-                        
-                        self.replacer.record_access(frame_id);
-                        // TODO set not evictable?
-
-                        self.page_to_frame.insert(page_id.clone(), frame_id);
-                        pte.page_id = Some(page_id);
-                        return Some(Rc::clone(&self.page_table[frame_id.0]));
-                    }
-                    None => {
-                        
-                        // no free frames, have to evict
-                        match self.replacer.evict() {
-                            Ok(frame_id) => {
-                                // remove old frame
-                                
-                                self.remove_old_page_from_frame(&frame_id);
-                                // read in new frame
-                                let mut pte = self.page_table[frame_id.0].borrow_mut();
-                                let buf = self.disk_manager.read_page(&page_id);
-                                pte.data.copy_from_slice(&buf);
-                                
-                                self.replacer.record_access(pte.frame_id);
-                                
-                                self.page_to_frame.insert(page_id.clone(), pte.frame_id);
-                                pte.page_id = Some(page_id);
-                                pte.is_dirty = false;
-
-                                
-                                return Some(Rc::clone(&self.page_table[frame_id.0]));
-                            },
-                            Err(_) => {
-                                None
-                            }
-                        }
-                    }
-                }
-            }
+    pub fn fetch_page (&mut self, page_id: PageId) -> Result<PageGuard<'a>, TError> {
+        // an error if no page is available in the free list and all other pages are currently pinned
+        if let Some(frame_id) = self.page_to_frame.get(&page_id).copied() {
+            return Ok(self.pin(frame_id));
         }
+        let frame_id = self.take_frame()?;
+        let buf = self.disk_manager.read_page(&page_id)?;
+        let mut pte = self.page_table[frame_id.0].borrow_mut();
+        pte.data.copy_from_slice(&buf);
+        pte.page_id = Some(page_id.clone());
+        pte.is_dirty = false;
+        std::mem::drop(pte);
+
+        self.page_to_frame.insert(page_id, frame_id);
+        Ok(self.pin(frame_id))
     }
 
-    fn remove_old_page_from_frame(&mut self, frame_id: &FrameId) {
-        let pte = self.page_table[frame_id.0].borrow_mut();
-        let page_id = pte.page_id.as_ref().unwrap().clone();
+    pub fn new_page(&mut self) -> Result<PageGuard<'a>, TError> {
+        // create a new page for new data that isnt in any page yet
+        let frame_id = self.take_frame()?;
+        let page_id = self.disk_manager.allocate_page();
+        let mut pte = self.page_table[frame_id.0].borrow_mut();
+        // zero out data
+        pte.data.fill(0);
+        pte.page_id = Some(page_id.clone());
+        // the page isn't in the file yet
+        pte.is_dirty = true;
+        std::mem::drop(pte);
+
+        self.page_to_frame.insert(page_id, frame_id);
+        Ok(self.pin(frame_id))
+    }
+
+    fn pin(&mut self, frame_id: FrameId) -> PageGuard<'a> {
+        self.replacer.record_access(frame_id);
+        self.replacer.set_evictable(frame_id, false);
+        PageGuard::new(Rc::clone(&self.page_table[frame_id.0]), Rc::clone(&self.pin_counts[frame_id.0]))
+    }
+
+    // a frame without a page in it, evicting a page nobody has pinned if all of them have one
+    fn take_frame(&mut self) -> Result<FrameId, TError> {
+        if let Some(frame_id) = self.find_free_frame() {
+            return Ok(frame_id);
+        }
+        self.release_unpinned_frames();
+        let frame_id = self.replacer.evict().map_err(|_| no_free_frame())?;
+        self.remove_old_page_from_frame(&frame_id)?;
+        Ok(frame_id)
+    }
+
+    fn remove_old_page_from_frame(&mut self, frame_id: &FrameId) -> Result<(), TError> {
+        let mut pte = self.page_table[frame_id.0].borrow_mut();
+        // frames are only evictable while they hold a page
+        let page_id = match &pte.page_id {
+            Some(page_id) => page_id.clone(),
+            None => return Ok(()),
+        };
         // remove old frame
         if pte.is_dirty {
-            self.disk_manager.write_page(&page_id, &pte.data);
+            self.disk_manager.write_page(&page_id, pte.data)?;
         
         }
+        pte.page_id = None;
+        pte.is_dirty = false;
         self.page_to_frame.remove(&page_id);
         self.replacer.remove(pte.frame_id);
+        Ok(())
+    }
+
+    // guards unpin their page when they are dropped, the replacer only hears about it here
+    fn release_unpinned_frames(&mut self) {
+        for (pte, pin_count) in self.page_table.iter().zip(&self.pin_counts) {
+            if pin_count.get() > 0 {
+                continue;
+            }
+            if let Ok(pte) = pte.try_borrow() {
+                if pte.page_id.is_some() {
+                    self.replacer.set_evictable(pte.frame_id, true);
+                }
+            }
+        }
     }
 
     fn find_free_frame(&self) -> Option<FrameId> {
+        // TODO use a better datastructure?
         for pte in self.page_table.iter() {
             let pte = pte.borrow();
             if pte.page_id.is_none() {
@@ -163,100 +214,43 @@ impl <'a> BufferPoolManager<'a> {
         None
     }
     
-    pub fn unpin_page(&mut self, page_id: PageId, is_dirty: bool) {
-        // TODO false if the page is not in the page table or its pin count is <= 0 before this call, true otherwise
-        let frame_id = self.page_to_frame.get(&page_id).unwrap();
-        let mut pte = self.page_table[frame_id.0].borrow_mut();
-        pte.pin_count -= 1;
-        pte.is_dirty |= is_dirty;
-        if pte.pin_count <= 0 {
-            self.replacer.set_evictable(frame_id.clone(), true)
-        }
-        
-    }
-    
-    pub fn flush_page(&mut self, page_id: &PageId) {
+    pub fn flush_page(&mut self, page_id: &PageId) -> Result<(), TError> {
         // flush a page regardless of its pin status.
-        // Flush the target page to disk
-        let frame_id = self.page_to_frame.get(page_id).unwrap();
-        let pte = self.page_table[frame_id.0].borrow_mut();
-        
-        self.disk_manager.write_page(pte.page_id.as_ref().unwrap(), &pte.data);
-
-    }
-    
-    pub fn new_page(&mut self) -> Option<Rc<RefCell<PageTableEntry<'a>>>> {
-        // create a new page for new data that isnt in any page yet
-        
-        match self.find_free_frame() {
-            Some(frame_id) => {
-                let mut pte = self.page_table[frame_id.0].borrow_mut();
-                let page_id = PageId::from(self.next_page_id);
-                self.next_page_id += 1;
-                // zero out data
-                pte.data.fill(0);
-
-                
-                self.replacer.record_access(frame_id);
-                // TODO set not evictable?
-
-                self.page_to_frame.insert(page_id.clone(), frame_id);
-                pte.page_id = Some(page_id);
-                pte.is_dirty = false;
-                return Some(Rc::clone(&self.page_table[frame_id.0]));
-            }
-            None => {
-                // no free frames, have to evict
-                match self.replacer.evict() {
-                    Ok(frame_id) => {
-                        // remove old frame
-                        self.remove_old_page_from_frame(&frame_id);
-
-                        let mut pte = self.page_table[frame_id.0].borrow_mut();
-                        let page_id = PageId::from(self.next_page_id);
-                        self.next_page_id += 1;
-                        // zero out data
-                        pte.data.fill(0);
-                    
-                        
-                        self.replacer.record_access(pte.frame_id);
-                        
-                        self.page_to_frame.insert(page_id.clone(), pte.frame_id);
-                        pte.page_id = Some(page_id);
-                        pte.is_dirty = false;
-
-                        
-                        return Some(Rc::clone(&self.page_table[frame_id.0]));
-                    },
-                    Err(_) => {
-                        None
-                    }
-                }
-            }
+        // Flush the target page to disk, pages that aren't in the pool are already there
+        let frame_id = match self.page_to_frame.get(page_id) {
+            Some(frame_id) => frame_id,
+            None => return Ok(()),
+        };
+        let mut pte = self.page_table[frame_id.0].borrow_mut();
+        if !pte.is_dirty {
+            return Ok(());
         }
+        self.disk_manager.write_page(page_id, pte.data)?;
+        pte.is_dirty = false;
+        Ok(())
     }
     
     pub fn delete_page(&mut self, page_id: &PageId) -> bool {
-        let frame_id = self.page_to_frame.get(page_id).unwrap().clone();
-        let pte = self.page_table[frame_id.0].borrow();
-        if pte.pin_count > 0 {
-            return false;
+        // false if the page is pinned, otherwise the page id can be handed out again by new_page
+        if let Some(frame_id) = self.page_to_frame.get(page_id).copied() {
+            if self.pin_counts[frame_id.0].get() > 0 {
+                return false;
+            }
+            let mut pte = self.page_table[frame_id.0].borrow_mut();
+            // the contents are gone, no need to write them back
+            pte.page_id = None;
+            pte.is_dirty = false;
+            std::mem::drop(pte);
+
+            self.replacer.remove(frame_id);
+            self.page_to_frame.remove(page_id);
         }
-        std::mem::drop(pte);
-
-        // TODO disk manager delete page?
-        self.replacer.remove(frame_id);
-        self.page_to_frame.remove(page_id);
-        self.flush_page(page_id);
-        let mut pte = self.page_table[frame_id.0].borrow_mut();
-        pte.page_id = None;
-        pte.pin_count = 0;
-        pte.is_dirty = false;
-        return true;        
+        self.disk_manager.deallocate_page(page_id);
+        true
     }
 
     
-    pub fn flush_all_pages(&mut self) {
+    pub fn flush_all_pages(&mut self) -> Result<(), TError> {
         let mut page_ids = Vec::new();
         for page_table_entry in &self.page_table {
             let pte = page_table_entry.borrow();
@@ -265,13 +259,18 @@ impl <'a> BufferPoolManager<'a> {
             }
         }
         for page_id in &page_ids {
-            self.flush_page(page_id)
+            self.flush_page(page_id)?;
         }
-        
+        self.disk_manager.write_free_pages()
+
     }
     
 }
 
+fn no_free_frame() -> TError {
+    TError::ResourceError(String::from("no free frame in the buffer pool, all pages are in use"))
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -282,7 +281,7 @@ mod tests {
         let _setup = TestSetup;
         let pool_size= 4;
         let mut memory = vec![0u8; pool_size * PAGE_SIZE];
-        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2);
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
         let p = buffer_pool.new_page().unwrap();
         assert!(p.borrow().page_id == Some(PageId(1)));
         let pte = buffer_pool.fetch_page(PageId::from(1)).unwrap();
@@ -294,13 +293,102 @@ mod tests {
         let _setup = TestSetup;
         let pool_size= 2;
         let mut memory = vec![0u8; pool_size * PAGE_SIZE];
-        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2);
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
         let p = buffer_pool.new_page().unwrap();
         assert!(p.borrow().page_id == Some(PageId(1)));
         let p2 = buffer_pool.new_page().unwrap();
         assert!(p2.borrow().page_id == Some(PageId(2)));
-        buffer_pool.unpin_page(PageId(1), false);
+        std::mem::drop(p);
         let p3 = buffer_pool.new_page().unwrap();
         assert!(p3.borrow().page_id == Some(PageId(3)));
     }
+
+    #[test]
+    fn unreferenced_pages_are_evicted() {
+        let _setup = TestSetup;
+        let pool_size= 2;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+        let page_ids: Vec<PageId> = (0..4).map(|i| {
+            let p = buffer_pool.new_page().unwrap();
+            p.borrow_mut().data.fill(i);
+            let page_id = p.borrow().page_id.clone().unwrap();
+            page_id
+        }).collect();
+        // written back when evicted
+        for (i, page_id) in page_ids.iter().enumerate() {
+            let p = buffer_pool.fetch_page(page_id.clone()).unwrap();
+            assert!(p.borrow().data.iter().all(|b| usize::from(*b) == i));
+        }
+    }
+
+    #[test]
+    fn deleted_pages_are_reused() {
+        let _setup = TestSetup;
+        let pool_size= 4;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+        let mut pages: Vec<_> = (0..pool_size).map(|_| buffer_pool.new_page().unwrap()).collect();
+        let page_ids: Vec<PageId> = pages.iter().map(|p| p.borrow().page_id.clone().unwrap()).collect();
+        // all frames are in use until one is deleted
+        assert_eq!(buffer_pool.new_page().err().map(|e| e.sqlstate()), Some("53000"));
+        pages.remove(1);
+        assert!(buffer_pool.delete_page(&page_ids[1]));
+        let p = buffer_pool.new_page().unwrap();
+        assert!(p.borrow().page_id == Some(page_ids[1].clone()));
+        p.borrow_mut().data.fill(1);
+        // pinned pages can't be deleted
+        assert!(!buffer_pool.delete_page(&page_ids[1]));
+        std::mem::drop(p);
+        assert!(buffer_pool.delete_page(&page_ids[1]));
+        // a reused page starts out empty
+        let p = buffer_pool.new_page().unwrap();
+        assert_eq!(vec![0; PAGE_SIZE], *p.borrow().data);
+    }
+
+    #[test]
+    fn cloned_guards_keep_pages_pinned() {
+        let _setup = TestSetup;
+        let pool_size= 2;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+        let p = buffer_pool.new_page().unwrap();
+        let page_id = p.borrow().page_id.clone().unwrap();
+        let kept = p.clone();
+        std::mem::drop(p);
+        // the clone still pins the page, so only the other frame is handed out
+        for _ in 0..3 {
+            let other = buffer_pool.new_page().unwrap();
+            assert!(other.borrow().page_id != Some(page_id.clone()));
+        }
+        assert!(!buffer_pool.delete_page(&page_id));
+        let _other = buffer_pool.new_page().unwrap();
+        assert_eq!(buffer_pool.new_page().err().map(|e| e.sqlstate()), Some("53000"));
+        std::mem::drop(kept);
+        std::mem::drop(_other);
+        assert!(buffer_pool.delete_page(&page_id));
+    }
+
+    #[test]
+    fn only_written_pages_are_dirty() {
+        let _setup = TestSetup;
+        let pool_size= 2;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+        let p = buffer_pool.new_page().unwrap();
+        let page_id = p.borrow().page_id.clone().unwrap();
+        std::mem::drop(p);
+        buffer_pool.flush_page(&page_id).unwrap();
+        // reading a page doesn't mean it has to be written back
+        let p = buffer_pool.fetch_page(page_id.clone()).unwrap();
+        assert!(p.borrow().data.iter().all(|b| *b == 0));
+        assert!(!p.borrow().is_dirty);
+        p.borrow_mut().data[0] = 1;
+        assert!(p.borrow().is_dirty);
+        std::mem::drop(p);
+        buffer_pool.flush_page(&page_id).unwrap();
+        let p = buffer_pool.fetch_page(page_id).unwrap();
+        assert!(!p.borrow().is_dirty);
+        assert_eq!(p.borrow().data[0], 1);
+    }
 }
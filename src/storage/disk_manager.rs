@@ -1,13 +1,39 @@
 
 
-use crate::config::config::{self, DATA_FILE, PAGE_SIZE};
+use crate::config::config::{self, PAGE_SIZE};
+
+use crate::parse::scanner::TError;
 
 use super::buffer_pool::{FrameId, PageId};
 use std::{fs::{self, File, OpenOptions}, io::{Read, Seek, SeekFrom, Write}, path::PathBuf};
 
+// freed pages start with this instead of a table page header,
+// so they can be found again the next time the file is opened
+const FREE_PAGE_MARKER: &[u8; 8] = b"FREEPAGE";
+
+#[cfg(not(test))]
+fn data_file_name() -> String {
+    String::from(config::DATA_FILE)
+}
+
+// tests run in parallel, each one gets a file named after the thread it runs on
+#[cfg(test)]
+fn data_file_name() -> String {
+    let name = std::thread::current().name().unwrap_or("test").replace("::", "-");
+    format!("{}.db", name)
+}
+
+pub fn data_file_path() -> PathBuf {
+    PathBuf::from(config::DATA_DIR).join(data_file_name())
+}
+
 pub struct DiskManager {
     file_dir: PathBuf,
     file: File,
+    // page 0 is the catalog, so this is at least 1
+    next_page_id: usize,
+    // deleted pages, handed out again before the file grows
+    free_pages: Vec<PageId>,
 }
 
 struct PathIndex(pub usize);
@@ -23,38 +49,87 @@ struct PageLocation {
 }
 
 impl DiskManager {
-    pub fn new() -> DiskManager {
-            fs::create_dir_all(config::DATA_DIR).unwrap();
+    pub fn new() -> Result<DiskManager, TError> {
+            fs::create_dir_all(config::DATA_DIR)?;
             let dir = PathBuf::from(config::DATA_DIR);
-            let path = dir.join(config::DATA_FILE);
-            let file = OpenOptions::new().write(true).read(true).create(true).open(path).unwrap();
-            DiskManager {file_dir: dir, file
+            let path = dir.join(data_file_name());
+            let file = OpenOptions::new().write(true).read(true).create(true).truncate(false).open(path)?;
+            let num_pages = usize::try_from(file.metadata()?.len()).unwrap_or(usize::MAX) / PAGE_SIZE;
+            let mut dm = DiskManager {file_dir: dir, file, next_page_id: num_pages.max(1), free_pages: Vec::new()
+            };
+            for index in 1..num_pages {
+                let page_id = PageId(index);
+                if dm.read_page(&page_id)?.starts_with(FREE_PAGE_MARKER) {
+                    dm.free_pages.push(page_id);
+                }
             }
+            Ok(dm)
+    }
+
+    pub fn allocate_page(&mut self) -> PageId {
+        match self.free_pages.pop() {
+            Some(page_id) => page_id,
+            None => {
+                let page_id = PageId(self.next_page_id);
+                self.next_page_id += 1;
+                page_id
+            }
+        }
+    }
+
+    pub fn deallocate_page(&mut self, page_id: &PageId) {
+        if !self.free_pages.contains(page_id) {
+            self.free_pages.push(page_id.clone());
+        }
+    }
+
+    // mark the free pages on disk, done when flushing so a free page
+    // that is allocated again before then is never written twice
+    pub fn write_free_pages(&mut self) -> Result<(), TError> {
+        let mut data = vec![0; PAGE_SIZE];
+        data[..FREE_PAGE_MARKER.len()].copy_from_slice(FREE_PAGE_MARKER);
+        for page_id in self.free_pages.clone() {
+            self.write_page(&page_id, &data)?;
+        }
+        Ok(())
     }
 
     fn get_file(&self, page_id: &PageId) -> PageLocation{
         // for now all pages live in one file
         let index: usize = page_id.0;
-        PageLocation {page_id: page_id.clone(), file: self.file_dir.join(DATA_FILE), index: PathIndex(index) }
+        PageLocation {page_id: page_id.clone(), file: self.file_dir.join(data_file_name()), index: PathIndex(index) }
     }
 
-    pub fn write_page(&mut self, page_id: &PageId, data: &[u8]) {
+    pub fn write_page(&mut self, page_id: &PageId, data: &[u8]) -> Result<(), TError> {
         let loc = self.get_file(page_id);
-        self.file.seek(SeekFrom::Start((loc.index.0 * PAGE_SIZE).try_into().unwrap())).unwrap();
-        self.file.write(data).unwrap();
-        self.file.flush().unwrap();
+        self.file.seek(SeekFrom::Start(page_offset(&loc)?))?;
+        self.file.write_all(data)?;
+        self.file.flush()?;
+        Ok(())
     }
 
-    pub fn read_page(&mut self, page_id: &PageId) -> Vec<u8> {
+    // pages past the end of the file read as zeros
+    pub fn read_page(&mut self, page_id: &PageId) -> Result<Vec<u8>, TError> {
         // for now all pages rae in one file
         let loc = self.get_file(page_id);
-        self.file.seek(SeekFrom::Start((loc.index.0 * PAGE_SIZE).try_into().unwrap())).unwrap();
+        self.file.seek(SeekFrom::Start(page_offset(&loc)?))?;
         let mut buffer = [0; PAGE_SIZE]; // TODO take mutable slice as param and .read into it directly
-        self.file.read(&mut buffer).unwrap();
-        buffer.to_vec()
+        let mut bytes_read = 0;
+        while bytes_read < PAGE_SIZE {
+            match self.file.read(&mut buffer[bytes_read..])? {
+                0 => break,
+                n => bytes_read += n,
+            }
+        }
+        Ok(buffer.to_vec())
     }
 }
 
+fn page_offset(loc: &PageLocation) -> Result<u64, TError> {
+    loc.index.0.checked_mul(PAGE_SIZE).and_then(|offset| u64::try_from(offset).ok())
+        .ok_or_else(|| TError::StorageError(format!("page {} is out of range", loc.page_id.0)))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{config::config::PAGE_SIZE, storage::buffer_pool::{FrameId, PageId}, test::TestSetup};
@@ -64,12 +139,13 @@ mod tests {
 
     #[test]
     fn simple() {
-        let mut dm = DiskManager::new();
+        let mut dm = DiskManager::new().unwrap();
         let data = vec![2; PAGE_SIZE];
         let p = PageId::from(0);
-        dm.write_page(&p, &data);
-        let r = dm.read_page(&p);
+        dm.write_page(&p, &data).unwrap();
+        let r = dm.read_page(&p).unwrap();
         assert_eq!(r, data);
+        assert_eq!(dm.read_page(&PageId::from(3)).unwrap(), vec![0; PAGE_SIZE]);
         let _setup = TestSetup;
     }
 }
\ No newline at end of file
@@ -0,0 +1,96 @@
+use crate::{config::config::PAGE_SIZE, parse::scanner::{INTERNAL_ERROR, TError}};
+
+use super::buffer_pool::{BufferPoolManager, PageId};
+
+
+/**
+ * Overflow pages hold values too big to be kept in their tuple, like TOAST in postgres.
+ * The value is split in chunks stored in a linked list of pages and the tuple keeps
+ * the id of the first one.
+ *
+ *  Page format (size in bytes):
+ *  ----------------------------------------------------
+ *  | NextPageId (4) | ChunkSize (2) | chunk ...        |
+ *  ----------------------------------------------------
+ *
+ *  A next page id of 0 ends the list.
+ */
+const OVERFLOW_PAGE_HEADER_SIZE: usize = 6;
+
+const CHUNK_SIZE: usize = PAGE_SIZE - OVERFLOW_PAGE_HEADER_SIZE;
+
+// writes a value to a new list of overflow pages, returning the id of the first one
+pub fn write_overflow(buffer_pool: &mut BufferPoolManager, value: &[u8]) -> Result<u32, TError> {
+    // written back to front so each page can point to the next, with only one page held at a time
+    let mut next_page_id: u32 = 0;
+    for chunk in value.chunks(CHUNK_SIZE).rev() {
+        let page = buffer_pool.new_page()?;
+        let mut page = page.borrow_mut();
+        page.data[0..4].copy_from_slice(&next_page_id.to_le_bytes());
+        page.data[4..6].copy_from_slice(&u16::try_from(chunk.len()).unwrap().to_le_bytes());
+        page.data[OVERFLOW_PAGE_HEADER_SIZE..OVERFLOW_PAGE_HEADER_SIZE + chunk.len()].copy_from_slice(chunk);
+        next_page_id = page.get_page_id().unwrap().0.try_into().unwrap();
+    }
+    Ok(next_page_id)
+}
+
+// reassembles a value of length bytes from the overflow pages starting at first_page_id
+pub fn read_overflow(buffer_pool: &mut BufferPoolManager, first_page_id: u32, length: usize) -> Result<Vec<u8>, TError> {
+    let mut value = Vec::with_capacity(length);
+    let mut page_id = first_page_id;
+    while page_id != 0 {
+        let (next_page_id, chunk) = read_page(buffer_pool, page_id)?;
+        value.extend(chunk);
+        page_id = next_page_id;
+    }
+    if value.len() != length {
+        return Err(TError::StorageError(format!("overflow value at page {} has {} bytes, expected {}", first_page_id, value.len(), length)));
+    }
+    Ok(value)
+}
+
+// deletes every page of the value starting at first_page_id
+pub fn free_overflow(buffer_pool: &mut BufferPoolManager, first_page_id: u32) -> Result<(), TError> {
+    let mut page_id = first_page_id;
+    while page_id != 0 {
+        let next_page_id = read_page(buffer_pool, page_id)?.0;
+        if !buffer_pool.delete_page(&PageId(page_id.try_into().unwrap())) {
+            return Err(TError::ExecutionError(INTERNAL_ERROR, format!("page {} is still in use", page_id)));
+        }
+        page_id = next_page_id;
+    }
+    Ok(())
+}
+
+fn read_page(buffer_pool: &mut BufferPoolManager, page_id: u32) -> Result<(u32, Vec<u8>), TError> {
+    let page = buffer_pool.fetch_page(PageId(page_id.try_into().unwrap()))?;
+    let page = page.borrow();
+    let next_page_id = u32::from_le_bytes(page.data[0..4].try_into().unwrap());
+    let chunk_size = usize::from(u16::from_le_bytes(page.data[4..6].try_into().unwrap()));
+    let chunk = page.data.get(OVERFLOW_PAGE_HEADER_SIZE..OVERFLOW_PAGE_HEADER_SIZE + chunk_size)
+        .ok_or_else(|| TError::StorageError(format!("overflow page {} holds more than fits in it", page_id)))?;
+    Ok((next_page_id, chunk.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::config::PAGE_SIZE, storage::buffer_pool::BufferPoolManager, test::TestSetup};
+
+    use super::{free_overflow, read_overflow, write_overflow};
+
+    #[test]
+    fn values_span_pages() {
+        let _setup = TestSetup;
+        let pool_size = 3;
+        let mut memory = vec![0u8; pool_size * PAGE_SIZE];
+        let mut buffer_pool = BufferPoolManager::new(&mut memory, pool_size, 2).unwrap();
+
+        // more pages than the pool has frames
+        let value: Vec<u8> = (0..5 * PAGE_SIZE).map(|i| (i % 251) as u8).collect();
+        let first_page_id = write_overflow(&mut buffer_pool, &value).unwrap();
+        assert_eq!(read_overflow(&mut buffer_pool, first_page_id, value.len()).unwrap(), value);
+        assert!(read_overflow(&mut buffer_pool, first_page_id, 10).is_err());
+
+        free_overflow(&mut buffer_pool, first_page_id).unwrap();
+    }
+}
@@ -1,8 +1,6 @@
-use std::{cell::RefCell, rc::Rc};
+use crate::{config::config::PAGE_SIZE, parse::scanner::TError};
 
-use crate::config::config::PAGE_SIZE;
-
-use super::buffer_pool::PageTableEntry;
+use super::buffer_pool::PageGuard;
 
 
 /**
@@ -24,24 +22,35 @@ use super::buffer_pool::PageTableEntry;
  * 
  *  Tuple entry format:
  *  | tuple offset (2) | tuple size (2)  | tuple meta (4)
+ *
+ *  Tuple meta is a set of flags, deleted tuples keep their slot and space until the page is compacted
  */
 
 const TABLE_PAGE_HEADER_SIZE: usize = 8; // in bytes
 
 const SLOT_ARRAY_ENTRY_SIZE: usize = 8;
 
-#[derive(Eq, Hash, PartialEq, Clone)]
+const TUPLE_DELETED: u32 = 1;
+
+#[derive(Eq, Hash, PartialEq, Clone, Debug)]
 pub struct TupleId(pub usize);
 
+// where a tuple is stored in its table
+#[derive(Eq, Hash, PartialEq, Clone, Debug)]
+pub struct RecordId {
+    pub page_id: u32,
+    pub tuple_id: TupleId,
+}
+
 pub struct TablePage<'a> {
     next_page_id: Option<u32>,
     num_tuples: u16,
     num_deleted_tuples: u16,
-    page: Rc<RefCell<PageTableEntry<'a>>>,
+    page: PageGuard<'a>,
 }
 
 impl <'a> TablePage<'a> {
-    pub fn new(page: Rc<RefCell<PageTableEntry<'a>>>) -> TablePage<'a> {
+    pub fn new(page: PageGuard<'a>) -> TablePage<'a> {
         let p = page.borrow();
 
         let next_page_id: &[u8] = &p.data[0..4];
@@ -60,6 +69,15 @@ impl <'a> TablePage<'a> {
         TablePage { next_page_id, num_tuples, num_deleted_tuples, page }
     }
 
+    pub fn get_next_page_id(&self) -> Option<u32> {
+        self.next_page_id
+    }
+
+    pub fn set_next_page_id(&mut self, next_page_id: u32) {
+        self.next_page_id = Some(next_page_id);
+        self.page.borrow_mut().data[0..4].copy_from_slice(&next_page_id.to_le_bytes());
+    }
+
     pub fn get_num_tuples(&self) -> u16 {
         self.num_tuples
     }
@@ -84,7 +102,7 @@ impl <'a> TablePage<'a> {
         } else {
             slot_end_offset = PAGE_SIZE;
         }
-        let proposed_tuple_offset = slot_end_offset - tuple.len();
+        let proposed_tuple_offset = slot_end_offset.checked_sub(tuple.len())?;
         if TABLE_PAGE_HEADER_SIZE + (num_tuples + 1) * SLOT_ARRAY_ENTRY_SIZE < proposed_tuple_offset {
             Some(proposed_tuple_offset)
         } else {
@@ -121,20 +139,89 @@ impl <'a> TablePage<'a> {
         }
     }
 
-    pub fn get_tuple(&self, tuple_id: TupleId) -> Vec<u8> {
-        if tuple_id.0 < self.get_num_tuples().into() {
-            let slot_index = TABLE_PAGE_HEADER_SIZE + tuple_id.0 * SLOT_ARRAY_ENTRY_SIZE;
-            
-            let tuple_offset_size_meta = &self.page.borrow().data[slot_index..slot_index+SLOT_ARRAY_ENTRY_SIZE];
-            let tuple_offset: usize = u16::from_le_bytes(tuple_offset_size_meta[0..2].try_into().unwrap()).try_into().unwrap();
-            let tuple_size: usize = u16::from_le_bytes(tuple_offset_size_meta[2..4].try_into().unwrap()).try_into().unwrap();
+    fn set_num_deleted_tuples(&mut self, num_deleted_tuples: u16) {
+        self.num_deleted_tuples = num_deleted_tuples;
+        let num_deleted_tuples = num_deleted_tuples.to_le_bytes();
+        self.page.borrow_mut().data[6..8].copy_from_slice(&num_deleted_tuples);
+    }
 
-            let tuple = self.page.borrow().data[tuple_offset..tuple_offset+tuple_size].to_vec();
+    fn get_tuple_meta(&self, tuple_id: &TupleId) -> u32 {
+        let meta_index = TABLE_PAGE_HEADER_SIZE + tuple_id.0 * SLOT_ARRAY_ENTRY_SIZE + 4;
+        u32::from_le_bytes(self.page.borrow().data[meta_index..meta_index+4].try_into().unwrap())
+    }
+
+    pub fn is_deleted(&self, tuple_id: &TupleId) -> bool {
+        self.get_tuple_meta(tuple_id) & TUPLE_DELETED != 0
+    }
+
+    pub fn delete_tuple(&mut self, tuple_id: &TupleId) -> Result<(), TError> {
+        self.check_tuple_id(tuple_id)?;
+        if self.is_deleted(tuple_id) {
+            return Ok(());
+        }
+        let meta = self.get_tuple_meta(tuple_id) | TUPLE_DELETED;
+        let meta_index = TABLE_PAGE_HEADER_SIZE + tuple_id.0 * SLOT_ARRAY_ENTRY_SIZE + 4;
+        self.page.borrow_mut().data[meta_index..meta_index+4].copy_from_slice(&meta.to_le_bytes());
+        self.set_num_deleted_tuples(self.num_deleted_tuples + 1);
+        Ok(())
+    }
 
-            tuple
+    // moves the tuples that aren't deleted together and forgets the deleted ones, which renumbers
+    // the tuples, so this is only for pages nothing refers into by tuple id, like the catalog
+    pub fn compact(&mut self) -> Result<(), TError> {
+        if self.num_deleted_tuples == 0 {
+            return Ok(());
+        }
+        let mut live = Vec::new();
+        for tuple_id in (0..usize::from(self.num_tuples)).map(TupleId) {
+            if !self.is_deleted(&tuple_id) {
+                live.push(self.get_tuple(tuple_id)?);
+            }
+        }
+        self.set_num_tuples(0);
+        self.set_num_deleted_tuples(0);
+        for tuple in live {
+            // they fit with the deleted ones in between, so they fit without
+            self.insert_tuple(tuple);
+        }
+        Ok(())
+    }
+
+    // overwrites a tuple with one of the same size
+    pub fn update_tuple(&mut self, tuple_id: &TupleId, tuple: &[u8]) -> Result<(), TError> {
+        let (tuple_offset, tuple_size) = self.tuple_location(tuple_id)?;
+        if tuple_size != tuple.len() {
+            return Err(TError::StorageError(format!("tuple {} can't change its size from {} to {} bytes in place", tuple_id.0, tuple_size, tuple.len())));
+        }
+        self.page.borrow_mut().data[tuple_offset..tuple_offset+tuple_size].copy_from_slice(tuple);
+        Ok(())
+    }
+
+    pub fn get_tuple(&self, tuple_id: TupleId) -> Result<Vec<u8>, TError> {
+        let (tuple_offset, tuple_size) = self.tuple_location(&tuple_id)?;
+        Ok(self.page.borrow().data[tuple_offset..tuple_offset+tuple_size].to_vec())
+    }
+
+    fn check_tuple_id(&self, tuple_id: &TupleId) -> Result<(), TError> {
+        let slot_end = TABLE_PAGE_HEADER_SIZE + (tuple_id.0 + 1) * SLOT_ARRAY_ENTRY_SIZE;
+        if tuple_id.0 < self.get_num_tuples().into() && slot_end <= PAGE_SIZE {
+            Ok(())
         } else {
-            panic!("invalid tuple id for this page");
-         }
+            Err(TError::StorageError(format!("invalid tuple id {} for a page with {} tuples", tuple_id.0, self.get_num_tuples())))
+        }
+    }
+
+    // offset and size of a tuple, checked to be inside the page
+    fn tuple_location(&self, tuple_id: &TupleId) -> Result<(usize, usize), TError> {
+        self.check_tuple_id(tuple_id)?;
+        let slot_index = TABLE_PAGE_HEADER_SIZE + tuple_id.0 * SLOT_ARRAY_ENTRY_SIZE;
+        let page = self.page.borrow();
+        let tuple_offset = usize::from(u16::from_le_bytes([page.data[slot_index], page.data[slot_index+1]]));
+        let tuple_size = usize::from(u16::from_le_bytes([page.data[slot_index+2], page.data[slot_index+3]]));
+        if tuple_offset + tuple_size > page.data.len() {
+            return Err(TError::StorageError(format!("tuple {} lies outside of its page", tuple_id.0)));
+        }
+        Ok((tuple_offset, tuple_size))
     }
 
 }
@@ -143,7 +230,7 @@ impl <'a> TablePage<'a> {
 mod tests {
     use std::{cell::RefCell, rc::Rc};
 
-    use crate::{config::config::PAGE_SIZE, storage::{buffer_pool::{FrameId, PageTableEntry}, table_page::TupleId}, test::TestSetup};
+    use crate::{config::config::PAGE_SIZE, storage::{buffer_pool::{FrameId, PageGuard, PageTableEntry}, table_page::TupleId}, test::TestSetup};
 
     use super::{TablePage};
 
@@ -159,7 +246,7 @@ mod tests {
             0x40, 0x00 // size = 64
         ];
         let pte = PageTableEntry::new(FrameId::from(0), &mut page_data);
-        let p = TablePage::new(Rc::from(RefCell::from(pte)));
+        let p = TablePage::new(PageGuard::new(Rc::from(RefCell::from(pte)), Rc::default()));
         assert_eq!(p.get_num_tuples(), 1);
         
         let tuple: Vec<u8> = vec![0; 32];
@@ -186,7 +273,7 @@ mod tests {
         ];
         page_data[..start.len()].copy_from_slice(&start);
         let pte = PageTableEntry::new(FrameId::from(0), &mut page_data);
-        let mut p = TablePage::new(Rc::from(RefCell::from(pte)));
+        let mut p = TablePage::new(PageGuard::new(Rc::from(RefCell::from(pte)), Rc::default()));
         assert_eq!(p.get_num_tuples(), 1);
         
         let tuple: Vec<u8> = vec![0xFF; 32];
@@ -194,10 +281,53 @@ mod tests {
         let res = p.insert_tuple(tuple.clone());
         assert!(res.is_some_and(|x| x == TupleId(1)));
 
-        let res = p.get_tuple(TupleId(1));
+        let res = p.get_tuple(TupleId(1)).unwrap();
         assert_eq!(res, tuple);
+        assert!(p.get_tuple(TupleId(2)).is_err());
 
         assert_eq!(p.get_num_tuples(), 2);
         let _setup = TestSetup;
     }
+
+    #[test]
+    fn test_delete_tuple() {
+        let mut page_data: Vec<u8> = vec![0; PAGE_SIZE];
+        let pte = PageTableEntry::new(FrameId::from(0), &mut page_data);
+        let mut p = TablePage::new(PageGuard::new(Rc::from(RefCell::from(pte)), Rc::default()));
+        p.insert_tuple(vec![1; 8]);
+        p.insert_tuple(vec![2; 8]);
+
+        p.delete_tuple(&TupleId(0)).unwrap();
+        p.delete_tuple(&TupleId(0)).unwrap();
+        assert!(p.delete_tuple(&TupleId(2)).is_err());
+        assert!(p.update_tuple(&TupleId(1), &[3; 4]).is_err());
+        assert!(p.is_deleted(&TupleId(0)));
+        assert!(!p.is_deleted(&TupleId(1)));
+        std::mem::drop(p);
+        // the count is stored in the header
+        assert_eq!(page_data[6..8], [1, 0]);
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut page_data: Vec<u8> = vec![0; PAGE_SIZE];
+        let pte = PageTableEntry::new(FrameId::from(0), &mut page_data);
+        let mut p = TablePage::new(PageGuard::new(Rc::from(RefCell::from(pte)), Rc::default()));
+        let mut inserted = 0;
+        while p.insert_tuple(vec![inserted; 100]).is_some() {
+            inserted += 1;
+        }
+        for tuple_id in (0..usize::from(inserted)).filter(|i| i % 2 == 0) {
+            p.delete_tuple(&TupleId(tuple_id)).unwrap();
+        }
+        assert!(p.insert_tuple(vec![0xFF; 100]).is_none());
+
+        p.compact().unwrap();
+        assert_eq!(p.get_num_tuples(), u16::from(inserted / 2));
+        assert_eq!(p.get_tuple(TupleId(0)).unwrap(), vec![1; 100]);
+        assert!(!p.is_deleted(&TupleId(0)));
+        assert!(p.insert_tuple(vec![0xFF; 100]).is_some());
+        std::mem::drop(p);
+        assert_eq!(page_data[6..8], [0, 0]);
+    }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
-use std::{fs, path::{Path, PathBuf}};
+use std::fs;
 
-use crate::config;
+use crate::storage::disk_manager::data_file_path;
 
 
 pub struct TestSetup;
@@ -8,10 +8,63 @@ pub struct TestSetup;
 impl Drop for TestSetup {
     fn drop(&mut self) {
         // destroy db
-        let dir = PathBuf::from(config::config::DATA_DIR);
-        let data_file_path = dir.join(config::config::DATA_FILE);
+        let data_file_path = data_file_path();
         if data_file_path.exists() {
             fs::remove_file(data_file_path).unwrap();
         }
     }
-}
\ No newline at end of file
+}
+
+// the seed of Random unless RANDOM_SEED is set, so the random tests are the same on every run
+#[cfg(test)]
+const DEFAULT_SEED: u64 = 0x2545f4914f6cdd1d;
+
+// xorshift, which prints its seed when a test using it fails so the failure can be reproduced with RANDOM_SEED
+#[cfg(test)]
+pub struct Random {
+    seed: u64,
+    state: u64,
+}
+
+#[cfg(test)]
+impl Random {
+    pub fn new() -> Random {
+        let seed = std::env::var("RANDOM_SEED").ok()
+            .map(|seed| parse_seed(&seed).unwrap_or_else(|| panic!("RANDOM_SEED {:?} is not a number", seed)))
+            .unwrap_or(DEFAULT_SEED);
+        Random::from_seed(seed)
+    }
+
+    pub fn from_seed(seed: u64) -> Random {
+        // xorshift would stay at zero forever
+        Random { seed, state: if seed == 0 { DEFAULT_SEED } else { seed } }
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state % n as u64) as usize
+    }
+
+    pub fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+#[cfg(test)]
+impl Drop for Random {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            eprintln!("random values came from RANDOM_SEED={:#x}", self.seed);
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse_seed(seed: &str) -> Option<u64> {
+    match seed.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => seed.parse().ok(),
+    }
+}
@@ -0,0 +1,8 @@
+pub mod array;
+pub mod bytea;
+pub mod cast;
+pub mod datetime;
+pub mod decimal;
+pub mod json;
+pub mod numeric;
+pub mod uuid;
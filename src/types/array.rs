@@ -0,0 +1,160 @@
+use std::fmt;
+
+use crate::{catalog::table_schema::{decode_value, encode_value, ColumnType}, parse::{scanner::TError, token::LiteralValue}};
+
+
+/**
+ * Arrays have one dimension and elements of a single type, which may be null. They are
+ * stored as
+ *  | number of elements (4) | null bitmap | values of the non null elements |
+ * where the values are encoded like in a tuple, and the whole is a varlena like text.
+ */
+pub fn encode_array(elements: &[LiteralValue], element_type: &ColumnType, res: &mut Vec<u8>) -> Result<(), TError> {
+    res.extend(u32::try_from(elements.len()).unwrap().to_le_bytes());
+    let mut null_bitmap = vec![0u8; elements.len().div_ceil(8)];
+    for (index, element) in elements.iter().enumerate() {
+        if let LiteralValue::NullValue = element {
+            null_bitmap[index / 8] |= 1 << (index % 8);
+        }
+    }
+    res.extend(null_bitmap);
+    for element in elements.iter().filter(|e| !matches!(e, LiteralValue::NullValue)) {
+        encode_value(element, element_type, res)?;
+    }
+    Ok(())
+}
+
+pub fn decode_array(data: &[u8], element_type: &ColumnType) -> Result<Vec<LiteralValue>, TError> {
+    let truncated = || TError::StorageError(String::from("stored array is truncated"));
+    let len = usize::try_from(u32::from_le_bytes(data.get(0..4).ok_or_else(truncated)?.try_into().unwrap())).unwrap();
+    let null_bitmap = data.get(4..4 + len.div_ceil(8)).ok_or_else(truncated)?;
+    let mut bytes_read = 4 + null_bitmap.len();
+    (0..len).map(|index| {
+        if null_bitmap[index / 8] & (1 << (index % 8)) != 0 {
+            Ok(LiteralValue::NullValue)
+        } else {
+            decode_value(element_type, data, &mut bytes_read)
+        }
+    }).collect()
+}
+
+/**
+ * Reads the text of an array, like {1,2,NULL} or {"a b","c\"d"}, into the text of its
+ * elements, None for the null ones. Elements can be quoted and backslash escapes the next
+ * character, unquoted elements have the whitespace around them removed.
+ */
+pub fn parse_array(text: &str) -> Option<Vec<Option<String>>> {
+    let inner = text.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let mut elements = Vec::new();
+    if inner.trim().is_empty() {
+        return Some(elements);
+    }
+    let mut chars = inner.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut element = String::new();
+        let quoted = chars.next_if_eq(&'"').is_some();
+        if quoted {
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => element.push(chars.next()?),
+                    c => element.push(c),
+                }
+            }
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        } else {
+            let mut escaped = false;
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                match c {
+                    // nested arrays aren't supported
+                    '"' | '{' | '}' => return None,
+                    '\\' => {
+                        element.push(chars.next()?);
+                        escaped = true;
+                    },
+                    c => element.push(c),
+                }
+            }
+            let trimmed = element.trim_end();
+            if trimmed.is_empty() {
+                return None;
+            }
+            element = trimmed.to_owned();
+            if !escaped && element.eq_ignore_ascii_case("null") {
+                elements.push(None);
+                if chars.next().is_none() {
+                    return Some(elements);
+                }
+                continue;
+            }
+        }
+        elements.push(Some(element));
+        match chars.next() {
+            Some(',') => {},
+            None => return Some(elements),
+            Some(_) => return None,
+        }
+    }
+}
+
+// {1,2,NULL}, elements that would read back differently are quoted
+pub struct DisplayArray<'a>(pub &'a [LiteralValue]);
+
+impl fmt::Display for DisplayArray<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+        for (index, element) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            if let LiteralValue::NullValue = element {
+                write!(f, "NULL")?;
+                continue;
+            }
+            let text = element.to_string();
+            let needs_quotes = text.is_empty() || text.eq_ignore_ascii_case("null")
+                || text.chars().any(|c| matches!(c, '{' | '}' | ',' | '"' | '\\') || c.is_whitespace());
+            if needs_quotes {
+                write!(f, "\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))?;
+            } else {
+                write!(f, "{}", text)?;
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{catalog::table_schema::ColumnType, parse::{scanner::TError, token::LiteralValue}};
+
+    use super::{decode_array, encode_array, parse_array, DisplayArray};
+
+    #[test]
+    fn parse_print_and_encode() {
+        let text = |s: &str| Some(s.to_owned());
+        assert_eq!(parse_array("{}"), Some(Vec::new()));
+        assert_eq!(parse_array(" { 1 , 2,NULL } "), Some(vec![text("1"), text("2"), None]));
+        assert_eq!(parse_array(r#"{"a b","c\"d",\null,"NULL"}"#), Some(vec![text("a b"), text("c\"d"), text("null"), text("NULL")]));
+        for invalid in ["", "1,2", "{1,}", "{,1}", "{{1},{2}}", "{\"a\"b}", "{\"a"] {
+            assert_eq!(parse_array(invalid), None, "{}", invalid);
+        }
+
+        let values = vec![LiteralValue::StringValue("a b".to_owned()), LiteralValue::NullValue, LiteralValue::StringValue("".to_owned()), LiteralValue::StringValue("x".to_owned())];
+        assert_eq!(DisplayArray(&values).to_string(), r#"{"a b",NULL,"",x}"#);
+        let mut encoded = Vec::new();
+        encode_array(&values, &ColumnType::Text, &mut encoded).unwrap();
+        assert_eq!(decode_array(&encoded, &ColumnType::Text).unwrap(), values);
+        for len in [2, 4, encoded.len() - 1] {
+            assert!(matches!(decode_array(&encoded[..len], &ColumnType::Text), Err(TError::StorageError(_))), "{}", len);
+        }
+
+        let values = vec![LiteralValue::IntValue(-7), LiteralValue::IntValue(300)];
+        let mut encoded = Vec::new();
+        encode_array(&values, &ColumnType::SmallInt, &mut encoded).unwrap();
+        assert_eq!(encoded.len(), 4 + 1 + 2 * 2);
+        assert_eq!(decode_array(&encoded, &ColumnType::SmallInt).unwrap(), values);
+        assert!(matches!(encode_array(&[LiteralValue::IntValue(40000)], &ColumnType::SmallInt, &mut Vec::new()), Err(TError::ExecutionError(..))));
+    }
+}
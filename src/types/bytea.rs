@@ -0,0 +1,66 @@
+use std::fmt;
+
+
+/**
+ * Reads the text form of a bytea. The hex format is \x followed by pairs of hex digits,
+ * anything else is the escape format where \\ is a backslash, \ and three octal digits
+ * is that byte and other characters stand for their utf-8 bytes.
+ */
+pub fn parse_bytea(text: &str) -> Option<Vec<u8>> {
+    if let Some(hex) = text.strip_prefix("\\x").or_else(|| text.strip_prefix("\\X")) {
+        let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+        if !digits.len().is_multiple_of(2) {
+            return None;
+        }
+        return digits.chunks(2).map(|pair| u8::from_str_radix(str::from_utf8(pair).ok()?, 16).ok()).collect();
+    }
+    let mut res = Vec::new();
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match &bytes[i..] {
+            [b'\\', b'\\', ..] => {
+                res.push(b'\\');
+                i += 2;
+            },
+            [b'\\', a @ b'0'..=b'3', b @ b'0'..=b'7', c @ b'0'..=b'7', ..] => {
+                res.push((a - b'0') * 64 + (b - b'0') * 8 + (c - b'0'));
+                i += 4;
+            },
+            [b'\\', ..] => return None,
+            [byte, ..] => {
+                res.push(*byte);
+                i += 1;
+            },
+            [] => unreachable!(),
+        }
+    }
+    Some(res)
+}
+
+// bytea values are shown in the hex format
+pub struct DisplayBytea<'a>(pub &'a [u8]);
+
+impl fmt::Display for DisplayBytea<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\\x")?;
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_bytea, DisplayBytea};
+
+    #[test]
+    fn hex_and_escape_formats() {
+        assert_eq!(parse_bytea("\\xDEAD beef"), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(parse_bytea("a\\\\b\\000"), Some(vec![b'a', b'\\', b'b', 0]));
+        assert_eq!(parse_bytea("\\xabc"), None);
+        assert_eq!(parse_bytea("\\q"), None);
+        assert_eq!(DisplayBytea(&[0, 0x1f, 0xff]).to_string(), "\\x001fff");
+    }
+}
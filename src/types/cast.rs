@@ -0,0 +1,170 @@
+use crate::{catalog::table_schema::ColumnType, parse::{scanner::{INVALID_DATETIME_FORMAT, INVALID_PARAMETER_VALUE, INVALID_TEXT_REPRESENTATION, TError}, token::LiteralValue}};
+
+use super::{array::parse_array, bytea::parse_bytea, json::Json, datetime::{cast_temporal, is_temporal, parse_date, parse_time, parse_timestamp, Interval}, decimal::Decimal, numeric::{cast_numeric, is_integer, is_numeric, rank}, uuid::parse_uuid};
+
+
+/**
+ * Where a conversion may happen without being asked for. Implicit conversions are made
+ * anywhere values of two types meet, assignment ones only when a value is written to a
+ * column and explicit ones only with CAST. Later contexts allow everything earlier ones do.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CoercionContext {
+    Implicit,
+    Assignment,
+    Explicit,
+}
+
+/**
+ * The coercion table, the first context a value of one type can be converted to another in,
+ * None if it can't be at all. Numbers widen implicitly and narrow on assignment, checking
+ * that they fit, dates widen to timestamps and everything can be written to text.
+ * String literals are not in here, the planner converts them to whatever type is needed.
+ */
+pub fn coercion(from: &ColumnType, to: &ColumnType) -> Option<CoercionContext> {
+    use ColumnType::*;
+    let context = match (from, to) {
+        (Decimal(_), Decimal(_)) => CoercionContext::Implicit,
+        // arrays convert like their elements
+        (Array(from), Array(to)) => return coercion(from, to),
+        _ if from == to => CoercionContext::Implicit,
+        _ if is_numeric(from) && is_numeric(to) => {
+            if rank(from) <= rank(to) { CoercionContext::Implicit } else { CoercionContext::Assignment }
+        },
+        (Date, Timestamp) => CoercionContext::Implicit,
+        (Timestamp, Date | Time) => CoercionContext::Assignment,
+        (_, Text) => CoercionContext::Assignment,
+        (Text, _) => CoercionContext::Explicit,
+        (Bool, t) | (t, Bool) if is_integer(t) => CoercionContext::Explicit,
+        (Json, t) if is_numeric(t) || *t == Bool => CoercionContext::Explicit,
+        _ => return None,
+    };
+    Some(context)
+}
+
+fn invalid_input(column_type: &ColumnType, text: &str) -> TError {
+    let code = if is_temporal(column_type) { INVALID_DATETIME_FORMAT } else { INVALID_TEXT_REPRESENTATION };
+    TError::ExecutionError(code, format!("invalid input syntax for type {}: {:?}", column_type, text))
+}
+
+// converts a value to any type the coercion table has a conversion to, nulls stay null
+pub fn cast(value: &LiteralValue, to: &ColumnType) -> Result<LiteralValue, TError> {
+    match (value, to) {
+        (LiteralValue::NullValue, _) => Ok(LiteralValue::NullValue),
+        (LiteralValue::StringValue(_), ColumnType::Text) => Ok(value.clone()),
+        (LiteralValue::StringValue(text), _) => parse_value(text, to),
+        (_, ColumnType::Text) => Ok(LiteralValue::StringValue(value.to_string())),
+        (LiteralValue::BooleanValue(b), t) if is_integer(t) => Ok(LiteralValue::IntValue(i64::from(*b))),
+        (LiteralValue::IntValue(i), ColumnType::Bool) => Ok(LiteralValue::BooleanValue(*i != 0)),
+        (LiteralValue::BooleanValue(_), ColumnType::Bool) => Ok(value.clone()),
+        (LiteralValue::JsonValue(json), _) => cast_json(json, to),
+        (LiteralValue::ArrayValue(elements), ColumnType::Array(element_type)) => {
+            let elements = elements.iter().map(|e| cast(e, element_type)).collect::<Result<_, _>>()?;
+            Ok(LiteralValue::ArrayValue(elements))
+        },
+        (_, t) if is_temporal(t) => cast_temporal(value, to),
+        _ => cast_numeric(value, to),
+    }
+}
+
+// reads a value of a type from its text, the way it is displayed or written in a literal
+fn parse_value(text: &str, to: &ColumnType) -> Result<LiteralValue, TError> {
+    if let ColumnType::Array(element_type) = to {
+        let elements = match parse_array(text) {
+            Some(elements) => elements,
+            None => return Err(TError::ExecutionError(INVALID_TEXT_REPRESENTATION, format!("malformed array literal: {:?}", text))),
+        };
+        let elements = elements.iter().map(|e| match e {
+            Some(e) => parse_value(e, element_type),
+            None => Ok(LiteralValue::NullValue),
+        }).collect::<Result<_, _>>()?;
+        return Ok(LiteralValue::ArrayValue(elements));
+    }
+    let trimmed = text.trim();
+    let value = match to {
+        t if is_integer(t) => trimmed.parse::<i64>().ok().map(LiteralValue::IntValue),
+        ColumnType::Real | ColumnType::Double => match trimmed.to_lowercase().as_str() {
+            "nan" => Some(LiteralValue::FloatValue(f64::NAN)),
+            "infinity" | "+infinity" | "inf" => Some(LiteralValue::FloatValue(f64::INFINITY)),
+            "-infinity" | "-inf" => Some(LiteralValue::FloatValue(f64::NEG_INFINITY)),
+            // rust also reads words like "infinity" in other spellings, those are handled above
+            lower if lower.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | '+' | '-')) => {
+                lower.parse::<f64>().ok().map(LiteralValue::FloatValue)
+            },
+            _ => None,
+        },
+        ColumnType::Decimal(_) => Decimal::parse(trimmed).map(LiteralValue::DecimalValue),
+        ColumnType::Bool => match trimmed.to_lowercase().as_str() {
+            "true" | "t" | "yes" | "y" | "on" | "1" => Some(LiteralValue::BooleanValue(true)),
+            "false" | "f" | "no" | "n" | "off" | "0" => Some(LiteralValue::BooleanValue(false)),
+            _ => None,
+        },
+        ColumnType::Date => parse_date(trimmed).map(LiteralValue::DateValue),
+        ColumnType::Time => parse_time(trimmed).map(LiteralValue::TimeValue),
+        ColumnType::Timestamp => parse_timestamp(trimmed).map(LiteralValue::TimestampValue),
+        ColumnType::Interval => Interval::parse(trimmed).map(LiteralValue::IntervalValue),
+        ColumnType::Bytea => parse_bytea(text).map(LiteralValue::BytesValue),
+        ColumnType::Json => Json::parse(text).map(LiteralValue::JsonValue),
+        ColumnType::Uuid => parse_uuid(text).map(LiteralValue::UuidValue),
+        _ => Some(LiteralValue::StringValue(text.to_owned())),
+    };
+    match value {
+        // the range of the type and the precision of decimals are checked like for numbers
+        Some(value) if is_numeric(to) => cast_numeric(&value, to),
+        Some(value) => Ok(value),
+        None => Err(invalid_input(to, text)),
+    }
+}
+
+// json numbers and bools convert to those types, other json values don't convert at all
+fn cast_json(json: &Json, to: &ColumnType) -> Result<LiteralValue, TError> {
+    match (json, to) {
+        (Json::Bool(b), ColumnType::Bool) => Ok(LiteralValue::BooleanValue(*b)),
+        (Json::Number(n), t) if is_numeric(t) => {
+            let value = match Decimal::parse(&n.to_string()) {
+                Some(d) if !matches!(t, ColumnType::Real | ColumnType::Double) => LiteralValue::DecimalValue(d),
+                _ => LiteralValue::FloatValue(*n),
+            };
+            cast_numeric(&value, to)
+        },
+        _ => Err(TError::ExecutionError(INVALID_PARAMETER_VALUE, format!("cannot cast json {} to type {}", json, to))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{catalog::table_schema::ColumnType, parse::token::LiteralValue};
+
+    use super::{cast, coercion, CoercionContext};
+
+    #[test]
+    fn coercion_table_and_casts() {
+        assert_eq!(coercion(&ColumnType::Int, &ColumnType::Decimal(Some((5, 2)))), Some(CoercionContext::Implicit));
+        assert_eq!(coercion(&ColumnType::BigInt, &ColumnType::SmallInt), Some(CoercionContext::Assignment));
+        assert_eq!(coercion(&ColumnType::BigInt, &ColumnType::Real), Some(CoercionContext::Implicit));
+        assert_eq!(coercion(&ColumnType::Date, &ColumnType::Timestamp), Some(CoercionContext::Implicit));
+        assert_eq!(coercion(&ColumnType::Bool, &ColumnType::Text), Some(CoercionContext::Assignment));
+        assert_eq!(coercion(&ColumnType::Text, &ColumnType::Int), Some(CoercionContext::Explicit));
+        assert_eq!(coercion(&ColumnType::Date, &ColumnType::Int), None);
+
+        let text = |s: &str| LiteralValue::StringValue(s.to_owned());
+        assert_eq!(cast(&text(" 42 "), &ColumnType::SmallInt).unwrap(), LiteralValue::IntValue(42));
+        assert!(cast(&text("70000"), &ColumnType::SmallInt).is_err());
+        assert!(cast(&text("4x"), &ColumnType::Int).is_err());
+        assert!(cast(&text("infinity"), &ColumnType::Double).unwrap() == LiteralValue::FloatValue(f64::INFINITY));
+        assert!(cast(&text("info"), &ColumnType::Double).is_err());
+        assert_eq!(cast(&text("1.005"), &ColumnType::Decimal(Some((3, 2)))).unwrap().to_string(), "1.01");
+        assert_eq!(cast(&text("Yes"), &ColumnType::Bool).unwrap(), LiteralValue::BooleanValue(true));
+        assert_eq!(cast(&text("2026-10-19"), &ColumnType::Date).unwrap().to_string(), "2026-10-19");
+        assert_eq!(cast(&LiteralValue::FloatValue(1.5), &ColumnType::Text).unwrap(), text("1.5"));
+        assert_eq!(cast(&LiteralValue::BooleanValue(true), &ColumnType::Int).unwrap(), LiteralValue::IntValue(1));
+        assert_eq!(cast(&LiteralValue::NullValue, &ColumnType::Date).unwrap(), LiteralValue::NullValue);
+
+        let int_array = ColumnType::Array(Box::new(ColumnType::Int));
+        assert_eq!(coercion(&int_array, &ColumnType::Array(Box::new(ColumnType::SmallInt))), Some(CoercionContext::Assignment));
+        assert_eq!(coercion(&int_array, &ColumnType::Int), None);
+        assert_eq!(cast(&text("{1, NULL}"), &int_array).unwrap(), LiteralValue::ArrayValue(vec![LiteralValue::IntValue(1), LiteralValue::NullValue]));
+        assert!(cast(&text("{1.5}"), &int_array).is_err());
+        assert_eq!(cast(&LiteralValue::ArrayValue(vec![text("a,b")]), &ColumnType::Text).unwrap(), text("{\"a,b\"}"));
+    }
+}
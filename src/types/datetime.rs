@@ -0,0 +1,614 @@
+use std::{cmp::Ordering, fmt, hash::{Hash, Hasher}, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::{catalog::table_schema::ColumnType, parse::{scanner::{DATA_EXCEPTION, DATETIME_FIELD_OVERFLOW, DIVISION_BY_ZERO, INVALID_PARAMETER_VALUE, TError}, token::LiteralValue}};
+
+use super::numeric::{to_f64, ArithmeticOperator};
+
+
+/**
+ * Dates are days since 1970-01-01, times microseconds since midnight and timestamps
+ * microseconds since 1970-01-01 00:00:00, all without a time zone. Only years 1 to 9999
+ * are valid, so conversions between them can't overflow.
+ */
+pub const MICROS_PER_SECOND: i64 = 1_000_000;
+const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+pub const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+// when intervals are compared or scaled a month is 30 days, like in postgres
+const DAYS_PER_MONTH: i64 = 30;
+
+const MIN_YEAR: i64 = 1;
+const MAX_YEAR: i64 = 9999;
+
+/**
+ * Months, days and microseconds are kept apart, since how long a month or a day is depends
+ * on the date they are added to. Equality and ordering go by the total length though.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+pub fn is_temporal(column_type: &ColumnType) -> bool {
+    matches!(column_type, ColumnType::Date | ColumnType::Time | ColumnType::Timestamp | ColumnType::Interval)
+}
+
+pub fn is_temporal_value(value: &LiteralValue) -> bool {
+    matches!(value, LiteralValue::DateValue(_) | LiteralValue::TimeValue(_) | LiteralValue::TimestampValue(_) | LiteralValue::IntervalValue(_))
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// days since 1970-01-01 of a day in the proleptic gregorian calendar
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// year, month and day of a number of days since 1970-01-01
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = u32::try_from(day_of_year - (153 * shifted_month + 2) / 5 + 1).unwrap();
+    let month = u32::try_from(if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 }).unwrap();
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+fn date_in_range(days: i64) -> bool {
+    (days_from_civil(MIN_YEAR, 1, 1)..=days_from_civil(MAX_YEAR, 12, 31)).contains(&days)
+}
+
+fn checked_date(days: Option<i64>) -> Result<LiteralValue, TError> {
+    match days {
+        Some(days) if date_in_range(days) => Ok(LiteralValue::DateValue(i32::try_from(days).unwrap())),
+        _ => Err(TError::ExecutionError(DATETIME_FIELD_OVERFLOW, String::from("date out of range"))),
+    }
+}
+
+fn checked_timestamp(micros: Option<i64>) -> Result<LiteralValue, TError> {
+    match micros {
+        Some(micros) if date_in_range(micros.div_euclid(MICROS_PER_DAY)) => Ok(LiteralValue::TimestampValue(micros)),
+        _ => Err(TError::ExecutionError(DATETIME_FIELD_OVERFLOW, String::from("timestamp out of range"))),
+    }
+}
+
+fn interval_out_of_range() -> TError {
+    TError::ExecutionError(DATETIME_FIELD_OVERFLOW, String::from("interval out of range"))
+}
+
+pub fn date_to_timestamp(days: i32) -> i64 {
+    i64::from(days) * MICROS_PER_DAY
+}
+
+// YYYY-MM-DD
+pub fn parse_date(s: &str) -> Option<i32> {
+    let mut parts = s.trim().splitn(3, '-');
+    let year_part = parts.next()?;
+    let year: i64 = year_part.parse().ok().filter(|_| year_part.len() >= 4)?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(MIN_YEAR..=MAX_YEAR).contains(&year) || !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    i32::try_from(days_from_civil(year, month, day)).ok()
+}
+
+// HH:MM, HH:MM:SS or HH:MM:SS.ffffff, as microseconds since midnight
+pub fn parse_time(s: &str) -> Option<i64> {
+    let micros = parse_clock(s.trim())?;
+    if micros >= MICROS_PER_DAY {
+        return None;
+    }
+    Some(micros)
+}
+
+// like a time, but hours can go past 23, as in the time part of an interval
+fn parse_clock(s: &str) -> Option<i64> {
+    let mut parts = s.split(':');
+    let hours: i64 = parts.next()?.parse().ok()?;
+    let minutes: i64 = parts.next()?.parse().ok()?;
+    let seconds = match parts.next() {
+        Some(seconds) => parse_seconds(seconds)?,
+        None => 0,
+    };
+    if parts.next().is_some() || hours < 0 || !(0..60).contains(&minutes) {
+        return None;
+    }
+    hours.checked_mul(MICROS_PER_HOUR)?.checked_add(minutes * MICROS_PER_MINUTE + seconds)
+}
+
+// SS or SS.ffffff in microseconds, fractions beyond microseconds are rounded
+fn parse_seconds(s: &str) -> Option<i64> {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if whole.is_empty() || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let whole: i64 = whole.parse().ok()?;
+    let fraction = format!("0.{}", fraction).parse::<f64>().ok()?;
+    let micros = whole * MICROS_PER_SECOND + (fraction * MICROS_PER_SECOND as f64).round() as i64;
+    if micros >= 60 * MICROS_PER_SECOND {
+        return None;
+    }
+    Some(micros)
+}
+
+// a date, optionally followed by a time after a space or a T
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (date, time) = match s.split_once([' ', 'T']) {
+        Some((date, time)) => (date, parse_time(time)?),
+        None => (s, 0),
+    };
+    Some(date_to_timestamp(parse_date(date)?) + time)
+}
+
+fn write_clock(f: &mut fmt::Formatter<'_>, micros: i64) -> fmt::Result {
+    let micros = micros.unsigned_abs();
+    let per_second = MICROS_PER_SECOND.unsigned_abs();
+    let seconds = micros / per_second;
+    write!(f, "{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)?;
+    let fraction = micros % per_second;
+    if fraction != 0 {
+        write!(f, ".{}", format!("{:06}", fraction).trim_end_matches('0'))?;
+    }
+    Ok(())
+}
+
+pub struct DisplayDate(pub i32);
+
+impl fmt::Display for DisplayDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = civil_from_days(i64::from(self.0));
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+pub struct DisplayTime(pub i64);
+
+impl fmt::Display for DisplayTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_clock(f, self.0)
+    }
+}
+
+pub struct DisplayTimestamp(pub i64);
+
+impl fmt::Display for DisplayTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.0.div_euclid(MICROS_PER_DAY);
+        write!(f, "{} ", DisplayDate(i32::try_from(days).unwrap()))?;
+        write_clock(f, self.0.rem_euclid(MICROS_PER_DAY))
+    }
+}
+
+impl Interval {
+    // the length with 30 day months and 24 hour days
+    pub fn total_micros(&self) -> i128 {
+        (i128::from(self.months) * i128::from(DAYS_PER_MONTH) + i128::from(self.days)) * i128::from(MICROS_PER_DAY) + i128::from(self.micros)
+    }
+
+    /**
+     * Quantities with units, like '1 year 2 months', '3 days 04:05:06' or '1.5 hours ago'.
+     * Fractions of months and days carry over into days and microseconds.
+     */
+    pub fn parse(s: &str) -> Option<Interval> {
+        let (mut months, mut days, mut micros) = (0.0, 0.0, 0.0);
+        let mut negate = false;
+        let mut words = s.split_whitespace().peekable();
+        while let Some(word) = words.next() {
+            if word == "ago" && words.peek().is_none() {
+                negate = true;
+            } else if word.contains(':') {
+                let (sign, clock) = match word.strip_prefix('-') {
+                    Some(clock) => (-1.0, clock),
+                    None => (1.0, word.strip_prefix('+').unwrap_or(word)),
+                };
+                micros += sign * parse_clock(clock)? as f64;
+            } else {
+                let quantity: f64 = word.parse().ok().filter(|q: &f64| q.is_finite())?;
+                let unit = words.next()?.to_lowercase();
+                match unit.as_str() {
+                    "microsecond" | "microseconds" | "us" => micros += quantity,
+                    "millisecond" | "milliseconds" | "ms" => micros += quantity * 1000.0,
+                    "second" | "seconds" | "sec" | "secs" | "s" => micros += quantity * MICROS_PER_SECOND as f64,
+                    "minute" | "minutes" | "min" | "mins" | "m" => micros += quantity * MICROS_PER_MINUTE as f64,
+                    "hour" | "hours" | "hr" | "hrs" | "h" => micros += quantity * MICROS_PER_HOUR as f64,
+                    "day" | "days" | "d" => days += quantity,
+                    "week" | "weeks" | "w" => days += quantity * 7.0,
+                    "month" | "months" | "mon" | "mons" => months += quantity,
+                    "year" | "years" | "yr" | "yrs" | "y" => months += quantity * 12.0,
+                    _ => return None,
+                }
+            }
+        }
+        let interval = Interval::from_parts(months, days, micros)?;
+        if negate { interval.checked_neg() } else { Some(interval) }
+    }
+
+    // whole months and days, with what is left of them carried down
+    fn from_parts(months: f64, days: f64, micros: f64) -> Option<Interval> {
+        let whole_months = months.trunc();
+        let days = days + (months - whole_months) * DAYS_PER_MONTH as f64;
+        let whole_days = days.trunc();
+        let micros = (micros + (days - whole_days) * MICROS_PER_DAY as f64).round();
+        let fits = |f: f64, max: f64| f.is_finite() && f.abs() <= max;
+        if !fits(whole_months, f64::from(i32::MAX)) || !fits(whole_days, f64::from(i32::MAX)) || !fits(micros, 9.2e18) {
+            return None;
+        }
+        Some(Interval { months: whole_months as i32, days: whole_days as i32, micros: micros as i64 })
+    }
+
+    pub fn checked_add(&self, other: &Interval) -> Option<Interval> {
+        Some(Interval {
+            months: self.months.checked_add(other.months)?,
+            days: self.days.checked_add(other.days)?,
+            micros: self.micros.checked_add(other.micros)?,
+        })
+    }
+
+    pub fn checked_neg(&self) -> Option<Interval> {
+        Some(Interval { months: self.months.checked_neg()?, days: self.days.checked_neg()?, micros: self.micros.checked_neg()? })
+    }
+
+    pub fn checked_mul(&self, factor: f64) -> Option<Interval> {
+        Interval::from_parts(f64::from(self.months) * factor, f64::from(self.days) * factor, self.micros as f64 * factor)
+    }
+
+    // [months (4)] [days (4)] [micros (8)]
+    pub fn encode(&self, res: &mut Vec<u8>) {
+        res.extend(self.months.to_le_bytes());
+        res.extend(self.days.to_le_bytes());
+        res.extend(self.micros.to_le_bytes());
+    }
+
+    pub fn decode(data: &[u8], bytes_read: &mut usize) -> Interval {
+        let months = i32::from_le_bytes(data[*bytes_read..*bytes_read+4].try_into().unwrap());
+        let days = i32::from_le_bytes(data[*bytes_read+4..*bytes_read+8].try_into().unwrap());
+        let micros = i64::from_le_bytes(data[*bytes_read+8..*bytes_read+16].try_into().unwrap());
+        *bytes_read += 16;
+        Interval { months, days, micros }
+    }
+}
+
+impl Ord for Interval {
+    fn cmp(&self, other: &Interval) -> Ordering {
+        self.total_micros().cmp(&other.total_micros())
+    }
+}
+
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Interval) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Interval {
+    fn eq(&self, other: &Interval) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Interval {}
+
+impl Hash for Interval {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.total_micros().hash(state);
+    }
+}
+
+// like postgres, 1 year 2 mons 3 days 04:05:06
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        let mut push = |n: i64, unit: &str, plural: &str| {
+            if n != 0 {
+                parts.push(format!("{} {}", n, if n == 1 { unit } else { plural }));
+            }
+        };
+        push(i64::from(self.months / 12), "year", "years");
+        push(i64::from(self.months % 12), "mon", "mons");
+        push(i64::from(self.days), "day", "days");
+        let parts = parts.join(" ");
+        if self.micros == 0 && !parts.is_empty() {
+            return write!(f, "{}", parts);
+        }
+        if !parts.is_empty() {
+            write!(f, "{} ", parts)?;
+        }
+        if self.micros < 0 {
+            write!(f, "-")?;
+        }
+        write_clock(f, self.micros)
+    }
+}
+
+pub fn now() -> i64 {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    i64::try_from(since_epoch.as_micros()).unwrap()
+}
+
+// timestamp plus interval, months are added first and keep the day where the month has it
+fn add_interval(timestamp: i64, interval: &Interval) -> Option<i64> {
+    let mut timestamp = timestamp;
+    if interval.months != 0 {
+        let (days, time) = (timestamp.div_euclid(MICROS_PER_DAY), timestamp.rem_euclid(MICROS_PER_DAY));
+        let (year, month, day) = civil_from_days(days);
+        let months = year * 12 + i64::from(month) - 1 + i64::from(interval.months);
+        let (year, month) = (months.div_euclid(12), u32::try_from(months.rem_euclid(12)).unwrap() + 1);
+        if !(MIN_YEAR..=MAX_YEAR).contains(&year) {
+            return None;
+        }
+        let day = day.min(days_in_month(year, month));
+        timestamp = days_from_civil(year, month, day) * MICROS_PER_DAY + time;
+    }
+    timestamp.checked_add(i64::from(interval.days).checked_mul(MICROS_PER_DAY)?)?.checked_add(interval.micros)
+}
+
+// the difference between two timestamps, in days and microseconds
+fn timestamp_difference(left: i64, right: i64) -> Interval {
+    let difference = left - right;
+    Interval { months: 0, days: i32::try_from(difference / MICROS_PER_DAY).unwrap(), micros: difference % MICROS_PER_DAY }
+}
+
+// dates as timestamps, for comparing and subtracting them with timestamps
+fn as_timestamp(value: &LiteralValue) -> Option<i64> {
+    match value {
+        LiteralValue::DateValue(d) => Some(date_to_timestamp(*d)),
+        LiteralValue::TimestampValue(t) => Some(*t),
+        _ => None,
+    }
+}
+
+pub fn compare_temporal(left: &LiteralValue, right: &LiteralValue) -> Option<Ordering> {
+    match (left, right) {
+        (LiteralValue::DateValue(l), LiteralValue::DateValue(r)) => Some(l.cmp(r)),
+        (LiteralValue::TimeValue(l), LiteralValue::TimeValue(r)) => Some(l.cmp(r)),
+        (LiteralValue::IntervalValue(l), LiteralValue::IntervalValue(r)) => Some(l.cmp(r)),
+        _ => Some(as_timestamp(left)?.cmp(&as_timestamp(right)?)),
+    }
+}
+
+/**
+ * The type of date and time arithmetic, None if the operator doesn't apply. Dates move by
+ * whole days, adding an interval to one gives a timestamp and intervals scale by numbers.
+ */
+pub fn temporal_result_type(operator: ArithmeticOperator, left: &ColumnType, right: &ColumnType) -> Option<ColumnType> {
+    use ColumnType::*;
+    let is_number = |t: &ColumnType| matches!(t, SmallInt | Int | BigInt | Real | Double | Decimal(_));
+    let is_int = |t: &ColumnType| matches!(t, SmallInt | Int | BigInt);
+    let result = match operator {
+        ArithmeticOperator::Add => match (left, right) {
+            (Date, t) | (t, Date) if is_int(t) => Date,
+            (Date, Interval | Time) | (Interval | Time, Date) | (Timestamp, Interval) | (Interval, Timestamp) => Timestamp,
+            (Time, Interval) | (Interval, Time) => Time,
+            (Interval, Interval) => Interval,
+            _ => return None,
+        },
+        ArithmeticOperator::Subtract => match (left, right) {
+            (Date, t) if is_int(t) => Date,
+            (Date, Date) => Int,
+            (Date | Timestamp, Interval) => Timestamp,
+            (Date | Timestamp, Date | Timestamp) | (Time, Time) | (Interval, Interval) => Interval,
+            (Time, Interval) => Time,
+            _ => return None,
+        },
+        ArithmeticOperator::Multiply => match (left, right) {
+            (Interval, t) | (t, Interval) if is_number(t) => Interval,
+            _ => return None,
+        },
+        ArithmeticOperator::Divide => match (left, right) {
+            (Interval, t) if is_number(t) => Interval,
+            _ => return None,
+        },
+        ArithmeticOperator::Modulo => return None,
+    };
+    Some(result)
+}
+
+// evaluates the arithmetic temporal_result_type allows, nulls give null
+pub fn temporal_arithmetic(operator: ArithmeticOperator, left: &LiteralValue, right: &LiteralValue) -> Result<LiteralValue, TError> {
+    use LiteralValue::*;
+    let interval = |interval: Option<Interval>| interval.map(IntervalValue).ok_or_else(interval_out_of_range);
+    let time = |micros: i64| TimeValue(micros.rem_euclid(MICROS_PER_DAY));
+    match (operator, left, right) {
+        (_, NullValue, _) | (_, _, NullValue) => Ok(NullValue),
+        (ArithmeticOperator::Add, DateValue(d), IntValue(n)) | (ArithmeticOperator::Add, IntValue(n), DateValue(d)) => {
+            checked_date(i64::from(*d).checked_add(*n))
+        },
+        (ArithmeticOperator::Subtract, DateValue(d), IntValue(n)) => checked_date(i64::from(*d).checked_sub(*n)),
+        (ArithmeticOperator::Subtract, DateValue(l), DateValue(r)) => Ok(IntValue(i64::from(*l) - i64::from(*r))),
+        (ArithmeticOperator::Add, DateValue(d), TimeValue(t)) | (ArithmeticOperator::Add, TimeValue(t), DateValue(d)) => {
+            checked_timestamp(Some(date_to_timestamp(*d) + t))
+        },
+        (ArithmeticOperator::Add, DateValue(_) | TimestampValue(_), IntervalValue(i)) => checked_timestamp(add_interval(as_timestamp(left).unwrap(), i)),
+        (ArithmeticOperator::Add, IntervalValue(i), DateValue(_) | TimestampValue(_)) => checked_timestamp(add_interval(as_timestamp(right).unwrap(), i)),
+        (ArithmeticOperator::Subtract, DateValue(_) | TimestampValue(_), IntervalValue(i)) => {
+            checked_timestamp(add_interval(as_timestamp(left).unwrap(), &i.checked_neg().ok_or_else(interval_out_of_range)?))
+        },
+        (ArithmeticOperator::Subtract, DateValue(_) | TimestampValue(_), DateValue(_) | TimestampValue(_)) => {
+            Ok(IntervalValue(timestamp_difference(as_timestamp(left).unwrap(), as_timestamp(right).unwrap())))
+        },
+        (ArithmeticOperator::Add, TimeValue(t), IntervalValue(i)) | (ArithmeticOperator::Add, IntervalValue(i), TimeValue(t)) => Ok(time(t + i.micros % MICROS_PER_DAY)),
+        (ArithmeticOperator::Subtract, TimeValue(t), IntervalValue(i)) => Ok(time(t - i.micros % MICROS_PER_DAY)),
+        (ArithmeticOperator::Subtract, TimeValue(l), TimeValue(r)) => Ok(IntervalValue(Interval { months: 0, days: 0, micros: l - r })),
+        (ArithmeticOperator::Add, IntervalValue(l), IntervalValue(r)) => interval(l.checked_add(r)),
+        (ArithmeticOperator::Subtract, IntervalValue(l), IntervalValue(r)) => interval(r.checked_neg().and_then(|r| l.checked_add(&r))),
+        (ArithmeticOperator::Multiply, IntervalValue(i), factor) | (ArithmeticOperator::Multiply, factor, IntervalValue(i)) if to_f64(factor).is_some() => {
+            interval(i.checked_mul(to_f64(factor).unwrap()))
+        },
+        (ArithmeticOperator::Divide, IntervalValue(i), divisor) if to_f64(divisor).is_some() => {
+            let divisor = to_f64(divisor).unwrap();
+            if divisor == 0.0 {
+                return Err(TError::ExecutionError(DIVISION_BY_ZERO, String::from("division by zero")));
+            }
+            interval(i.checked_mul(1.0 / divisor))
+        },
+        _ => Err(TError::ExecutionError(DATA_EXCEPTION, format!("cannot apply {:?} to {} and {}", operator, left, right))),
+    }
+}
+
+// dates become timestamps at midnight and timestamps lose their time or date
+pub fn cast_temporal(value: &LiteralValue, column_type: &ColumnType) -> Result<LiteralValue, TError> {
+    match (value, column_type) {
+        (LiteralValue::NullValue, _) => Ok(LiteralValue::NullValue),
+        (LiteralValue::DateValue(d), ColumnType::Timestamp) => Ok(LiteralValue::TimestampValue(date_to_timestamp(*d))),
+        (LiteralValue::TimestampValue(t), ColumnType::Date) => Ok(LiteralValue::DateValue(i32::try_from(t.div_euclid(MICROS_PER_DAY)).unwrap())),
+        (LiteralValue::TimestampValue(t), ColumnType::Time) => Ok(LiteralValue::TimeValue(t.rem_euclid(MICROS_PER_DAY))),
+        (LiteralValue::DateValue(_), ColumnType::Date) | (LiteralValue::TimeValue(_), ColumnType::Time)
+            | (LiteralValue::TimestampValue(_), ColumnType::Timestamp) | (LiteralValue::IntervalValue(_), ColumnType::Interval) => Ok(value.clone()),
+        _ => Err(TError::ExecutionError(DATA_EXCEPTION, format!("cannot convert {} to {}", value, column_type))),
+    }
+}
+
+// the fields extract takes for each type
+pub fn extract_fields(column_type: &ColumnType) -> &'static [&'static str] {
+    match column_type {
+        ColumnType::Date | ColumnType::Timestamp => &["year", "quarter", "month", "day", "dow", "doy", "hour", "minute", "second", "epoch"],
+        ColumnType::Time => &["hour", "minute", "second", "epoch"],
+        ColumnType::Interval => &["year", "month", "day", "hour", "minute", "second", "epoch"],
+        _ => &[],
+    }
+}
+
+// a field of a date, time, timestamp or interval, as a double since seconds have fractions
+pub fn extract(field: &str, value: &LiteralValue) -> Result<LiteralValue, TError> {
+    let seconds = |micros: i64| micros as f64 / MICROS_PER_SECOND as f64;
+    let result = match value {
+        LiteralValue::NullValue => return Ok(LiteralValue::NullValue),
+        LiteralValue::DateValue(_) | LiteralValue::TimestampValue(_) => {
+            let timestamp = as_timestamp(value).unwrap();
+            let (days, time) = (timestamp.div_euclid(MICROS_PER_DAY), timestamp.rem_euclid(MICROS_PER_DAY));
+            let (year, month, day) = civil_from_days(days);
+            match field {
+                "year" => year as f64,
+                "quarter" => f64::from((month - 1) / 3 + 1),
+                "month" => f64::from(month),
+                "day" => f64::from(day),
+                // sunday is 0, 1970-01-01 was a thursday
+                "dow" => (days + 4).rem_euclid(7) as f64,
+                "doy" => (days - days_from_civil(year, 1, 1) + 1) as f64,
+                "epoch" => seconds(timestamp),
+                _ => return extract(field, &LiteralValue::TimeValue(time)),
+            }
+        },
+        LiteralValue::TimeValue(time) => match field {
+            "hour" => (time / MICROS_PER_HOUR) as f64,
+            "minute" => (time / MICROS_PER_MINUTE % 60) as f64,
+            "second" => seconds(time % MICROS_PER_MINUTE),
+            "epoch" => seconds(*time),
+            _ => return Err(unsupported_field(field, value)),
+        },
+        LiteralValue::IntervalValue(interval) => match field {
+            "year" => f64::from(interval.months / 12),
+            "month" => f64::from(interval.months % 12),
+            "day" => f64::from(interval.days),
+            "hour" => (interval.micros / MICROS_PER_HOUR) as f64,
+            "minute" => (interval.micros / MICROS_PER_MINUTE % 60) as f64,
+            "second" => seconds(interval.micros % MICROS_PER_MINUTE),
+            "epoch" => interval.total_micros() as f64 / MICROS_PER_SECOND as f64,
+            _ => return Err(unsupported_field(field, value)),
+        },
+        _ => return Err(unsupported_field(field, value)),
+    };
+    Ok(LiteralValue::FloatValue(result))
+}
+
+fn unsupported_field(field: &str, value: &LiteralValue) -> TError {
+    TError::ExecutionError(INVALID_PARAMETER_VALUE, format!("unit {:?} not supported for {}", field, value))
+}
+
+pub const DATE_TRUNC_FIELDS: [&str; 8] = ["second", "minute", "hour", "day", "week", "month", "quarter", "year"];
+
+// the timestamp cut down to the start of its second, minute, ... or year, weeks start on monday
+pub fn date_trunc(field: &str, value: &LiteralValue) -> Result<LiteralValue, TError> {
+    let timestamp = match as_timestamp(value) {
+        Some(timestamp) => timestamp,
+        None if matches!(value, LiteralValue::NullValue) => return Ok(LiteralValue::NullValue),
+        None => return Err(TError::ExecutionError(DATA_EXCEPTION, format!("date_trunc takes a timestamp, not {}", value))),
+    };
+    let days = timestamp.div_euclid(MICROS_PER_DAY);
+    let (year, month, _) = civil_from_days(days);
+    let truncated = match field {
+        "second" => timestamp - timestamp.rem_euclid(MICROS_PER_SECOND),
+        "minute" => timestamp - timestamp.rem_euclid(MICROS_PER_MINUTE),
+        "hour" => timestamp - timestamp.rem_euclid(MICROS_PER_HOUR),
+        "day" => days * MICROS_PER_DAY,
+        // 1970-01-05 was a monday
+        "week" => (days - (days + 3).rem_euclid(7)) * MICROS_PER_DAY,
+        "month" => days_from_civil(year, month, 1) * MICROS_PER_DAY,
+        "quarter" => days_from_civil(year, (month - 1) / 3 * 3 + 1, 1) * MICROS_PER_DAY,
+        "year" => days_from_civil(year, 1, 1) * MICROS_PER_DAY,
+        _ => return Err(TError::ExecutionError(INVALID_PARAMETER_VALUE, format!("unit {:?} not recognized by date_trunc", field))),
+    };
+    checked_timestamp(Some(truncated))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::token::LiteralValue;
+
+    use super::{civil_from_days, date_trunc, days_from_civil, extract, parse_date, parse_time, parse_timestamp, temporal_arithmetic, DisplayTimestamp, Interval};
+    use crate::types::numeric::ArithmeticOperator;
+
+    #[test]
+    fn calendar_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        for days in [-719162, -1, 0, 59, 365, 11016, 2932896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(parse_date("2024-02-29"), Some(19782));
+        assert!(parse_date("2023-02-29").is_none() && parse_date("2023-13-01").is_none() && parse_date("23-01-01").is_none());
+        assert_eq!(parse_time("13:45:30.25"), Some(((13 * 60 + 45) * 60 + 30) * 1_000_000 + 250_000));
+        assert!(parse_time("24:00").is_none() && parse_time("12:60").is_none());
+        assert_eq!(DisplayTimestamp(parse_timestamp("2026-01-31T08:00:00.5").unwrap()).to_string(), "2026-01-31 08:00:00.5");
+    }
+
+    #[test]
+    fn intervals() {
+        let i = |s: &str| Interval::parse(s).unwrap();
+        assert_eq!(i("1 year 2 months 3 days 04:05:06").to_string(), "1 year 2 mons 3 days 04:05:06");
+        assert_eq!(i("1.5 hours").to_string(), "01:30:00");
+        assert_eq!(i("2 days ago").to_string(), "-2 days");
+        assert_eq!(i("1 mon"), i("30 days"));
+        assert!(i("1 day") < i("25 hours"));
+        assert!(Interval::parse("3 fortnights").is_none());
+
+        let ts = |s: &str| LiteralValue::TimestampValue(parse_timestamp(s).unwrap());
+        let add = |l: &LiteralValue, r: &LiteralValue| temporal_arithmetic(ArithmeticOperator::Add, l, r).unwrap().to_string();
+        assert_eq!(add(&ts("2024-01-31 10:00"), &LiteralValue::IntervalValue(i("1 month"))), "2024-02-29 10:00:00");
+        assert_eq!(add(&LiteralValue::DateValue(parse_date("2024-12-31").unwrap()), &LiteralValue::IntValue(1)), "2025-01-01");
+        let difference = temporal_arithmetic(ArithmeticOperator::Subtract, &ts("2024-03-01 12:00"), &ts("2024-02-28")).unwrap();
+        assert_eq!(difference.to_string(), "2 days 12:00:00");
+        assert!(temporal_arithmetic(ArithmeticOperator::Add, &ts("9999-12-31"), &LiteralValue::IntervalValue(i("1 day"))).is_err());
+
+        assert_eq!(extract("dow", &ts("2026-10-19")).unwrap(), LiteralValue::FloatValue(1.0));
+        assert_eq!(extract("second", &ts("2026-10-19 01:02:03.5")).unwrap(), LiteralValue::FloatValue(3.5));
+        assert_eq!(date_trunc("week", &ts("2026-10-21 13:00")).unwrap(), ts("2026-10-19"));
+        assert_eq!(date_trunc("quarter", &ts("2026-08-21")).unwrap(), ts("2026-07-01"));
+    }
+}
@@ -0,0 +1,324 @@
+use std::{cmp::Ordering, fmt, hash::{Hash, Hasher}};
+
+
+// the most digits a mantissa can hold, also the largest scale
+pub const MAX_PRECISION: u32 = 38;
+
+// digits after the point in the result of a division, unless the operands have more
+const DIVISION_SCALE: u32 = 16;
+
+/**
+ * Exact decimal number, mantissa / 10^scale. The scale is kept as written or computed,
+ * so 1.50 displays as 1.50, but equality and hashing go by value.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn from_i64(i: i64) -> Decimal {
+        Decimal { mantissa: i128::from(i), scale: 0 }
+    }
+
+    // digits, an optional point and an optional exponent, like 12, -1.5 or 2.5e-3,
+    // None when the digits don't fit in the mantissa or more than MAX_PRECISION of them are after the point
+    pub fn parse(s: &str) -> Option<Decimal> {
+        let (number, exponent) = match s.find(['e', 'E']) {
+            Some(position) => (&s[..position], s[position + 1..].parse::<i32>().ok()?),
+            None => (s, 0),
+        };
+        let (negative, number) = match number.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, number.strip_prefix('+').unwrap_or(number)),
+        };
+        let (integer, mut fraction) = number.split_once('.').unwrap_or((number, ""));
+        if integer.is_empty() && fraction.is_empty() || !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        // zeros at the end only make the scale larger, so they can go when it is too large
+        while i64::try_from(fraction.len()).ok()? - i64::from(exponent) > i64::from(MAX_PRECISION) && fraction.ends_with('0') {
+            fraction = &fraction[..fraction.len() - 1];
+        }
+        let mut mantissa: i128 = 0;
+        for c in integer.chars().chain(fraction.chars()) {
+            mantissa = mantissa.checked_mul(10)?.checked_add(i128::from(c.to_digit(10)?))?;
+        }
+        if negative {
+            mantissa = -mantissa;
+        }
+        let scale = i64::try_from(fraction.len()).ok()? - i64::from(exponent);
+        if scale < 0 {
+            let mantissa = mantissa.checked_mul(10i128.checked_pow(u32::try_from(-scale).ok()?)?)?;
+            return Some(Decimal { mantissa, scale: 0 });
+        }
+        let scale = u32::try_from(scale).ok()?;
+        if scale > MAX_PRECISION {
+            return (mantissa == 0).then_some(Decimal { mantissa, scale: MAX_PRECISION });
+        }
+        Some(Decimal { mantissa, scale })
+    }
+
+    // the decimal with the shortest digits that read back as the double, None for infinities, nan
+    // and doubles that need more than MAX_PRECISION digits after the point or are too large
+    pub fn from_f64(f: f64) -> Option<Decimal> {
+        if !f.is_finite() {
+            return None;
+        }
+        Decimal::parse(&format!("{:e}", f)).map(|d| d.normalize())
+    }
+
+    pub fn to_f64(self) -> f64 {
+        // going through the text keeps the closest double, dividing would round twice
+        self.to_string().parse().unwrap()
+    }
+
+    // rounded to the nearest integer, halves away from zero
+    pub fn to_i64(self) -> Option<i64> {
+        i64::try_from(self.rescale(0)?.mantissa).ok()
+    }
+
+    // the same value with another number of digits after the point, rounding halves away from zero
+    pub fn rescale(&self, scale: u32) -> Option<Decimal> {
+        match scale.cmp(&self.scale) {
+            Ordering::Equal => Some(*self),
+            Ordering::Greater => {
+                let mantissa = self.mantissa.checked_mul(10i128.checked_pow(scale - self.scale)?)?;
+                Some(Decimal { mantissa, scale })
+            },
+            Ordering::Less => Decimal { mantissa: self.mantissa, scale: 0 }.divide_by_power(self.scale - scale, scale),
+        }
+    }
+
+    // mantissa / 10^power rounded, with the given scale
+    fn divide_by_power(&self, power: u32, scale: u32) -> Option<Decimal> {
+        if power > MAX_PRECISION {
+            return Some(Decimal { mantissa: 0, scale });
+        }
+        let divisor = 10i128.pow(power);
+        Some(Decimal { mantissa: round_division(self.mantissa, divisor)?, scale })
+    }
+
+    // without trailing zeros after the point
+    pub fn normalize(&self) -> Decimal {
+        let mut d = *self;
+        while d.scale > 0 && d.mantissa % 10 == 0 {
+            d.mantissa /= 10;
+            d.scale -= 1;
+        }
+        d
+    }
+
+    // number of digits in the mantissa
+    pub fn digits(&self) -> u32 {
+        let mut digits = 1;
+        let mut m = self.mantissa.unsigned_abs();
+        while m >= 10 {
+            m /= 10;
+            digits += 1;
+        }
+        digits
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+
+    pub fn checked_neg(&self) -> Option<Decimal> {
+        Some(Decimal { mantissa: self.mantissa.checked_neg()?, scale: self.scale })
+    }
+
+    pub fn checked_add(&self, other: &Decimal) -> Option<Decimal> {
+        let (left, right) = align(self, other)?;
+        Some(Decimal { mantissa: left.mantissa.checked_add(right.mantissa)?, scale: left.scale })
+    }
+
+    pub fn checked_sub(&self, other: &Decimal) -> Option<Decimal> {
+        self.checked_add(&other.checked_neg()?)
+    }
+
+    pub fn checked_mul(&self, other: &Decimal) -> Option<Decimal> {
+        let mantissa = self.mantissa.checked_mul(other.mantissa)?;
+        let product = Decimal { mantissa, scale: self.scale + other.scale };
+        if product.scale > MAX_PRECISION {
+            return product.rescale(MAX_PRECISION);
+        }
+        Some(product)
+    }
+
+    // None on overflow, callers check for division by zero
+    pub fn checked_div(&self, other: &Decimal) -> Option<Decimal> {
+        let scale = DIVISION_SCALE.max(self.scale).max(other.scale);
+        // (m1 / 10^s1) / (m2 / 10^s2) = m1 * 10^(scale + s2 - s1) / m2 / 10^scale
+        let shift = i64::from(scale) + i64::from(other.scale) - i64::from(self.scale);
+        let dividend = self.mantissa.checked_mul(10i128.checked_pow(u32::try_from(shift).ok()?)?)?;
+        Some(Decimal { mantissa: round_division(dividend, other.mantissa)?, scale })
+    }
+
+    pub fn checked_rem(&self, other: &Decimal) -> Option<Decimal> {
+        let (left, right) = align(self, other)?;
+        Some(Decimal { mantissa: left.mantissa.checked_rem(right.mantissa)?, scale: left.scale })
+    }
+
+    /**
+     * Appends a key that sorts like the value when compared byte by byte, see encode_key.
+     * Positive numbers are [2][exponent (2)][digits + 1]* [0] without trailing zeros, where the
+     * exponent is where the point goes in the digits. Negative numbers are [0] and the
+     * inverted bytes of their absolute value, so larger absolute values sort first.
+     */
+    pub fn encode_key(&self, res: &mut Vec<u8>) {
+        let d = self.normalize();
+        if d.is_zero() {
+            res.push(1);
+            return;
+        }
+        let digits = d.mantissa.unsigned_abs().to_string();
+        let exponent = i16::try_from(i64::try_from(digits.len()).unwrap() - i64::from(d.scale)).unwrap();
+        let mut key = Vec::new();
+        key.extend(((exponent as u16) ^ (1 << 15)).to_be_bytes());
+        key.extend(digits.trim_end_matches('0').bytes().map(|b| b - b'0' + 1));
+        key.push(0);
+        if d.mantissa < 0 {
+            res.push(0);
+            res.extend(key.iter().map(|b| !b));
+        } else {
+            res.push(2);
+            res.extend(key);
+        }
+    }
+
+    // [scale] [mantissa (16)]
+    pub fn encode(&self, res: &mut Vec<u8>) {
+        res.push(u8::try_from(self.scale).unwrap());
+        res.extend(self.mantissa.to_le_bytes());
+    }
+
+    pub fn decode(data: &[u8], bytes_read: &mut usize) -> Decimal {
+        let scale = u32::from(data[*bytes_read]);
+        let mantissa = i128::from_le_bytes(data[*bytes_read+1..*bytes_read+17].try_into().unwrap());
+        *bytes_read += 17;
+        Decimal { mantissa, scale }
+    }
+}
+
+// both at the larger scale
+fn align(left: &Decimal, right: &Decimal) -> Option<(Decimal, Decimal)> {
+    let scale = left.scale.max(right.scale);
+    Some((left.rescale(scale)?, right.rescale(scale)?))
+}
+
+// dividend / divisor rounding halves away from zero
+fn round_division(dividend: i128, divisor: i128) -> Option<i128> {
+    let quotient = dividend.checked_div(divisor)?;
+    let remainder = dividend % divisor;
+    if remainder.unsigned_abs() >= divisor.unsigned_abs() - remainder.unsigned_abs() {
+        let away = if (dividend < 0) == (divisor < 0) { 1 } else { -1 };
+        return quotient.checked_add(away);
+    }
+    Some(quotient)
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        match align(self, other) {
+            Some((left, right)) => left.mantissa.cmp(&right.mantissa),
+            // the one that needed more digits to align is further from zero
+            None if self.scale < other.scale => self.mantissa.cmp(&0),
+            None => 0.cmp(&other.mantissa),
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let d = self.normalize();
+        d.mantissa.hash(state);
+        d.scale.hash(state);
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = usize::try_from(self.scale).unwrap();
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        if fraction.is_empty() {
+            write!(f, "{}{}", sign, integer)
+        } else {
+            write!(f, "{}{}.{}", sign, integer, fraction)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decimal;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::parse(s).unwrap()
+    }
+
+    #[test]
+    fn parse_display_and_arithmetic() {
+        assert_eq!(d("12.50").to_string(), "12.50");
+        assert_eq!(d("-0.05").to_string(), "-0.05");
+        assert_eq!(d("1.5e3").to_string(), "1500");
+        assert_eq!(d("25e-3").to_string(), "0.025");
+        assert!(Decimal::parse("1.2.3").is_none() && Decimal::parse(".").is_none());
+        // digits past the largest scale aren't rounded away, unless they are zeros
+        assert!(Decimal::parse("1.5e-40").is_none() && Decimal::parse("1e39").is_none());
+        assert!(Decimal::parse("0.000000000000000000000000000000000000001").is_none());
+        assert_eq!(d("1.50000000000000000000000000000000000000000000").to_string(), "1.50000000000000000000000000000000000000");
+        assert!(d("0e-50").is_zero());
+        assert_eq!(d("1e-38").to_string(), "0.00000000000000000000000000000000000001");
+
+        assert_eq!(d("1.5").checked_add(&d("2.25")).unwrap().to_string(), "3.75");
+        assert_eq!(d("1.5").checked_sub(&d("2.25")).unwrap().to_string(), "-0.75");
+        assert_eq!(d("1.5").checked_mul(&d("-0.2")).unwrap().to_string(), "-0.30");
+        assert_eq!(d("1").checked_div(&d("3")).unwrap().to_string(), "0.3333333333333333");
+        assert_eq!(d("2").checked_div(&d("3")).unwrap().to_string(), "0.6666666666666667");
+        assert_eq!(d("7.5").checked_rem(&d("2")).unwrap().to_string(), "1.5");
+        assert!(d("1e37").checked_mul(&d("100")).is_none());
+
+        assert_eq!(d("2.5").to_i64(), Some(3));
+        assert_eq!(d("-2.5").to_i64(), Some(-3));
+        assert_eq!(d("1.005").rescale(2).unwrap().to_string(), "1.01");
+        assert_eq!(d("1.50"), d("1.5"));
+        assert!(d("-1.25") < d("-1.2") && d("0.1") < d("1e-0"));
+        assert_eq!(Decimal::from_f64(0.1).unwrap().to_string(), "0.1");
+        assert!(Decimal::from_f64(1.5e-40).is_none() && Decimal::from_f64(1e308).is_none());
+    }
+
+    #[test]
+    fn keys_sort_like_values() {
+        let values = ["-1000", "-1.25", "-1.2", "-0.5", "0", "0.001", "0.5", "1", "1.2", "1.25", "9.99", "10", "123456"];
+        let keys: Vec<Vec<u8>> = values.iter().map(|v| {
+            let mut key = Vec::new();
+            d(v).encode_key(&mut key);
+            key
+        }).collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        d("1.50").encode_key(&mut a);
+        d("1.5").encode_key(&mut b);
+        assert_eq!(a, b);
+    }
+}
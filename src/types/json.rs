@@ -0,0 +1,540 @@
+use std::{cmp::Ordering, fmt};
+
+use crate::parse::scanner::TError;
+
+
+// deeper documents are rejected, so parsing and printing them can't overflow the stack
+const MAX_DEPTH: usize = 256;
+
+/**
+ * A parsed json document. Object keys are sorted and unique, a key given twice keeps its
+ * last value, like jsonb in postgres, so equal documents have the same encoding.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+// a step of a json_extract path, $.key or $[index]
+#[derive(Debug, PartialEq)]
+pub enum PathStep {
+    Key(String),
+    Index(i64),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Option<Json> {
+        let mut parser = JsonParser { chars: text.chars().collect(), position: 0 };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        match parser.position == parser.chars.len() {
+            true => Some(value),
+            false => None,
+        }
+    }
+
+    // the value of a key of an object
+    pub fn field(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.binary_search_by(|(k, _)| k.as_str().cmp(key)).ok().map(|i| &entries[i].1),
+            _ => None,
+        }
+    }
+
+    // an element of an array, negative indexes count from the end
+    pub fn element(&self, index: i64) -> Option<&Json> {
+        match self {
+            Json::Array(elements) => {
+                let index = if index < 0 { i64::try_from(elements.len()).ok()? + index } else { index };
+                elements.get(usize::try_from(index).ok()?)
+            },
+            _ => None,
+        }
+    }
+
+    // follows a path of keys, which are array indexes for arrays, as in #>
+    pub fn path(&self, path: &[String]) -> Option<&Json> {
+        path.iter().try_fold(self, |json, step| match json {
+            Json::Array(_) => json.element(step.parse().ok()?),
+            _ => json.field(step),
+        })
+    }
+
+    pub fn extract(&self, path: &[PathStep]) -> Option<&Json> {
+        path.iter().try_fold(self, |json, step| match step {
+            PathStep::Key(key) => json.field(key),
+            PathStep::Index(index) => json.element(*index),
+        })
+    }
+
+    /**
+     * Binary encoding, a tag byte followed by the value:
+     *  null 0, false 1, true 2, number 3 [f64 (8)], integer 4 [zigzag varint],
+     *  string 5 [varint length] [utf-8 bytes], array 6 [varint count] [elements],
+     *  object 7 [varint count] ([varint key length] [key] [value])*
+     */
+    pub fn encode(&self, res: &mut Vec<u8>) {
+        match self {
+            Json::Null => res.push(0),
+            Json::Bool(b) => res.push(1 + u8::from(*b)),
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => {
+                res.push(4);
+                let i = *n as i64;
+                write_varint(((i << 1) ^ (i >> 63)) as u64, res);
+            },
+            Json::Number(n) => {
+                res.push(3);
+                res.extend(n.to_le_bytes());
+            },
+            Json::String(s) => {
+                res.push(5);
+                write_string(s, res);
+            },
+            Json::Array(elements) => {
+                res.push(6);
+                write_varint(elements.len() as u64, res);
+                for element in elements {
+                    element.encode(res);
+                }
+            },
+            Json::Object(entries) => {
+                res.push(7);
+                write_varint(entries.len() as u64, res);
+                for (key, value) in entries {
+                    write_string(key, res);
+                    value.encode(res);
+                }
+            },
+        }
+    }
+
+    // bytes that don't hold an encoded document are a storage error, not a panic
+    pub fn decode(data: &[u8], bytes_read: &mut usize) -> Result<Json, TError> {
+        Json::decode_at(data, bytes_read, 0)
+    }
+
+    fn decode_at(data: &[u8], bytes_read: &mut usize, depth: usize) -> Result<Json, TError> {
+        if depth > MAX_DEPTH {
+            return Err(corrupt());
+        }
+        let tag = read_bytes(data, bytes_read, 1)?[0];
+        Ok(match tag {
+            0 => Json::Null,
+            1 | 2 => Json::Bool(tag == 2),
+            3 => Json::Number(f64::from_le_bytes(read_bytes(data, bytes_read, 8)?.try_into().unwrap())),
+            4 => {
+                let u = read_varint(data, bytes_read)?;
+                Json::Number((((u >> 1) as i64) ^ -((u & 1) as i64)) as f64)
+            },
+            5 => Json::String(read_string(data, bytes_read)?),
+            6 => {
+                let count = read_varint(data, bytes_read)?;
+                Json::Array((0..count).map(|_| Json::decode_at(data, bytes_read, depth + 1)).collect::<Result<_, _>>()?)
+            },
+            7 => {
+                let count = read_varint(data, bytes_read)?;
+                Json::Object((0..count).map(|_| Ok((read_string(data, bytes_read)?, Json::decode_at(data, bytes_read, depth + 1)?))).collect::<Result<_, TError>>()?)
+            },
+            _ => return Err(corrupt()),
+        })
+    }
+
+    /**
+     * The order of jsonb in postgres: null < strings < numbers < bools < arrays < objects.
+     * Longer arrays and objects come after shorter ones, ones of the same length are compared
+     * element by element, objects by their sorted keys first and then their values.
+     */
+    pub fn compare(&self, other: &Json) -> Ordering {
+        let rank = |json: &Json| match json {
+            Json::Null => 0,
+            Json::String(_) => 1,
+            Json::Number(_) => 2,
+            Json::Bool(_) => 3,
+            Json::Array(_) => 4,
+            Json::Object(_) => 5,
+        };
+        match (self, other) {
+            (Json::String(l), Json::String(r)) => l.cmp(r),
+            (Json::Number(l), Json::Number(r)) => l.partial_cmp(r).unwrap_or(Ordering::Equal),
+            (Json::Bool(l), Json::Bool(r)) => l.cmp(r),
+            (Json::Array(l), Json::Array(r)) => l.len().cmp(&r.len())
+                .then_with(|| l.iter().zip(r).map(|(l, r)| l.compare(r)).find(|o| o.is_ne()).unwrap_or(Ordering::Equal)),
+            (Json::Object(l), Json::Object(r)) => l.len().cmp(&r.len())
+                .then_with(|| l.iter().map(|(k, _)| k).cmp(r.iter().map(|(k, _)| k)))
+                .then_with(|| l.iter().zip(r).map(|((_, l), (_, r))| l.compare(r)).find(|o| o.is_ne()).unwrap_or(Ordering::Equal)),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        self.encode(&mut res);
+        res
+    }
+}
+
+// like postgres shows jsonb, with a space after colons and commas
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(s) => write_quoted(s, f),
+            Json::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_quoted(key, f)?;
+                    write!(f, ": {}", value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+fn write_quoted(s: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            '\u{8}' => write!(f, "\\b")?,
+            '\u{c}' => write!(f, "\\f")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+fn write_varint(mut u: u64, res: &mut Vec<u8>) {
+    while u >= 0x80 {
+        res.push((u as u8) | 0x80);
+        u >>= 7;
+    }
+    res.push(u as u8);
+}
+
+fn corrupt() -> TError {
+    TError::StorageError(String::from("json value is corrupt"))
+}
+
+fn read_bytes<'d>(data: &'d [u8], bytes_read: &mut usize, len: usize) -> Result<&'d [u8], TError> {
+    let bytes = data.get(*bytes_read..bytes_read.saturating_add(len)).ok_or_else(corrupt)?;
+    *bytes_read += len;
+    Ok(bytes)
+}
+
+fn read_varint(data: &[u8], bytes_read: &mut usize) -> Result<u64, TError> {
+    let mut u = 0;
+    let mut shift = 0;
+    loop {
+        let byte = read_bytes(data, bytes_read, 1)?[0];
+        // a u64 takes at most 10 bytes
+        if shift > 63 {
+            return Err(corrupt());
+        }
+        u |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(u);
+        }
+        shift += 7;
+    }
+}
+
+fn write_string(s: &str, res: &mut Vec<u8>) {
+    write_varint(s.len() as u64, res);
+    res.extend(s.as_bytes());
+}
+
+fn read_string(data: &[u8], bytes_read: &mut usize) -> Result<String, TError> {
+    let len = usize::try_from(read_varint(data, bytes_read)?).map_err(|_| corrupt())?;
+    let bytes = read_bytes(data, bytes_read, len)?;
+    Ok(str::from_utf8(bytes).map_err(|_| corrupt())?.to_owned())
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl JsonParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.position += 1;
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Option<Json> {
+        for expected in keyword.chars() {
+            if self.next() != Some(expected) {
+                return None;
+            }
+        }
+        Some(value)
+    }
+
+    fn value(&mut self, depth: usize) -> Option<Json> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        self.skip_whitespace();
+        match self.peek()? {
+            'n' => self.keyword("null", Json::Null),
+            't' => self.keyword("true", Json::Bool(true)),
+            'f' => self.keyword("false", Json::Bool(false)),
+            '"' => self.string().map(Json::String),
+            '[' => {
+                self.position += 1;
+                let mut elements = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.position += 1;
+                    return Some(Json::Array(elements));
+                }
+                loop {
+                    elements.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.next()? {
+                        ',' => continue,
+                        ']' => return Some(Json::Array(elements)),
+                        _ => return None,
+                    }
+                }
+            },
+            '{' => {
+                self.position += 1;
+                let mut entries: Vec<(String, Json)> = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.position += 1;
+                    return Some(Json::Object(entries));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    if self.next()? != ':' {
+                        return None;
+                    }
+                    let value = self.value(depth + 1)?;
+                    match entries.binary_search_by(|(k, _)| k.cmp(&key)) {
+                        Ok(i) => entries[i].1 = value,
+                        Err(i) => entries.insert(i, (key, value)),
+                    }
+                    self.skip_whitespace();
+                    match self.next()? {
+                        ',' => continue,
+                        '}' => return Some(Json::Object(entries)),
+                        _ => return None,
+                    }
+                }
+            },
+            _ => self.number(),
+        }
+    }
+
+    // -? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?
+    fn number(&mut self) -> Option<Json> {
+        let start = self.position;
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+        let digits = |parser: &mut JsonParser| {
+            let start = parser.position;
+            while parser.peek().is_some_and(|c| c.is_ascii_digit()) {
+                parser.position += 1;
+            }
+            parser.position - start
+        };
+        let integer_start = self.position;
+        let integer_digits = digits(self);
+        if integer_digits == 0 || (integer_digits > 1 && self.chars[integer_start] == '0') {
+            return None;
+        }
+        if self.peek() == Some('.') {
+            self.position += 1;
+            if digits(self) == 0 {
+                return None;
+            }
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.position += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.position += 1;
+            }
+            if digits(self) == 0 {
+                return None;
+            }
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        text.parse::<f64>().ok().filter(|n| n.is_finite()).map(Json::Number)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.next()? != '"' {
+            return None;
+        }
+        let mut s = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Some(s),
+                '\\' => {
+                    let c = match self.next()? {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let high = self.hex4()?;
+                            if (0xd800..0xdc00).contains(&high) {
+                                // a surrogate pair
+                                if self.next()? != '\\' || self.next()? != 'u' {
+                                    return None;
+                                }
+                                let low = self.hex4()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return None;
+                                }
+                                char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))?
+                            } else {
+                                char::from_u32(high)?
+                            }
+                        },
+                        _ => return None,
+                    };
+                    s.push(c);
+                },
+                c if (c as u32) < 0x20 => return None,
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let mut u = 0;
+        for _ in 0..4 {
+            u = u * 16 + self.next()?.to_digit(16)?;
+        }
+        Some(u)
+    }
+}
+
+// a postgres text array of path elements, like '{a,0,"b c"}'
+pub fn parse_text_path(text: &str) -> Option<Vec<String>> {
+    let inner = text.trim().strip_prefix('{')?.strip_suffix('}')?;
+    if inner.trim().is_empty() {
+        return Some(Vec::new());
+    }
+    inner.split(',').map(|element| {
+        let element = element.trim();
+        match element.strip_prefix('"') {
+            Some(quoted) => quoted.strip_suffix('"').map(|s| s.to_owned()),
+            None => Some(element.to_owned()),
+        }
+    }).collect()
+}
+
+// a json_extract path, $ followed by .key, ."quoted key" or [index] steps
+pub fn parse_json_path(text: &str) -> Option<Vec<PathStep>> {
+    let mut rest = text.trim().strip_prefix('$')?;
+    let mut steps = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']')?;
+            steps.push(PathStep::Index(after[..end].trim().parse().ok()?));
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix(".\"") {
+            let end = after.find('"')?;
+            steps.push(PathStep::Key(after[..end].to_owned()));
+            rest = &after[end + 1..];
+        } else {
+            let after = rest.strip_prefix('.')?;
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return None;
+            }
+            steps.push(PathStep::Key(after[..end].to_owned()));
+            rest = &after[end..];
+        }
+    }
+    Some(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::scanner::TError;
+
+    use super::{parse_json_path, parse_text_path, Json, PathStep};
+
+    #[test]
+    fn parse_print_and_encode() {
+        let json = Json::parse(r#" {"b": [1, 2.5, -3e2, true, null], "a": {"x": "q\"\u00e9\ud83d\ude00"}, "b": [1, 2.5, -300, true, null]} "#).unwrap();
+        assert_eq!(json.to_string(), r#"{"a": {"x": "q\"é😀"}, "b": [1, 2.5, -300, true, null]}"#);
+        let bytes = json.to_bytes();
+        assert_eq!(Json::decode(&bytes, &mut 0).unwrap(), json);
+        // truncated or unknown bytes are an error
+        for corrupt in [&bytes[..bytes.len() - 1], &[9], &[5, 2, 0xff, 0xfe], &[6, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]] {
+            assert!(matches!(Json::decode(corrupt, &mut 0), Err(TError::StorageError(_))), "{:?}", corrupt);
+        }
+        assert!(bytes.len() < json.to_string().len());
+
+        for text in ["", "{", "[1,]", "01", "1.", "{\"a\" 1}", "\"\\x\"", "nul", "[1] 2", "1e999"] {
+            assert_eq!(Json::parse(text), None, "{}", text);
+        }
+        assert_eq!(Json::parse(&"[".repeat(1000)), None);
+    }
+
+    #[test]
+    fn paths() {
+        let json = Json::parse(r#"{"a": [10, {"b": "c"}], "d e": 1}"#).unwrap();
+        assert_eq!(json.field("d e"), Some(&Json::Number(1.0)));
+        assert_eq!(json.field("a").unwrap().element(-1).unwrap().field("b"), Some(&Json::String("c".to_owned())));
+        assert_eq!(json.path(&parse_text_path("{a, 1, b}").unwrap()), Some(&Json::String("c".to_owned())));
+        assert_eq!(json.path(&parse_text_path("{a,x}").unwrap()), None);
+
+        let path = parse_json_path(r#"$.a[1].b"#).unwrap();
+        assert_eq!(path, vec![PathStep::Key("a".to_owned()), PathStep::Index(1), PathStep::Key("b".to_owned())]);
+        assert_eq!(json.extract(&parse_json_path(r#"$."d e""#).unwrap()), Some(&Json::Number(1.0)));
+        assert_eq!(json.extract(&parse_json_path("$").unwrap()), Some(&json));
+        for path in ["a", "$.", "$[x]", "$a"] {
+            assert_eq!(parse_json_path(path), None, "{}", path);
+        }
+    }
+}
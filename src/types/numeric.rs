@@ -0,0 +1,310 @@
+use std::cmp::Ordering;
+
+use crate::{catalog::table_schema::{real_to_f64, ColumnType}, parse::{scanner::{DATA_EXCEPTION, DIVISION_BY_ZERO, NUMERIC_VALUE_OUT_OF_RANGE, TError}, token::LiteralValue}};
+
+use super::decimal::Decimal;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArithmeticOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+// -0 is 0 and every nan is the same, so equal floats have the same bits
+pub fn normalize_float(f: f64) -> f64 {
+    if f.is_nan() {
+        f64::NAN
+    } else if f == 0.0 {
+        0.0
+    } else {
+        f
+    }
+}
+
+pub fn to_f64(value: &LiteralValue) -> Option<f64> {
+    match value {
+        LiteralValue::IntValue(i) => Some(*i as f64),
+        LiteralValue::FloatValue(f) => Some(*f),
+        LiteralValue::DecimalValue(d) => Some(d.to_f64()),
+        _ => None,
+    }
+}
+
+fn to_decimal(value: &LiteralValue) -> Option<Decimal> {
+    match value {
+        LiteralValue::IntValue(i) => Some(Decimal::from_i64(*i)),
+        LiteralValue::DecimalValue(d) => Some(*d),
+        _ => None,
+    }
+}
+
+fn float_cmp(left: f64, right: f64) -> Ordering {
+    // nan is larger than any other number and equal to itself, so it sorts last
+    match (left.is_nan(), right.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => left.partial_cmp(&right).unwrap(),
+    }
+}
+
+// compares numbers of any type by value, None when one of them isn't a number
+pub fn compare_numeric(left: &LiteralValue, right: &LiteralValue) -> Option<Ordering> {
+    match (left, right) {
+        (LiteralValue::IntValue(l), LiteralValue::IntValue(r)) => Some(l.cmp(r)),
+        (LiteralValue::FloatValue(_), _) | (_, LiteralValue::FloatValue(_)) => {
+            Some(float_cmp(to_f64(left)?, to_f64(right)?))
+        },
+        _ => Some(to_decimal(left)?.cmp(&to_decimal(right)?)),
+    }
+}
+
+fn division_by_zero() -> TError {
+    TError::ExecutionError(DIVISION_BY_ZERO, String::from("division by zero"))
+}
+
+/**
+ * Ints stay ints (bigints, the planner narrows them back), anything with a float
+ * gives a double and otherwise a decimal is exact. Overflow is an error, never a wrap.
+ */
+pub fn arithmetic(operator: ArithmeticOperator, left: &LiteralValue, right: &LiteralValue) -> Result<LiteralValue, TError> {
+    match (left, right) {
+        (LiteralValue::NullValue, _) | (_, LiteralValue::NullValue) => Ok(LiteralValue::NullValue),
+        (LiteralValue::IntValue(l), LiteralValue::IntValue(r)) => {
+            if matches!(operator, ArithmeticOperator::Divide | ArithmeticOperator::Modulo) && *r == 0 {
+                return Err(division_by_zero());
+            }
+            let result = match operator {
+                ArithmeticOperator::Add => l.checked_add(*r),
+                ArithmeticOperator::Subtract => l.checked_sub(*r),
+                ArithmeticOperator::Multiply => l.checked_mul(*r),
+                // truncates towards zero
+                ArithmeticOperator::Divide => l.checked_div(*r),
+                // only min % -1 overflows, and it has no remainder
+                ArithmeticOperator::Modulo => Some(l.checked_rem(*r).unwrap_or(0)),
+            };
+            result.map(LiteralValue::IntValue).ok_or_else(|| TError::ExecutionError(NUMERIC_VALUE_OUT_OF_RANGE, String::from("bigint out of range")))
+        },
+        (LiteralValue::FloatValue(_), _) | (_, LiteralValue::FloatValue(_)) => {
+            let (l, r) = match (to_f64(left), to_f64(right)) {
+                (Some(l), Some(r)) => (l, r),
+                _ => return Err(operator_error(operator, left, right)),
+            };
+            if matches!(operator, ArithmeticOperator::Divide | ArithmeticOperator::Modulo) && r == 0.0 {
+                return Err(division_by_zero());
+            }
+            let result = match operator {
+                ArithmeticOperator::Add => l + r,
+                ArithmeticOperator::Subtract => l - r,
+                ArithmeticOperator::Multiply => l * r,
+                ArithmeticOperator::Divide => l / r,
+                ArithmeticOperator::Modulo => l % r,
+            };
+            if result.is_infinite() && l.is_finite() && r.is_finite() {
+                return Err(TError::ExecutionError(NUMERIC_VALUE_OUT_OF_RANGE, String::from("value out of range: overflow")));
+            }
+            Ok(LiteralValue::FloatValue(result))
+        },
+        _ => {
+            let (l, r) = match (to_decimal(left), to_decimal(right)) {
+                (Some(l), Some(r)) => (l, r),
+                _ => return Err(operator_error(operator, left, right)),
+            };
+            if matches!(operator, ArithmeticOperator::Divide | ArithmeticOperator::Modulo) && r.is_zero() {
+                return Err(division_by_zero());
+            }
+            let result = match operator {
+                ArithmeticOperator::Add => l.checked_add(&r),
+                ArithmeticOperator::Subtract => l.checked_sub(&r),
+                ArithmeticOperator::Multiply => l.checked_mul(&r),
+                ArithmeticOperator::Divide => l.checked_div(&r),
+                ArithmeticOperator::Modulo => l.checked_rem(&r),
+            };
+            result.map(LiteralValue::DecimalValue).ok_or_else(|| TError::ExecutionError(NUMERIC_VALUE_OUT_OF_RANGE, String::from("numeric value out of range")))
+        },
+    }
+}
+
+fn operator_error(operator: ArithmeticOperator, left: &LiteralValue, right: &LiteralValue) -> TError {
+    TError::ExecutionError(DATA_EXCEPTION, format!("cannot apply {:?} to {} and {}", operator, left, right))
+}
+
+pub fn negate(value: &LiteralValue) -> Result<LiteralValue, TError> {
+    match value {
+        LiteralValue::NullValue => Ok(LiteralValue::NullValue),
+        LiteralValue::IntValue(i) => i.checked_neg().map(LiteralValue::IntValue)
+            .ok_or_else(|| TError::ExecutionError(NUMERIC_VALUE_OUT_OF_RANGE, String::from("bigint out of range"))),
+        LiteralValue::FloatValue(f) => Ok(LiteralValue::FloatValue(-f)),
+        LiteralValue::DecimalValue(d) => d.checked_neg().map(LiteralValue::DecimalValue)
+            .ok_or_else(|| TError::ExecutionError(NUMERIC_VALUE_OUT_OF_RANGE, String::from("numeric value out of range"))),
+        LiteralValue::IntervalValue(i) => i.checked_neg().map(LiteralValue::IntervalValue)
+            .ok_or_else(|| TError::ExecutionError(NUMERIC_VALUE_OUT_OF_RANGE, String::from("interval out of range"))),
+        _ => Err(TError::ExecutionError(DATA_EXCEPTION, format!("cannot negate {}", value))),
+    }
+}
+
+pub fn is_numeric(column_type: &ColumnType) -> bool {
+    matches!(column_type, ColumnType::SmallInt | ColumnType::Int | ColumnType::BigInt
+        | ColumnType::Real | ColumnType::Double | ColumnType::Decimal(_))
+}
+
+pub fn is_integer(column_type: &ColumnType) -> bool {
+    matches!(column_type, ColumnType::SmallInt | ColumnType::Int | ColumnType::BigInt)
+}
+
+// position in the promotion order, numbers of different types are converted to the higher one
+pub fn rank(column_type: &ColumnType) -> u8 {
+    match column_type {
+        ColumnType::SmallInt => 0,
+        ColumnType::Int => 1,
+        ColumnType::BigInt => 2,
+        ColumnType::Decimal(_) => 3,
+        ColumnType::Real => 4,
+        _ => 5,
+    }
+}
+
+// the type both numbers are converted to before they are combined
+pub fn common_type(left: &ColumnType, right: &ColumnType) -> ColumnType {
+    match (left, right) {
+        // a real doesn't have the digits for a bigint or a decimal
+        (ColumnType::Real, ColumnType::BigInt | ColumnType::Decimal(_))
+            | (ColumnType::BigInt | ColumnType::Decimal(_), ColumnType::Real) => ColumnType::Double,
+        _ if rank(left) >= rank(right) => unconstrained(left),
+        _ => unconstrained(right),
+    }
+}
+
+// results of arithmetic on decimals can have any precision
+fn unconstrained(column_type: &ColumnType) -> ColumnType {
+    match column_type {
+        ColumnType::Decimal(_) => ColumnType::Decimal(None),
+        _ => column_type.clone(),
+    }
+}
+
+fn out_of_range(column_type: &ColumnType) -> TError {
+    TError::ExecutionError(NUMERIC_VALUE_OUT_OF_RANGE, format!("{} out of range", column_type))
+}
+
+/**
+ * Converts a number to a numeric column type, checking it fits. Floats become ints
+ * rounding halves to even, decimals rounding halves away from zero, like postgres.
+ */
+pub fn cast_numeric(value: &LiteralValue, column_type: &ColumnType) -> Result<LiteralValue, TError> {
+    if matches!(value, LiteralValue::NullValue) {
+        return Ok(LiteralValue::NullValue);
+    }
+    match column_type {
+        ColumnType::SmallInt | ColumnType::Int | ColumnType::BigInt => {
+            let i = match value {
+                LiteralValue::IntValue(i) => Some(*i),
+                LiteralValue::FloatValue(f) => {
+                    let f = f.round_ties_even();
+                    // i64::MAX as f64 rounds up to 2^63, which doesn't fit
+                    if f >= -(2f64.powi(63)) && f < 2f64.powi(63) { Some(f as i64) } else { None }
+                },
+                LiteralValue::DecimalValue(d) => d.to_i64(),
+                _ => return Err(cast_error(value, column_type)),
+            };
+            let fits = match (i, column_type) {
+                (Some(i), ColumnType::SmallInt) => i16::try_from(i).is_ok(),
+                (Some(i), ColumnType::Int) => i32::try_from(i).is_ok(),
+                (i, _) => i.is_some(),
+            };
+            match i {
+                Some(i) if fits => Ok(LiteralValue::IntValue(i)),
+                _ => Err(out_of_range(column_type)),
+            }
+        },
+        ColumnType::Real | ColumnType::Double => {
+            let f = to_f64(value).ok_or_else(|| cast_error(value, column_type))?;
+            if *column_type == ColumnType::Double {
+                return Ok(LiteralValue::FloatValue(f));
+            }
+            let real = f as f32;
+            if real.is_infinite() && f.is_finite() {
+                return Err(TError::ExecutionError(NUMERIC_VALUE_OUT_OF_RANGE, String::from("value out of range: overflow")));
+            }
+            Ok(LiteralValue::FloatValue(real_to_f64(real)))
+        },
+        ColumnType::Decimal(params) => {
+            let d = match value {
+                LiteralValue::FloatValue(f) => Decimal::from_f64(*f)
+                    .ok_or_else(|| TError::ExecutionError(NUMERIC_VALUE_OUT_OF_RANGE, format!("cannot convert {} to numeric", value)))?,
+                _ => to_decimal(value).ok_or_else(|| cast_error(value, column_type))?,
+            };
+            let (precision, scale) = match params {
+                Some(params) => *params,
+                None => return Ok(LiteralValue::DecimalValue(d)),
+            };
+            match d.rescale(u32::from(scale)) {
+                Some(d) if d.is_zero() || d.digits() <= u32::from(precision) => Ok(LiteralValue::DecimalValue(d)),
+                _ => Err(TError::ExecutionError(NUMERIC_VALUE_OUT_OF_RANGE, format!(
+                    "numeric field overflow: a field with precision {}, scale {} must round to an absolute value less than 10^{}",
+                    precision, scale, precision - scale))),
+            }
+        },
+        _ => Err(cast_error(value, column_type)),
+    }
+}
+
+fn cast_error(value: &LiteralValue, column_type: &ColumnType) -> TError {
+    TError::ExecutionError(DATA_EXCEPTION, format!("cannot convert {} to {}", value, column_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::{catalog::table_schema::ColumnType, parse::token::LiteralValue, types::decimal::Decimal};
+
+    use super::{arithmetic, cast_numeric, common_type, compare_numeric, ArithmeticOperator};
+
+    fn d(s: &str) -> LiteralValue {
+        LiteralValue::DecimalValue(Decimal::parse(s).unwrap())
+    }
+
+    #[test]
+    fn mixed_arithmetic_and_overflow() {
+        use ArithmeticOperator::*;
+        let (i, f) = (LiteralValue::IntValue, LiteralValue::FloatValue);
+        assert_eq!(arithmetic(Divide, &i(7), &i(-2)).unwrap(), i(-3));
+        assert_eq!(arithmetic(Modulo, &i(-7), &i(2)).unwrap(), i(-1));
+        assert_eq!(arithmetic(Add, &i(1), &d("0.5")).unwrap().to_string(), "1.5");
+        assert_eq!(arithmetic(Multiply, &d("0.5"), &f(3.0)).unwrap(), f(1.5));
+        assert!(arithmetic(Add, &i(i64::MAX), &i(1)).is_err());
+        assert!(arithmetic(Divide, &i(1), &i(0)).is_err());
+        assert!(arithmetic(Divide, &f(1.0), &d("0")).is_err());
+        assert!(arithmetic(Multiply, &f(1e300), &f(1e300)).is_err());
+        assert_eq!(arithmetic(Add, &i(1), &LiteralValue::NullValue).unwrap(), LiteralValue::NullValue);
+
+        assert_eq!(compare_numeric(&i(1), &d("1.0")), Some(Ordering::Equal));
+        assert_eq!(compare_numeric(&f(f64::NAN), &f(f64::INFINITY)), Some(Ordering::Greater));
+        assert_eq!(i(2), f(2.0));
+    }
+
+    #[test]
+    fn casts_check_ranges() {
+        let i = LiteralValue::IntValue;
+        assert_eq!(cast_numeric(&i(32767), &ColumnType::SmallInt).unwrap(), i(32767));
+        assert!(cast_numeric(&i(32768), &ColumnType::SmallInt).is_err());
+        assert!(cast_numeric(&i(1 << 31), &ColumnType::Int).is_err());
+        assert_eq!(cast_numeric(&LiteralValue::FloatValue(2.5), &ColumnType::Int).unwrap(), i(2));
+        assert_eq!(cast_numeric(&d("2.5"), &ColumnType::Int).unwrap(), i(3));
+        assert!(cast_numeric(&LiteralValue::FloatValue(1e19), &ColumnType::BigInt).is_err());
+        assert_eq!(cast_numeric(&LiteralValue::FloatValue(0.1), &ColumnType::Real).unwrap().to_string(), "0.1");
+        assert!(cast_numeric(&LiteralValue::FloatValue(1e39), &ColumnType::Real).is_err());
+        assert_eq!(cast_numeric(&d("123.456"), &ColumnType::Decimal(Some((5, 2)))).unwrap().to_string(), "123.46");
+        assert!(cast_numeric(&d("1234.5"), &ColumnType::Decimal(Some((5, 2)))).is_err());
+
+        assert_eq!(common_type(&ColumnType::Int, &ColumnType::SmallInt), ColumnType::Int);
+        assert_eq!(common_type(&ColumnType::Real, &ColumnType::BigInt), ColumnType::Double);
+        assert_eq!(common_type(&ColumnType::Decimal(None), &ColumnType::Real), ColumnType::Double);
+        assert_eq!(common_type(&ColumnType::BigInt, &ColumnType::Decimal(Some((4, 2)))), ColumnType::Decimal(None));
+    }
+}
@@ -0,0 +1,86 @@
+use std::{collections::hash_map::RandomState, fmt, hash::{BuildHasher, Hasher}, sync::atomic::{AtomicU64, Ordering}, time::{SystemTime, UNIX_EPOCH}};
+
+
+/**
+ * Reads a uuid written as 32 hex digits, optionally in groups of 8-4-4-4-12 separated by
+ * hyphens and inside braces, in either case.
+ */
+pub fn parse_uuid(text: &str) -> Option<[u8; 16]> {
+    let text = text.trim();
+    let text = text.strip_prefix('{').and_then(|t| t.strip_suffix('}')).unwrap_or(text);
+    let digits: Vec<u8> = if text.len() == 36 {
+        let groups: Vec<&str> = text.split('-').collect();
+        if groups.iter().map(|g| g.len()).collect::<Vec<_>>() != [8, 4, 4, 4, 12] {
+            return None;
+        }
+        groups.concat().into_bytes()
+    } else {
+        text.as_bytes().to_vec()
+    };
+    if digits.len() != 32 || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    let mut res = [0u8; 16];
+    for (byte, pair) in res.iter_mut().zip(digits.chunks(2)) {
+        *byte = u8::from_str_radix(str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(res)
+}
+
+/**
+ * A version 4 uuid. The random bits come from the hashers std seeds randomly for each
+ * process, which is good enough for keys but not for secrets.
+ */
+pub fn random_uuid() -> [u8; 16] {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let mut res = [0u8; 16];
+    for half in res.chunks_mut(8) {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        half.copy_from_slice(&hasher.finish().to_be_bytes());
+    }
+    // version 4 and the variant of rfc 4122
+    res[6] = (res[6] & 0x0f) | 0x40;
+    res[8] = (res[8] & 0x3f) | 0x80;
+    res
+}
+
+// lowercase hex in groups of 8-4-4-4-12
+pub struct DisplayUuid<'a>(pub &'a [u8; 16]);
+
+impl fmt::Display for DisplayUuid<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, byte) in self.0.iter().enumerate() {
+            if [4, 6, 8, 10].contains(&index) {
+                write!(f, "-")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_uuid, random_uuid, DisplayUuid};
+
+    #[test]
+    fn parse_print_and_generate() {
+        let uuid = parse_uuid("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11").unwrap();
+        assert_eq!(uuid[0], 0xa0);
+        assert_eq!(DisplayUuid(&uuid).to_string(), "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11");
+        for same in ["A0EEBC99-9C0B-4EF8-BB6D-6BB9BD380A11", "{a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11}", "a0eebc999c0b4ef8bb6d6bb9bd380a11"] {
+            assert_eq!(parse_uuid(same), Some(uuid), "{}", same);
+        }
+        for invalid in ["", "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a1", "a0eebc99-9c0b4-ef8-bb6d-6bb9bd380a11", "g0eebc999c0b4ef8bb6d6bb9bd380a11", "+0eebc999c0b4ef8bb6d6bb9bd380a11"] {
+            assert_eq!(parse_uuid(invalid), None, "{}", invalid);
+        }
+
+        let (a, b) = (random_uuid(), random_uuid());
+        assert_ne!(a, b);
+        assert_eq!(DisplayUuid(&a).to_string().as_bytes()[14], b'4');
+        assert_eq!(a[8] >> 6, 2);
+    }
+}